@@ -0,0 +1,35 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use image::{Rgb, RgbImage};
+use quickstitch::Stitcher;
+
+/// Builds a synthetic strip made of noisy "panels" separated by plain white gutters.
+fn synthetic_strip(width: u32, height: u32) -> RgbImage {
+    RgbImage::from_fn(width, height, |x, y| {
+        if (y / 800) % 2 == 0 {
+            let v = ((x * 31 + y * 17) % 251) as u8;
+            Rgb([v, v.wrapping_mul(3), v.wrapping_mul(7)])
+        } else {
+            Rgb([255, 255, 255])
+        }
+    })
+}
+
+fn width(c: &mut Criterion) {
+    let mut group = c.benchmark_group("find_splitpoints/width");
+    group.sample_size(10);
+    for width in [800, 2000, 6000] {
+        let path = std::env::temp_dir().join(format!("quickstitch_bench_strip_{width}.png"));
+        synthetic_strip(width, 12000).save(&path).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(width), &path, |b, path| {
+            b.iter_batched(
+                || Stitcher::new().load(&[path], None, false).unwrap(),
+                |stitcher| stitcher.stitch(5000, 5, 242),
+                criterion::BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, width);
+criterion_main!(benches);
@@ -0,0 +1,60 @@
+//! Finds splitpoints in arbitrary pixels, with arbitrary options.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use image::{ImageFormat, RgbImage};
+use libfuzzer_sys::fuzz_target;
+use quickstitch::{BackgroundMode, LoadConfig, StitchConfig, Stitcher};
+use std::io::Cursor;
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    width: u8,
+    target_height: u16,
+    scan_interval: u16,
+    sensitivity: u8,
+    auto_sensitivity: bool,
+    avoid_text: Option<u8>,
+    background: Option<[u8; 3]>,
+    pixels: Vec<u8>,
+}
+
+fuzz_target!(|input: Input| {
+    let width = input.width.max(1) as u32;
+    let height = (input.pixels.len() / (width as usize * 3)) as u32;
+    let Some(image) = RgbImage::from_raw(
+        width,
+        height,
+        input.pixels[..(width * height * 3) as usize].to_vec(),
+    ) else {
+        return;
+    };
+    // images are only accepted encoded, so go through PNG
+    let mut png = Vec::new();
+    if image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .is_err()
+    {
+        return;
+    }
+    let Ok(loaded) = Stitcher::new().load_bytes(&[png], &LoadConfig::new()) else {
+        return;
+    };
+    let background = match input.background {
+        Some(color) => BackgroundMode::Color(image::Rgb(color)),
+        None => BackgroundMode::Auto,
+    };
+    let _ = loaded.auto_sensitivity();
+    let stitched = loaded.stitch_with(
+        &StitchConfig::new()
+            .target_height(input.target_height as usize)
+            .scan_interval(input.scan_interval as usize)
+            .sensitivity(input.sensitivity)
+            .auto_sensitivity(input.auto_sensitivity)
+            .avoid_text(input.avoid_text.map(u32::from))
+            .background_mode(background),
+    );
+    let splitpoints = stitched.get_splitpoits();
+    assert!(splitpoints.windows(2).all(|pair| pair[0] < pair[1]));
+});
@@ -0,0 +1,21 @@
+//! Loads arbitrary bytes as an archive (zip, 7z, or RAR, which is rejected).
+
+#![no_main]
+
+use std::io::Write;
+
+use libfuzzer_sys::fuzz_target;
+use quickstitch::{ArchiveLimits, LoadConfig, Stitcher};
+
+fuzz_target!(|archive: &[u8]| {
+    // archives are read from disk, so the bytes are written to a file first
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(archive).unwrap();
+    let limits = ArchiveLimits::new()
+        .max_total_size(64 << 20)
+        .max_decode_work(256 << 20);
+    let config = LoadConfig::new()
+        .ignore_unloadable(true)
+        .archive_limits(limits);
+    let _ = Stitcher::new().load_archive(file.path(), &config);
+});
@@ -0,0 +1,11 @@
+//! Loads arbitrary bytes as a set of encoded images.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use quickstitch::{LoadConfig, Stitcher};
+
+fuzz_target!(|images: Vec<&[u8]>| {
+    let config = LoadConfig::new().ignore_unloadable(true).auto_rotate(true);
+    let _ = Stitcher::new().load_bytes(&images, &config);
+});
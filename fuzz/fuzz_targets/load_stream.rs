@@ -0,0 +1,10 @@
+//! Loads images from an arbitrary framed stream.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use quickstitch::{LoadConfig, Stitcher};
+
+fuzz_target!(|stream: &[u8]| {
+    let _ = Stitcher::new().load_stream(stream, &LoadConfig::new());
+});
@@ -0,0 +1,10 @@
+//! Extracts the images embedded in an arbitrary PDF.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use quickstitch::raw::pdf_images;
+
+fuzz_target!(|pdf: &[u8]| {
+    let _ = pdf_images(pdf);
+});
@@ -6,17 +6,103 @@
 
 mod stitcher;
 
-pub use stitcher::image_loader::Sort;
-pub use stitcher::image_splitter::ImageOutputFormat;
+pub use stitcher::chapter_breaks::ChapterBreaks;
+pub use stitcher::decoder::{Decoder, DecoderRegistry};
+pub use stitcher::edit::PageLimits;
+pub use stitcher::encoder::{Encoder, EncoderRegistry};
+pub use stitcher::golden;
+pub use stitcher::host_profile::{HostProfile, HostProfiles};
+pub use stitcher::image_loader::{
+    AlphaPolicy, AnimatedPolicy, Direction, LoadConfig, ResizeFilter, Sort, UpscalePolicy,
+    WidthStrategy, STITCHED_ASPECT_RATIO, STITCHED_HEIGHT_SPREAD,
+};
+pub use stitcher::image_splitter::{
+    BackgroundMode, ColorProfile, DimensionPolicy, ExportConfig, ExportPlan, ExportReport,
+    ExportedPage, ImageOutputFormat, PageTimes, PlannedPage, Region, SanitizePolicy, SplitStrategy,
+    StitchConfig, Verify, WidthPolicy,
+};
+pub use stitcher::jpeg::ChromaSubsampling;
+pub use stitcher::messages::Catalog;
+pub use stitcher::metadata::PageMetadata;
+pub use stitcher::metrics::{RunMetrics, RunWarning};
+pub use stitcher::output_sink::{OutputSink, Writers};
+pub use stitcher::overlay::{Overlay, OverlayPosition};
+pub use stitcher::page_cache::PageCache;
+pub use stitcher::page_numbers::PageNumbers;
+pub use stitcher::pipeline;
+pub use stitcher::progress::Progress;
+pub use stitcher::raw;
+pub use stitcher::score_cache::ScoreCache;
+pub use stitcher::sharpen::Sharpen;
+pub use stitcher::stage_cache::StageCache;
+pub use stitcher::stats::{Duplicate, FileStats, LoadReport, PageStats, SkippedImage, Stats};
+pub use stitcher::stream;
+pub use stitcher::strip::{Strip, StripStorage};
+pub use stitcher::sweep::{Sweep, SweepRun};
+pub use stitcher::temp::TempPolicy;
+pub use stitcher::throttle::{Priority, Throttle};
+pub use stitcher::virtual_strip::{StripRows, VirtualStrip};
 
-use std::path::Path;
+#[cfg(any(feature = "zip", feature = "7z"))]
+pub use stitcher::archive::ArchiveLimits;
+#[cfg(feature = "zip")]
+pub use stitcher::comic_info::ComicInfo;
+#[cfg(feature = "pdf")]
+pub use stitcher::pdf::PdfPageSize;
+#[cfg(feature = "title-card")]
+pub use stitcher::title_card::TitleCard;
 
 use image::RgbImage;
+use std::{
+    io::{Read, Write},
+    ops::Range,
+    path::Path,
+    sync::{Mutex, OnceLock, PoisonError},
+    time::Instant,
+};
+
 use stitcher::{
-    image_loader::{find_images, load_images, ImageLoaderError},
-    image_splitter::{find_splitpoints, find_splitpoints_debug, split_image, ImageSplitterError},
+    chapter_breaks::detect_chapter_breaks,
+    edit::Splitpoints,
+    image_loader::{
+        concat_strips, find_loadable_images, load_images, load_images_from_bytes,
+        load_images_virtual, load_streamed, stitched_seams, IccProfile, ImageLoaderError,
+    },
+    image_splitter::{
+        calibrate_sensitivity, estimate_scan_interval, estimate_sensitivity, export_region,
+        find_splitpoints_debug, merge_pages, plan_export, scan_to_stream, split_image,
+        split_image_to_stream, split_image_to_writers, split_to_size, ImageSplitterError, Scan,
+    },
+    stream::read_frame,
+    strip::preview,
+    sweep::{compare_runs, run_sweep},
 };
 
+#[cfg(any(feature = "zip", feature = "7z"))]
+use stitcher::archive::load_archive;
+#[cfg(feature = "glob")]
+use stitcher::glob::glob_images;
+#[cfg(feature = "zip")]
+use stitcher::image_splitter::split_image_to_cbz;
+#[cfg(feature = "pdf")]
+use stitcher::image_splitter::split_image_to_pdf;
+#[cfg(feature = "tar")]
+use stitcher::image_splitter::split_image_to_tar;
+#[cfg(feature = "pdf")]
+use stitcher::pdf_reader::load_pdf;
+#[cfg(feature = "serde")]
+use stitcher::saved_splitpoints::{load_splitpoints, save_splitpoints};
+#[cfg(feature = "title-card")]
+use stitcher::title_card::prepend_title_card;
+
+/// Runs blocking work on tokio's blocking thread pool, resuming any panic it raises.
+#[cfg(feature = "tokio")]
+async fn run_blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    tokio::task::spawn_blocking(f)
+        .await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+}
+
 mod seal {
     pub trait Seal {}
 }
@@ -27,22 +113,63 @@ pub trait StitcherState: seal::Seal {}
 pub struct Empty;
 
 // Images have been loaded and combined
-pub struct Loaded {
-    strip: RgbImage,
+pub struct Loaded<S = Strip> {
+    strip: S,
+    // the row at which each chapter starts
+    chapters: Vec<usize>,
+    direction: Direction,
+    // the row at which each image but the first starts, if the images look like pages which were already
+    // stitched
+    seams: Vec<usize>,
+    // where strips made from this one keep their scratch files
+    temp: TempPolicy,
+    // the colour profile of the images, embedded into the pages by default
+    icc_profile: IccProfile,
+    stats: Stats,
+}
+
+impl<S: StripRows> Loaded<S> {
+    fn new(strip: S, config: &LoadConfig, mut stats: Stats) -> Self {
+        let seams = stitched_seams(&stats.files, strip.width(), config);
+        stats.already_stitched = !seams.is_empty();
+        let icc_profile = stats.files.iter().find_map(|file| file.icc_profile.clone());
+        Self {
+            strip,
+            chapters: vec![0],
+            direction: config.direction,
+            seams,
+            temp: config.temp.clone(),
+            icc_profile,
+            stats,
+        }
+    }
 }
 
 // Images have been cut up
-pub struct Stitched {
-    strip: RgbImage,
+pub struct Stitched<S = Strip> {
+    strip: S,
     splitpoints: Vec<usize>,
+    chapters: Vec<usize>,
+    direction: Direction,
+    limits: PageLimits,
+    // rows cut for lack of a clean row by a strict stitch, which block exporting until edited by hand
+    worst_cases: Vec<usize>,
+    // only needed for the strips title cards are added to
+    #[cfg_attr(not(feature = "title-card"), allow(dead_code))]
+    temp: TempPolicy,
+    icc_profile: IccProfile,
+    // behind a mutex, as exporting only borrows the stitcher
+    stats: Mutex<Stats>,
+    // the strip copied into an `RgbImage` the first time `view_image` is called
+    image: OnceLock<RgbImage>,
 }
 
 impl seal::Seal for Empty {}
-impl seal::Seal for Loaded {}
-impl seal::Seal for Stitched {}
+impl<S> seal::Seal for Loaded<S> {}
+impl<S> seal::Seal for Stitched<S> {}
 impl StitcherState for Empty {}
-impl StitcherState for Loaded {}
-impl StitcherState for Stitched {}
+impl<S> StitcherState for Loaded<S> {}
+impl<S> StitcherState for Stitched<S> {}
 
 pub struct Stitcher<S: StitcherState> {
     data: S,
@@ -56,12 +183,13 @@ impl Stitcher<Empty> {
         ignore_unloadable: bool,
         sort: Sort,
     ) -> Result<Stitcher<Loaded>, ImageLoaderError> {
-        let images = find_images(directory, sort)?;
-        Ok(Stitcher {
-            data: Loaded {
-                strip: load_images(&images, width, ignore_unloadable)?,
-            },
-        })
+        self.load_dir_with(
+            directory,
+            &LoadConfig::new()
+                .width(width)
+                .ignore_unloadable(ignore_unloadable)
+                .sort(sort),
+        )
     }
     pub fn load(
         self,
@@ -69,71 +197,902 @@ impl Stitcher<Empty> {
         width: Option<u32>,
         ignore_unloadable: bool,
     ) -> Result<Stitcher<Loaded>, ImageLoaderError> {
+        self.load_with(
+            images,
+            &LoadConfig::new()
+                .width(width)
+                .ignore_unloadable(ignore_unloadable),
+        )
+    }
+    /// Does exactly the same thing as `load_dir`, but takes its options from a [`LoadConfig`].
+    pub fn load_dir_with(
+        self,
+        directory: impl AsRef<Path>,
+        config: &LoadConfig,
+    ) -> Result<Stitcher<Loaded>, ImageLoaderError> {
+        let directory = directory.as_ref();
+        let images = find_loadable_images(directory, config)?;
+        match self.load_with(&images, config) {
+            // If an image found by the scan was removed or modified before it could be loaded,
+            // the directory is still being written to.
+            Err(ImageLoaderError::NotFound | ImageLoaderError::SourceChangedDuringRun(_))
+                if config.rescan_on_change =>
+            {
+                let images = find_loadable_images(directory, config)?;
+                Stitcher::new().load_with(&images, config)
+            }
+            Err(ImageLoaderError::NotFound) => Err(ImageLoaderError::SourceChangedDuringRun(
+                directory.to_path_buf(),
+            )),
+            res => res,
+        }
+    }
+    /// Loads the images matching a glob pattern (such as `raws/ch-12/*.png`), ordered according to the `sort`
+    /// option of the config. See [`raw::glob_images`] for the syntax of patterns.
+    ///
+    /// This is for selecting images on platforms whose shells don't expand patterns, such as Windows.
+    #[cfg(feature = "glob")]
+    pub fn load_glob(
+        self,
+        pattern: &str,
+        config: &LoadConfig,
+    ) -> Result<Stitcher<Loaded>, ImageLoaderError> {
+        let images = glob_images(pattern, config)?;
+        self.load_with(&images, config)
+    }
+    /// Does exactly the same thing as `load`, but takes its options from a [`LoadConfig`].
+    pub fn load_with(
+        self,
+        images: &[impl AsRef<Path>],
+        config: &LoadConfig,
+    ) -> Result<Stitcher<Loaded>, ImageLoaderError> {
+        let mut stats = Stats::default();
+        let strip = load_images(images, config, &mut stats)?;
+        Ok(Stitcher {
+            data: Loaded::new(strip, config, stats),
+        })
+    }
+    /// Does exactly the same thing as `load_with`, but the images are kept separate in a [`VirtualStrip`]
+    /// instead of being copied into a single strip, which halves the memory needed for long chapters.
+    ///
+    /// A virtual strip can be stitched and exported like any other, but can't be previewed or stitched with
+    /// `stitch_debug`.
+    pub fn load_virtual(
+        self,
+        images: &[impl AsRef<Path>],
+        config: &LoadConfig,
+    ) -> Result<Stitcher<Loaded<VirtualStrip>>, ImageLoaderError> {
+        let mut stats = Stats::default();
+        let strip = load_images_virtual(images, config, &mut stats)?;
+        Ok(Stitcher {
+            data: Loaded::new(strip, config, stats),
+        })
+    }
+    /// Does exactly the same thing as `load_with`, but the images are encoded images held in memory (such as
+    /// uploads or drag-and-dropped buffers) rather than files. The format of each image is guessed from its
+    /// contents.
+    pub fn load_bytes(
+        self,
+        images: &[impl AsRef<[u8]>],
+        config: &LoadConfig,
+    ) -> Result<Stitcher<Loaded>, ImageLoaderError> {
+        let mut stats = Stats::default();
+        let strip = load_images_from_bytes(images, config, &mut stats)?;
+        Ok(Stitcher {
+            data: Loaded::new(strip, config, stats),
+        })
+    }
+    /// Loads one encoded image from each of the given readers, in order. The format of each image is guessed
+    /// from its contents.
+    ///
+    /// Each reader is read to the end while the images before it are being decoded.
+    pub fn load_readers(
+        self,
+        readers: impl IntoIterator<Item = impl Read>,
+        config: &LoadConfig,
+    ) -> Result<Stitcher<Loaded>, ImageLoaderError> {
+        let images = readers.into_iter().map(|mut reader| {
+            let mut image = Vec::new();
+            reader.read_to_end(&mut image).map(|_| image)
+        });
+        let mut stats = Stats::default();
+        let strip = load_streamed(images, config, &mut stats)?;
+        Ok(Stitcher {
+            data: Loaded::new(strip, config, stats),
+        })
+    }
+    /// Loads the images inside a `.cbz`/`.zip` or `.cb7`/`.7z` archive, without extracting it to disk. Each
+    /// format is read with the feature of the same name.
+    ///
+    /// Images in 7z archives must be stored, or compressed with LZMA or LZMA2. RAR archives aren't supported,
+    /// so a `.cbr` can only be loaded if it is really a zip archive which was renamed.
+    ///
+    /// Image entries are ordered according to the `sort` option of the config, using their full path
+    /// within the archive.
+    #[cfg(any(feature = "zip", feature = "7z"))]
+    pub fn load_archive(
+        self,
+        archive: impl AsRef<Path>,
+        config: &LoadConfig,
+    ) -> Result<Stitcher<Loaded>, ImageLoaderError> {
+        let mut stats = Stats::default();
+        let strip = load_archive(archive, config, &mut stats)?;
+        Ok(Stitcher {
+            data: Loaded::new(strip, config, stats),
+        })
+    }
+    /// Loads the images embedded in a PDF, for raws which are distributed as PDFs of page images.
+    ///
+    /// Pages aren't rendered: the images drawn on each page are extracted and loaded in page order. Text and
+    /// vector art aren't part of the strip, and pages which only hold text or vector art are skipped.
+    #[cfg(feature = "pdf")]
+    pub fn load_pdf(
+        self,
+        pdf: impl AsRef<Path>,
+        config: &LoadConfig,
+    ) -> Result<Stitcher<Loaded>, ImageLoaderError> {
+        let mut stats = Stats::default();
+        let strip = load_pdf(pdf, config, &mut stats)?;
+        Ok(Stitcher {
+            data: Loaded::new(strip, config, stats),
+        })
+    }
+    /// Loads images from a stream (such as stdin) rather than from files.
+    ///
+    /// Each image must be framed as its length in bytes (a big-endian `u64`) followed by the encoded image,
+    /// and the stream must end (EOF) after the last image. The format of each image is guessed from its
+    /// contents.
+    ///
+    /// Images are decoded while the rest of the stream is still being read. See
+    /// [`LoadConfig::queue_depth`] for how far ahead of decoding reading can get.
+    pub fn load_stream(
+        self,
+        mut reader: impl Read,
+        config: &LoadConfig,
+    ) -> Result<Stitcher<Loaded>, ImageLoaderError> {
+        let frames = std::iter::from_fn(|| read_frame(&mut reader).transpose());
+        let mut stats = Stats::default();
+        let strip = load_streamed(frames, config, &mut stats)?;
+        Ok(Stitcher {
+            data: Loaded::new(strip, config, stats),
+        })
+    }
+    /// Concatenates several loaded chapters into one continuous strip, for e.g. season compilations.
+    ///
+    /// The start of each chapter is preserved, and when the strip is stitched, pages are always cut at
+    /// chapter boundaries so that no page straddles two chapters.
+    ///
+    /// Only the `width`, `spill_threshold`, `temp_policy` and `direction` options of the config are
+    /// used, and the direction must be the one the chapters were loaded in. The width is chosen from the
+    /// widths of the chapters (by default, the narrowest), and other chapters are resized to match.
+    pub fn concat(
+        self,
+        chapters: impl IntoIterator<Item = Stitcher<Loaded>>,
+        config: &LoadConfig,
+    ) -> Result<Stitcher<Loaded>, ImageLoaderError> {
+        // the timings of each chapter are carried over into the combined strip
+        let mut stats = Stats::default();
+        let strips = chapters
+            .into_iter()
+            .map(|chapter| {
+                stats.load += chapter.data.stats.load;
+                stats.copy += chapter.data.stats.copy;
+                stats.files.extend(chapter.data.stats.files);
+                (chapter.data.strip, chapter.data.chapters)
+            })
+            .collect();
+        let copy_start = Instant::now();
+        let (strip, chapters) = concat_strips(strips, config)?;
+        stats.copy += copy_start.elapsed();
         Ok(Stitcher {
             data: Loaded {
-                strip: load_images(images, width, ignore_unloadable)?,
+                strip,
+                chapters,
+                direction: config.direction,
+                // the chapters may have been resized, so their seams are no longer known
+                seams: Vec::new(),
+                temp: config.temp.clone(),
+                icc_profile: stats.files.iter().find_map(|file| file.icc_profile.clone()),
+                stats,
             },
         })
     }
+    /// Does exactly the same thing as `load_dir_with`, but the images are loaded on tokio's blocking thread
+    /// pool, so that the executor isn't stalled while they are decoded.
+    #[cfg(feature = "tokio")]
+    pub async fn load_dir_async(
+        self,
+        directory: impl AsRef<Path>,
+        config: &LoadConfig,
+    ) -> Result<Stitcher<Loaded>, ImageLoaderError> {
+        let directory = directory.as_ref().to_path_buf();
+        let config = config.clone();
+        run_blocking(move || self.load_dir_with(directory, &config)).await
+    }
     pub fn new() -> Stitcher<Empty> {
         Stitcher { data: Empty {} }
     }
 }
 
-impl Stitcher<Loaded> {
+impl Default for Stitcher<Empty> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: StripRows> Stitcher<Loaded<S>> {
+    /// The row at which each chapter starts.
+    pub fn chapters(&self) -> &[usize] {
+        &self.data.chapters
+    }
+    /// Measures the noise in regions of the strip which are known to be background, and returns the
+    /// sensitivity which accepts rows as clean as them, or `None` if no region overlaps the strip.
+    ///
+    /// Setting the regions with [`StitchConfig::background`] does this automatically.
+    pub fn calibrate_sensitivity(&self, background: &[Region]) -> Option<u8> {
+        calibrate_sensitivity(&self.data.strip, background)
+    }
+    /// Samples the rows of the strip and suggests a sensitivity which separates its background from its
+    /// content, or `None` if too little of the strip looks like background to tell.
+    ///
+    /// Setting [`StitchConfig::auto_sensitivity`] applies this automatically.
+    pub fn auto_sensitivity(&self) -> Option<u8> {
+        estimate_sensitivity(&self.data.strip)
+    }
+    /// Measures the heights of the gutters between panels in a sample of the strip, and returns a scan
+    /// interval which won't skip over them at the given sensitivity, or `None` if no gutters were found.
+    ///
+    /// Setting [`StitchConfig::auto_scan_interval`] applies this automatically.
+    pub fn auto_scan_interval(&self, sensitivity: u8) -> Option<usize> {
+        estimate_scan_interval(&self.data.strip, sensitivity)
+    }
+    /// Splits the strip into chapters at the chapter breaks found in it, for sources which deliver a whole
+    /// volume as a single strip. See [`ChapterBreaks`].
+    ///
+    /// When the strip is stitched, pages are always cut at the start of each chapter, and the cuts
+    /// within each chapter are found as usual. Chapters which were already known are kept.
+    pub fn split_chapters(mut self, breaks: &ChapterBreaks) -> Self {
+        let detected = detect_chapter_breaks(&self.data.strip, breaks);
+        self.data.chapters.extend(detected);
+        self.data.chapters.sort_unstable();
+        self.data.chapters.dedup();
+        self
+    }
+    /// Finds the splitpoints with every combination of options in the sweep, for comparing them before
+    /// settling on the options for a new series.
+    pub fn sweep(&self, sweep: &Sweep) -> Vec<SweepRun> {
+        run_sweep(&self.data.strip, sweep, &self.data.chapters)
+    }
+    /// The time taken by each phase so far.
+    pub fn stats(&self) -> &Stats {
+        &self.data.stats
+    }
+    /// The images which were given but left out of the strip: those which couldn't be loaded (when
+    /// `ignore_unloadable` is set) and the duplicates which were skipped.
+    pub fn load_report(&self) -> LoadReport {
+        LoadReport::new(&self.data.stats)
+    }
     pub fn stitch(
         self,
         target_height: usize,
         scan_interval: usize,
         sensitivity: u8,
-    ) -> Stitcher<Stitched> {
-        let splitpoints =
-            find_splitpoints(&self.data.strip, target_height, scan_interval, sensitivity);
+    ) -> Stitcher<Stitched<S>> {
+        self.stitch_with(
+            &StitchConfig::new()
+                .target_height(target_height)
+                .scan_interval(scan_interval)
+                .sensitivity(sensitivity),
+        )
+    }
+    /// Does exactly the same thing as `stitch`, but takes its options from a [`StitchConfig`].
+    ///
+    /// If the config is strict, rows cut for lack of a clean row block exporting until they are edited by
+    /// hand. See [`StitchConfig::strict`] and [`Stitcher::worst_case_cuts`].
+    ///
+    /// If the images looked like pages which were already stitched, they are cut again along their seams
+    /// with the target height of the config. See [`LoadConfig::detect_stitched`].
+    pub fn stitch_with(mut self, config: &StitchConfig) -> Stitcher<Stitched<S>> {
+        let score_start = Instant::now();
+        let mut worst_cases = Vec::new();
+        let splitpoints = self.scan(config).run(
+            &self.data.strip,
+            config.strict.then_some(&mut worst_cases),
+            None,
+        );
+        self.data.stats.score += score_start.elapsed();
         Stitcher {
             data: Stitched {
                 strip: self.data.strip,
                 splitpoints,
+                chapters: self.data.chapters,
+                direction: self.data.direction,
+                limits: PageLimits::default(),
+                worst_cases,
+                temp: self.data.temp,
+                icc_profile: self.data.icc_profile,
+                stats: Mutex::new(self.data.stats),
+                image: OnceLock::new(),
             },
         }
     }
+    /// Does exactly the same thing as `stitch_with`, but makes the pages fit the given platform: the target
+    /// height is lowered so that pages fit its max page height, and pages over its max size in bytes are cut
+    /// again until they fit (see `split_to_size`).
+    ///
+    /// Export the pages in [`HostProfile::format`] with [`HostProfile::export_config`], which are also what
+    /// the size of each page is measured with.
+    pub fn stitch_for_host(
+        self,
+        profile: &HostProfile,
+        config: &StitchConfig,
+        export: &ExportConfig,
+    ) -> Result<Stitcher<Stitched<S>>, ImageSplitterError> {
+        let config = profile.stitch_config(config, self.data.strip.width());
+        let mut stitched = self.stitch_with(&config);
+        if let Some(max_bytes) = profile.page_bytes() {
+            stitched.split_to_size(
+                max_bytes,
+                &config,
+                &profile.format(),
+                &profile.export_config(export),
+            )?;
+        }
+        Ok(stitched)
+    }
+    /// Stitches the strip and writes the pages to a stream at the same time, framed the same way as by
+    /// `export_stream`. Each page is written as soon as the cut at its bottom has been found, so that e.g. an
+    /// uploader reading the stream can start publishing the first pages of a chapter while the rest of it is
+    /// still being scanned.
+    ///
+    /// Returns the stitched strip, which can be edited and exported again as usual. If the stitch config is
+    /// strict, the stream ends before the first page which would be cut for lack of a clean row, and
+    /// [`ImageSplitterError::WorstCaseCut`] is thrown. Pages aren't reported to
+    /// [`Progress::page_written`], as the number of pages isn't known until the scan is over.
+    pub fn stitch_to_stream(
+        mut self,
+        writer: impl Write,
+        config: &StitchConfig,
+        output_filetype: ImageOutputFormat,
+        export: &ExportConfig,
+    ) -> Result<Stitcher<Stitched<S>>, Vec<ImageSplitterError>> {
+        let mut stats = std::mem::take(&mut self.data.stats);
+        let (splitpoints, worst_cases) = scan_to_stream(
+            &self.data.strip,
+            &self.scan(config),
+            writer,
+            &output_filetype,
+            &export.for_strip(self.data.direction, &self.data.icc_profile),
+            &mut stats,
+        )?;
+        Ok(Stitcher {
+            data: Stitched {
+                strip: self.data.strip,
+                splitpoints,
+                chapters: self.data.chapters,
+                direction: self.data.direction,
+                limits: PageLimits::default(),
+                worst_cases,
+                temp: self.data.temp,
+                icc_profile: self.data.icc_profile,
+                stats: Mutex::new(stats),
+                image: OnceLock::new(),
+            },
+        })
+    }
+    /// Where and how to scan the strip with the given config.
+    fn scan<'a>(&'a self, config: &'a StitchConfig) -> Scan<'a> {
+        Scan {
+            config,
+            boundaries: &self.data.chapters,
+            seams: &self.data.seams,
+        }
+    }
+    /// Uses splitpoints saved by [`Stitcher::save_splitpoints`] instead of scanning the strip, e.g. after
+    /// they have been reviewed or edited by another tool.
+    ///
+    /// Throws an error if the file can't be read, or if the splitpoints weren't found in a strip of the
+    /// same dimensions, aren't in increasing order, or don't cut at the start of every chapter.
+    #[cfg(feature = "serde")]
+    pub fn with_splitpoints(
+        self,
+        path: impl AsRef<Path>,
+    ) -> Result<Stitcher<Stitched<S>>, ImageSplitterError> {
+        let strip = &self.data.strip;
+        let splitpoints = load_splitpoints(
+            path.as_ref(),
+            (strip.width(), strip.height()),
+            &self.data.chapters,
+        )?;
+        Ok(Stitcher {
+            data: Stitched {
+                strip: self.data.strip,
+                splitpoints,
+                chapters: self.data.chapters,
+                direction: self.data.direction,
+                limits: PageLimits::default(),
+                worst_cases: Vec::new(),
+                temp: self.data.temp,
+                icc_profile: self.data.icc_profile,
+                stats: Mutex::new(self.data.stats),
+                image: OnceLock::new(),
+            },
+        })
+    }
+    /// Does exactly the same thing as `stitch_with`, but the rows are scanned on tokio's blocking thread
+    /// pool, so that the executor isn't stalled while they are.
+    #[cfg(feature = "tokio")]
+    pub async fn stitch_async(self, config: &StitchConfig) -> Stitcher<Stitched<S>>
+    where
+        S: Send + 'static,
+    {
+        let config = config.clone();
+        run_blocking(move || self.stitch_with(&config)).await
+    }
+}
+
+impl Stitcher<Loaded> {
+    /// Produces a heavily downscaled copy of the strip, at most `max_height` pixels tall, for showing an
+    /// overview before the strip is stitched.
+    pub fn preview(&self, max_height: u32) -> RgbImage {
+        preview(&self.data.strip, max_height)
+    }
+    /// Does the same thing as `sweep`, but also saves a comparison of the runs to the given path, as an
+    /// image of a preview (at most `max_height` pixels tall) of the strip for each run, side by side and
+    /// left to right in the order of the runs, with its cuts drawn across it in red.
+    ///
+    /// The format of the image is chosen from the extension of the path.
+    pub fn export_sweep(
+        &self,
+        sweep: &Sweep,
+        path: impl AsRef<Path>,
+        max_height: u32,
+    ) -> Result<Vec<SweepRun>, ImageSplitterError> {
+        let runs = self.sweep(sweep);
+        let preview = preview(&self.data.strip, max_height);
+        compare_runs(&preview, self.data.strip.height() as usize, &runs).save(path)?;
+        Ok(runs)
+    }
     pub fn stitch_debug(
         mut self,
         target_height: usize,
         scan_interval: usize,
         sensitivity: u8,
     ) -> Stitcher<Stitched> {
+        let score_start = Instant::now();
         let splitpoints = find_splitpoints_debug(
             &mut self.data.strip,
             target_height,
             scan_interval,
             sensitivity,
+            &self.data.chapters,
         );
+        self.data.stats.score += score_start.elapsed();
         Stitcher {
             data: Stitched {
                 strip: self.data.strip,
                 splitpoints,
+                chapters: self.data.chapters,
+                direction: self.data.direction,
+                limits: PageLimits::default(),
+                worst_cases: Vec::new(),
+                temp: self.data.temp,
+                icc_profile: self.data.icc_profile,
+                stats: Mutex::new(self.data.stats),
+                image: OnceLock::new(),
             },
         }
     }
 }
 
-impl Stitcher<Stitched> {
+impl<S: StripRows> Stitcher<Stitched<S>> {
+    /// The row at which each chapter starts. Every chapter start is also a splitpoint.
+    pub fn chapters(&self) -> &[usize] {
+        &self.data.chapters
+    }
+    /// The combined image, as an [`RgbImage`].
+    ///
+    /// The strip is copied into the image the first time this is called, as it may be memory-mapped or kept
+    /// as separate images. Use `view_strip` to read it without the copy.
     pub fn view_image(&self) -> &RgbImage {
+        self.data
+            .image
+            .get_or_init(|| self.data.strip.copy_rows(0, self.data.strip.height()))
+    }
+    /// The combined image, in whatever storage it was loaded into (a [`Strip`] unless it was loaded with
+    /// `load_virtual`). Unlike `view_image`, this never copies it.
+    pub fn view_strip(&self) -> &S {
         &self.data.strip
     }
     pub fn export(
         &self,
         output_directory: impl AsRef<Path>,
         output_filetype: ImageOutputFormat,
-    ) -> Result<(), Vec<ImageSplitterError>> {
+    ) -> Result<ExportReport, Vec<ImageSplitterError>> {
+        self.export_with(output_directory, output_filetype, &ExportConfig::default())
+    }
+    pub fn export_with(
+        &self,
+        output_directory: impl AsRef<Path>,
+        output_filetype: ImageOutputFormat,
+        config: &ExportConfig,
+    ) -> Result<ExportReport, Vec<ImageSplitterError>> {
+        self.check_reviewed().map_err(|error| vec![error])?;
         split_image(
             &self.data.strip,
             &self.data.splitpoints,
+            &self.data.chapters,
+            output_directory,
+            output_filetype,
+            &config.for_strip(self.data.direction, &self.data.icc_profile),
+            &mut self
+                .data
+                .stats
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        )
+    }
+    /// Works out what `export_with` would write with the same arguments (the path, dimensions and estimated
+    /// size of each page), without encoding or writing anything, e.g. to show a dry run.
+    pub fn plan_export(
+        &self,
+        output_directory: impl AsRef<Path>,
+        output_filetype: &ImageOutputFormat,
+        config: &ExportConfig,
+    ) -> Result<ExportPlan, ImageSplitterError> {
+        plan_export(
+            &self.data.strip,
+            &self.data.splitpoints,
+            &self.data.chapters,
+            output_directory,
+            output_filetype,
+            &config.for_strip(self.data.direction, &self.data.icc_profile),
+        )
+    }
+    /// Does exactly the same thing as `export_with`, but the pages are encoded and written on tokio's blocking
+    /// thread pool, so that the executor isn't stalled while they are.
+    ///
+    /// As the export may outlive the call, the stitcher is shared through an [`Arc`](std::sync::Arc).
+    #[cfg(feature = "tokio")]
+    pub async fn export_async(
+        self: std::sync::Arc<Self>,
+        output_directory: impl AsRef<Path>,
+        output_filetype: ImageOutputFormat,
+        config: &ExportConfig,
+    ) -> Result<ExportReport, Vec<ImageSplitterError>>
+    where
+        S: Send + Sync + 'static,
+    {
+        let output_directory = output_directory.as_ref().to_path_buf();
+        let config = config.clone();
+        run_blocking(move || self.export_with(output_directory, output_filetype, &config)).await
+    }
+    /// Does exactly the same thing as `export_with`, but pages which were already written by an earlier,
+    /// interrupted export to the same directory are left as is, rather than being encoded again.
+    ///
+    /// Pages are only left as is if the manifest of the earlier export (see [`ExportConfig::manifest`])
+    /// shows they have the same contents, and they are still intact on disk. If there is no manifest,
+    /// every page is written.
+    pub fn export_resume(
+        &self,
+        output_directory: impl AsRef<Path>,
+        output_filetype: ImageOutputFormat,
+        config: &ExportConfig,
+    ) -> Result<ExportReport, Vec<ImageSplitterError>> {
+        self.export_with(
             output_directory,
             output_filetype,
+            &config.clone().resume(true),
+        )
+    }
+    /// Writes the pages to a stream (such as stdout) rather than to files, in reading order.
+    ///
+    /// Each page is framed the same way as the images read by `load_stream`: its length in bytes
+    /// (a big-endian `u64`) followed by the encoded page.
+    pub fn export_stream(
+        &self,
+        writer: impl Write,
+        output_filetype: ImageOutputFormat,
+        config: &ExportConfig,
+    ) -> Result<(), Vec<ImageSplitterError>> {
+        self.check_reviewed().map_err(|error| vec![error])?;
+        split_image_to_stream(
+            &self.data.strip,
+            &self.data.splitpoints,
+            writer,
+            output_filetype,
+            &config.for_strip(self.data.direction, &self.data.icc_profile),
+            &mut self
+                .data
+                .stats
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
         )
     }
+    /// Hands each encoded page to the given sink rather than writing it to a file, e.g. to keep the pages
+    /// in memory or upload them straight to object storage.
+    ///
+    /// Pages are named the same way as they would be by `export`. Pass a `Vec<(String, Vec<u8>)>` (by
+    /// mutable reference) to collect the pages, or [`Writers`] to write each page to a writer of your own.
+    pub fn export_to_writers(
+        &self,
+        sink: impl OutputSink,
+        output_filetype: ImageOutputFormat,
+        config: &ExportConfig,
+    ) -> Result<(), Vec<ImageSplitterError>> {
+        self.check_reviewed().map_err(|error| vec![error])?;
+        split_image_to_writers(
+            &self.data.strip,
+            &self.data.splitpoints,
+            sink,
+            output_filetype,
+            &config.for_strip(self.data.direction, &self.data.icc_profile),
+            &mut self
+                .data
+                .stats
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        )
+    }
+    /// Writes the pages to a stream (such as stdout) as a tar archive, for use in shell pipelines.
+    ///
+    /// Pages are named the same way as they would be by `export`.
+    #[cfg(feature = "tar")]
+    pub fn export_tar(
+        &self,
+        writer: impl Write,
+        output_filetype: ImageOutputFormat,
+        config: &ExportConfig,
+    ) -> Result<(), Vec<ImageSplitterError>> {
+        self.check_reviewed().map_err(|error| vec![error])?;
+        split_image_to_tar(
+            &self.data.strip,
+            &self.data.splitpoints,
+            writer,
+            output_filetype,
+            &config.for_strip(self.data.direction, &self.data.icc_profile),
+            &mut self
+                .data
+                .stats
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        )
+    }
+    /// Writes the pages straight into a CBZ (zip) archive at the given path, rather than to loose files.
+    ///
+    /// Pages are named the same way as they would be by `export`. If the config has [`ComicInfo`] set, a
+    /// `ComicInfo.xml` file is included as well.
+    #[cfg(feature = "zip")]
+    pub fn export_cbz(
+        &self,
+        path: impl AsRef<Path>,
+        output_filetype: ImageOutputFormat,
+        config: &ExportConfig,
+    ) -> Result<(), Vec<ImageSplitterError>> {
+        self.check_reviewed().map_err(|error| vec![error])?;
+        split_image_to_cbz(
+            &self.data.strip,
+            &self.data.splitpoints,
+            &self.data.chapters,
+            path,
+            output_filetype,
+            &config.for_strip(self.data.direction, &self.data.icc_profile),
+            &mut self
+                .data
+                .stats
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        )
+    }
+    /// Writes the pages into a single PDF document at the given path, each page embedded as a JPEG of the
+    /// given quality.
+    ///
+    /// The page size and resolution are set with [`ExportConfig::pdf_layout`]. If there are several
+    /// chapters, the start of each one is added to the outline of the document.
+    #[cfg(feature = "pdf")]
+    pub fn export_pdf(
+        &self,
+        path: impl AsRef<Path>,
+        quality: u8,
+        config: &ExportConfig,
+    ) -> Result<(), Vec<ImageSplitterError>> {
+        self.check_reviewed().map_err(|error| vec![error])?;
+        split_image_to_pdf(
+            &self.data.strip,
+            &self.data.splitpoints,
+            &self.data.chapters,
+            path,
+            quality,
+            &config.for_strip(self.data.direction, &self.data.icc_profile),
+            &mut self
+                .data
+                .stats
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        )
+    }
+    /// Writes the given rows of the strip to `path` as a standalone image, regardless of where the strip
+    /// has been cut, e.g. to pull out a single panel or a problem area for review or typesetting.
+    ///
+    /// The region is encoded the same way as pages are. Throws an error if the rows aren't within the
+    /// strip, or if the image can't be encoded or written.
+    pub fn export_region(
+        &self,
+        rows: Range<usize>,
+        path: impl AsRef<Path>,
+        output_filetype: ImageOutputFormat,
+        config: &ExportConfig,
+    ) -> Result<(), ImageSplitterError> {
+        export_region(
+            &self.data.strip,
+            rows,
+            path.as_ref(),
+            &output_filetype,
+            &config.for_strip(self.data.direction, &self.data.icc_profile),
+        )
+    }
+    /// Merges consecutive pages into as few long pages as possible, each at most `max_height` pixels tall
+    /// and at most `max_bytes` bytes once exported in the given format, for readers which prefer a handful
+    /// of long images over many pages.
+    ///
+    /// Pages are only merged at existing splitpoints, so stitch with a smaller target height than
+    /// `max_height` to leave room to choose from. Pages which exceed either limit on their own are left
+    /// as they are, and chapters are never merged together.
+    pub fn merge_pages(
+        &mut self,
+        max_height: usize,
+        max_bytes: u64,
+        output_filetype: &ImageOutputFormat,
+        config: &ExportConfig,
+    ) -> Result<(), ImageSplitterError> {
+        self.data.splitpoints = merge_pages(
+            &self.data.strip,
+            &self.data.splitpoints,
+            &self.data.chapters,
+            max_height,
+            max_bytes,
+            output_filetype,
+            &config.for_strip(self.data.direction, &self.data.icc_profile),
+        )?;
+        Ok(())
+    }
+    /// Cuts pages which would be more than `max_bytes` bytes once exported in the given format into
+    /// smaller pages, for upload sites which limit the size of each image.
+    ///
+    /// Oversized pages are stitched again on their own with a smaller target height, using the other
+    /// options of `stitch`, until every page fits. Pages a single row tall are left as they are.
+    pub fn split_to_size(
+        &mut self,
+        max_bytes: u64,
+        stitch: &StitchConfig,
+        output_filetype: &ImageOutputFormat,
+        config: &ExportConfig,
+    ) -> Result<(), ImageSplitterError> {
+        self.data.splitpoints = split_to_size(
+            &self.data.strip,
+            &self.data.splitpoints,
+            max_bytes,
+            stitch,
+            output_filetype,
+            &config.for_strip(self.data.direction, &self.data.icc_profile),
+        )?;
+        Ok(())
+    }
+    /// The time taken by each phase so far, including any exports.
+    pub fn stats(&self) -> Stats {
+        self.data
+            .stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+    /// Summarises the run so far and the given export of it, for showing in an application's own
+    /// dashboard. See [`RunMetrics`].
+    pub fn metrics(&self, report: &ExportReport) -> RunMetrics {
+        RunMetrics::new(&self.stats(), report)
+    }
     pub fn get_splitpoits(&self) -> &Vec<usize> {
         &self.data.splitpoints
     }
+    /// Saves the splitpoints to a JSON file, so the strip can be exported later without scanning it again.
+    /// See [`Stitcher::with_splitpoints`].
+    #[cfg(feature = "serde")]
+    pub fn save_splitpoints(&self, path: impl AsRef<Path>) -> Result<(), ImageSplitterError> {
+        let strip = &self.data.strip;
+        save_splitpoints(
+            path.as_ref(),
+            (strip.width(), strip.height()),
+            &self.data.splitpoints,
+        )
+    }
+    /// Sets the heights pages must stay within when splitpoints are edited by hand (defaults to no limits).
+    /// See [`PageLimits`].
+    pub fn with_page_limits(mut self, limits: PageLimits) -> Self {
+        self.data.limits = limits;
+        self
+    }
+    /// Adds a cut at the given row, for correcting a page which should have been cut.
+    ///
+    /// Throws an error if the row is outside of the strip or already a splitpoint, or if either of the
+    /// resulting pages would be outside of the page limits.
+    pub fn add_splitpoint(&mut self, row: usize) -> Result<(), ImageSplitterError> {
+        self.data.splitpoints = self.editable_splitpoints().add(row)?;
+        self.data.worst_cases.retain(|&cut| cut != row);
+        Ok(())
+    }
+    /// Removes the cut at the given row, merging the pages either side of it.
+    ///
+    /// Throws an error if there is no splitpoint at the row, if it starts a chapter (or the strip), or if
+    /// the merged page would be outside of the page limits.
+    pub fn remove_splitpoint(&mut self, row: usize) -> Result<(), ImageSplitterError> {
+        self.data.splitpoints = self.editable_splitpoints().remove(row)?;
+        Ok(())
+    }
+    /// Moves the cut at row `from` to row `to`, which may be past other splitpoints.
+    ///
+    /// Throws an error if the splitpoint can't be removed, or a splitpoint can't be added at `to`. See
+    /// [`Stitcher::remove_splitpoint`] and [`Stitcher::add_splitpoint`].
+    pub fn move_splitpoint(&mut self, from: usize, to: usize) -> Result<(), ImageSplitterError> {
+        self.data.splitpoints = self.editable_splitpoints().relocate(from, to)?;
+        self.data.worst_cases.retain(|&cut| cut != to);
+        Ok(())
+    }
+    /// The rows which a strict stitch cut for lack of a clean row, and which haven't been moved, removed,
+    /// or added back by hand since. Exporting fails while there are any. See [`StitchConfig::strict`].
+    pub fn worst_case_cuts(&self) -> Vec<usize> {
+        self.data
+            .worst_cases
+            .iter()
+            .copied()
+            .filter(|row| self.data.splitpoints.binary_search(row).is_ok())
+            .collect()
+    }
+    /// Throws [`ImageSplitterError::WorstCaseCut`] for the first worst case cut which hasn't been edited by
+    /// hand.
+    fn check_reviewed(&self) -> Result<(), ImageSplitterError> {
+        match self.worst_case_cuts().first() {
+            Some(&row) => Err(ImageSplitterError::WorstCaseCut(row)),
+            None => Ok(()),
+        }
+    }
+    fn editable_splitpoints(&self) -> Splitpoints<'_> {
+        Splitpoints {
+            splitpoints: &self.data.splitpoints,
+            chapters: &self.data.chapters,
+            limits: self.data.limits,
+        }
+    }
+}
+
+#[cfg(feature = "title-card")]
+impl Stitcher<Stitched> {
+    /// Inserts a generated title card at the start of the output, as a page of its own.
+    ///
+    /// The card is rendered at the width of the strip, and belongs to the first chapter. Throws an error if
+    /// the strip is memory-mapped and the new temp file can't be created.
+    pub fn with_title_card(self, card: &TitleCard) -> Result<Self, ImageSplitterError> {
+        let strip = prepend_title_card(card, &self.data.strip, &self.data.temp)?;
+        let card_height = strip.height() as usize - self.data.strip.height() as usize;
+        let shift = |rows: Vec<usize>| -> Vec<usize> {
+            rows.into_iter()
+                .map(|row| if row == 0 { 0 } else { row + card_height })
+                .collect()
+        };
+        let mut splitpoints = shift(self.data.splitpoints);
+        splitpoints.insert(1, card_height);
+        Ok(Stitcher {
+            data: Stitched {
+                strip,
+                splitpoints,
+                chapters: shift(self.data.chapters),
+                direction: self.data.direction,
+                limits: self.data.limits,
+                worst_cases: shift(self.data.worst_cases),
+                temp: self.data.temp,
+                icc_profile: self.data.icc_profile,
+                stats: self.data.stats,
+                image: OnceLock::new(),
+            },
+        })
+    }
 }
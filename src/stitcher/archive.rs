@@ -0,0 +1,280 @@
+//! This module is for loading images straight out of `.cbz`/`.zip` and `.cb7`/`.7z` archives.
+//!
+//! RAR archives aren't supported, as there is no RAR decompressor to depend on (see [`load_archive`]).
+
+#[cfg(feature = "zip")]
+use std::io::BufReader;
+use std::{
+    fs::File,
+    io::{Read, Seek},
+    path::{Path, PathBuf},
+};
+
+#[cfg(feature = "zip")]
+use zip::ZipArchive;
+
+#[cfg(feature = "7z")]
+use super::seven_zip::SevenZipArchive;
+use super::{
+    image_loader::{load_streamed, sort_paths, ImageLoaderError, LoadConfig, Sort},
+    stats::Stats,
+    strip::Strip,
+};
+
+/// The signature every RAR archive starts with, which are recognized only to report them as unsupported.
+const RAR_SIGNATURE: &[u8] = b"Rar!\x1a\x07";
+/// The signature every 7z archive starts with.
+const SEVEN_ZIP_SIGNATURE: &[u8] = b"7z\xbc\xaf\x27\x1c";
+
+/// Limits on what an archive may hold, so that a malicious archive (e.g. a zip bomb submitted to a server)
+/// can't exhaust memory or keep the loader busy (defaults to 10 000 entries, 256 MiB per image, 4 GiB in
+/// total and 16 GiB of decompression work).
+///
+/// Sizes are counted as entries are decompressed, rather than trusted from the archive's own listing. Solid
+/// 7z archives are decompressed a block of entries at a time, so each block must fit within the total limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArchiveLimits {
+    pub(crate) max_entries: usize,
+    pub(crate) max_entry_size: u64,
+    pub(crate) max_total_size: u64,
+    pub(crate) max_decode_work: u64,
+}
+
+impl Default for ArchiveLimits {
+    fn default() -> Self {
+        Self {
+            max_entries: 10_000,
+            max_entry_size: 256 << 20,
+            max_total_size: 4 << 30,
+            max_decode_work: 16 << 30,
+        }
+    }
+}
+
+impl ArchiveLimits {
+    /// Creates the default limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Limits that accept any archive, for archives which are known to be trustworthy.
+    pub fn unlimited() -> Self {
+        Self {
+            max_entries: usize::MAX,
+            max_entry_size: u64::MAX,
+            max_total_size: u64::MAX,
+            max_decode_work: u64::MAX,
+        }
+    }
+    /// Sets how many entries (of any kind, not only images) the archive may have at most.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+    /// Sets how many bytes each image may take up at most once decompressed.
+    pub fn max_entry_size(mut self, max_entry_size: u64) -> Self {
+        self.max_entry_size = max_entry_size;
+        self
+    }
+    /// Sets how many bytes all the images together may take up at most once decompressed.
+    pub fn max_total_size(mut self, max_total_size: u64) -> Self {
+        self.max_total_size = max_total_size;
+        self
+    }
+    /// Sets how many bytes may be decompressed at most while the archive is read. Unlike the total size,
+    /// this counts the archive's own compressed header, and the blocks of a solid 7z archive each time they
+    /// are decompressed again (which happens when the order images are loaded in alternates between them).
+    pub fn max_decode_work(mut self, max_decode_work: u64) -> Self {
+        self.max_decode_work = max_decode_work;
+        self
+    }
+}
+
+/// An archive in one of the supported formats, which images are read out of one at a time.
+pub(crate) trait Archive {
+    /// The paths of the files in the archive (not its directories), as they are stored in it.
+    fn files(&self) -> Vec<PathBuf>;
+    /// Reads the file at `path` (one of [`Archive::files`]), stopping one byte past `limit` bytes so that
+    /// a file going over the limit can be told apart.
+    fn read(&mut self, path: &Path, limit: u64) -> Result<Vec<u8>, ImageLoaderError>;
+}
+
+/// Loads the images inside an archive into a single image strip, without extracting it to disk.
+///
+/// `.cbz`/`.zip` archives are read with the `zip` feature and `.cb7`/`.7z` archives with the `7z` feature.
+/// The format is told from the contents of the file rather than its extension, as `.cbr` files are often
+/// zip archives which have been renamed. Actual RAR archives aren't supported, and fail to load with
+/// [`ImageLoaderError::InvalidArchive`].
+///
+/// Entries of 7z archives must be stored, or compressed with LZMA or LZMA2. Other entries fail to load with
+/// [`ImageLoaderError::UnsupportedArchiveEntry`].
+///
+/// Entries are picked and ordered the same way as the files of a directory (see `find_images`), using their
+/// full path within the archive. Entries are decompressed one at a time, while the ones before them are
+/// being decoded.
+///
+/// Throws an error if:
+///  - The archive cannot be opened, is a RAR archive or of a format which isn't enabled, or is damaged.
+///  - An entry has a path which would escape the directory the archive is extracted to (such as an absolute
+///    path, or one starting with `..`), as only a malicious archive would have one.
+///  - The archive exceeds the [`ArchiveLimits`] of the config, including how much may be decompressed while
+///    reading it.
+///  - The archive does not contain any jpg, jpeg, png, or webp images.
+///  - An image is stored in a way which isn't supported (such as encrypted), or cannot be decompressed or
+///    decoded.
+pub fn load_archive(
+    path: impl AsRef<Path>,
+    config: &LoadConfig,
+    stats: &mut Stats,
+) -> Result<Strip, ImageLoaderError> {
+    let limits = config.archive_limits;
+    let mut archive = open(File::open(path)?, &limits)?;
+    let mut names: Vec<PathBuf> = archive
+        .files()
+        .into_iter()
+        // skip the resource forks macOS adds when compressing
+        .filter(|name| config.accepts(name) && !name.starts_with("__MACOSX"))
+        .collect();
+    if names.is_empty() {
+        return Err(ImageLoaderError::NoImagesInDirectory);
+    }
+    // entries aren't files on disk, so their times can't be read
+    let sort = match config.sort {
+        Sort::ModifiedTime | Sort::ExifTimestamp => Sort::Natural,
+        sort => sort,
+    };
+    sort_paths(&mut names, sort);
+
+    let mut error = None;
+    let mut total_size = 0;
+    let entries = names.iter().map_while(|name| {
+        // one byte past the limits is read, to tell whether the entry goes over them
+        let remaining = limits.max_total_size.saturating_sub(total_size);
+        let limit = limits.max_entry_size.min(remaining);
+        let entry = archive.read(name, limit).and_then(|image| {
+            let size = image.len() as u64;
+            if size > limits.max_entry_size {
+                return Err(ImageLoaderError::ArchiveEntryTooLarge {
+                    path: name.clone(),
+                    limit: limits.max_entry_size,
+                });
+            }
+            if size > remaining {
+                return Err(ImageLoaderError::ArchiveTooLarge(limits.max_total_size));
+            }
+            total_size += size;
+            Ok(image)
+        });
+        match entry {
+            Ok(image) => Some(Ok(image)),
+            Err(e) => {
+                error = Some(e);
+                None
+            }
+        }
+    });
+    let strip = load_streamed(entries, config, stats);
+    match error {
+        Some(e) => Err(e),
+        None => strip,
+    }
+}
+
+/// Opens an archive in whichever format it is.
+fn open(mut file: File, limits: &ArchiveLimits) -> Result<Box<dyn Archive>, ImageLoaderError> {
+    let mut signature = [0; 8];
+    let read = file.read(&mut signature)?;
+    file.rewind()?;
+    let signature = &signature[..read];
+    if signature.starts_with(RAR_SIGNATURE) {
+        return Err(ImageLoaderError::InvalidArchive(
+            "it is a RAR archive, which isn't supported".to_owned(),
+        ));
+    }
+    if signature.starts_with(SEVEN_ZIP_SIGNATURE) {
+        #[cfg(feature = "7z")]
+        return Ok(Box::new(SevenZipArchive::open(file, limits)?));
+        #[cfg(not(feature = "7z"))]
+        return Err(ImageLoaderError::InvalidArchive(
+            "it is a 7z archive, and the 7z feature isn't enabled".to_owned(),
+        ));
+    }
+    // zip archives are read from their end, so anything else is tried as one
+    #[cfg(feature = "zip")]
+    return Ok(Box::new(ZipReader::open(file, limits)?));
+    #[cfg(not(feature = "zip"))]
+    Err(ImageLoaderError::InvalidArchive(
+        "it isn't a 7z archive".to_owned(),
+    ))
+}
+
+/// Checks that an archive doesn't have more entries than the limits allow.
+pub(crate) fn check_entry_count(
+    count: usize,
+    limits: &ArchiveLimits,
+) -> Result<(), ImageLoaderError> {
+    if count > limits.max_entries {
+        return Err(ImageLoaderError::TooManyArchiveEntries(limits.max_entries));
+    }
+    Ok(())
+}
+
+/// Turns the path of an entry into a relative path, failing if it would escape the directory the archive
+/// is extracted to. Both `/` and `\` are treated as separators, as archives made on Windows use the latter.
+#[cfg(feature = "7z")]
+pub(crate) fn enclosed_path(name: &str) -> Result<PathBuf, ImageLoaderError> {
+    let unsafe_entry = || ImageLoaderError::UnsafeArchiveEntry(PathBuf::from(name));
+    if name.starts_with(['/', '\\']) || name.contains('\0') {
+        return Err(unsafe_entry());
+    }
+    let mut path = PathBuf::new();
+    for component in name.split(['/', '\\']) {
+        match component {
+            "" | "." => {}
+            ".." => return Err(unsafe_entry()),
+            // drive letters, such as `C:`
+            component if component.contains(':') => return Err(unsafe_entry()),
+            component => path.push(component),
+        }
+    }
+    Ok(path)
+}
+
+/// The zip archives read with the `zip` crate.
+#[cfg(feature = "zip")]
+struct ZipReader(ZipArchive<BufReader<File>>);
+
+#[cfg(feature = "zip")]
+impl ZipReader {
+    fn open(file: File, limits: &ArchiveLimits) -> Result<Self, ImageLoaderError> {
+        let mut archive = ZipArchive::new(BufReader::new(file))?;
+        check_entry_count(archive.len(), limits)?;
+        for index in 0..archive.len() {
+            let entry = archive.by_index_raw(index)?;
+            if entry.enclosed_name().is_none() {
+                return Err(ImageLoaderError::UnsafeArchiveEntry(PathBuf::from(
+                    entry.name(),
+                )));
+            }
+        }
+        Ok(Self(archive))
+    }
+}
+
+#[cfg(feature = "zip")]
+impl Archive for ZipReader {
+    fn files(&self) -> Vec<PathBuf> {
+        self.0
+            .file_names()
+            .filter(|name| !name.ends_with('/'))
+            .map(PathBuf::from)
+            .collect()
+    }
+    fn read(&mut self, path: &Path, limit: u64) -> Result<Vec<u8>, ImageLoaderError> {
+        let entry = self.0.by_name(&path.to_string_lossy())?;
+        let mut image = Vec::with_capacity(entry.size().min(limit) as usize);
+        entry
+            .take(limit.saturating_add(1))
+            .read_to_end(&mut image)?;
+        Ok(image)
+    }
+}
@@ -0,0 +1,100 @@
+//! This module detects chapter breaks within a single strip, for sources which deliver a whole volume as
+//! one enormous image with a decorative break between chapters.
+
+use rayon::{iter::ParallelIterator, slice::ParallelSlice};
+
+use super::{image_splitter::score_rows, virtual_strip::StripRows};
+
+/// How many rows are scored by each task.
+const ROWS_PER_CHUNK: usize = 256;
+
+/// What a chapter break looks like. See [`detect_chapter_breaks`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChapterBreaks {
+    min_height: usize,
+    max_interruption: usize,
+    sensitivity: u8,
+    scan_interval: usize,
+}
+
+impl Default for ChapterBreaks {
+    fn default() -> Self {
+        Self {
+            min_height: 2000,
+            max_interruption: 300,
+            sensitivity: 242,
+            scan_interval: 4,
+        }
+    }
+}
+
+impl ChapterBreaks {
+    /// Creates the default description of a chapter break.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets how many pixels tall a break must be at least (defaults to 2000).
+    ///
+    /// This should be much taller than the gutters between panels of the source.
+    pub fn min_height(mut self, min_height: usize) -> Self {
+        self.min_height = min_height;
+        self
+    }
+    /// Sets how many pixels tall the graphics (such as a logo or "To be continued") within a break may be
+    /// at most (defaults to 300).
+    pub fn max_interruption(mut self, max_interruption: usize) -> Self {
+        self.max_interruption = max_interruption;
+        self
+    }
+    /// Sets the threshold at which a row counts as blank, as with [`crate::StitchConfig::sensitivity`]
+    /// (defaults to 242).
+    pub fn sensitivity(mut self, sensitivity: u8) -> Self {
+        self.sensitivity = sensitivity;
+        self
+    }
+    /// Sets the interval at which rows are scanned (defaults to 4). An interval of 0 is treated as 1.
+    pub fn scan_interval(mut self, scan_interval: usize) -> Self {
+        self.scan_interval = scan_interval;
+        self
+    }
+}
+
+/// Finds the chapter breaks within the image, and returns the row at which each chapter starts, including
+/// the first.
+///
+/// A break is a stretch of blank rows (rows of a single color, which may differ from row to row, as with a
+/// gradient) at least `min_height` pixels tall, interrupted only by graphics no taller than
+/// `max_interruption` pixels. Breaks at the very start or end of the image don't start a chapter. Each
+/// chapter starts halfway through the break before it.
+pub fn detect_chapter_breaks(image: &impl StripRows, breaks: &ChapterBreaks) -> Vec<usize> {
+    let height = image.height() as usize;
+    let limit = u8::MAX - breaks.sensitivity;
+    let step = breaks.scan_interval.max(1);
+    let rows: Vec<usize> = (0..height).step_by(step).collect();
+    let blank: Vec<(usize, bool)> = rows
+        .par_chunks(ROWS_PER_CHUNK)
+        .flat_map_iter(|rows| score_rows(image, rows))
+        .map(|(row, score)| (row, score <= limit))
+        .collect();
+
+    // (first row, last row) of each stretch of blank rows, merging stretches which are only interrupted
+    // briefly
+    let mut stretches: Vec<(usize, usize)> = Vec::new();
+    for (row, _) in blank.into_iter().filter(|&(_, blank)| blank) {
+        match stretches.last_mut() {
+            Some((_, last)) if row - *last <= breaks.max_interruption.max(step) => *last = row,
+            _ => stretches.push((row, row)),
+        }
+    }
+
+    let mut chapters = vec![0];
+    chapters.extend(
+        stretches
+            .into_iter()
+            .filter(|&(first, last)| {
+                first > 0 && last + step < height && last - first >= breaks.min_height
+            })
+            .map(|(first, last)| (first + last) / 2),
+    );
+    chapters
+}
@@ -0,0 +1,131 @@
+//! This module generates the `ComicInfo.xml` metadata file included in CBZ archives, as read by comic
+//! readers such as Mihon, Kavita and Komga.
+
+use std::fmt::Write;
+
+/// The metadata written to `ComicInfo.xml` when exporting to a CBZ archive.
+///
+/// Every field is optional. The page list (including a bookmark at the start of each chapter) is always
+/// generated from the export itself.
+#[derive(Debug, Clone, Default)]
+pub struct ComicInfo {
+    title: Option<String>,
+    series: Option<String>,
+    number: Option<String>,
+    volume: Option<u32>,
+    writer: Option<String>,
+    summary: Option<String>,
+    language: Option<String>,
+    web: Option<String>,
+}
+
+impl ComicInfo {
+    /// Creates metadata with every field unset.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets the title of the chapter.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+    /// Sets the name of the series.
+    pub fn series(mut self, series: impl Into<String>) -> Self {
+        self.series = Some(series.into());
+        self
+    }
+    /// Sets the chapter number, which may be fractional (e.g. `"12.5"`).
+    pub fn number(mut self, number: impl Into<String>) -> Self {
+        self.number = Some(number.into());
+        self
+    }
+    /// Sets the volume number.
+    pub fn volume(mut self, volume: u32) -> Self {
+        self.volume = Some(volume);
+        self
+    }
+    /// Sets the author(s) of the story.
+    pub fn writer(mut self, writer: impl Into<String>) -> Self {
+        self.writer = Some(writer.into());
+        self
+    }
+    /// Sets a description of the chapter.
+    pub fn summary(mut self, summary: impl Into<String>) -> Self {
+        self.summary = Some(summary.into());
+        self
+    }
+    /// Sets the language of the text, as an ISO code (e.g. `"en"`).
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+    /// Sets a link to where the series can be found.
+    pub fn web(mut self, web: impl Into<String>) -> Self {
+        self.web = Some(web.into());
+        self
+    }
+}
+
+/// A page listed in `ComicInfo.xml`.
+pub(crate) struct ComicPage {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) size: usize,
+    /// The chapter the page starts, if any.
+    pub(crate) bookmark: Option<usize>,
+}
+
+/// Escapes the characters which can't appear as-is in XML text or attributes.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+impl ComicInfo {
+    /// Generates the contents of `ComicInfo.xml`.
+    pub(crate) fn to_xml(&self, pages: &[ComicPage]) -> String {
+        // writing to a string can't fail
+        let mut xml = String::from(concat!(
+            r#"<?xml version="1.0" encoding="utf-8"?>"#,
+            "\n",
+            r#"<ComicInfo xmlns:xsd="http://www.w3.org/2001/XMLSchema" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">"#,
+            "\n"
+        ));
+        let fields = [
+            ("Title", self.title.clone()),
+            ("Series", self.series.clone()),
+            ("Number", self.number.clone()),
+            ("Volume", self.volume.map(|volume| volume.to_string())),
+            ("Summary", self.summary.clone()),
+            ("Writer", self.writer.clone()),
+            ("Web", self.web.clone()),
+            ("LanguageISO", self.language.clone()),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                let _ = writeln!(xml, "  <{name}>{}</{name}>", escape(&value));
+            }
+        }
+        let _ = writeln!(xml, "  <PageCount>{}</PageCount>", pages.len());
+        xml.push_str("  <Pages>\n");
+        for (index, page) in pages.iter().enumerate() {
+            let _ = write!(
+                xml,
+                r#"    <Page Image="{index}" ImageWidth="{}" ImageHeight="{}" ImageSize="{}""#,
+                page.width, page.height, page.size
+            );
+            if index == 0 {
+                xml.push_str(r#" Type="FrontCover""#);
+            }
+            if let Some(chapter) = page.bookmark {
+                let _ = write!(xml, r#" Bookmark="Chapter {}""#, chapter + 1);
+            }
+            xml.push_str(" />\n");
+        }
+        xml.push_str("  </Pages>\n</ComicInfo>\n");
+        xml
+    }
+}
@@ -0,0 +1,86 @@
+//! This module contains the extension point for input formats which quickstitch can't decode itself.
+//!
+//! AVIF and HEIC images aren't accepted as inputs on their own, as their decoders depend on system
+//! libraries (the `avif` feature only adds AVIF output). They are only found and loaded once a decoder for
+//! them is registered.
+
+use std::{fmt, path::Path, sync::Arc};
+
+use image::{DynamicImage, GenericImageView, ImageError};
+
+/// An input format provided by a downstream crate (e.g. an AVIF decoder wrapping `dav1d`, or a HEIC decoder
+/// wrapping `libheif`).
+///
+/// Decoders are registered in a [`DecoderRegistry`], which is given to the loader with
+/// [`crate::LoadConfig::decoders`]. Files with one of the extensions of a decoder are then found alongside
+/// the built-in formats, and decoded with it.
+pub trait Decoder: Send + Sync {
+    /// The file extensions (without the leading `.`) of the images this decoder reads, e.g. `["avif"]`.
+    /// Extensions are matched regardless of case.
+    fn extensions(&self) -> &[&str];
+
+    /// Whether the given bytes hold an image this decoder reads, for images which have no file name, such as
+    /// the ones loaded from memory, streamed or read from an archive (defaults to `false`).
+    ///
+    /// AVIF and HEIC images, for instance, start with an `ftyp` box naming their brand at byte 4.
+    fn recognizes(&self, _bytes: &[u8]) -> bool {
+        false
+    }
+
+    /// Decodes an image. The EXIF orientation of the image, if it has one, should already be applied.
+    fn decode(&self, bytes: &[u8]) -> Result<DynamicImage, ImageError>;
+
+    /// Reads the dimensions of an image (defaults to decoding the whole image). Overriding this with a
+    /// decoder which only reads the header of the image makes loading faster.
+    fn dimensions(&self, bytes: &[u8]) -> Result<(u32, u32), ImageError> {
+        self.decode(bytes).map(|image| image.dimensions())
+    }
+}
+
+/// A set of custom decoders.
+#[derive(Clone, Default)]
+pub struct DecoderRegistry {
+    decoders: Vec<Arc<dyn Decoder>>,
+}
+
+impl DecoderRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Registers a decoder. It takes precedence over the decoders registered before it, and over the
+    /// built-in formats, for the extensions it lists.
+    pub fn register(&mut self, decoder: impl Decoder + 'static) {
+        self.decoders.push(Arc::new(decoder));
+    }
+    /// Looks up the decoder for the extension of the given path.
+    pub fn for_path(&self, path: &Path) -> Option<&dyn Decoder> {
+        let extension = path.extension()?.to_str()?;
+        self.decoders
+            .iter()
+            .rev()
+            .find(|decoder| {
+                decoder
+                    .extensions()
+                    .iter()
+                    .any(|known| known.eq_ignore_ascii_case(extension))
+            })
+            .map(|decoder| decoder.as_ref())
+    }
+    /// Looks up the decoder which recognizes the given bytes.
+    pub fn for_bytes(&self, bytes: &[u8]) -> Option<&dyn Decoder> {
+        self.decoders
+            .iter()
+            .rev()
+            .find(|decoder| decoder.recognizes(bytes))
+            .map(|decoder| decoder.as_ref())
+    }
+}
+
+impl fmt::Debug for DecoderRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.decoders.iter().map(|decoder| decoder.extensions()))
+            .finish()
+    }
+}
@@ -0,0 +1,127 @@
+//! This module contains the manual editing of splitpoints, for correcting cuts made in the wrong place.
+
+use super::image_splitter::ImageSplitterError;
+
+/// The heights pages must stay within when splitpoints are edited by hand (defaults to no limits).
+///
+/// Only pages which an edit creates are checked, so pages which were already out of bounds (e.g. because
+/// the stitcher couldn't find anywhere to cut) don't prevent editing elsewhere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageLimits {
+    min_height: usize,
+    max_height: usize,
+}
+
+impl Default for PageLimits {
+    fn default() -> Self {
+        Self {
+            min_height: 1,
+            max_height: usize::MAX,
+        }
+    }
+}
+
+impl PageLimits {
+    /// Creates limits which accept pages of any height.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets how many pixels tall each page must be at least (defaults to 1).
+    pub fn min_height(mut self, min_height: usize) -> Self {
+        self.min_height = min_height.max(1);
+        self
+    }
+    /// Sets how many pixels tall each page may be at most (defaults to no limit).
+    pub fn max_height(mut self, max_height: usize) -> Self {
+        self.max_height = max_height;
+        self
+    }
+    fn check(&self, height: usize) -> Result<(), ImageSplitterError> {
+        if height < self.min_height {
+            return Err(ImageSplitterError::PageTooShort {
+                height,
+                min: self.min_height,
+            });
+        }
+        if height > self.max_height {
+            return Err(ImageSplitterError::PageTooTall {
+                height,
+                max: self.max_height,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The splitpoints being edited, along with what constrains them.
+pub(crate) struct Splitpoints<'a> {
+    pub(crate) splitpoints: &'a [usize],
+    pub(crate) chapters: &'a [usize],
+    pub(crate) limits: PageLimits,
+}
+
+impl Splitpoints<'_> {
+    /// The splitpoints with a cut added at `row`.
+    pub(crate) fn add(&self, row: usize) -> Result<Vec<usize>, ImageSplitterError> {
+        let mut edited = self.splitpoints.to_vec();
+        self.insert(&mut edited, row)?;
+        self.checked(edited)
+    }
+    /// The splitpoints without the cut at `row`.
+    pub(crate) fn remove(&self, row: usize) -> Result<Vec<usize>, ImageSplitterError> {
+        let mut edited = self.splitpoints.to_vec();
+        edited.remove(self.editable(row)?);
+        self.checked(edited)
+    }
+    /// The splitpoints with the cut at `from` moved to `to`, which may be past other splitpoints.
+    pub(crate) fn relocate(
+        &self,
+        from: usize,
+        to: usize,
+    ) -> Result<Vec<usize>, ImageSplitterError> {
+        let mut edited = self.splitpoints.to_vec();
+        edited.remove(self.editable(from)?);
+        self.insert(&mut edited, to)?;
+        self.checked(edited)
+    }
+    /// Inserts a cut at `row` into `splitpoints`, if it is within the image and not already there.
+    fn insert(&self, splitpoints: &mut Vec<usize>, row: usize) -> Result<(), ImageSplitterError> {
+        let height = self.splitpoints.last().copied().unwrap_or(0);
+        if row == 0 || row >= height {
+            return Err(ImageSplitterError::SplitpointOutOfBounds(row));
+        }
+        match splitpoints.binary_search(&row) {
+            Ok(_) => Err(ImageSplitterError::SplitpointExists(row)),
+            Err(index) => {
+                splitpoints.insert(index, row);
+                Ok(())
+            }
+        }
+    }
+    /// The index of the splitpoint at `row`, if it can be removed or moved.
+    fn editable(&self, row: usize) -> Result<usize, ImageSplitterError> {
+        let height = self.splitpoints.last().copied().unwrap_or(0);
+        if row == 0 || row >= height {
+            return Err(ImageSplitterError::SplitpointOutOfBounds(row));
+        }
+        if self.chapters.contains(&row) {
+            return Err(ImageSplitterError::ChapterSplitpoint(row));
+        }
+        self.splitpoints
+            .binary_search(&row)
+            .map_err(|_| ImageSplitterError::SplitpointNotFound(row))
+    }
+    /// Checks every page of the edited splitpoints which isn't one of the original pages.
+    fn checked(&self, edited: Vec<usize>) -> Result<Vec<usize>, ImageSplitterError> {
+        for page in edited.windows(2) {
+            let original = self
+                .splitpoints
+                .binary_search(&page[0])
+                .is_ok_and(|index| self.splitpoints.get(index + 1) == Some(&page[1]));
+            if !original {
+                self.limits.check(page[1] - page[0])?;
+            }
+        }
+        Ok(edited)
+    }
+}
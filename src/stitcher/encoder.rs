@@ -0,0 +1,50 @@
+//! This module contains the extension point for output formats which quickstitch doesn't know about.
+
+use std::{collections::HashMap, fmt, io::Write, sync::Arc};
+
+use image::{ImageError, RgbImage};
+
+/// An output format provided by a downstream crate.
+///
+/// Encoders are registered under a name in an [`EncoderRegistry`], and are selected by exporting with
+/// `ImageOutputFormat::Custom(name)`.
+pub trait Encoder: Send + Sync {
+    /// The file extension (without the leading `.`) given to pages written by this encoder.
+    fn extension(&self) -> &str;
+
+    /// Encodes a single page into the writer.
+    fn encode(&self, page: &RgbImage, writer: &mut dyn Write) -> Result<(), ImageError>;
+
+    /// A rough, pessimistic ratio between the encoded size of a page and its raw RGB size, used by
+    /// the disk space preflight check.
+    fn size_factor(&self) -> f64 {
+        1.0
+    }
+}
+
+/// A set of named custom encoders.
+#[derive(Clone, Default)]
+pub struct EncoderRegistry {
+    encoders: HashMap<String, Arc<dyn Encoder>>,
+}
+
+impl EncoderRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Registers an encoder under the given name, replacing any encoder previously registered under it.
+    pub fn register(&mut self, name: impl Into<String>, encoder: impl Encoder + 'static) {
+        self.encoders.insert(name.into(), Arc::new(encoder));
+    }
+    /// Looks up the encoder registered under the given name.
+    pub fn get(&self, name: &str) -> Option<&dyn Encoder> {
+        self.encoders.get(name).map(|encoder| encoder.as_ref())
+    }
+}
+
+impl fmt::Debug for EncoderRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.encoders.keys()).finish()
+    }
+}
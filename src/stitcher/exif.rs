@@ -0,0 +1,122 @@
+//! This module reads the date an image was taken from its EXIF metadata, used to order images by
+//! [`Sort::ExifTimestamp`](super::image_loader::Sort::ExifTimestamp).
+//!
+//! EXIF metadata is a TIFF structure: a header giving the byte order and the offset of the first IFD (a
+//! table of tagged entries), where one entry points to a second IFD holding the EXIF-specific tags. Only
+//! the date tags are looked for, so nothing else is parsed.
+
+use std::path::Path;
+
+use image::{ImageDecoder, ImageReader};
+
+/// The tag of the entry pointing to the EXIF IFD.
+const EXIF_IFD: u16 = 0x8769;
+/// The date tags, from the most to the least preferred: when the picture was taken and digitized (both in
+/// the EXIF IFD), and when the file was last changed (in the first IFD).
+const DATE_TIME_ORIGINAL: u16 = 0x9003;
+const DATE_TIME_DIGITIZED: u16 = 0x9004;
+const DATE_TIME: u16 = 0x0132;
+/// The type of entries holding text.
+const ASCII: u16 = 2;
+
+/// Reads the date the image at the given path was taken, as written in its EXIF metadata
+/// (`YYYY:MM:DD HH:MM:SS`, which sorts chronologically as text), without decoding the image.
+///
+/// Returns `None` if the image can't be read or has no date.
+pub(crate) fn exif_timestamp(path: &Path) -> Option<String> {
+    let mut decoder = ImageReader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .into_decoder()
+        .ok()?;
+    date_time(&decoder.exif_metadata().ok()??)
+}
+
+/// Finds the date within EXIF metadata.
+fn date_time(exif: &[u8]) -> Option<String> {
+    // some formats keep the marker which precedes the metadata in JPEGs
+    let tiff = exif.strip_prefix(b"Exif\0\0").unwrap_or(exif);
+    let tiff = Tiff {
+        data: tiff,
+        little_endian: match tiff.get(..4)? {
+            [b'I', b'I', 42, 0] => true,
+            [b'M', b'M', 0, 42] => false,
+            _ => return None,
+        },
+    };
+    let ifd = tiff.u32(4)? as usize;
+    let exif_ifd = tiff
+        .entry(ifd, EXIF_IFD)
+        .and_then(|entry| tiff.u32(entry + 8));
+    [DATE_TIME_ORIGINAL, DATE_TIME_DIGITIZED]
+        .into_iter()
+        .filter_map(|tag| tiff.ascii(exif_ifd? as usize, tag))
+        .chain(tiff.ascii(ifd, DATE_TIME))
+        .find(|date| is_date(date))
+}
+
+/// Whether text is a date as EXIF writes them. Cameras without a clock write zeros or spaces instead.
+fn is_date(date: &str) -> bool {
+    date.len() == 19
+        && date.bytes().enumerate().all(|(index, byte)| match index {
+            4 | 7 => byte == b':',
+            10 => byte == b' ',
+            13 | 16 => byte == b':',
+            _ => byte.is_ascii_digit(),
+        })
+        && !date.starts_with("0000")
+}
+
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl Tiff<'_> {
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes = self
+            .data
+            .get(offset..offset.checked_add(2)?)?
+            .try_into()
+            .ok()?;
+        Some(match self.little_endian {
+            true => u16::from_le_bytes(bytes),
+            false => u16::from_be_bytes(bytes),
+        })
+    }
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes = self
+            .data
+            .get(offset..offset.checked_add(4)?)?
+            .try_into()
+            .ok()?;
+        Some(match self.little_endian {
+            true => u32::from_le_bytes(bytes),
+            false => u32::from_be_bytes(bytes),
+        })
+    }
+    /// Finds the offset of the entry with the given tag within the IFD at the given offset.
+    fn entry(&self, ifd: usize, tag: u16) -> Option<usize> {
+        let count = self.u16(ifd)? as usize;
+        (0..count)
+            .map(|index| ifd + 2 + index * 12)
+            .find(|&entry| self.u16(entry) == Some(tag))
+    }
+    /// Reads the text of the entry with the given tag within the IFD at the given offset.
+    fn ascii(&self, ifd: usize, tag: u16) -> Option<String> {
+        let entry = self.entry(ifd, tag)?;
+        if self.u16(entry + 2)? != ASCII {
+            return None;
+        }
+        let len = self.u32(entry + 4)? as usize;
+        // text of up to 4 bytes is held in the entry itself, and longer text wherever it points to
+        let offset = match len {
+            0..=4 => entry + 8,
+            _ => self.u32(entry + 8)? as usize,
+        };
+        let text = self.data.get(offset..offset.checked_add(len)?)?;
+        let text = text.split(|&byte| byte == 0).next()?;
+        Some(String::from_utf8_lossy(text).into_owned())
+    }
+}
@@ -0,0 +1,52 @@
+//! This module finds images by glob pattern (such as `raws/ch-12/*.png`), for platforms whose shells don't
+//! expand patterns themselves, such as Windows.
+
+use std::{collections::HashSet, io, path::PathBuf};
+
+use glob::{glob_with, MatchOptions};
+
+use super::image_loader::{sort_paths, ImageLoaderError, LoadConfig};
+
+/// Finds the images matching a glob pattern, ordered according to the `sort` option of the config.
+///
+/// Patterns follow the syntax of the [`glob`] crate. Within each component of the path:
+///  - `*` matches any run of characters, and `?` any single character.
+///  - `[abc]` matches any of the characters within it, `[a-z]` any character within the range, and `[!abc]`
+///    any character but the ones within it. Wildcards can be matched literally this way, e.g. `[*]`.
+///  - A component which is only `**` matches any number of directories, including none.
+///
+/// As in shells, wildcards don't match the leading `.` of hidden files, nor a `/`. Names are matched
+/// regardless of case on Windows. Only the files which can be loaded are kept (see `find_loadable_images`).
+///
+/// Throws an error if:
+///  - The pattern is invalid (such as a `[` without a matching `]`).
+///  - No image matches the pattern.
+///  - A directory the pattern goes through cannot be read.
+pub fn glob_images(pattern: &str, config: &LoadConfig) -> Result<Vec<PathBuf>, ImageLoaderError> {
+    let options = MatchOptions {
+        case_sensitive: !cfg!(windows),
+        require_literal_separator: true,
+        require_literal_leading_dot: true,
+    };
+    let paths =
+        glob_with(pattern, options).map_err(|error| ImageLoaderError::InvalidGlobPattern {
+            pattern: pattern.to_owned(),
+            reason: error.msg.to_owned(),
+        })?;
+
+    // `**` can reach the same file in several ways, such as with `**/**/*.png`. Duplicates are removed
+    // before sorting, as orders other than by name needn't place them next to each other.
+    let mut seen = HashSet::new();
+    let mut images = Vec::new();
+    for path in paths {
+        let path = path.map_err(io::Error::from)?;
+        if path.is_file() && config.accepts(&path) && seen.insert(path.clone()) {
+            images.push(path);
+        }
+    }
+    if images.is_empty() {
+        return Err(ImageLoaderError::NoGlobMatches(pattern.to_owned()));
+    }
+    sort_paths(&mut images, config.sort);
+    Ok(images)
+}
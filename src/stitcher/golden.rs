@@ -0,0 +1,355 @@
+//! This module contains support for round-trip tests against golden files: a synthetic chapter is run
+//! through the whole pipeline, and the pages it produces are compared to the ones recorded in a file.
+//!
+//! It is public so that downstream packagers can check a build of quickstitch the same way its own tests
+//! do (`cargo test --test golden`). Pages are compared by the hash of their decoded pixels rather than of
+//! their encoded bytes, so the results don't depend on the version of the encoders, as long as the output
+//! format is lossless.
+//!
+//! ```no_run
+//! use quickstitch::golden::{self, Synthetic};
+//! use quickstitch::{ExportConfig, ImageOutputFormat, StitchConfig};
+//!
+//! let golden = golden::run(
+//!     &Synthetic::new(),
+//!     StitchConfig::new().target_height(2000),
+//!     ImageOutputFormat::Png,
+//!     ExportConfig::new(),
+//! )?;
+//! // set QUICKSTITCH_BLESS to record the current output instead
+//! golden.check("tests/golden/default.txt")?;
+//! # Ok::<(), golden::GoldenError>(())
+//! ```
+
+use std::{
+    env,
+    fmt::{self, Display},
+    fs,
+    io::{self, Cursor},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use image::{ImageError, ImageFormat, Rgb, RgbImage};
+use thiserror::Error;
+
+use super::{
+    image_splitter::{ExportConfig, ImageOutputFormat, StitchConfig},
+    pipeline::{self, Memory, Pages, PipelineError},
+};
+
+/// The environment variable which, when set, makes [`Golden::check`] record the output instead of comparing
+/// it.
+pub const BLESS_VAR: &str = "QUICKSTITCH_BLESS";
+
+/// Errors thrown while running or checking a golden test.
+#[derive(Error, Debug)]
+pub enum GoldenError {
+    #[error("{0}")]
+    Pipeline(#[from] PipelineError),
+    #[error("A page couldn't be decoded: {0}")]
+    Decode(#[from] ImageError),
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("Line {0} of the golden file is invalid")]
+    Parse(usize),
+    #[error("The output doesn't match {path}: {difference}")]
+    Mismatch { path: PathBuf, difference: String },
+}
+
+/// A chapter of synthetic images, generated the same way on every platform.
+///
+/// The chapter is a column of noisy panels separated by plain white gutters, cut into images of equal
+/// height without regard for the panels, as the raws of a webtoon are.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Synthetic {
+    width: u32,
+    images: u32,
+    image_height: u32,
+    panel_height: u32,
+    gutter_height: u32,
+    seed: u64,
+}
+
+impl Default for Synthetic {
+    fn default() -> Self {
+        Self {
+            width: 480,
+            images: 4,
+            image_height: 1600,
+            panel_height: 700,
+            gutter_height: 90,
+            seed: 1,
+        }
+    }
+}
+
+impl Synthetic {
+    /// Creates the default chapter, of 4 images 480×1600 pixels.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets the width of the images in pixels (defaults to 480).
+    pub fn width(mut self, width: u32) -> Self {
+        self.width = width;
+        self
+    }
+    /// Sets how many images the chapter is cut into (defaults to 4).
+    pub fn images(mut self, images: u32) -> Self {
+        self.images = images;
+        self
+    }
+    /// Sets the height of each image in pixels (defaults to 1600).
+    pub fn image_height(mut self, image_height: u32) -> Self {
+        self.image_height = image_height;
+        self
+    }
+    /// Sets the height of each panel in pixels (defaults to 700).
+    pub fn panel_height(mut self, panel_height: u32) -> Self {
+        self.panel_height = panel_height;
+        self
+    }
+    /// Sets the height of the gutters between panels in pixels (defaults to 90).
+    pub fn gutter_height(mut self, gutter_height: u32) -> Self {
+        self.gutter_height = gutter_height;
+        self
+    }
+    /// Sets the seed of the noise within the panels (defaults to 1).
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+    /// Generates the images, in reading order.
+    pub fn generate(&self) -> Vec<RgbImage> {
+        let period = (self.panel_height + self.gutter_height).max(1);
+        (0..self.images)
+            .map(|index| {
+                RgbImage::from_fn(self.width, self.image_height, |x, y| {
+                    let row = index * self.image_height + y;
+                    if row % period >= self.panel_height {
+                        return Rgb([255, 255, 255]);
+                    }
+                    // blocks of 4×4 pixels, so the panels look more like line art than static
+                    let [r, g, b, ..] =
+                        mix(self.seed, (row / 4) as u64, (x / 4) as u64).to_le_bytes();
+                    Rgb([r, g, b])
+                })
+            })
+            .collect()
+    }
+    /// Generates the images and encodes them as PNG, ready to be loaded.
+    pub fn encode(&self) -> Result<Vec<Vec<u8>>, ImageError> {
+        self.generate()
+            .into_iter()
+            .map(|image| {
+                let mut bytes = Vec::new();
+                image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)?;
+                Ok(bytes)
+            })
+            .collect()
+    }
+}
+
+/// Hashes a position into pseudo-random bits (SplitMix64), which are the same on every platform.
+fn mix(seed: u64, row: u64, column: u64) -> u64 {
+    let mut z = seed
+        .wrapping_add(row.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+        .wrapping_add(column.wrapping_mul(0xbf58_476d_1ce4_e5b9));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Hashes bytes with 64-bit FNV-1a, which (unlike the hasher of the standard library) is stable across
+/// builds.
+fn stable_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// A single page of a golden run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GoldenPage {
+    /// The file name the page would have been written to.
+    pub name: String,
+    /// The width and height of the page in pixels.
+    pub dimensions: (u32, u32),
+    /// The hash of the decoded pixels of the page.
+    pub hash: u64,
+}
+
+/// The outcome of a run, as recorded in a golden file.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Golden {
+    /// The row at which each page starts, followed by the height of the strip.
+    pub splitpoints: Vec<usize>,
+    /// Every page, in reading order.
+    pub pages: Vec<GoldenPage>,
+}
+
+/// Runs the synthetic chapter through the whole pipeline with the given options, and records the pages
+/// which would have been written.
+///
+/// The splitpoints are worked out from the heights of the pages, so they are only meaningful if the export
+/// config keeps pages the same height as they were cut (i.e. for the default reading direction, without a
+/// page width).
+pub fn run(
+    synthetic: &Synthetic,
+    stitch_config: StitchConfig,
+    output_filetype: ImageOutputFormat,
+    export_config: ExportConfig,
+) -> Result<Golden, GoldenError> {
+    let mut encoded: Vec<(String, Vec<u8>)> = Vec::new();
+    pipeline::builder()
+        .source(Memory(synthetic.encode()?))
+        .detector(stitch_config)
+        .format(output_filetype)
+        .export_config(export_config)
+        .sink(Pages(&mut encoded))
+        .build()?
+        .run()?;
+    let mut golden = Golden {
+        splitpoints: vec![0],
+        pages: Vec::with_capacity(encoded.len()),
+    };
+    for (name, bytes) in encoded {
+        let page = image::load_from_memory(&bytes)?.into_rgb8();
+        let mut contents = Vec::with_capacity(page.len() + 8);
+        contents.extend(page.width().to_le_bytes());
+        contents.extend(page.height().to_le_bytes());
+        contents.extend(page.as_raw());
+        let previous = golden.splitpoints.last().copied().unwrap_or(0);
+        golden.splitpoints.push(previous + page.height() as usize);
+        golden.pages.push(GoldenPage {
+            name,
+            dimensions: page.dimensions(),
+            hash: stable_hash(&contents),
+        });
+    }
+    Ok(golden)
+}
+
+impl Golden {
+    /// Compares the run to the golden file at the given path.
+    ///
+    /// If the [`BLESS_VAR`] environment variable is set, the run is written to the file instead, which is
+    /// how golden files are created and updated.
+    ///
+    /// Throws [`GoldenError::Mismatch`] describing the first difference if the run doesn't match.
+    pub fn check(&self, path: impl AsRef<Path>) -> Result<(), GoldenError> {
+        let path = path.as_ref();
+        if env::var_os(BLESS_VAR).is_some() {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, self.to_string())?;
+            return Ok(());
+        }
+        let expected: Golden = fs::read_to_string(path)?.parse()?;
+        match expected.difference(self) {
+            Some(difference) => Err(GoldenError::Mismatch {
+                path: path.to_path_buf(),
+                difference,
+            }),
+            None => Ok(()),
+        }
+    }
+    /// Describes the first difference between the expected run (`self`) and the actual one, if there is one.
+    pub fn difference(&self, actual: &Golden) -> Option<String> {
+        if self.splitpoints != actual.splitpoints {
+            return Some(format!(
+                "expected splitpoints {:?}, found {:?}",
+                self.splitpoints, actual.splitpoints
+            ));
+        }
+        if self.pages.len() != actual.pages.len() {
+            return Some(format!(
+                "expected {} pages, found {}",
+                self.pages.len(),
+                actual.pages.len()
+            ));
+        }
+        self.pages
+            .iter()
+            .zip(&actual.pages)
+            .find(|(expected, actual)| expected != actual)
+            .map(|(expected, actual)| {
+                format!(
+                    "expected page {}, found {}",
+                    PageLine(expected),
+                    PageLine(actual)
+                )
+            })
+    }
+}
+
+/// Formats a page the way it is written in a golden file, without the leading keyword.
+struct PageLine<'a>(&'a GoldenPage);
+
+impl Display for PageLine<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let GoldenPage {
+            name,
+            dimensions: (width, height),
+            hash,
+        } = self.0;
+        write!(f, "{name} {width}x{height} {hash:016x}")
+    }
+}
+
+/// Writes the golden file format: a comment, a line listing the splitpoints, and a line per page with its
+/// name, dimensions and hash.
+impl Display for Golden {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# quickstitch golden file, written with {BLESS_VAR} set")?;
+        write!(f, "splitpoints")?;
+        for splitpoint in &self.splitpoints {
+            write!(f, " {splitpoint}")?;
+        }
+        writeln!(f)?;
+        for page in &self.pages {
+            writeln!(f, "page {}", PageLine(page))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Golden {
+    type Err = GoldenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut golden = Golden::default();
+        for (index, line) in s.lines().enumerate() {
+            let invalid = || GoldenError::Parse(index + 1);
+            let mut words = line.split_whitespace();
+            match words.next() {
+                None => {}
+                Some(word) if word.starts_with('#') => {}
+                Some("splitpoints") => {
+                    golden.splitpoints = words
+                        .map(|word| word.parse().map_err(|_| invalid()))
+                        .collect::<Result<_, _>>()?;
+                }
+                Some("page") => {
+                    let (Some(name), Some(dimensions), Some(hash), None) =
+                        (words.next(), words.next(), words.next(), words.next())
+                    else {
+                        return Err(invalid());
+                    };
+                    let (width, height) = dimensions.split_once('x').ok_or_else(invalid)?;
+                    golden.pages.push(GoldenPage {
+                        name: name.to_string(),
+                        dimensions: (
+                            width.parse().map_err(|_| invalid())?,
+                            height.parse().map_err(|_| invalid())?,
+                        ),
+                        hash: u64::from_str_radix(hash, 16).map_err(|_| invalid())?,
+                    });
+                }
+                Some(_) => return Err(invalid()),
+            }
+        }
+        Ok(golden)
+    }
+}
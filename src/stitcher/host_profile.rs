@@ -0,0 +1,184 @@
+//! This module contains the export constraints of publishing platforms, so pages can be made to fit a
+//! platform without looking its limits up by hand.
+
+use super::image_splitter::{ExportConfig, ImageOutputFormat, StitchConfig, WidthPolicy};
+
+/// The constraints a platform puts on the images uploaded to it.
+///
+/// Profiles are plain data: the built-in ones are listed in [`HostProfiles::built_in`], and new ones can be
+/// made with the setters and added to a [`HostProfiles`] registry.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HostProfile {
+    name: String,
+    page_width: Option<u32>,
+    max_page_height: Option<usize>,
+    max_page_bytes: Option<u64>,
+    formats: Vec<ImageOutputFormat>,
+}
+
+/// A built-in profile, as it is written down in [`BUILT_IN`].
+struct BuiltIn {
+    name: &'static str,
+    page_width: Option<u32>,
+    max_page_height: Option<usize>,
+    max_page_bytes: Option<u64>,
+    formats: &'static [ImageOutputFormat],
+}
+
+/// The built-in profiles, with the limits published by each platform when they were added. Platforms change
+/// their limits from time to time, so add a profile of the same name to override one which is out of date.
+const BUILT_IN: &[BuiltIn] = &[
+    BuiltIn {
+        name: "webtoon-canvas",
+        page_width: Some(800),
+        max_page_height: Some(1280),
+        max_page_bytes: Some(2_000_000),
+        formats: &[ImageOutputFormat::Jpg(90), ImageOutputFormat::Png],
+    },
+    BuiltIn {
+        name: "discord",
+        page_width: None,
+        max_page_height: None,
+        max_page_bytes: Some(10_000_000),
+        formats: &[
+            ImageOutputFormat::Webp,
+            ImageOutputFormat::Jpg(90),
+            ImageOutputFormat::Png,
+        ],
+    },
+    BuiltIn {
+        name: "imgur",
+        page_width: None,
+        max_page_height: None,
+        max_page_bytes: Some(20_000_000),
+        formats: &[ImageOutputFormat::Png, ImageOutputFormat::Jpg(90)],
+    },
+];
+
+impl HostProfile {
+    /// Creates a profile with no constraints, which accepts PNG.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            page_width: None,
+            max_page_height: None,
+            max_page_bytes: None,
+            formats: vec![ImageOutputFormat::Png],
+        }
+    }
+    /// Sets the width every page must have (defaults to `None`, i.e. pages keep the width of the strip).
+    pub fn page_width(mut self, page_width: Option<u32>) -> Self {
+        self.page_width = page_width;
+        self
+    }
+    /// Sets how many pixels tall each page may be at most, once scaled to the page width (defaults to
+    /// `None`, i.e. no limit).
+    pub fn max_page_height(mut self, max_page_height: Option<usize>) -> Self {
+        self.max_page_height = max_page_height;
+        self
+    }
+    /// Sets how many bytes each page may take up at most (defaults to `None`, i.e. no limit).
+    pub fn max_page_bytes(mut self, max_page_bytes: Option<u64>) -> Self {
+        self.max_page_bytes = max_page_bytes;
+        self
+    }
+    /// Sets the formats the platform accepts, in order of preference (defaults to PNG only). An empty list
+    /// is treated as PNG only.
+    pub fn formats(mut self, formats: Vec<ImageOutputFormat>) -> Self {
+        self.formats = formats;
+        self
+    }
+    /// The name the profile is looked up by.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// How many bytes each page may take up at most, if it is limited.
+    pub fn page_bytes(&self) -> Option<u64> {
+        self.max_page_bytes
+    }
+    /// The preferred format of the platform, which pages should be exported in.
+    pub fn format(&self) -> ImageOutputFormat {
+        self.formats
+            .first()
+            .cloned()
+            .unwrap_or(ImageOutputFormat::Png)
+    }
+    /// The given stitch config with its target height lowered, if needed, so that pages cut from a strip
+    /// `strip_width` pixels wide fit the max page height once scaled to the page width.
+    pub fn stitch_config(&self, config: &StitchConfig, strip_width: u32) -> StitchConfig {
+        let config = config.clone();
+        let Some(max_page_height) = self.max_page_height else {
+            return config;
+        };
+        // the most rows of the strip a page can take before it is scaled to the page width
+        let max_rows = match self.page_width {
+            Some(page_width) => {
+                max_page_height as u64 * strip_width as u64 / page_width.max(1) as u64
+            }
+            None => max_page_height as u64,
+        }
+        .max(1) as usize;
+        let target_height = config.target_height.min(max_rows);
+        config.target_height(target_height)
+    }
+    /// The given export config with the page width of the platform, and the other formats it accepts as
+    /// fallbacks.
+    pub fn export_config(&self, config: &ExportConfig) -> ExportConfig {
+        let mut config = config
+            .clone()
+            .fallbacks(self.formats.iter().skip(1).cloned().collect());
+        if let Some(page_width) = self.page_width {
+            config = config.uniform_width(Some(page_width), WidthPolicy::Resize);
+        }
+        config
+    }
+}
+
+/// A set of named host profiles, starting with the built-in ones.
+#[derive(Debug, Clone)]
+pub struct HostProfiles {
+    profiles: Vec<HostProfile>,
+}
+
+impl Default for HostProfiles {
+    fn default() -> Self {
+        Self::built_in()
+    }
+}
+
+impl HostProfiles {
+    /// Creates a registry holding the built-in profiles: `webtoon-canvas`, `discord` and `imgur`.
+    pub fn built_in() -> Self {
+        let profiles = BUILT_IN
+            .iter()
+            .map(|built_in| {
+                HostProfile::new(built_in.name)
+                    .page_width(built_in.page_width)
+                    .max_page_height(built_in.max_page_height)
+                    .max_page_bytes(built_in.max_page_bytes)
+                    .formats(built_in.formats.to_vec())
+            })
+            .collect();
+        Self { profiles }
+    }
+    /// Creates an empty registry.
+    pub fn empty() -> Self {
+        Self {
+            profiles: Vec::new(),
+        }
+    }
+    /// Adds a profile, replacing any profile of the same name.
+    pub fn add(&mut self, profile: HostProfile) {
+        self.profiles
+            .retain(|existing| existing.name != profile.name);
+        self.profiles.push(profile);
+    }
+    /// Looks up the profile with the given name.
+    pub fn get(&self, name: &str) -> Option<&HostProfile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+    /// Every profile, in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = &HostProfile> {
+        self.profiles.iter()
+    }
+}
@@ -1,14 +1,41 @@
 //! This module is for all methods involved in getting selected images loaded into memory.
 
 use image::{
-    error::ImageError, image_dimensions, imageops::FilterType::Lanczos3, GenericImage, ImageReader,
+    codecs::{png::PngDecoder, webp::WebPDecoder},
+    error::ImageError,
+    imageops::{self, FilterType},
+    metadata::Orientation,
+    DynamicImage, GenericImage, ImageDecoder, ImageFormat, ImageReader, ImageResult, Limits, Rgb,
     RgbImage,
 };
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+#[cfg(any(feature = "zip", feature = "7z"))]
+use super::archive::ArchiveLimits;
+use super::{
+    decoder::{Decoder, DecoderRegistry},
+    exif::exif_timestamp,
+    progress::{Progress, ProgressHook, Tally},
+    sharpen::Sharpen,
+    stats::{Duplicate, FileStats, SkippedImage, Stats},
+    strip::{new_strip, reshape_strip, Strip},
+    temp::TempPolicy,
+    throttle::{throttled, with_threads, Priority, Throttle},
+    virtual_strip::VirtualStrip,
+};
+use itertools::Itertools;
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelBridge,
+    ParallelIterator,
+};
 use std::{
-    fs::read_dir,
-    io,
+    collections::HashMap,
+    fs::{self, read_dir},
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{self, BufRead, Cursor, Seek},
     path::{Path, PathBuf},
+    sync::{mpsc::sync_channel, Arc},
+    thread,
+    time::{Duration, Instant, SystemTime},
 };
 use thiserror::Error;
 
@@ -24,14 +51,55 @@ pub enum ImageLoaderError {
     // Logical Errors
     #[error("No images were found in the selected directory")]
     NoImagesInDirectory,
+    #[error("No images match the pattern {0}")]
+    NoGlobMatches(String),
+    #[error("The pattern {pattern} is invalid, as {reason}")]
+    InvalidGlobPattern { pattern: String, reason: String },
+    #[error("The order listed in {path:?} can't be used, as {reason}")]
+    InvalidOrder { path: PathBuf, reason: String },
     #[error("Expected a directory")]
     ExpectedDirectory,
+    #[error("No chapters were provided")]
+    NoChapters,
+    #[error("{0:?} was modified or removed while images were being loaded")]
+    SourceChangedDuringRun(PathBuf),
+    #[error("The images have no pixels to stitch")]
+    EmptyImage,
+    #[error("The combined image would be {0} pixels tall, which is more than can be stitched")]
+    StripTooTall(u64),
+    #[error("{0:?} would be extracted outside of the archive")]
+    UnsafeArchiveEntry(PathBuf),
+    #[error("The archive has more than {0} entries")]
+    TooManyArchiveEntries(usize),
+    #[error("{path:?} is more than {limit} bytes once decompressed")]
+    ArchiveEntryTooLarge { path: PathBuf, limit: u64 },
+    #[error("The images in the archive are more than {0} bytes once decompressed")]
+    ArchiveTooLarge(u64),
+    #[error("Reading the archive would take decompressing more than {0} bytes")]
+    ArchiveDecodeWorkTooLarge(u64),
+    #[error("The archive can't be read, as {0}")]
+    InvalidArchive(String),
+    #[error("{path:?} is stored in the archive in a way which isn't supported ({reason})")]
+    UnsupportedArchiveEntry { path: PathBuf, reason: String },
+    #[error("{} is animated", describe_source(*.index, .path.as_deref()))]
+    AnimatedImage { index: usize, path: Option<PathBuf> },
+    #[error("The file is not a valid PDF")]
+    InvalidPdf,
+    #[error("The PDF does not contain any images")]
+    NoImagesInPdf,
+    #[error(
+        "An image on page {page} of the PDF is stored in a way which isn't supported ({reason})"
+    )]
+    UnsupportedPdfImage { page: usize, reason: String },
 
     // upstream errors
     #[error("{0}")]
     ImageError(ImageError),
     #[error("{0}")]
     IoError(io::Error),
+    #[cfg(feature = "zip")]
+    #[error("{0}")]
+    ZipError(zip::result::ZipError),
 }
 
 impl From<ImageError> for ImageLoaderError {
@@ -40,6 +108,16 @@ impl From<ImageError> for ImageLoaderError {
     }
 }
 
+#[cfg(feature = "zip")]
+impl From<zip::result::ZipError> for ImageLoaderError {
+    fn from(value: zip::result::ZipError) -> Self {
+        match value {
+            zip::result::ZipError::Io(e) => e.into(),
+            e => Self::ZipError(e),
+        }
+    }
+}
+
 impl From<io::Error> for ImageLoaderError {
     fn from(value: io::Error) -> Self {
         use io::ErrorKind as Kind;
@@ -72,16 +150,623 @@ pub enum Sort {
         )
     )]
     Natural,
+    /// Orders files from the least to the most recently modified, such as in the order they were
+    /// downloaded.
+    ///
+    /// Images within archives are ordered by [`Sort::Natural`] instead.
+    #[cfg_attr(feature = "cli", clap(alias = "m"))]
+    #[cfg_attr(
+        feature = "cli",
+        clap(help = "Sorts files from the least to the most recently modified.")
+    )]
+    ModifiedTime,
+    /// Orders images by the date they were taken, as written in their EXIF metadata. Images without a date
+    /// come last, ordered by [`Sort::Natural`].
+    ///
+    /// Images within archives are ordered by [`Sort::Natural`] instead.
+    #[cfg_attr(feature = "cli", clap(alias = "e"))]
+    #[cfg_attr(
+        feature = "cli",
+        clap(
+            help = "Sorts images by the date they were taken, read from their EXIF metadata. Images without one come last."
+        )
+    )]
+    ExifTimestamp,
+}
+
+/// How the width images are resized to is chosen from the widths of the images.
+///
+/// Images are resized to a single width so that they can be stacked, which by default is the width of the
+/// narrowest one. A single narrow image (such as one which was accidentally cropped) then downscales the
+/// whole chapter, which the other strategies avoid.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum WidthStrategy {
+    /// The width of the narrowest image, so that no image is upscaled.
+    #[default]
+    Min,
+    /// The width of the widest image, so that no image is downscaled.
+    Max,
+    /// The median width of the images (the narrower of the two middle widths, for an even number of
+    /// images).
+    Median,
+    /// The width the most images have (the narrowest of them, if several are as common).
+    MostCommon,
+    /// The given width, whatever the widths of the images.
+    Fixed(u32),
+}
+
+impl WidthStrategy {
+    /// The width to resize images of the given widths to, or 0 if there are none.
+    pub(crate) fn choose(self, widths: impl IntoIterator<Item = u32>) -> u32 {
+        let mut widths: Vec<u32> = widths.into_iter().collect();
+        widths.sort_unstable();
+        match self {
+            WidthStrategy::Min => widths.first().copied().unwrap_or(0),
+            WidthStrategy::Max => widths.last().copied().unwrap_or(0),
+            WidthStrategy::Median => match widths.len() {
+                0 => 0,
+                len => widths[(len - 1) / 2],
+            },
+            // the widths are sorted, so equal widths are next to each other, and the first of the longest
+            // runs is the narrowest
+            WidthStrategy::MostCommon => widths
+                .chunk_by(|a, b| a == b)
+                .rev()
+                .max_by_key(|run| run.len())
+                .map_or(0, |run| run[0]),
+            WidthStrategy::Fixed(width) => width,
+        }
+    }
+}
+
+/// What is done with images narrower than the width of the strip, which would otherwise be upscaled and
+/// look blurry.
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum UpscalePolicy {
+    #[cfg_attr(
+        feature = "cli",
+        clap(help = "Upscales narrower images to the width of the strip.")
+    )]
+    #[default]
+    Allow,
+    #[cfg_attr(
+        feature = "cli",
+        clap(help = "Centres narrower images on a band of the gap colour as wide as the strip.")
+    )]
+    Pad,
+    #[cfg_attr(
+        feature = "cli",
+        clap(help = "Lowers the width of the strip to that of the narrowest image.")
+    )]
+    Shrink,
+}
+
+/// The filter images are resized with, when they don't already have the width of the strip.
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ResizeFilter {
+    #[cfg_attr(
+        feature = "cli",
+        clap(help = "Takes the nearest pixel, keeping hard edges, such as those of pixel art.")
+    )]
+    Nearest,
+    #[cfg_attr(
+        feature = "cli",
+        clap(help = "Blends the nearest pixels linearly, which is fast but soft.")
+    )]
+    Triangle,
+    #[cfg_attr(
+        feature = "cli",
+        clap(help = "A cubic filter, sharper than triangle with less ringing than Lanczos.")
+    )]
+    CatmullRom,
+    #[cfg_attr(
+        feature = "cli",
+        clap(help = "The sharpest filter, which can ring around hard edges such as line art.")
+    )]
+    #[default]
+    Lanczos3,
+}
+
+impl ResizeFilter {
+    fn filter_type(self) -> FilterType {
+        match self {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Triangle => FilterType::Triangle,
+            ResizeFilter::CatmullRom => FilterType::CatmullRom,
+            ResizeFilter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+/// The direction images are placed in, one after the other.
+///
+/// Horizontal strips are turned 90° clockwise as they are loaded, so that the columns of the spread become
+/// the rows of the strip: the left edge of the first image is the top of the strip. Everything which works
+/// on rows (stitching, previews, splitpoints, and `page_width`, which becomes the height of each page)
+/// therefore works on columns instead, and pages are turned back when they are exported.
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Direction {
+    #[cfg_attr(feature = "cli", clap(alias = "v"))]
+    #[cfg_attr(feature = "cli", clap(help = "Stacks images top to bottom."))]
+    #[default]
+    Vertical,
+    #[cfg_attr(feature = "cli", clap(alias = "h"))]
+    #[cfg_attr(
+        feature = "cli",
+        clap(help = "Places images left to right, for raws delivered as slices of a wide spread.")
+    )]
+    Horizontal,
+}
+
+impl Direction {
+    /// The dimensions an image of the given dimensions has once turned into a strip.
+    pub(crate) fn turn_dimensions(self, (width, height): (u32, u32)) -> (u32, u32) {
+        match self {
+            Direction::Vertical => (width, height),
+            Direction::Horizontal => (height, width),
+        }
+    }
+    /// Turns an image so that it can be stacked into a strip.
+    fn turn(self, image: DynamicImage) -> DynamicImage {
+        match self {
+            Direction::Vertical => image,
+            Direction::Horizontal => image.rotate90(),
+        }
+    }
+    /// Turns a page cut out of a strip back the way its images were.
+    pub(crate) fn unturn(self, page: RgbImage) -> RgbImage {
+        match self {
+            Direction::Vertical => page,
+            Direction::Horizontal => imageops::rotate270(&page),
+        }
+    }
+}
+
+/// What is done with animated images (APNG or animated WebP), which can't be stitched as a whole.
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AnimatedPolicy {
+    #[cfg_attr(
+        feature = "cli",
+        clap(help = "Loads the first frame (or the default image of an APNG) of animated images.")
+    )]
+    #[default]
+    FirstFrame,
+    #[cfg_attr(
+        feature = "cli",
+        clap(help = "Leaves animated images out of the strip.")
+    )]
+    Skip,
+    #[cfg_attr(
+        feature = "cli",
+        clap(help = "Fails to load if any image is animated.")
+    )]
+    Error,
+}
+
+/// How the transparent parts of images are flattened as they are loaded, since the strip has no alpha
+/// channel.
+///
+/// Images are composited over a solid background, blending partly transparent pixels into it. Images
+/// without an alpha channel are left as they are.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AlphaPolicy {
+    /// Composites images over white, the background of most pages.
+    #[default]
+    White,
+    /// Composites images over black.
+    Black,
+    /// Composites images over the given colour.
+    Color(Rgb<u8>),
+}
+
+impl AlphaPolicy {
+    /// The colour images are composited over.
+    fn background(self) -> Rgb<u8> {
+        match self {
+            AlphaPolicy::White => Rgb([255, 255, 255]),
+            AlphaPolicy::Black => Rgb([0, 0, 0]),
+            AlphaPolicy::Color(color) => color,
+        }
+    }
+    /// Composites an image over the background, if it has an alpha channel.
+    fn flatten(self, image: DynamicImage) -> DynamicImage {
+        if !image.color().has_alpha() {
+            return image;
+        }
+        let Rgb(background) = self.background();
+        let mut image = image.into_rgba8();
+        for pixel in image.pixels_mut() {
+            let alpha = pixel[3] as u32;
+            for (channel, &background) in pixel.0[..3].iter_mut().zip(&background) {
+                *channel = ((*channel as u32 * alpha + background as u32 * (255 - alpha) + 127)
+                    / 255) as u8;
+            }
+            pixel[3] = 255;
+        }
+        DynamicImage::ImageRgba8(image).into_rgb8().into()
+    }
+}
+
+/// Options which control how images are loaded into a strip.
+#[derive(Debug, Clone)]
+pub struct LoadConfig {
+    pub(crate) width: WidthStrategy,
+    pub(crate) resize_filter: ResizeFilter,
+    pub(crate) upscale: UpscalePolicy,
+    pub(crate) sharpen: Option<Sharpen>,
+    pub(crate) trim_margins: Option<u8>,
+    pub(crate) ignore_unloadable: bool,
+    pub(crate) strict: bool,
+    pub(crate) sort: Sort,
+    pub(crate) spill_threshold: Option<u64>,
+    pub(crate) temp: TempPolicy,
+    pub(crate) rescan_on_change: bool,
+    pub(crate) auto_rotate: bool,
+    pub(crate) exif_orientation: bool,
+    pub(crate) alpha: AlphaPolicy,
+    pub(crate) dither: bool,
+    pub(crate) animated: AnimatedPolicy,
+    pub(crate) skip_duplicates: bool,
+    pub(crate) decoders: DecoderRegistry,
+    pub(crate) detect_stitched: bool,
+    pub(crate) direction: Direction,
+    pub(crate) inter_image_gap: u32,
+    pub(crate) gap_color: Rgb<u8>,
+    pub(crate) queue_depth: usize,
+    pub(crate) throttle: Option<(Throttle, Priority)>,
+    pub(crate) io_throttle: Option<(Throttle, Priority)>,
+    pub(crate) threads: Option<usize>,
+    pub(crate) progress: Option<ProgressHook>,
+    #[cfg(any(feature = "zip", feature = "7z"))]
+    pub(crate) archive_limits: ArchiveLimits,
+}
+
+impl Default for LoadConfig {
+    fn default() -> Self {
+        Self {
+            width: WidthStrategy::Min,
+            resize_filter: ResizeFilter::default(),
+            upscale: UpscalePolicy::default(),
+            sharpen: None,
+            trim_margins: None,
+            ignore_unloadable: false,
+            strict: false,
+            sort: Sort::Natural,
+            spill_threshold: None,
+            temp: TempPolicy::default(),
+            rescan_on_change: false,
+            auto_rotate: false,
+            exif_orientation: true,
+            alpha: AlphaPolicy::default(),
+            dither: true,
+            animated: AnimatedPolicy::default(),
+            skip_duplicates: false,
+            decoders: DecoderRegistry::default(),
+            detect_stitched: false,
+            direction: Direction::Vertical,
+            inter_image_gap: 0,
+            gap_color: Rgb([255, 255, 255]),
+            queue_depth: rayon::current_num_threads() * 2,
+            throttle: None,
+            io_throttle: None,
+            threads: None,
+            progress: None,
+            #[cfg(any(feature = "zip", feature = "7z"))]
+            archive_limits: ArchiveLimits::default(),
+        }
+    }
+}
+
+impl LoadConfig {
+    /// Creates a config with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets the width that the final stitched images will have (defaults to the width of the narrowest image).
+    ///
+    /// This is a shorthand for [`WidthStrategy::Fixed`] or [`WidthStrategy::Min`]; see `width_strategy`
+    /// for the other ways of choosing it.
+    pub fn width(mut self, width: Option<u32>) -> Self {
+        self.width = match width {
+            Some(width) => WidthStrategy::Fixed(width),
+            None => WidthStrategy::Min,
+        };
+        self
+    }
+    /// Sets how the width that the final stitched images will have is chosen from the widths of the images
+    /// (defaults to [`WidthStrategy::Min`]).
+    pub fn width_strategy(mut self, width_strategy: WidthStrategy) -> Self {
+        self.width = width_strategy;
+        self
+    }
+    /// Sets the filter images are resized to the width of the strip with (defaults to
+    /// [`ResizeFilter::Lanczos3`]).
+    ///
+    /// Pixel-art style raws keep their hard edges with [`ResizeFilter::Nearest`], and line art can show less
+    /// ringing with [`ResizeFilter::CatmullRom`] or [`ResizeFilter::Triangle`].
+    pub fn resize_filter(mut self, resize_filter: ResizeFilter) -> Self {
+        self.resize_filter = resize_filter;
+        self
+    }
+    /// Sets what is done with images narrower than the width of the strip (defaults to
+    /// [`UpscalePolicy::Allow`]).
+    ///
+    /// With [`UpscalePolicy::Pad`], narrower images are centred on the colour of `gap_color`.
+    pub fn upscale(mut self, upscale: UpscalePolicy) -> Self {
+        self.upscale = upscale;
+        self
+    }
+    /// Sets the unsharp mask applied to images after they are resized, to restore the screentones and
+    /// lettering which downscaling softens (defaults to none). See [`Sharpen`].
+    pub fn sharpen(mut self, sharpen: Option<Sharpen>) -> Self {
+        self.sharpen = sharpen;
+        self
+    }
+    /// Sets whether white or black margins at the left and right of the strip are cropped, for tighter pages
+    /// (defaults to `None`, i.e. never).
+    ///
+    /// A column is part of a margin if every pixel in it, in every image, is within the given tolerance of
+    /// white (or of black) in each channel. Margins are cropped after the images are resized, so the strip
+    /// ends up narrower than the width it was loaded with. Nothing is cropped if the images are blank.
+    pub fn trim_margins(mut self, tolerance: Option<u8>) -> Self {
+        self.trim_margins = tolerance;
+        self
+    }
+    /// The width to resize images of the given widths to, chosen by the width strategy and lowered to the
+    /// narrowest of them if upscaling is avoided that way.
+    pub(crate) fn choose_width(&self, widths: impl IntoIterator<Item = u32>) -> u32 {
+        let widths: Vec<u32> = widths.into_iter().collect();
+        let width = self.width.choose(widths.iter().copied());
+        match self.upscale {
+            UpscalePolicy::Shrink => widths.into_iter().min().map_or(width, |min| width.min(min)),
+            UpscalePolicy::Allow | UpscalePolicy::Pad => width,
+        }
+    }
+    /// Applies the unsharp mask of the config, if it has one, to an image which was resized.
+    pub(crate) fn sharpened(&self, image: RgbImage) -> RgbImage {
+        match &self.sharpen {
+            Some(sharpen) => sharpen.apply(image),
+            None => image,
+        }
+    }
+    /// Sets whether images which fail to load are skipped rather than causing an error (defaults to `false`).
+    ///
+    /// Sometimes, there is an issue where the same page exists twice, except one of them is completely empty.
+    /// For cases like this, this setting exists to allow you to only load images that are able to be loaded.
+    ///
+    /// The skipped images are listed, along with why they couldn't be loaded, in [`Stats::skipped`] (see
+    /// also `Stitcher::load_report`).
+    pub fn ignore_unloadable(mut self, ignore_unloadable: bool) -> Self {
+        self.ignore_unloadable = ignore_unloadable;
+        self
+    }
+    /// Sets whether loading fails rather than silently working around a problem (defaults to `false`).
+    ///
+    /// Images which fail to load are always errors in strict mode, even if `ignore_unloadable` is set.
+    /// See also [`crate::StitchConfig::strict`] and [`crate::ExportConfig::strict`].
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+    /// Whether images which fail to load are skipped.
+    pub(crate) fn skips_unloadable(&self) -> bool {
+        self.ignore_unloadable && !self.strict
+    }
+    /// Sets how images found in a directory are ordered (defaults to [`Sort::Natural`]).
+    pub fn sort(mut self, sort: Sort) -> Self {
+        self.sort = sort;
+        self
+    }
+    /// Sets the size (in bytes) above which the strip is backed by a memory-mapped temp file instead of
+    /// heap memory (defaults to `None`, i.e. never).
+    ///
+    /// This lets enormous chapters complete (slowly) instead of running out of memory.
+    pub fn spill_threshold(mut self, spill_threshold: Option<u64>) -> Self {
+        self.spill_threshold = spill_threshold;
+        self
+    }
+    /// Sets the directory spill files are created in (defaults to the system temp directory).
+    ///
+    /// This is a shorthand for setting the directory of the [`TempPolicy`].
+    pub fn temp_directory(mut self, temp_directory: impl Into<PathBuf>) -> Self {
+        self.temp = self.temp.directory(temp_directory);
+        self
+    }
+    /// Sets where spill files are created, and how large they may be (defaults to
+    /// [`TempPolicy::default`]).
+    ///
+    /// The policy is kept for the strips made from the loaded one, e.g. when a title card is added.
+    pub fn temp_policy(mut self, temp: TempPolicy) -> Self {
+        self.temp = temp;
+        self
+    }
+    /// Sets whether a directory is scanned and loaded again (once) if one of its images is modified or
+    /// removed while loading, e.g. because a sync client is still writing to it (defaults to `false`).
+    ///
+    /// Otherwise, a [`ImageLoaderError::SourceChangedDuringRun`] error is thrown.
+    pub fn rescan_on_change(mut self, rescan_on_change: bool) -> Self {
+        self.rescan_on_change = rescan_on_change;
+        self
+    }
+    /// Sets whether images which look like they were scanned sideways are rotated before stitching
+    /// (defaults to `false`).
+    ///
+    /// An image is considered sideways if it is wider than it is tall and its lines of text run vertically.
+    /// Such images are rotated 90° clockwise, and are marked in the [`FileStats`] of the load.
+    pub fn auto_rotate(mut self, auto_rotate: bool) -> Self {
+        self.auto_rotate = auto_rotate;
+        self
+    }
+    /// Sets whether images are turned the way their EXIF orientation tag says before stitching (defaults
+    /// to `true`).
+    ///
+    /// Photographed or edited raws often store their pixels sideways or upside down, along with a tag saying
+    /// how they should be displayed. Images without the tag are loaded as they are either way. Detecting
+    /// sideways images with [`LoadConfig::auto_rotate`] happens after the tag has been applied.
+    pub fn exif_orientation(mut self, exif_orientation: bool) -> Self {
+        self.exif_orientation = exif_orientation;
+        self
+    }
+    /// Sets how the transparent parts of images are flattened (defaults to [`AlphaPolicy::White`]).
+    pub fn alpha(mut self, alpha: AlphaPolicy) -> Self {
+        self.alpha = alpha;
+        self
+    }
+    /// Sets whether images with more than 8 bits per channel, such as 16-bit PNG scans, are dithered as
+    /// they are converted to the 8 bits per channel of the strip (defaults to `true`).
+    ///
+    /// Otherwise each channel is rounded to the nearest 8-bit value, which can make smooth gradients band.
+    ///
+    /// Either way, the extra precision is lost: strips and exported pages are 8 bits per channel throughout,
+    /// and there is no 16-bit output.
+    pub fn dither(mut self, dither: bool) -> Self {
+        self.dither = dither;
+        self
+    }
+    /// Sets what is done with animated images (defaults to [`AnimatedPolicy::FirstFrame`]).
+    ///
+    /// Whatever the policy, the animated images found are listed in [`Stats::animated`]. Animated images
+    /// are errors with [`AnimatedPolicy::Error`] even if `ignore_unloadable` is set.
+    pub fn animated(mut self, animated: AnimatedPolicy) -> Self {
+        self.animated = animated;
+        self
+    }
+    /// Sets whether images which are exact duplicates of an earlier image (such as the same file saved twice
+    /// under different names) are left out of the strip (defaults to `false`).
+    ///
+    /// Images are compared by the length and a 64-bit hash of their encoded bytes, so images which look the
+    /// same but were encoded differently aren't duplicates. The skipped images are listed in
+    /// [`Stats::duplicates`].
+    pub fn skip_duplicates(mut self, skip_duplicates: bool) -> Self {
+        self.skip_duplicates = skip_duplicates;
+        self
+    }
+    /// Sets the decoders used for formats which aren't built in (defaults to an empty registry).
+    ///
+    /// Without a decoder, only jpg, jpeg, png and webp images are found and loaded. AVIF and HEIC images in
+    /// particular are skipped unless a decoder for them is registered.
+    pub fn decoders(mut self, decoders: DecoderRegistry) -> Self {
+        self.decoders = decoders;
+        self
+    }
+    /// Whether a path has the extension of a built-in format, or of one of the custom decoders.
+    pub(crate) fn accepts(&self, path: &Path) -> bool {
+        is_image_path(path) || self.decoders.for_path(path).is_some()
+    }
+    /// Whether an image is left out of the strip, given whether it is animated.
+    fn skips_animated(&self, animated: bool) -> bool {
+        animated && self.animated == AnimatedPolicy::Skip
+    }
+    /// Sets whether images which look like pages that were already stitched are cut again along the seams
+    /// between them (defaults to `false`).
+    ///
+    /// Images look stitched when there are several of them, all of them but the last are at least
+    /// [`STITCHED_ASPECT_RATIO`] times as tall as they are wide, and their heights are within
+    /// [`STITCHED_HEIGHT_SPREAD`] of each other. When they do, the old pages are merged back together up to
+    /// the target height wherever they fit, and only old pages which are still too tall are scanned again,
+    /// instead of every row of the strip being scanned from scratch. Seams are only kept where they fall on
+    /// clean rows. This is reported by
+    /// [`Stats::already_stitched`] and by a [`crate::RunWarning::AlreadyStitched`] warning.
+    pub fn detect_stitched(mut self, detect_stitched: bool) -> Self {
+        self.detect_stitched = detect_stitched;
+        self
+    }
+    /// Sets the direction images are placed in (defaults to [`Direction::Vertical`]).
+    ///
+    /// When placing images horizontally, `width` sets the height images are resized to instead, and the
+    /// gap of [`LoadConfig::inter_image_gap`] is inserted between columns.
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+    /// Sets the height (in pixels) of the gap inserted between every pair of consecutive images, and the
+    /// color it is filled with (defaults to no gap, and white).
+    ///
+    /// This keeps the boundaries between the original pages visible in the stitched output.
+    pub fn inter_image_gap(mut self, gap: u32, color: Rgb<u8>) -> Self {
+        self.inter_image_gap = gap;
+        self.gap_color = color;
+        self
+    }
+    /// Sets how many images read from a stream may wait to be decoded at once (defaults to twice the number
+    /// of threads in the thread pool).
+    ///
+    /// Reading stalls while the queue is full, which bounds memory use when reading is faster than decoding.
+    pub fn queue_depth(mut self, queue_depth: usize) -> Self {
+        self.queue_depth = queue_depth;
+        self
+    }
+    /// Sets a throttle limiting how many images are decoded at once, shared with any other jobs using it,
+    /// and the priority of this job within it (defaults to no throttle).
+    pub fn throttle(mut self, throttle: Throttle, priority: Priority) -> Self {
+        self.throttle = Some((throttle, priority));
+        self
+    }
+    /// Sets a throttle limiting how many image files are read at once, shared with any other jobs using it,
+    /// and the priority of this job within it (defaults to no throttle).
+    ///
+    /// Files are read in full before they are decoded, so on spinning disks and network shares, a low limit
+    /// (even 1) avoids thrashing the disk while still decoding on every thread.
+    pub fn io_throttle(mut self, throttle: Throttle, priority: Priority) -> Self {
+        self.io_throttle = Some((throttle, priority));
+        self
+    }
+    /// Sets how many threads images are decoded and resized on (defaults to `None`, i.e. rayon's global
+    /// thread pool).
+    pub fn threads(mut self, threads: Option<usize>) -> Self {
+        self.threads = threads;
+        self
+    }
+    /// Sets where to report each image as it is loaded (defaults to nowhere). See [`Progress`].
+    pub fn progress(mut self, progress: Option<Arc<dyn Progress>>) -> Self {
+        self.progress = progress.map(ProgressHook);
+        self
+    }
+    /// Sets the limits on what archives loaded with `load_archive` may hold (defaults to
+    /// [`ArchiveLimits::default`]).
+    #[cfg(any(feature = "zip", feature = "7z"))]
+    pub fn archive_limits(mut self, archive_limits: ArchiveLimits) -> Self {
+        self.archive_limits = archive_limits;
+        self
+    }
 }
 
 /// Finds all `.jpg`, `.jpeg`, `.png` and `.webp` images within a directory.
 ///
+/// The images are ordered by `sort`, unless the directory has an `order.txt` (or, with the `serde` feature,
+/// an `order.json`) listing their order, for chapters whose file names don't sort correctly by any rule. See
+/// [`ORDER_FILES`] for how they're written.
+///
 /// Throws an error if:
 ///  - The directory is invalid or does not contain any images.
 ///  - The directory does not contain any jpg, jpeg, png, or webp images.
+///  - An entry of the directory cannot be read.
+///  - The order file can't be read, or lists a file which isn't one of the images.
 pub fn find_images(
     directory_path: impl AsRef<Path>,
     sort: Sort,
+) -> Result<Vec<PathBuf>, ImageLoaderError> {
+    find_matching_images(directory_path, sort, is_image_path)
+}
+
+/// Does exactly the same thing as [`find_images`], but also finds the images which the custom decoders of
+/// the config can decode, and orders them according to the config.
+pub fn find_loadable_images(
+    directory_path: impl AsRef<Path>,
+    config: &LoadConfig,
+) -> Result<Vec<PathBuf>, ImageLoaderError> {
+    find_matching_images(directory_path, config.sort, |path| config.accepts(path))
+}
+
+/// Finds the images within a directory which match the given predicate.
+fn find_matching_images(
+    directory_path: impl AsRef<Path>,
+    sort: Sort,
+    matches: impl Fn(&Path) -> bool,
 ) -> Result<Vec<PathBuf>, ImageLoaderError> {
     // create pathbuf, check if path is a directory
     let path = directory_path.as_ref();
@@ -90,110 +775,1298 @@ pub fn find_images(
     }
 
     // get images
-    let mut images: Vec<_> = read_dir(directory_path)?
-        .into_iter()
-        .map(|file| file.unwrap().path())
-        .filter(|path| match path.extension() {
-            Some(os_str) => match os_str.to_str() {
-                Some("jpg" | "webp" | "jpeg" | "png") => true,
-                _ => false,
-            },
-            _ => false,
-        })
-        .collect();
+    let mut images: Vec<_> = read_dir(path)?
+        .map(|file| file.map(|file| file.path()))
+        .filter_ok(|path| matches(path))
+        .collect::<Result<_, _>>()?;
 
     // if no images were found
     if images.is_empty() {
         return Err(ImageLoaderError::NoImagesInDirectory);
     }
 
-    match sort {
-        Sort::Logical => images.sort(),
-        Sort::Natural => images
-            .sort_by(|a, b| natord::compare(&a.display().to_string(), &b.display().to_string())),
+    sort_paths(&mut images, sort);
+    if let Some((order_file, order)) = read_order_file(path)? {
+        apply_order(&mut images, &order_file, order)?;
     }
 
     // return images
     Ok(images)
 }
 
+/// The files which can list the order of the images in a directory, in the order they're looked for.
+///
+/// `order.txt` lists one file name per line, ignoring blank lines and lines starting with `#`. `order.json`
+/// is an array of file names. The listed images come first, in the order they're listed, followed by any
+/// images which aren't listed, ordered by `sort`.
+pub const ORDER_FILES: [&str; 2] = ["order.txt", "order.json"];
+
+/// Reads the file names listed in the order file of a directory, if it has one, along with the path of the
+/// order file.
+fn read_order_file(directory: &Path) -> Result<Option<(PathBuf, Vec<String>)>, ImageLoaderError> {
+    let txt = directory.join(ORDER_FILES[0]);
+    if txt.is_file() {
+        let order = fs::read_to_string(&txt)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_owned)
+            .collect();
+        return Ok(Some((txt, order)));
+    }
+    #[cfg(feature = "serde")]
+    {
+        let json = directory.join(ORDER_FILES[1]);
+        if json.is_file() {
+            let order = serde_json::from_str(&fs::read_to_string(&json)?).map_err(|error| {
+                ImageLoaderError::InvalidOrder {
+                    path: json.clone(),
+                    reason: error.to_string(),
+                }
+            })?;
+            return Ok(Some((json, order)));
+        }
+    }
+    Ok(None)
+}
+
+/// Moves the images listed in an order file to the front, in the order they're listed.
+fn apply_order(
+    images: &mut Vec<PathBuf>,
+    order_file: &Path,
+    order: Vec<String>,
+) -> Result<(), ImageLoaderError> {
+    let invalid = |reason: String| ImageLoaderError::InvalidOrder {
+        path: order_file.to_path_buf(),
+        reason,
+    };
+    let mut unlisted = std::mem::take(images);
+    for name in order {
+        let Some(index) = unlisted
+            .iter()
+            .position(|image| image.file_name().is_some_and(|file| file == name.as_str()))
+        else {
+            return Err(invalid(
+                match images.iter().any(|image| image.ends_with(&name)) {
+                    true => format!("{name:?} is listed more than once"),
+                    false => format!("{name:?} isn't one of the images in the directory"),
+                },
+            ));
+        };
+        images.push(unlisted.remove(index));
+    }
+    images.append(&mut unlisted);
+    Ok(())
+}
+
+/// Checks whether a path has the extension of a supported image format.
+pub fn is_image_path(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|os_str| os_str.to_str()),
+        Some("jpg" | "webp" | "jpeg" | "png")
+    )
+}
+
+/// Sorts paths in the given order.
+///
+/// Files whose modification time or EXIF timestamp can't be read are placed after the others, ordered by
+/// [`Sort::Natural`]; files with the same time are also ordered by [`Sort::Natural`].
+pub fn sort_paths(paths: &mut [PathBuf], sort: Sort) {
+    let natural = |a: &PathBuf, b: &PathBuf| {
+        natord::compare(&a.display().to_string(), &b.display().to_string())
+    };
+    match sort {
+        Sort::Logical => paths.sort(),
+        Sort::Natural => paths.sort_by(natural),
+        // the sorts by time are stable, so ties keep the natural order
+        Sort::ModifiedTime => {
+            paths.sort_by(natural);
+            paths.sort_by_cached_key(|path| {
+                let modified = fs::metadata(path).and_then(|metadata| metadata.modified());
+                (modified.is_err(), modified.ok())
+            });
+        }
+        Sort::ExifTimestamp => {
+            paths.sort_by(natural);
+            paths.sort_by_cached_key(|path| {
+                let timestamp = exif_timestamp(path);
+                (timestamp.is_none(), timestamp)
+            });
+        }
+    }
+}
+
+/// Where a single image is loaded from.
+#[derive(Debug, Clone, Copy)]
+pub enum Source<'a> {
+    /// An image file on disk.
+    Path(&'a Path),
+    /// An encoded image held in memory. The format is guessed from its contents.
+    Bytes(&'a [u8]),
+}
+
+impl Source<'_> {
+    /// Reads the dimensions the image will have once decoded (and turned according to its EXIF orientation,
+    /// if the config says so) without decoding it.
+    fn dimensions(&self, config: &LoadConfig) -> Result<(u32, u32), ImageLoaderError> {
+        Ok(match *self {
+            Source::Path(path) => match config.decoders.for_path(path) {
+                Some(decoder) => decoder.dimensions(&fs::read(path)?)?,
+                None => oriented_dimensions(ImageReader::open(path)?, config.exif_orientation)?,
+            },
+            Source::Bytes(bytes) => match config.decoders.for_bytes(bytes) {
+                Some(decoder) => decoder.dimensions(bytes)?,
+                None => oriented_dimensions(
+                    ImageReader::new(Cursor::new(bytes)).with_guessed_format()?,
+                    config.exif_orientation,
+                )?,
+            },
+        })
+    }
+    /// Decodes the image, going through the throttles of the config, along with the ICC profile embedded
+    /// in it, if any.
+    ///
+    /// Files are read in full before being decoded, so that reading and decoding can be throttled apart.
+    fn decode(&self, config: &LoadConfig) -> Result<(DynamicImage, IccProfile), ImageLoaderError> {
+        match *self {
+            Source::Path(path) => {
+                let bytes = throttled(&config.io_throttle, || fs::read(path))?;
+                if let Some(decoder) = config.decoders.for_path(path) {
+                    return Ok(throttled(&config.throttle, || {
+                        decode_custom(decoder, &bytes, config)
+                    })?);
+                }
+                let reader = match ImageFormat::from_path(path) {
+                    Ok(format) => ImageReader::with_format(Cursor::new(&bytes[..]), format),
+                    Err(_) => ImageReader::new(Cursor::new(&bytes[..])).with_guessed_format()?,
+                };
+                Ok(throttled(&config.throttle, || {
+                    decode_with_profile(reader, config)
+                })?)
+            }
+            Source::Bytes(bytes) => {
+                if let Some(decoder) = config.decoders.for_bytes(bytes) {
+                    return Ok(throttled(&config.throttle, || {
+                        decode_custom(decoder, bytes, config)
+                    })?);
+                }
+                let reader = ImageReader::new(Cursor::new(bytes)).with_guessed_format()?;
+                Ok(throttled(&config.throttle, || {
+                    decode_with_profile(reader, config)
+                })?)
+            }
+        }
+    }
+    /// Whether the image is animated, reading only its header. Images which can't be read aren't.
+    fn is_animated(&self) -> bool {
+        match *self {
+            Source::Path(path) => ImageReader::open(path).is_ok_and(is_animated),
+            Source::Bytes(bytes) => ImageReader::new(Cursor::new(bytes))
+                .with_guessed_format()
+                .is_ok_and(is_animated),
+        }
+    }
+    /// The size and modification time of a file, used to detect files changing while they are being loaded.
+    ///
+    /// Images held in memory can't change, so they have no snapshot.
+    fn snapshot(&self) -> Option<(u64, Option<SystemTime>)> {
+        match *self {
+            Source::Path(path) => {
+                let metadata = path.metadata().ok()?;
+                Some((metadata.len(), metadata.modified().ok()))
+            }
+            Source::Bytes(_) => None,
+        }
+    }
+    fn path(&self) -> Option<PathBuf> {
+        match *self {
+            Source::Path(path) => Some(path.to_path_buf()),
+            Source::Bytes(_) => None,
+        }
+    }
+    /// The length and hash of the encoded image, which duplicates are found by. Files which can't be read
+    /// have none, and are left for decoding to fail on.
+    fn content_key(&self, config: &LoadConfig) -> Option<(usize, u64)> {
+        match *self {
+            Source::Path(path) => throttled(&config.io_throttle, || fs::read(path))
+                .ok()
+                .map(|bytes| content_key(&bytes)),
+            Source::Bytes(bytes) => Some(content_key(bytes)),
+        }
+    }
+}
+
+/// The length and hash of an encoded image.
+fn content_key(bytes: &[u8]) -> (usize, u64) {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    (bytes.len(), hasher.finish())
+}
+
+/// For each image, the position of the earlier image it's an exact duplicate of, if there is one and the
+/// config skips duplicates.
+fn find_duplicates(sources: &[Source], config: &LoadConfig) -> Vec<Option<usize>> {
+    if !config.skip_duplicates {
+        return vec![None; sources.len()];
+    }
+    let keys: Vec<_> = sources
+        .par_iter()
+        .map(|source| source.content_key(config))
+        .collect();
+    let mut originals = HashMap::new();
+    keys.into_iter()
+        .enumerate()
+        .map(|(index, key)| {
+            let original = *originals.entry(key?).or_insert(index);
+            (original != index).then_some(original)
+        })
+        .collect()
+}
+
+/// Pixels with a luma below this are counted as dark when looking for lines of text.
+const DARK_LUMA: u8 = 96;
+
+/// Counts the gaps (runs of lines without any dark pixels) between lines which have some.
+fn gaps(profile: &[u32]) -> usize {
+    let runs: Vec<bool> = profile.iter().map(|&count| count > 0).dedup().collect();
+    // blank runs at either end aren't between lines
+    let inner = match (
+        runs.iter().position(|&dark| dark),
+        runs.iter().rposition(|&dark| dark),
+    ) {
+        (Some(first), Some(last)) => &runs[first..=last],
+        _ => return 0,
+    };
+    inner.iter().filter(|&&dark| !dark).count()
+}
+
+/// An ICC colour profile, shared between everything which embeds it.
+pub(crate) type IccProfile = Option<Arc<[u8]>>;
+
+/// Decodes an image along with the ICC profile embedded in it, if any, turning it according to its EXIF
+/// orientation and flattening its transparency as the config says.
+fn decode_with_profile(
+    reader: ImageReader<impl BufRead + Seek>,
+    config: &LoadConfig,
+) -> ImageResult<(DynamicImage, IccProfile)> {
+    let mut decoder = reader.into_decoder()?;
+    // `into_decoder` doesn't check the size of the decoded image against the limits as `decode` does, so a
+    // header claiming huge dimensions would otherwise be allocated for
+    Limits::default().reserve(decoder.total_bytes())?;
+    // a profile or orientation which can't be read is ignored, as the pixels are still usable without it
+    let profile = decoder.icc_profile().ok().flatten().map(Arc::from);
+    let orientation = config
+        .exif_orientation
+        .then(|| decoder.orientation().ok())
+        .flatten();
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    if let Some(orientation) = orientation {
+        image.apply_orientation(orientation);
+    }
+    Ok((to_8_bit(image, config), profile))
+}
+
+/// Decodes an image with a custom decoder. Custom decoders don't give the ICC profile of the image.
+fn decode_custom(
+    decoder: &dyn Decoder,
+    bytes: &[u8],
+    config: &LoadConfig,
+) -> ImageResult<(DynamicImage, IccProfile)> {
+    Ok((to_8_bit(decoder.decode(bytes)?, config), None))
+}
+
+/// Converts an image to 8 bits per channel without alpha, as the strip holds them, flattening its
+/// transparency and dithering it as the config says.
+fn to_8_bit(image: DynamicImage, config: &LoadConfig) -> DynamicImage {
+    if config.dither && is_high_depth(&image) {
+        dither(image, config.alpha)
+    } else {
+        config.alpha.flatten(image)
+    }
+}
+
+/// The 4×4 Bayer matrix, which orders the thresholds images are dithered with.
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Whether an image has more than 8 bits per channel.
+fn is_high_depth(image: &DynamicImage) -> bool {
+    let color = image.color();
+    color.bytes_per_pixel() > color.channel_count()
+}
+
+/// Converts an image with more than 8 bits per channel to 8 bits with ordered dithering, compositing it over
+/// the background of the alpha policy on the way.
+fn dither(image: DynamicImage, alpha: AlphaPolicy) -> DynamicImage {
+    let Rgb(background) = alpha.background();
+    let image = image.into_rgba32f();
+    RgbImage::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b, a] = image
+            .get_pixel(x, y)
+            .0
+            .map(|channel| channel.clamp(0.0, 1.0));
+        // spreads the rounding error of neighbouring pixels evenly between -0.5 and 0.5
+        let threshold = (BAYER[y as usize % 4][x as usize % 4] as f32 + 0.5) / 16.0 - 0.5;
+        let channels = [r, g, b];
+        Rgb(std::array::from_fn(|index| {
+            let value = channels[index] * a + background[index] as f32 / 255.0 * (1.0 - a);
+            (value * 255.0 + threshold).round().clamp(0.0, 255.0) as u8
+        }))
+    })
+    .into()
+}
+
+/// Whether an image is animated, which among the supported formats only PNG and WebP images can be.
+fn is_animated(reader: ImageReader<impl BufRead + Seek>) -> bool {
+    match reader.format() {
+        Some(ImageFormat::Png) => PngDecoder::new(reader.into_inner())
+            .and_then(|decoder| decoder.is_apng())
+            .unwrap_or(false),
+        Some(ImageFormat::WebP) => {
+            WebPDecoder::new(reader.into_inner()).is_ok_and(|decoder| decoder.has_animation())
+        }
+        _ => false,
+    }
+}
+
+/// Describes an image for an error: by its path if it has one, otherwise by its position.
+pub(crate) fn describe_source(index: usize, path: Option<&Path>) -> String {
+    match path {
+        Some(path) => format!("{path:?}"),
+        None => format!("The image at index {index}"),
+    }
+}
+
+/// Reads the dimensions of an image from its header, swapped if `orient` is set and its EXIF orientation
+/// turns it sideways.
+fn oriented_dimensions(
+    reader: ImageReader<impl BufRead + Seek>,
+    orient: bool,
+) -> ImageResult<(u32, u32)> {
+    let mut decoder = reader.into_decoder()?;
+    let (width, height) = decoder.dimensions();
+    let sideways = orient
+        && decoder.orientation().is_ok_and(|orientation| {
+            matches!(
+                orientation,
+                Orientation::Rotate90
+                    | Orientation::Rotate270
+                    | Orientation::Rotate90FlipH
+                    | Orientation::Rotate270FlipH
+            )
+        });
+    Ok(if sideways {
+        (height, width)
+    } else {
+        (width, height)
+    })
+}
+
+/// Guesses whether an image is sideways from the orientation of its lines of text.
+///
+/// Lines of text are separated by rows without any dark pixels, while words on different lines rarely
+/// line up, so upright text has many more gaps between rows than between columns. The image is considered
+/// sideways if it is the other way around.
+fn is_sideways(image: &DynamicImage) -> bool {
+    let thumbnail = image.thumbnail(512, 512).into_luma8();
+    let mut rows = vec![0; thumbnail.height() as usize];
+    let mut columns = vec![0; thumbnail.width() as usize];
+    for (x, y, pixel) in thumbnail.enumerate_pixels() {
+        if pixel[0] < DARK_LUMA {
+            rows[y as usize] += 1;
+            columns[x as usize] += 1;
+        }
+    }
+    let (rows, columns) = (gaps(&rows), gaps(&columns));
+    columns >= 3 && columns > rows * 2
+}
+
 /// Loads the images at the provided paths into a single image strip.
 ///
-/// If the `width` parameter is set to `None`, the width of the image with the smallest width will be used.
+/// If the `width` of the config is set to `None`, the width of the image with the smallest width will be used.
 /// Otherwise, the given width will be used.
 ///
 /// Parameters:
 ///  - paths: A slice containing paths to each individual input image.
-///  - width: The width that the final stitched images will have.
-///  - ignore_unloadable: Sometimes, there is an issue where the same page exists twice,
-///                       except one of them is completely empty. For cases like this,
-///                       this setting exists to allow you to only load images that are
-///                       able to be loaded.
+///  - config: The options to load the images with. See [`LoadConfig`].
+///  - stats: Where the time taken by each phase is recorded.
 ///
 /// Throws an error if:
 ///  - The directory is invalid or does not contain any images.
 ///  - The directory does not contain any jpg, jpeg, png, or webp images.
 ///  - An image cannot be opened.
+///  - An image is modified or removed while the images are being loaded.
+///  - The images have no pixels, or the combined image would be too tall.
 pub fn load_images(
     paths: &[impl AsRef<Path>],
-    width: Option<u32>,
-    ignore_unloadable: bool,
-) -> Result<RgbImage, ImageLoaderError> {
-    // get a vec of path refs from the generic parameter
-    let paths = paths.iter().map(|p| p.as_ref()).collect::<Vec<&Path>>();
+    config: &LoadConfig,
+    stats: &mut Stats,
+) -> Result<Strip, ImageLoaderError> {
+    let sources: Vec<_> = paths.iter().map(|p| Source::Path(p.as_ref())).collect();
+    load_sources(&sources, config, stats)
+}
+
+/// Does exactly the same thing as the `load_images` function, but the images may also be held in memory.
+///
+/// The strip is allocated up front from the dimensions in the headers of the images, and each image is
+/// copied into it and dropped as soon as it has been decoded, so only a few decoded images are held in
+/// memory alongside the strip at once.
+///
+/// Throws an error if:
+///  - No images were provided.
+///  - An image cannot be opened.
+///  - An image file is modified or removed while the images are being loaded.
+///  - The images have no pixels, or the combined image would be too tall.
+pub fn load_sources(
+    sources: &[Source],
+    config: &LoadConfig,
+    stats: &mut Stats,
+) -> Result<Strip, ImageLoaderError> {
+    with_threads(config.threads, || {
+        let survey = survey_sources(sources, config)?;
+        let mut stack = StripStack::new(&survey, config)?;
+        decode_surveyed(sources, config, stats, &survey, |index, image| {
+            stack.push(index, image)
+        })?;
+        let copy_start = Instant::now();
+        let strip = stack.finish()?;
+        stats.copy += copy_start.elapsed();
+        Ok(strip)
+    })
+}
+
+/// Does exactly the same thing as the `load_images` function, but the images are kept as they are in a
+/// [`VirtualStrip`] instead of being copied into a single strip.
+///
+/// Throws an error if:
+///  - No images were provided, or none of them could be loaded.
+///  - An image cannot be opened.
+///  - An image is modified or removed while the images are being loaded.
+///  - The images have no pixels, or the combined image would be too tall.
+pub fn load_images_virtual(
+    paths: &[impl AsRef<Path>],
+    config: &LoadConfig,
+    stats: &mut Stats,
+) -> Result<VirtualStrip, ImageLoaderError> {
+    let sources: Vec<_> = paths.iter().map(|p| Source::Path(p.as_ref())).collect();
+    let (images, _) = with_threads(config.threads, || decode_sources(&sources, config, stats))?;
+    let images = trim_margins(images, config);
+    strip_height(
+        images.iter().map(|image| image.height()),
+        config.inter_image_gap,
+    )?;
+    // every image has been resized to the same width, and the strip isn't too tall, so this only fails if
+    // there are no images
+    VirtualStrip::new(images, config.inter_image_gap, config.gap_color)
+        .ok_or(ImageLoaderError::NoImagesInDirectory)
+}
+
+/// Decodes the given images, rotating and resizing them as needed, without stacking them into a strip.
+pub(crate) fn decode_images(
+    sources: &[Source],
+    config: &LoadConfig,
+    stats: &mut Stats,
+) -> Result<Vec<RgbImage>, ImageLoaderError> {
+    with_threads(config.threads, || decode_sources(sources, config, stats))
+        .map(|(images, _)| images)
+}
+
+/// Decodes the given images, rotating and resizing them as needed, and returns them along with the width
+/// they were all resized to.
+fn decode_sources(
+    sources: &[Source],
+    config: &LoadConfig,
+    stats: &mut Stats,
+) -> Result<(Vec<RgbImage>, u32), ImageLoaderError> {
+    let survey = survey_sources(sources, config)?;
+    let mut images = Vec::new();
+    decode_surveyed(sources, config, stats, &survey, |_, image| {
+        images.push(image);
+        Ok(())
+    })?;
+    Ok((images, survey.width))
+}
+
+/// What is known about the sources before any of them is decoded for good.
+struct Survey {
+    load_start: Instant,
+    snapshots: Vec<Option<(u64, Option<SystemTime>)>>,
+    duplicates: Vec<Option<usize>>,
+    animated: Vec<bool>,
+    /// The sources which are left out of the strip.
+    skipped: Vec<bool>,
+    rotated: Vec<bool>,
+    /// The width to resize images to.
+    width: u32,
+    /// The height each image will have once resized, going by its header (0 for images which are left out
+    /// or can't be read).
+    heights: Vec<u32>,
+}
+
+/// Finds out which sources are left out of the strip, which of them are rotated, and the dimensions they
+/// will have once resized, from their headers.
+fn survey_sources(sources: &[Source], config: &LoadConfig) -> Result<Survey, ImageLoaderError> {
+    let load_start = Instant::now();
+    let ignore_unloadable = config.skips_unloadable();
 
-    let dimensions = paths
+    let snapshots: Vec<_> = sources.iter().map(Source::snapshot).collect();
+
+    let duplicates = find_duplicates(sources, config);
+    let animated: Vec<bool> = sources.par_iter().map(Source::is_animated).collect();
+    if config.animated == AnimatedPolicy::Error {
+        if let Some(index) = animated.iter().position(|&animated| animated) {
+            return Err(ImageLoaderError::AnimatedImage {
+                index,
+                path: sources[index].path(),
+            });
+        }
+    }
+    // the images which are left out of the strip
+    let skipped: Vec<bool> = animated
         .iter()
-        .map(|&image| image_dimensions(image).map_err(|e| ImageLoaderError::from(e)));
-    let dimensions: Vec<_> = if ignore_unloadable {
-        dimensions.filter_map(|res| res.ok()).collect()
-    } else {
-        dimensions.collect::<Result<Vec<(u32, u32)>, ImageLoaderError>>()?
-    };
+        .zip(&duplicates)
+        .map(|(&animated, duplicate)| config.skips_animated(animated) || duplicate.is_some())
+        .collect();
+
+    // Landscape images are decoded an extra time to check whether they are sideways, so that the
+    // dimensions they will have once rotated can be used below.
+    let rotated: Vec<bool> = sources
+        .par_iter()
+        .zip(&skipped)
+        .map(|(source, &skipped)| {
+            config.auto_rotate
+                && !skipped
+                && source.dimensions(config).is_ok_and(|(w, h)| w > h)
+                && source
+                    .decode(config)
+                    .is_ok_and(|(image, _)| is_sideways(&image))
+        })
+        .collect();
+
+    let dimensions: Vec<Option<(u32, u32)>> = sources
+        .iter()
+        .zip(&rotated)
+        .zip(&skipped)
+        .map(|((source, &rotated), &skipped)| {
+            if skipped {
+                return Ok(None);
+            }
+            match source.dimensions(config) {
+                Ok((w, h)) => {
+                    let dimensions = if rotated { (h, w) } else { (w, h) };
+                    Ok(Some(config.direction.turn_dimensions(dimensions)))
+                }
+                Err(_) if ignore_unloadable => Ok(None),
+                Err(error) => Err(error),
+            }
+        })
+        .collect::<Result<_, ImageLoaderError>>()?;
+    if dimensions.iter().all(Option::is_none) {
+        return Err(ImageLoaderError::NoImagesInDirectory);
+    }
 
     // the width to resize images to
-    let width = match width {
-        Some(v) => v,
-        None => {
-            // find_images will already throw an error if the directory does not contain any images, so unwrap is safe here.
-            dimensions.iter().map(|pair| pair.0).min().unwrap()
-        }
-    };
+    let width = config.choose_width(dimensions.iter().flatten().map(|pair| pair.0));
 
-    // the height to resize images to
-    let height = dimensions.iter().map(|pair| pair.1).max().unwrap();
+    // the height of the tallest image
+    let height = dimensions
+        .iter()
+        .flatten()
+        .map(|pair| pair.1)
+        .max()
+        .unwrap_or(0);
+    if width == 0 || height == 0 {
+        return Err(ImageLoaderError::EmptyImage);
+    }
 
-    // load images
-    let images = paths.par_iter().map(|&image_path| {
-        let image = ImageReader::open(image_path)?
-            .decode()
-            .map_err(|e| ImageLoaderError::from(e))?;
+    let heights = dimensions
+        .iter()
+        .map(|dimensions| dimensions.map_or(0, |(w, h)| fitted_height(w, h, width, config)))
+        .collect();
+    Ok(Survey {
+        load_start,
+        snapshots,
+        duplicates,
+        animated,
+        skipped,
+        rotated,
+        width,
+        heights,
+    })
+}
 
-        if image.width() == width {
-            // noop if widths match
-            Ok(image.into())
-        } else {
-            // resize image otherwise
-            Ok(image.resize(width, height, Lanczos3).into())
+/// Decodes the surveyed sources, rotating and resizing them as needed, and hands each image along with the
+/// index of its source to `sink` in order.
+///
+/// Images are decoded as many at a time as there are threads, and handed over before the next ones are
+/// decoded, so only a few decoded images are held at once.
+fn decode_surveyed(
+    sources: &[Source],
+    config: &LoadConfig,
+    stats: &mut Stats,
+    survey: &Survey,
+    mut sink: impl FnMut(usize, RgbImage) -> Result<(), ImageLoaderError>,
+) -> Result<(), ImageLoaderError> {
+    let ignore_unloadable = config.skips_unloadable();
+    let Survey {
+        load_start,
+        snapshots,
+        duplicates,
+        animated,
+        skipped,
+        rotated,
+        width,
+        ..
+    } = survey;
+    let width = *width;
+
+    let tally = Tally::new(&config.progress);
+    let indices: Vec<usize> = (0..sources.len())
+        .filter(|&index| !skipped[index])
+        .collect();
+    let mut files = Vec::new();
+    let mut unloadable = Vec::new();
+    let mut in_sink = Duration::ZERO;
+    for chunk in indices.chunks(rayon::current_num_threads().max(1)) {
+        let decoded: Vec<_> = chunk
+            .par_iter()
+            .map(|&index| {
+                let source = &sources[index];
+                let decode_start = Instant::now();
+                let (mut image, icc_profile) = match source.decode(config) {
+                    Ok(decoded) => decoded,
+                    Err(error) => return Err((index, error)),
+                };
+                if rotated[index] {
+                    image = image.rotate90();
+                }
+                let image = config.direction.turn(image);
+                let decode = decode_start.elapsed();
+
+                let resize_start = Instant::now();
+                let image = fit(image, width, config);
+                tally.tick(|progress, loaded| progress.image_loaded(loaded, Some(sources.len())));
+                let file = FileStats {
+                    index,
+                    path: source.path(),
+                    rotated: rotated[index],
+                    height: image.height(),
+                    icc_profile,
+                    decode,
+                    resize: resize_start.elapsed(),
+                };
+                Ok((image, file))
+            })
+            .collect();
+        for result in decoded {
+            match result {
+                Ok((image, file)) => {
+                    let sink_start = Instant::now();
+                    sink(file.index, image)?;
+                    in_sink += sink_start.elapsed();
+                    files.push(file);
+                }
+                Err((index, error)) if ignore_unloadable => unloadable.push((index, error)),
+                Err((_, error)) => return Err(error),
+            }
         }
+    }
+    // Only files which existed to begin with are checked, as missing files have already been handled above.
+    if let Some((source, _)) = sources
+        .iter()
+        .zip(snapshots)
+        .find(|(source, before)| before.is_some() && source.snapshot() != **before)
+    {
+        // only file sources have snapshots, so the source must have a path.
+        return Err(ImageLoaderError::SourceChangedDuringRun(
+            source.path().unwrap_or_default(),
+        ));
+    }
+    stats.files.extend(files);
+    stats.animated.extend(
+        sources
+            .iter()
+            .zip(animated)
+            .enumerate()
+            .filter(|(_, (_, &animated))| animated)
+            .map(|(index, (source, _))| (index, source.path())),
+    );
+    stats
+        .skipped
+        .extend(unloadable.into_iter().map(|(index, error)| SkippedImage {
+            index,
+            path: sources[index].path(),
+            error: Arc::new(error),
+        }));
+    stats.duplicates.extend(
+        duplicates
+            .iter()
+            .enumerate()
+            .filter_map(|(index, &original)| {
+                Some(Duplicate {
+                    index,
+                    path: sources[index].path(),
+                    original: original?,
+                })
+            }),
+    );
+    // the time spent in the sink is accounted for as copying rather than loading
+    stats.copy += in_sink;
+    stats.load += load_start.elapsed().saturating_sub(in_sink);
+    Ok(())
+}
+
+/// Does exactly the same thing as [`load_images`], but the images are encoded images held in memory rather
+/// than files. The format of each image is guessed from its contents.
+pub fn load_images_from_bytes(
+    images: &[impl AsRef<[u8]>],
+    config: &LoadConfig,
+    stats: &mut Stats,
+) -> Result<Strip, ImageLoaderError> {
+    let sources: Vec<_> = images.iter().map(|b| Source::Bytes(b.as_ref())).collect();
+    load_sources(&sources, config, stats)
+}
+
+/// Loads images which are read one at a time (e.g. from a stream) into a single image strip.
+///
+/// Reading and decoding overlap: each image is handed to the thread pool to be decoded as soon as it has
+/// been read, while the next one is being read. At most `queue_depth` images (see [`LoadConfig`]) wait to
+/// be decoded at once, which bounds the memory used when reading is faster than decoding.
+///
+/// Unlike [`load_sources`], images are only resized once all of them have been decoded, as the width
+/// to resize them to isn't known until then.
+///
+/// Throws an error if:
+///  - An entry cannot be read.
+///  - No images were read.
+///  - An image cannot be decoded.
+///  - The images have no pixels, or the combined image would be too tall.
+pub fn load_streamed(
+    entries: impl Iterator<Item = io::Result<Vec<u8>>>,
+    config: &LoadConfig,
+    stats: &mut Stats,
+) -> Result<Strip, ImageLoaderError> {
+    let load_start = Instant::now();
+    let (sender, receiver) = sync_channel::<(usize, Vec<u8>)>(config.queue_depth.max(1));
+    let tally = Tally::new(&config.progress);
+    let tally = &tally;
+    let (read, mut decoded) = thread::scope(|scope| {
+        let decoder = scope.spawn(move || {
+            with_threads(config.threads, || {
+                receiver
+                    .into_iter()
+                    .par_bridge()
+                    .map(|(index, bytes)| {
+                        let decode_start = Instant::now();
+                        let source = Source::Bytes(&bytes);
+                        let animated = source.is_animated();
+                        let image = match config.animated {
+                            AnimatedPolicy::Error if animated => {
+                                Err(ImageLoaderError::AnimatedImage { index, path: None })
+                            }
+                            _ if config.skips_animated(animated) => {
+                                tally.tick(|progress, loaded| progress.image_loaded(loaded, None));
+                                return (index, animated, None, decode_start.elapsed());
+                            }
+                            _ => source.decode(config),
+                        };
+                        let image = image.map(|(image, icc_profile)| {
+                            let (image, rotated) = if config.auto_rotate
+                                && image.width() > image.height()
+                                && is_sideways(&image)
+                            {
+                                (image.rotate90(), true)
+                            } else {
+                                (image, false)
+                            };
+                            (config.direction.turn(image), rotated, icc_profile)
+                        });
+                        tally.tick(|progress, loaded| progress.image_loaded(loaded, None));
+                        (index, animated, Some(image), decode_start.elapsed())
+                    })
+                    .collect::<Vec<_>>()
+            })
+        });
+        let mut originals = HashMap::new();
+        let mut duplicates = Vec::new();
+        let read = entries.enumerate().try_for_each(|(index, entry)| {
+            let entry = entry?;
+            if config.skip_duplicates {
+                let original = *originals.entry(content_key(&entry)).or_insert(index);
+                if original != index {
+                    duplicates.push(Duplicate {
+                        index,
+                        path: None,
+                        original,
+                    });
+                    return Ok(());
+                }
+            }
+            // the decoder only hangs up if it panicked, which is resumed below
+            let _ = sender.send((index, entry));
+            Ok::<_, io::Error>(())
+        });
+        drop(sender);
+        let decoded = decoder
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+        (read.map(|_| duplicates), decoded)
     });
-    let images: Vec<RgbImage> = if ignore_unloadable {
-        images.filter_map(|res| res.ok()).collect::<Vec<_>>()
+    let duplicates = read?;
+    decoded.sort_by_key(|(index, ..)| *index);
+    let animated: Vec<_> = decoded
+        .iter()
+        .filter(|(_, animated, ..)| *animated)
+        .map(|(index, ..)| (*index, None))
+        .collect();
+    // skipped images have nothing to decode
+    let decoded = decoded
+        .into_iter()
+        .filter_map(|(index, _, image, decode)| Some((index, image?, decode)))
+        .map(|(index, image, decode)| match image {
+            Ok((image, rotated, icc_profile)) => Ok((index, image, rotated, icc_profile, decode)),
+            Err(error) => Err((index, error)),
+        });
+    let (decoded, unloadable): (Vec<_>, Vec<_>) = if config.skips_unloadable() {
+        decoded.partition_result()
     } else {
-        images.collect::<Result<Vec<RgbImage>, ImageLoaderError>>()?
+        let decoded = decoded
+            .collect::<Result<_, _>>()
+            .map_err(|(_, error)| error)?;
+        (decoded, Vec::new())
+    };
+    if decoded.is_empty() {
+        return Err(ImageLoaderError::NoImagesInDirectory);
+    }
+
+    let width = config.choose_width(decoded.iter().map(|(_, image, ..)| image.width()));
+    let height = decoded
+        .iter()
+        .map(|(_, image, ..)| image.height())
+        .max()
+        .unwrap_or(0);
+    if width == 0 || height == 0 {
+        return Err(ImageLoaderError::EmptyImage);
+    }
+
+    let (images, files): (Vec<_>, Vec<_>) = with_threads(config.threads, || {
+        decoded
+            .into_par_iter()
+            .map(|(index, image, rotated, icc_profile, decode)| {
+                let resize_start = Instant::now();
+                let image = fit(image, width, config);
+                let file = FileStats {
+                    index,
+                    path: None,
+                    rotated,
+                    height: image.height(),
+                    icc_profile,
+                    decode,
+                    resize: resize_start.elapsed(),
+                };
+                (image, file)
+            })
+            .unzip()
+    });
+    stats.files.extend(files);
+    stats.animated.extend(animated);
+    stats
+        .skipped
+        .extend(unloadable.into_iter().map(|(index, error)| SkippedImage {
+            index,
+            path: None,
+            error: Arc::new(error),
+        }));
+    stats.duplicates.extend(duplicates);
+    stats.load += load_start.elapsed();
+    combine(images, width, config, stats)
+}
+
+/// How many times as tall as they are wide images must be to look like pages which were already stitched.
+pub const STITCHED_ASPECT_RATIO: f64 = 3.0;
+
+/// How much shorter than the tallest of them images may be, as a fraction of its height, to look like pages
+/// which were already stitched.
+pub const STITCHED_HEIGHT_SPREAD: f64 = 0.25;
+
+/// The rows at which each image but the first starts in a strip of the given width, if the loaded images
+/// look like pages which were already stitched (see [`LoadConfig::detect_stitched`]), or nothing otherwise.
+///
+/// The last image is only checked for not being taller than the others, as the last page of a stitched
+/// chapter is usually shorter.
+pub(crate) fn stitched_seams(files: &[FileStats], width: u32, config: &LoadConfig) -> Vec<usize> {
+    let Some((_, pages)) = files.split_last() else {
+        return Vec::new();
     };
+    let tallest = files.iter().map(|file| file.height).max().unwrap_or(0);
+    let shortest = pages.iter().map(|file| file.height).min().unwrap_or(0);
+    let stitched = config.detect_stitched
+        && !pages.is_empty()
+        && shortest as f64 >= width as f64 * STITCHED_ASPECT_RATIO
+        && shortest as f64 >= tallest as f64 * (1.0 - STITCHED_HEIGHT_SPREAD);
+    if !stitched {
+        return Vec::new();
+    }
+    pages
+        .iter()
+        .scan(0, |row, file| {
+            *row += (file.height + config.inter_image_gap) as usize;
+            Some(*row)
+        })
+        .collect()
+}
+
+/// Resizes an image to the given width with the filter of the config (sharpening it afterwards if the config
+/// says so), unless it already has it, or pads it to the width if it's narrower and the config avoids
+/// upscaling that way.
+pub(crate) fn fit(image: DynamicImage, width: u32, config: &LoadConfig) -> RgbImage {
+    if image.width() == width {
+        // noop if widths match
+        image.into()
+    } else if image.width() < width && config.upscale == UpscalePolicy::Pad {
+        let image = RgbImage::from(image);
+        let mut padded = RgbImage::from_pixel(width, image.height(), config.gap_color);
+        imageops::replace(&mut padded, &image, ((width - image.width()) / 2).into(), 0);
+        padded
+    } else {
+        // resize image otherwise, keeping its aspect ratio
+        let height = fitted_height(image.width(), image.height(), width, config);
+        let image = image
+            .resize_exact(width, height, config.resize_filter.filter_type())
+            .into();
+        config.sharpened(image)
+    }
+}
+
+/// The height an image of the given dimensions has once [`fit`] to the given width.
+fn fitted_height(w: u32, h: u32, width: u32, config: &LoadConfig) -> u32 {
+    if w == width || (w < width && config.upscale == UpscalePolicy::Pad) {
+        h
+    } else {
+        (h as f64 * (width as f64 / w as f64))
+            .round()
+            .clamp(1.0, u32::MAX as f64) as u32
+    }
+}
+
+/// A strip which images are copied into as soon as they are decoded, so that each of them can be dropped
+/// straight away rather than all of them being held until the strip is put together.
+struct StripStack<'a> {
+    strip: Strip,
+    config: &'a LoadConfig,
+    /// The heights predicted for the images by their headers, which the strip is allocated for.
+    heights: &'a [u32],
+    /// How many images have been copied in so far.
+    count: usize,
+    /// The row below the images copied in so far.
+    cursor: u32,
+    margins: Option<Margins>,
+}
+
+impl<'a> StripStack<'a> {
+    fn new(survey: &'a Survey, config: &'a LoadConfig) -> Result<Self, ImageLoaderError> {
+        let height = strip_height(
+            survey.heights.iter().copied().filter(|&height| height > 0),
+            config.inter_image_gap,
+        )?;
+        Ok(Self {
+            strip: new_strip(survey.width, height, config.spill_threshold, &config.temp)?,
+            config,
+            heights: &survey.heights,
+            count: 0,
+            cursor: 0,
+            margins: config
+                .trim_margins
+                .map(|tolerance| Margins::new(tolerance, survey.width)),
+        })
+    }
+
+    /// Copies the image of the source at `index`, which must have the width of the strip, below the images
+    /// copied in so far.
+    fn push(&mut self, index: usize, image: RgbImage) -> Result<(), ImageLoaderError> {
+        let width = self.strip.width();
+        let gap = if self.count > 0 {
+            self.config.inter_image_gap
+        } else {
+            0
+        };
+        let bottom = self.cursor as u64 + gap as u64 + image.height() as u64;
+        if bottom > self.strip.height() as u64 {
+            // The header of the image understated its height, so make room for it and the images after it.
+            let rest = self.heights[index + 1..]
+                .iter()
+                .filter(|&&height| height > 0)
+                .map(|&height| height as u64 + self.config.inter_image_gap as u64)
+                .sum::<u64>();
+            let height = u32::try_from(bottom + rest)
+                .map_err(|_| ImageLoaderError::StripTooTall(bottom + rest))?;
+            let strip =
+                std::mem::replace(&mut self.strip, new_strip(0, 0, None, &self.config.temp)?);
+            self.strip = reshape_strip(
+                strip,
+                0,
+                width,
+                height,
+                self.config.spill_threshold,
+                &self.config.temp,
+            )?;
+        }
+
+        for row in self.cursor..self.cursor + gap {
+            for column in 0..width {
+                self.strip.put_pixel(column, row, self.config.gap_color);
+            }
+        }
+        self.cursor += gap;
+        // This should never throw an error because the strip was made tall enough for the image above.
+        self.strip
+            .copy_from(&image, 0, self.cursor)
+            .expect("all according to keikaku");
+        self.cursor += image.height();
+        self.count += 1;
+        if let Some(margins) = &mut self.margins {
+            margins.add(&image);
+        }
+        Ok(())
+    }
+
+    /// Crops the strip to the images copied into it, trimming their margins if the config says so.
+    fn finish(self) -> Result<Strip, ImageLoaderError> {
+        let width = self.strip.width();
+        let (left, width) = self
+            .margins
+            .and_then(|margins| margins.trimmed(width))
+            .unwrap_or((0, width));
+        Ok(reshape_strip(
+            self.strip,
+            left,
+            width,
+            self.cursor,
+            self.config.spill_threshold,
+            &self.config.temp,
+        )?)
+    }
+}
+
+/// Stacks the given images, which must all have the given width, into a single strip.
+pub(crate) fn combine(
+    images: Vec<RgbImage>,
+    width: u32,
+    config: &LoadConfig,
+    stats: &mut Stats,
+) -> Result<Strip, ImageLoaderError> {
+    let copy_start = Instant::now();
+    let images = trim_margins(images, config);
+    let width = images.first().map_or(width, RgbImage::width);
 
     // combine all images into one big strip
-    let mut combined_image = RgbImage::new(width, images.iter().map(|image| image.height()).sum());
+    let gap = config.inter_image_gap;
+    let mut combined_image = new_strip(
+        width,
+        strip_height(images.iter().map(|image| image.height()), gap)?,
+        config.spill_threshold,
+        &config.temp,
+    )?;
     let mut height_cursor = 0;
 
-    for i in images {
-        // This should never throw an error because the combined image height is set to the sum of all image heights.
+    for (index, i) in images.iter().enumerate() {
+        if index > 0 && gap > 0 {
+            for row in height_cursor..height_cursor + gap {
+                for column in 0..width {
+                    combined_image.put_pixel(column, row, config.gap_color);
+                }
+            }
+            height_cursor += gap;
+        }
+        // This should never throw an error because the combined image height is set to the sum of all image
+        // heights and gaps.
         combined_image
-            .copy_from(&i, 0, height_cursor)
+            .copy_from(i, 0, height_cursor)
             .expect("all according to keikaku");
         height_cursor += i.height();
     }
+    stats.copy += copy_start.elapsed();
 
     Ok(combined_image)
 }
+
+/// Crops the margins at the left and right of images of the same width, if the config says so. See
+/// [`LoadConfig::trim_margins`].
+fn trim_margins(images: Vec<RgbImage>, config: &LoadConfig) -> Vec<RgbImage> {
+    let (Some(tolerance), Some(width)) = (config.trim_margins, images.first().map(RgbImage::width))
+    else {
+        return images;
+    };
+    let mut margins = Margins::new(tolerance, width);
+    images.iter().for_each(|image| margins.add(image));
+    let Some((left, width)) = margins.trimmed(width) else {
+        return images;
+    };
+    images
+        .into_par_iter()
+        .map(|image| {
+            let height = image.height();
+            imageops::crop_imm(&image, left, 0, width, height).to_image()
+        })
+        .collect()
+}
+
+/// The levels margins are made up of: black and white.
+const MARGIN_LEVELS: [u8; 2] = [0, u8::MAX];
+
+/// How many columns at the left and right of every image seen so far are black (or white) within the
+/// tolerance, for each of the [`MARGIN_LEVELS`].
+struct Margins {
+    tolerance: u8,
+    left: [u32; 2],
+    right: [u32; 2],
+}
+
+impl Margins {
+    fn new(tolerance: u8, width: u32) -> Self {
+        Self {
+            tolerance,
+            left: [width; 2],
+            right: [width; 2],
+        }
+    }
+
+    /// Narrows the margins down to the columns which are also margin in the given image.
+    fn add(&mut self, image: &RgbImage) {
+        let tolerance = self.tolerance;
+        let width = image.width();
+        for (index, level) in MARGIN_LEVELS.into_iter().enumerate() {
+            let is_margin = |column: u32| {
+                (0..image.height()).all(|row| {
+                    let pixel = image.get_pixel(column, row);
+                    pixel
+                        .0
+                        .iter()
+                        .all(|channel| channel.abs_diff(level) <= tolerance)
+                })
+            };
+            self.left[index] = (0..self.left[index])
+                .take_while(|&column| is_margin(column))
+                .count() as u32;
+            self.right[index] = (0..self.right[index])
+                .take_while(|&column| is_margin(width - 1 - column))
+                .count() as u32;
+        }
+    }
+
+    /// The first column and the width left once the margins are cropped from images of the given width, or
+    /// `None` if there is nothing to crop.
+    fn trimmed(&self, width: u32) -> Option<(u32, u32)> {
+        let left = self.left.into_iter().max().unwrap_or(0);
+        let right = self.right.into_iter().max().unwrap_or(0);
+        // blank images are all margin, and are left as they are
+        (left + right > 0 && left + right < width).then(|| (left, width - left - right))
+    }
+}
+
+/// The height of a strip made up of images of the given heights, separated by `gap` rows.
+///
+/// Throws an error if the strip would be taller than the largest image which can be stitched.
+pub(crate) fn strip_height(
+    heights: impl Iterator<Item = u32>,
+    gap: u32,
+) -> Result<u32, ImageLoaderError> {
+    let (count, sum) = heights.fold((0u64, 0u64), |(count, sum), height| {
+        (count + 1, sum + height as u64)
+    });
+    let height = sum + gap as u64 * count.saturating_sub(1);
+    u32::try_from(height).map_err(|_| ImageLoaderError::StripTooTall(height))
+}
+
+/// Concatenates several already-loaded strips (e.g. one per chapter) into a single strip.
+///
+/// Each strip is given as the strip itself along with the rows at which its chapters start, and the
+/// chapter starts of the combined strip are returned alongside it, so chapter markers are preserved
+/// even when concatenating strips which were themselves concatenated.
+///
+/// The width is chosen from the widths of the strips by the width strategy of the config.
+/// Strips which don't match the width are resized, along with their chapter starts.
+///
+/// Throws an error if:
+///  - No strips were provided.
+///  - The combined strip would be too tall.
+///  - The config requests spilling to a temp file, and the file can't be created.
+pub fn concat_strips(
+    strips: Vec<(Strip, Vec<usize>)>,
+    config: &LoadConfig,
+) -> Result<(Strip, Vec<usize>), ImageLoaderError> {
+    if strips.is_empty() {
+        return Err(ImageLoaderError::NoChapters);
+    }
+    let width = config.choose_width(strips.iter().map(|(strip, _)| strip.width()));
+    if width == 0 {
+        return Err(ImageLoaderError::EmptyImage);
+    }
+
+    // strips narrower than the width are padded rather than upscaled
+    let pads = |strip_width: u32| strip_width < width && config.upscale == UpscalePolicy::Pad;
+    // the height each strip will have once resized
+    let heights: Vec<u32> = strips
+        .iter()
+        .map(|(strip, _)| {
+            if strip.width() == width || pads(strip.width()) {
+                strip.height()
+            } else {
+                (strip.height() as u64 * width as u64 / strip.width().max(1) as u64)
+                    .try_into()
+                    .unwrap_or(u32::MAX)
+            }
+        })
+        .collect();
+
+    let mut combined_image = new_strip(
+        width,
+        strip_height(heights.iter().copied(), 0)?,
+        config.spill_threshold,
+        &config.temp,
+    )?;
+    let mut chapters = Vec::new();
+    let mut height_cursor = 0;
+
+    for ((strip, starts), height) in strips.into_iter().zip(heights) {
+        chapters.extend(starts.iter().map(|&start| {
+            height_cursor as usize + start * height as usize / strip.height().max(1) as usize
+        }));
+        if strip.width() == width {
+            combined_image
+                .copy_from(&strip, 0, height_cursor)
+                .expect("all according to keikaku");
+        } else if pads(strip.width()) {
+            let left = (width - strip.width()) / 2;
+            let right = width - strip.width() - left;
+            for (x, band) in [(0, left), (left + strip.width(), right)] {
+                combined_image
+                    .copy_from(
+                        &RgbImage::from_pixel(band, height, config.gap_color),
+                        x,
+                        height_cursor,
+                    )
+                    .expect("all according to keikaku");
+            }
+            combined_image
+                .copy_from(&strip, left, height_cursor)
+                .expect("all according to keikaku");
+        } else {
+            combined_image
+                .copy_from(
+                    &config.sharpened(imageops::resize(
+                        &strip,
+                        width,
+                        height,
+                        config.resize_filter.filter_type(),
+                    )),
+                    0,
+                    height_cursor,
+                )
+                .expect("all according to keikaku");
+        }
+        height_cursor += height;
+    }
+    chapters.dedup();
+
+    Ok((combined_image, chapters))
+}
@@ -1,87 +1,809 @@
 //! This module consists of functions related to the splitting of the combined image.
 
 use std::{
-    fs::File,
-    io::{self, BufWriter},
-    path::Path,
+    borrow::Cow,
+    collections::HashMap,
+    fs::{self, File},
+    hash::{Hash, Hasher},
+    io::{self, BufWriter, Cursor, Write},
+    ops::Range,
+    panic,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc},
+    thread,
+    time::{Duration, Instant, SystemTime},
 };
 
 use image::{
-    codecs::{jpeg::JpegEncoder, png::PngEncoder, webp::WebPEncoder},
-    GenericImageView, ImageError, Pixel, Rgb, RgbImage,
+    codecs::{
+        bmp::BmpEncoder, jpeg::JpegEncoder, png::PngEncoder, qoi::QoiEncoder, tiff::TiffEncoder,
+        webp::WebPEncoder,
+    },
+    imageops::{self, FilterType},
+    GenericImageView, ImageEncoder, ImageError, ImageReader, Pixel, Rgb, RgbImage,
+};
+
+#[cfg(feature = "avif")]
+use image::codecs::avif::AvifEncoder;
+#[cfg(feature = "webp-lossy")]
+use image::{error::EncodingError, ImageFormat};
+
+#[cfg(feature = "zip")]
+use super::comic_info::{ComicInfo, ComicPage};
+#[cfg(feature = "pdf")]
+use super::pdf::{write_pdf, PdfPage, PdfPageSize, DEFAULT_DPI};
+use super::{
+    encoder::{Encoder, EncoderRegistry},
+    image_loader::{Direction, IccProfile},
+    jpeg::{write_jpeg, ChromaSubsampling},
+    lock::DirectoryLock,
+    manifest::{content_hash, Manifest, ManifestEntry},
+    metadata::PageMetadata,
+    output_sink::OutputSink,
+    overlay::Overlay,
+    page_cache::{CachedPage, PageCache},
+    page_numbers::PageNumbers,
+    palette::write_palette_png,
+    progress::{Progress, ProgressHook, Tally},
+    score_cache::ScoreCache,
+    stats::{PageStats, Stats},
+    stream::write_frame,
+    strip::Strip,
+    throttle::{throttled, with_threads, Priority, Throttle},
+    virtual_strip::StripRows,
 };
 use itertools::Itertools;
-use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use rayon::{
+    iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator},
+    slice::ParallelSlice,
+};
 use thiserror::Error;
 
+/// Options which control where the combined image is cut. See [`find_splitpoints`].
+#[derive(Debug, Clone)]
+pub struct StitchConfig {
+    pub(crate) target_height: usize,
+    pub(crate) strategy: SplitStrategy,
+    pub(crate) aspect_ratio: (Option<f64>, Option<f64>),
+    pub(crate) scan_interval: usize,
+    pub(crate) sensitivity: u8,
+    pub(crate) background: Vec<Region>,
+    pub(crate) background_mode: BackgroundMode,
+    pub(crate) auto_sensitivity: bool,
+    pub(crate) auto_scan_interval: bool,
+    pub(crate) text_band: Option<u32>,
+    pub(crate) threads: Option<usize>,
+    pub(crate) score_cache: Option<ScoreCache>,
+    pub(crate) parallel_scoring: bool,
+    pub(crate) progress: Option<ProgressHook>,
+    pub(crate) strict: bool,
+}
+
+impl Default for StitchConfig {
+    fn default() -> Self {
+        Self {
+            target_height: 5000,
+            strategy: SplitStrategy::TargetHeight,
+            aspect_ratio: (None, None),
+            scan_interval: 5,
+            sensitivity: 242,
+            background: Vec::new(),
+            background_mode: BackgroundMode::default(),
+            auto_sensitivity: false,
+            auto_scan_interval: false,
+            text_band: None,
+            threads: None,
+            score_cache: None,
+            parallel_scoring: false,
+            progress: None,
+            strict: false,
+        }
+    }
+}
+
+impl StitchConfig {
+    /// Creates a config with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets how many pixels tall each page should be at most (defaults to 5000). A height of 0 is treated as 1.
+    ///
+    /// This is only used by [`SplitStrategy::TargetHeight`].
+    pub fn target_height(mut self, target_height: usize) -> Self {
+        self.target_height = target_height;
+        self
+    }
+    /// Sets how the number of pages is decided (defaults to [`SplitStrategy::TargetHeight`]).
+    pub fn strategy(mut self, strategy: SplitStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+    /// Sets how many times taller than wide each page must be at least and may be at most (defaults to no
+    /// limits), for reader apps which crop pages with extreme aspect ratios.
+    ///
+    /// For instance, a maximum of 4.0 keeps pages of an 800 pixel wide strip within 3200 pixels, however
+    /// tall the target height is. Rows outside of these limits aren't considered when looking for where
+    /// to cut, but the last page of each chapter may still be shorter than the minimum.
+    ///
+    /// A limit which isn't a finite number above 0 is ignored, and so are both limits if the minimum is
+    /// greater than the maximum.
+    pub fn aspect_ratio(mut self, min: Option<f64>, max: Option<f64>) -> Self {
+        let valid = |ratio: Option<f64>| ratio.filter(|ratio| ratio.is_finite() && *ratio > 0.0);
+        self.aspect_ratio = match (valid(min), valid(max)) {
+            (Some(min), Some(max)) if min > max => (None, None),
+            limits => limits,
+        };
+        self
+    }
+    /// Sets the interval at which rows of pixels are scanned (defaults to 5). An interval of 0 is treated as 1.
+    pub fn scan_interval(mut self, scan_interval: usize) -> Self {
+        self.scan_interval = scan_interval;
+        self
+    }
+    /// Sets the threshold (between 0 and 255) at which a row can be marked as a splitpoint (defaults to 242,
+    /// i.e. roughly 95%).
+    ///
+    /// 0 accepts any row, while 255 only accepts rows whose pixels are all exactly the same color.
+    pub fn sensitivity(mut self, sensitivity: u8) -> Self {
+        self.sensitivity = sensitivity;
+        self
+    }
+    /// Sets regions of the combined image which are known to be background (defaults to none).
+    ///
+    /// If any are set, the sensitivity is calibrated from the noise measured in them instead. See
+    /// [`calibrate_sensitivity`].
+    pub fn background(mut self, regions: Vec<Region>) -> Self {
+        self.background = regions;
+        self
+    }
+    /// Sets whether the sensitivity is estimated from the image instead (defaults to `false`). See
+    /// [`estimate_sensitivity`].
+    ///
+    /// If the estimate fails, the sensitivity set with [`StitchConfig::sensitivity`] is used. Background
+    /// regions take precedence over this.
+    pub fn auto_sensitivity(mut self, auto_sensitivity: bool) -> Self {
+        self.auto_sensitivity = auto_sensitivity;
+        self
+    }
+    /// Sets whether the scan interval is derived from the heights of the gutters between panels instead
+    /// (defaults to `false`). See [`estimate_scan_interval`].
+    ///
+    /// If the estimate fails, the scan interval set with [`StitchConfig::scan_interval`] is used.
+    pub fn auto_scan_interval(mut self, auto_scan_interval: bool) -> Self {
+        self.auto_scan_interval = auto_scan_interval;
+        self
+    }
+    /// Sets the color of the gutters between panels, which rows are preferred to be cut in (defaults to
+    /// [`BackgroundMode::AnyUniform`]).
+    pub fn background_mode(mut self, background_mode: BackgroundMode) -> Self {
+        self.background_mode = background_mode;
+        self
+    }
+    /// Sets whether to avoid cutting between lines of text, and how far apart (in pixels) lines of text can
+    /// be at most (defaults to `None`, i.e. rows are cut wherever they are clean).
+    ///
+    /// Clean rows with text-like ink right above and right below them, in the same columns, are passed
+    /// over, so that dialogue on a plain background (such as borderless bubbles or narration) isn't split
+    /// across pages. If no other clean row is within reach, the least busy row which isn't between lines of
+    /// text is cut instead.
+    pub fn avoid_text(mut self, line_spacing: Option<u32>) -> Self {
+        self.text_band = line_spacing;
+        self
+    }
+    /// Sets how many threads rows are scored on (defaults to `None`, i.e. rayon's global thread pool).
+    pub fn threads(mut self, threads: Option<usize>) -> Self {
+        self.threads = threads;
+        self
+    }
+    /// Sets a directory to keep the score of every row in, so that stitching the same images again skips
+    /// scanning them entirely (defaults to none). See [`ScoreCache`].
+    pub fn score_cache(mut self, score_cache: Option<ScoreCache>) -> Self {
+        self.score_cache = score_cache;
+        self
+    }
+    /// Sets whether every row is scored up front, in parallel, before walking from cut to cut (defaults to
+    /// `false`).
+    ///
+    /// Finding the cuts is sequential, as each cut depends on the one before it, so it only keeps a single
+    /// core busy. Scoring every row first keeps all of them busy, at the cost of scoring about
+    /// `scan_interval` times as many rows, so this is faster on long strips on machines with many cores,
+    /// and on strips with few clean rows, where the scan has to look far back from each cut. The walk
+    /// itself then only looks up scores, and finds the same cuts. A [`ScoreCache`] scores rows the same
+    /// way, and also keeps the scores for later runs.
+    pub fn parallel_scoring(mut self, parallel_scoring: bool) -> Self {
+        self.parallel_scoring = parallel_scoring;
+        self
+    }
+    /// Sets where to report how far the scan for splitpoints has reached (defaults to nowhere). See
+    /// [`Progress`].
+    pub fn progress(mut self, progress: Option<Arc<dyn Progress>>) -> Self {
+        self.progress = progress.map(ProgressHook);
+        self
+    }
+    /// Sets whether a page which can't be cut at a clean row is an error, rather than being cut at the
+    /// least busy row (defaults to `false`).
+    ///
+    /// [`crate::Stitcher`] still makes the cut, so that it can be reviewed, but refuses to export the pages
+    /// until the cut has been moved, removed, or added back by hand. Pipelines fail straight away. See also [`crate::LoadConfig::strict`] and [`ExportConfig::strict`].
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+    /// The heights pages of the given image must stay within, from the aspect ratio limits.
+    fn page_heights(&self, image: &impl StripRows) -> (usize, usize) {
+        let width = image.width() as f64;
+        let (min, max) = self.aspect_ratio;
+        (
+            min.map_or(0, |ratio| (ratio * width).ceil() as usize),
+            max.map_or(usize::MAX, |ratio| ((ratio * width) as usize).max(1)),
+        )
+    }
+    /// The scan interval to stitch the given image with, at the given sensitivity.
+    fn scan_interval_for(&self, image: &impl StripRows, sensitivity: u8) -> usize {
+        self.auto_scan_interval
+            .then(|| estimate_scan_interval(image, sensitivity))
+            .flatten()
+            .unwrap_or(self.scan_interval)
+    }
+    /// The sensitivity to stitch the given image with.
+    pub(crate) fn sensitivity_for(&self, image: &impl StripRows) -> u8 {
+        calibrate_sensitivity(image, &self.background)
+            .or_else(|| {
+                self.auto_sensitivity
+                    .then(|| estimate_sensitivity(image))
+                    .flatten()
+            })
+            .unwrap_or(self.sensitivity)
+    }
+}
+
+/// How the number of pages the combined image is cut into is decided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SplitStrategy {
+    /// Pages are cut as late as possible without exceeding [`StitchConfig::target_height`], so the number
+    /// of pages depends on where the image can be cut.
+    #[default]
+    TargetHeight,
+    /// The image is cut into about this many pages of roughly equal height, for platforms which limit how
+    /// many pages an episode may have. A value of 0 is treated as 1, and one above the height of the image
+    /// as that height.
+    ///
+    /// Each chapter gets a share of the pages in proportion to its height (but at least one), and each cut
+    /// is made at the clean row closest to where it would make the rest of the pages of its chapter
+    /// equally tall. The target height is ignored.
+    PageCount(usize),
+}
+
+/// The color of the gutters between panels.
+///
+/// Uniform rows of the background color are preferred over other uniform rows (such as a solid-colored
+/// sky in the middle of a panel) when choosing where to cut. If there aren't any within reach, any
+/// uniform row is used, as with [`BackgroundMode::AnyUniform`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackgroundMode {
+    /// The background is detected as the most common color at the left and right edges of the strip.
+    Auto,
+    White,
+    Black,
+    Color(Rgb<u8>),
+    /// Any uniform row is as good as any other, whatever its color.
+    #[default]
+    AnyUniform,
+}
+
+impl BackgroundMode {
+    /// The background color of the given image, if any.
+    fn color(self, image: &impl StripRows) -> Option<Rgb<u8>> {
+        match self {
+            BackgroundMode::Auto => detect_background(image),
+            BackgroundMode::White => Some(Rgb([255, 255, 255])),
+            BackgroundMode::Black => Some(Rgb([0, 0, 0])),
+            BackgroundMode::Color(color) => Some(color),
+            BackgroundMode::AnyUniform => None,
+        }
+    }
+}
+
+/// Finds the most common color at the left and right edges of the image, which is where the gutters
+/// between panels usually show through.
+///
+/// Similar colors are counted together, and their average is returned.
+fn detect_background(image: &impl StripRows) -> Option<Rgb<u8>> {
+    let last_column = image.width().checked_sub(1)?;
+    // (count, sum of each channel) of each bucket of similar colors
+    let mut buckets: HashMap<[u8; 3], (u64, [u64; 3])> = HashMap::new();
+    for row in 0..image.height() {
+        for column in [0, last_column] {
+            let pixel = image.pixel(column, row).0;
+            let (count, sums) = buckets
+                .entry(pixel.map(|channel| channel >> 4))
+                .or_default();
+            *count += 1;
+            for (sum, channel) in sums.iter_mut().zip(pixel) {
+                *sum += channel as u64;
+            }
+        }
+    }
+    let (count, sums) = buckets.into_values().max_by_key(|&(count, _)| count)?;
+    Some(Rgb(sums.map(|sum| (sum / count) as u8)))
+}
+
+/// What makes a row a clean splitpoint.
+#[derive(Debug, Clone, Copy)]
+struct Criteria {
+    sensitivity: u8,
+    /// If set, clean rows of this color are preferred.
+    background: Option<Rgb<u8>>,
+    /// If set, clean rows between lines of text up to this far apart aren't cut.
+    text_band: Option<u32>,
+    /// Rows which would make a page shorter than this aren't cut, unless they start a chapter.
+    min_height: usize,
+    /// Rows which would make a page taller than this aren't cut, unless they start a chapter.
+    max_height: usize,
+}
+
+impl Criteria {
+    /// Whether a clean row lies between lines of text, if text is being avoided.
+    fn splits_text(&self, image: &impl StripRows, row: usize) -> bool {
+        self.text_band
+            .is_some_and(|band| between_text_lines(image, row, band))
+    }
+    /// Whether a clean row is the color of the background, if there is one.
+    fn is_background(&self, image: &impl StripRows, row: usize) -> bool {
+        let limit = u8::MAX - self.sensitivity;
+        self.background.is_none_or(|background| {
+            let color = image.pixel(0, row as u32);
+            color
+                .0
+                .iter()
+                .zip(background.0)
+                .all(|(&a, b)| a.abs_diff(b) <= limit)
+        })
+    }
+}
+
+/// Lists the rows that should be scanned when looking for a splitpoint above `cursor`, from the bottom up.
+fn scan_rows(
+    image: &impl StripRows,
+    cursor: usize,
+    target_height: usize,
+    scan_interval: usize,
+) -> Vec<usize> {
+    (0..cursor.min(image.height() as usize))
+        .rev()
+        .take(target_height)
+        .step_by(scan_interval.max(1))
+        .collect()
+}
+
+/// Calculates the max pixel diff (in luma) between horizontally adjacent pixels for each of the given rows.
+///
+/// Returns `(row, max pixel diff)` pairs in the same order as `rows`.
+pub(crate) fn score_rows(image: &impl StripRows, rows: &[usize]) -> Vec<(usize, u8)> {
+    let channels = Rgb::<u8>::CHANNEL_COUNT as usize;
+    rows.iter()
+        .map(|&row| {
+            let score = image
+                .row(row as u32)
+                .chunks_exact(channels)
+                .map(|pixel| Rgb::from_slice(pixel).to_luma().0[0])
+                .tuple_windows::<(_, _)>()
+                .fold(0, |a, (luma_a, luma_b)| a.max(luma_a.abs_diff(luma_b)));
+            (row, score)
+        })
+        .collect()
+}
+
+/// How many rows are scored by each task when scoring every row of the combined image.
+const ROWS_PER_CHUNK: usize = 256;
+
+/// Scores every row of the image in parallel, the same way as [`score_rows`].
+pub(crate) fn score_every_row(image: &impl StripRows) -> Vec<u8> {
+    let rows: Vec<usize> = (0..image.height() as usize).collect();
+    rows.par_chunks(ROWS_PER_CHUNK)
+        .flat_map_iter(|rows| score_rows(image, rows))
+        .map(|(_, score)| score)
+        .collect()
+}
+
+/// Where the scores of scanned rows come from.
+pub(crate) enum RowScores<'a> {
+    /// Rows are scored as they are scanned.
+    Scan,
+    /// The score of every row, as read from a [`ScoreCache`] or scored up front.
+    Cached(&'a [u8]),
+}
+
+impl RowScores<'_> {
+    /// Returns `(row, max pixel diff)` pairs in the same order as `rows`.
+    fn score(&self, image: &impl StripRows, rows: &[usize]) -> Vec<(usize, u8)> {
+        match self {
+            RowScores::Scan => score_rows(image, rows),
+            RowScores::Cached(scores) => rows.iter().map(|&row| (row, scores[row])).collect(),
+        }
+    }
+}
+
+/// A rectangle of the combined image, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// How much noise above the measured noise floor is still accepted as background.
+const NOISE_MARGIN: u8 = 4;
+
+/// Derives a sensitivity from regions of the combined image which are known to be background, rather than
+/// relying on a single global setting.
+///
+/// Each row of each region is scored the same way as rows are when looking for splitpoints, and the
+/// returned sensitivity accepts the noisiest of them (plus a small margin), so that rows as clean as the
+/// marked background are cut at. This is much more robust for noisy JPEG raws.
+///
+/// Regions are clipped to the image. Returns `None` if no region overlaps the image.
+pub fn calibrate_sensitivity(image: &impl StripRows, regions: &[Region]) -> Option<u8> {
+    let (image_width, image_height) = (image.width(), image.height());
+    let noise_floor = regions
+        .iter()
+        .flat_map(|region| {
+            let columns =
+                region.x.min(image_width)..region.x.saturating_add(region.width).min(image_width);
+            let rows = region.y.min(image_height)
+                ..region.y.saturating_add(region.height).min(image_height);
+            // a region must be at least two pixels wide to have any pairs of pixels to compare
+            let rows = if columns.len() >= 2 { rows } else { 0..0 };
+            rows.map(move |row| {
+                columns
+                    .clone()
+                    .map(|column| image.pixel(column, row).to_luma().0[0])
+                    .tuple_windows::<(_, _)>()
+                    .fold(0, |a, (luma_a, luma_b)| a.max(luma_a.abs_diff(luma_b)))
+            })
+        })
+        .max()?;
+    Some(u8::MAX - noise_floor.saturating_add(NOISE_MARGIN))
+}
+
+/// How many rows are sampled (at most) when estimating the sensitivity.
+const SENSITIVITY_SAMPLES: usize = 4096;
+
+/// The share of sampled rows which must look like background for a sensitivity to be suggested.
+const MIN_BACKGROUND_SHARE: f64 = 0.01;
+
+/// Suggests a sensitivity for the image from the scores of a sample of its rows, without needing to know
+/// where its background is.
+///
+/// Rows fall into two groups: background rows, whose scores are just noise (low for clean digital raws,
+/// higher for noisy scans), and rows with content in them, whose scores are much higher. The two are
+/// separated with Otsu's method, and the returned sensitivity accepts the noisiest 95% of the background
+/// rows (plus a small margin), as with [`calibrate_sensitivity`].
+///
+/// Returns `None` if the image has too few rows which look like background to tell.
+pub fn estimate_sensitivity(image: &impl StripRows) -> Option<u8> {
+    let height = image.height() as usize;
+    let step = height.div_ceil(SENSITIVITY_SAMPLES).max(1);
+    let rows: Vec<usize> = (0..height).step_by(step).collect();
+    let mut scores: Vec<u8> = score_rows(image, &rows)
+        .into_iter()
+        .map(|(_, score)| score)
+        .collect();
+    scores.sort_unstable();
+
+    // Otsu's method: the threshold which maximises the variance between the two groups
+    let mut histogram = [0u64; 256];
+    for &score in &scores {
+        histogram[score as usize] += 1;
+    }
+    let total = scores.len() as f64;
+    let sum: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(score, &count)| score as f64 * count as f64)
+        .sum();
+    let (mut below, mut below_sum) = (0.0, 0.0);
+    let mut best = (0.0, None);
+    for (threshold, &count) in histogram.iter().enumerate().take(255) {
+        below += count as f64;
+        below_sum += threshold as f64 * count as f64;
+        let above = total - below;
+        if below == 0.0 || above == 0.0 {
+            continue;
+        }
+        let between = below * above * (below_sum / below - (sum - below_sum) / above).powi(2);
+        if between > best.0 {
+            best = (between, Some(threshold as u8));
+        }
+    }
+    let threshold = best.1?;
+    let background = scores.partition_point(|&score| score <= threshold);
+    if (background as f64) < total * MIN_BACKGROUND_SHARE {
+        return None;
+    }
+    let noise_floor = scores[(background - 1) * 95 / 100];
+    Some(u8::MAX - noise_floor.saturating_add(NOISE_MARGIN))
+}
+
+/// How many windows of consecutive rows are sampled (at most) when estimating the scan interval.
+const GUTTER_WINDOWS: usize = 16;
+
+/// How many rows tall each window sampled when estimating the scan interval is.
+const GUTTER_WINDOW_HEIGHT: usize = 2048;
+
+/// Derives a scan interval which won't skip over the gutters between panels, from the heights of the
+/// gutters in a sample of the image.
+///
+/// A row is only cut at if it and the next two rows scanned are all clean, so a gutter must span three
+/// scanned rows to be found. The returned interval fits three scanned rows into all but the thinnest tenth
+/// of the gutters found, wherever they start (rows of a single color at the given sensitivity, as with
+/// [`StitchConfig::sensitivity`]).
+///
+/// Returns `None` if no gutters were found in the sample.
+pub fn estimate_scan_interval(image: &impl StripRows, sensitivity: u8) -> Option<usize> {
+    let height = image.height() as usize;
+    let limit = u8::MAX - sensitivity;
+    let windows = height.div_ceil(GUTTER_WINDOW_HEIGHT).min(GUTTER_WINDOWS);
+    let starts: Vec<usize> = (0..windows)
+        .map(|window| window * height.saturating_sub(GUTTER_WINDOW_HEIGHT) / (windows - 1).max(1))
+        .collect();
+    let mut gutters: Vec<usize> = starts
+        .par_iter()
+        .flat_map_iter(|&start| {
+            let rows: Vec<usize> = (start..(start + GUTTER_WINDOW_HEIGHT).min(height)).collect();
+            let scores = score_rows(image, &rows);
+            // Runs of clean rows, leaving out those cut off by the edges of the window, as their full
+            // height isn't known.
+            scores
+                .chunk_by(|a, b| (a.1 <= limit) == (b.1 <= limit))
+                .filter(|run| run[0].1 <= limit)
+                .filter(|run| run[0].0 != rows[0] && run[run.len() - 1].0 != rows[rows.len() - 1])
+                .map(|run| run.len())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    gutters.sort_unstable();
+    let thinnest = *gutters.get(gutters.len() / 10)?;
+    Some((thinnest / 3).max(1))
+}
+
 /// Finds all the rows of pixels which should be cut.
 ///
 /// Input parameters:
 ///  - `image` - A reference to the combined image.
-///  - `target_height` - How many pixels tall each page should be at most.
-///  - `scan_interval` - The interval at which rows of pixels will be scanned.
-///  - `sensitivity` - A value between 0 and 255, determining the threshold at which a row can be marked as a splitpoint.
-///     - 0 would be no sensitivity, i.e. it doesn't matter what the pixels in the row are, it will be set as a splitpoint.
-///     - 255 would be full sensitivity, i.e. all pixels in the row must be exactly the same color for it to be set as a splitpoint.
+///  - `config` - Where and how to cut the image. See [`StitchConfig`].
+///  - `boundaries` - Rows at which the image must be cut, such as the start of each chapter. Any boundary within
+///    reach of the previous splitpoint is used as the next splitpoint, so pages never straddle a boundary.
 pub fn find_splitpoints(
-    image: &RgbImage,
-    target_height: usize,
-    scan_interval: usize,
-    sensitivity: u8,
+    image: &impl StripRows,
+    config: &StitchConfig,
+    boundaries: &[usize],
 ) -> Vec<usize> {
-    let target_height = target_height + 1;
-    let limit = u8::MAX - sensitivity;
+    detect_splitpoints(image, config, boundaries, None, None)
+}
+
+/// Does exactly the same thing as [`find_splitpoints`], except that if the config is strict (see
+/// [`StitchConfig::strict`]), it throws [`ImageSplitterError::WorstCaseCut`] for the first row which had to
+/// be cut even though it isn't clean.
+pub(crate) fn try_find_splitpoints(
+    image: &impl StripRows,
+    config: &StitchConfig,
+    boundaries: &[usize],
+) -> Result<Vec<usize>, ImageSplitterError> {
+    let mut worst_cases = Vec::new();
+    let splitpoints = detect_splitpoints(
+        image,
+        config,
+        boundaries,
+        config.strict.then_some(&mut worst_cases),
+        None,
+    );
+    match worst_cases.first() {
+        Some(&row) => Err(ImageSplitterError::WorstCaseCut(row)),
+        None => Ok(splitpoints),
+    }
+}
+
+/// Called with each splitpoint as soon as a scan has settled on it, in order, and whether it was cut for lack
+/// of a clean row.
+pub(crate) type CutHook<'a> = &'a (dyn Fn(usize, bool) + Sync);
+
+/// The scan behind [`find_splitpoints`], which also records the rows cut for lack of a clean row in
+/// `worst_cases`, and reports each splitpoint to `cuts` as soon as it is found, if provided.
+pub(crate) fn detect_splitpoints(
+    image: &impl StripRows,
+    config: &StitchConfig,
+    boundaries: &[usize],
+    worst_cases: Option<&mut Vec<usize>>,
+    cuts: Option<CutHook>,
+) -> Vec<usize> {
+    with_threads(config.threads, || {
+        let (min_height, max_height) = config.page_heights(image);
+        let sensitivity = config.sensitivity_for(image);
+        let scan_interval = config.scan_interval_for(image, sensitivity);
+        let criteria = Criteria {
+            sensitivity,
+            background: config.background_mode.color(image),
+            text_band: config.text_band,
+            min_height,
+            max_height,
+        };
+        let report = ScanReport {
+            marks: None,
+            progress: config.progress.as_ref().map(|hook| &*hook.0),
+            worst_cases,
+            cuts,
+        };
+        let cached = match &config.score_cache {
+            Some(score_cache) => Some(score_cache.scores(image)),
+            None => config.parallel_scoring.then(|| score_every_row(image)),
+        };
+        let scores = match &cached {
+            Some(scores) => RowScores::Cached(scores),
+            None => RowScores::Scan,
+        };
+        match config.strategy {
+            SplitStrategy::TargetHeight => scan_splitpoints(
+                image,
+                config.target_height,
+                scan_interval,
+                criteria,
+                &scores,
+                boundaries,
+                report,
+            ),
+            SplitStrategy::PageCount(pages) => spread_splitpoints(
+                image,
+                pages,
+                scan_interval,
+                criteria,
+                &scores,
+                boundaries,
+                report,
+            ),
+        }
+    })
+}
+
+/// Where and how to scan a strip for splitpoints.
+pub(crate) struct Scan<'a> {
+    pub(crate) config: &'a StitchConfig,
+    /// Rows at which the strip must be cut, such as the start of each chapter.
+    pub(crate) boundaries: &'a [usize],
+    /// The tops of the pages the strip is made of, if it looks like it was already stitched.
+    pub(crate) seams: &'a [usize],
+}
+
+impl Scan<'_> {
+    /// Finds the splitpoints, cutting the strip again along its seams if it has any.
+    pub(crate) fn run(
+        &self,
+        image: &impl StripRows,
+        worst_cases: Option<&mut Vec<usize>>,
+        cuts: Option<CutHook>,
+    ) -> Vec<usize> {
+        if self.seams.is_empty() {
+            detect_splitpoints(image, self.config, self.boundaries, worst_cases, cuts)
+        } else {
+            resplit_splitpoints(
+                image,
+                self.config,
+                self.boundaries,
+                self.seams,
+                worst_cases,
+                cuts,
+            )
+        }
+    }
+}
+
+/// Does exactly the same thing as [`detect_splitpoints`], but for a strip of pages which were already
+/// stitched, whose tops (other than the first) are given as `seams`.
+///
+/// Consecutive old pages are merged for as long as they fit within the target height together, so the old
+/// cuts are kept wherever possible, and only old pages which are still too tall are scanned again. Seams
+/// which don't fall on clean rows (under the sensitivity of the config) aren't kept, so the old pages on
+/// either side of them are treated as one. The seams
+/// are ignored when splitting into a number of pages, which scans the whole strip as usual.
+pub(crate) fn resplit_splitpoints(
+    image: &impl StripRows,
+    config: &StitchConfig,
+    boundaries: &[usize],
+    seams: &[usize],
+    mut worst_cases: Option<&mut Vec<usize>>,
+    cuts: Option<CutHook>,
+) -> Vec<usize> {
+    if !matches!(config.strategy, SplitStrategy::TargetHeight) {
+        return detect_splitpoints(image, config, boundaries, worst_cases, cuts);
+    }
+    let height = image.height() as usize;
+    let target_height = config
+        .target_height
+        .min(config.page_heights(image).1)
+        .max(1);
+    // Seams are only kept on clean rows (on both sides of the cut), so the old pages on either side of any
+    // other seam are merged and scanned again like any other span.
+    let limit = u8::MAX - config.sensitivity_for(image);
+    let clean_seams: Vec<usize> = seams
+        .iter()
+        .copied()
+        .filter(|&seam| {
+            seam > 0
+                && seam < height
+                && score_rows(image, &[seam - 1, seam])
+                    .iter()
+                    .all(|&(_, score)| score <= limit)
+        })
+        .collect();
+    let mut old_cuts: Vec<usize> = clean_seams
+        .iter()
+        .chain(boundaries)
+        .copied()
+        .filter(|&cut| cut > 0 && cut < height)
+        .chain([height])
+        .collect();
+    old_cuts.sort_unstable();
+    old_cuts.dedup();
+
+    // merge old pages, cutting at the furthest seam within reach of the previous cut
+    let mut merged = vec![0];
+    let mut furthest = None;
+    for cut in old_cuts {
+        let previous = *merged.last().expect("merged pages start at 0");
+        if cut - previous > target_height {
+            merged.extend(furthest.take());
+        }
+        let previous = *merged.last().expect("merged pages start at 0");
+        if cut == height || boundaries.contains(&cut) || cut - previous > target_height {
+            merged.push(cut);
+            furthest = None;
+        } else {
+            furthest = Some(cut);
+        }
+    }
+
+    // scan old pages which are too tall on their own, as if they were separate strips
+    let stitch = config.clone().score_cache(None).progress(None);
     let mut splitpoints = vec![0];
-    let mut cursor = target_height;
-    loop {
-        let row_max_pixel_diffs = image
-            .rows()
-            .map(|row| {
-                row.into_iter()
-                    .tuple_windows::<(_, _)>()
-                    .fold(0, |a, (pixel_a, pixel_b)| {
-                        a.max(pixel_a.to_luma().0[0].abs_diff(pixel_b.to_luma().0[0]))
-                    })
-            })
-            .enumerate()
-            .take(cursor)
-            .rev()
-            .take(target_height)
-            .step_by(scan_interval)
-            .tuple_windows::<(_, _, _)>();
-        let mut min_splitpoint: Option<(usize, u8)> = None;
-        // This is to figure out how the loop exits. If a clean splitpoint (splitpoint which is under threshold) is found,
-        // we won't need to push the min_splitpoint into the splitpoints vector.
-        let mut clean_splitpoint_found = false;
-        for (a, b, c) in row_max_pixel_diffs {
-            // Debug mode
-            // If all three rows' pixel diffs are below the threshold, mark it as a cut point.
-            if a.1 <= limit && b.1 <= limit && c.1 <= limit {
-                splitpoints.push(a.0);
-                cursor = a.0 + target_height;
-                clean_splitpoint_found = true;
-                break;
-            }
-            // Otherwise, keep track of the minimum maximum of the three rows' max pixel diff.
-            let curr_max = a.1.max(b.1.max(c.1));
-            match min_splitpoint {
-                Some(prev) => {
-                    if prev.1 > curr_max {
-                        min_splitpoint = Some(a)
+    for (start, length) in page_ranges(&merged) {
+        if length > target_height {
+            let window = RowWindow {
+                image,
+                start: start as u32,
+                height: length as u32,
+            };
+            let mut forced = Vec::new();
+            // the end of the window is reported below, as the end of the old page
+            let window_cuts = cuts.map(|cuts| {
+                move |cut: usize, forced: bool| {
+                    if cut < length {
+                        cuts(start + cut, forced)
                     }
                 }
-                None => min_splitpoint = Some(a),
+            });
+            let window_cuts = detect_splitpoints(
+                &window,
+                &stitch,
+                &[],
+                worst_cases.is_some().then_some(&mut forced),
+                window_cuts.as_ref().map(|hook| hook as CutHook),
+            );
+            splitpoints.extend(
+                window_cuts
+                    .into_iter()
+                    .filter(|&cut| cut > 0 && cut < length)
+                    .map(|cut| start + cut),
+            );
+            if let Some(worst_cases) = worst_cases.as_deref_mut() {
+                worst_cases.extend(forced.into_iter().map(|row| start + row));
             }
         }
-        if !clean_splitpoint_found && min_splitpoint.is_some() {
-            splitpoints.push(min_splitpoint.unwrap().0);
-            cursor = min_splitpoint.unwrap().0 + target_height;
-        }
-        if cursor > image.height() as usize {
-            break;
+        splitpoints.push(start + length);
+        if let Some(cuts) = cuts {
+            cuts(start + length, false);
         }
     }
-    splitpoints.push(image.height() as usize);
     splitpoints
 }
 
@@ -89,84 +811,321 @@ pub fn find_splitpoints(
 /// marked red (if max pixel diff exceeds threshold) or sky blue (if max pixel diff is below threshold)
 /// to indicate the max pixel diff.
 ///
-/// As a copy of the image must be created, this function may be slower than `find_splitpoints`.
+/// As the marks are only drawn once all splitpoints have been found (so that they don't affect the scan),
+/// this function may be slightly slower than `find_splitpoints`.
 ///
 /// Input parameters:
 ///  - `image` - A mutable reference to the combined image.
 ///  - `target_height` - How many pixels tall each page should be at most.
 ///  - `scan_interval` - The interval at which rows of pixels will be scanned.
 ///  - `sensitivity` - A value between 0 and 255, determining the threshold at which a row can be marked as a splitpoint.
-///     - 0 would be no sensitivity, i.e. it doesn't matter what the pixels in the row are, it will be set as a splitpoint.
-///     - 255 would be full sensitivity, i.e. all pixels in the row must be exactly the same color for it to be set as a splitpoint.
+///    - 0 would be no sensitivity, i.e. it doesn't matter what the pixels in the row are, it will be set as a splitpoint.
+///    - 255 would be full sensitivity, i.e. all pixels in the row must be exactly the same color for it to be set as a splitpoint.
+///  - `boundaries` - Rows at which the image must be cut, such as the start of each chapter. Any boundary within
+///    reach of the previous splitpoint is used as the next splitpoint, so pages never straddle a boundary.
 pub fn find_splitpoints_debug(
-    image: &mut RgbImage,
+    image: &mut Strip,
     target_height: usize,
     scan_interval: usize,
     sensitivity: u8,
+    boundaries: &[usize],
 ) -> Vec<usize> {
-    let target_height = target_height + 1;
-    let limit = u8::MAX - sensitivity;
+    let mut marks = Vec::new();
+    let splitpoints = scan_splitpoints(
+        image,
+        target_height,
+        scan_interval,
+        Criteria {
+            sensitivity,
+            background: None,
+            text_band: None,
+            min_height: 0,
+            max_height: usize::MAX,
+        },
+        &RowScores::Scan,
+        boundaries,
+        ScanReport {
+            marks: Some(&mut marks),
+            ..Default::default()
+        },
+    );
+    for (row, to_mark, colour) in marks {
+        for pixel in 0..to_mark {
+            image.put_pixel(pixel, row, colour);
+        }
+    }
+    splitpoints
+}
+
+/// What the scan reports as it goes, besides the splitpoints themselves.
+#[derive(Default)]
+struct ScanReport<'a> {
+    /// If provided, a `(row, length, colour)` mark is recorded for each scan line which was considered.
+    marks: Option<&'a mut Vec<(u32, u32, Rgb<u8>)>>,
+    /// If provided, each splitpoint found is reported as the row the scan has reached.
+    progress: Option<&'a dyn Progress>,
+    /// If provided, each row which was cut even though it isn't clean is recorded.
+    worst_cases: Option<&'a mut Vec<usize>>,
+    /// If provided, each splitpoint is reported as soon as it is found.
+    cuts: Option<CutHook<'a>>,
+}
+
+/// The scan shared by `find_splitpoints` and `find_splitpoints_debug`.
+fn scan_splitpoints(
+    image: &impl StripRows,
+    target_height: usize,
+    scan_interval: usize,
+    criteria: Criteria,
+    scores: &RowScores,
+    boundaries: &[usize],
+    report: ScanReport,
+) -> Vec<usize> {
+    let ScanReport {
+        mut marks,
+        progress,
+        mut worst_cases,
+        cuts,
+    } = report;
+    let height = image.height() as usize;
+    // Pages can't be taller than the image, nor less than a row tall, which also keeps the cursor from
+    // overflowing.
+    let target_height = target_height
+        .min(criteria.max_height)
+        .clamp(1, height.max(1))
+        + 1;
+    let limit = u8::MAX - criteria.sensitivity;
+    let splits_text = |row: usize| criteria.splits_text(image, row);
+    let is_background = |row: usize| criteria.is_background(image, row);
     let mut splitpoints = vec![0];
     let mut cursor = target_height;
-    let ref_image = image.clone();
+    // the width of the mark for a given max pixel diff
+    let mark_width = |diff: u8| (image.width() as f32 * (diff as f32 / u8::MAX as f32)) as u32;
+    // the first boundary after the given splitpoint, if there is one
+    let next_boundary = |splitpoint: usize| {
+        boundaries
+            .iter()
+            .copied()
+            .filter(|&boundary| boundary > splitpoint && boundary < height)
+            .min()
+    };
     loop {
-        let row_max_pixel_diffs = ref_image
-            .rows()
-            .map(|row| {
-                row.into_iter()
-                    .tuple_windows::<(_, _)>()
-                    .fold(0, |a, (pixel_a, pixel_b)| {
-                        a.max(pixel_a.to_luma().0[0].abs_diff(pixel_b.to_luma().0[0]))
+        let previous = splitpoints[splitpoints.len() - 1];
+        // whether the splitpoint found in this round is cut for lack of a clean row
+        let mut forced = false;
+        match next_boundary(previous) {
+            // If a boundary is within reach, cut there regardless of what the rows look like.
+            Some(boundary) if boundary < cursor => {
+                splitpoints.push(boundary);
+                cursor = boundary + target_height;
+            }
+            _ => {
+                let mut rows = scan_rows(image, cursor, target_height, scan_interval);
+                rows.retain(|&row| row >= previous.saturating_add(criteria.min_height));
+                let scores = scores.score(image, &rows);
+                let row_max_pixel_diffs = scores.iter().copied().tuple_windows::<(_, _, _)>();
+                // If any clean row is the color of the background, cut at the first one.
+                let background_row = criteria.background.and_then(|_| {
+                    row_max_pixel_diffs.clone().find(|&(a, b, c)| {
+                        a.1 <= limit
+                            && b.1 <= limit
+                            && c.1 <= limit
+                            && is_background(a.0)
+                            && !splits_text(a.0)
                     })
-            })
-            .enumerate()
-            .take(cursor)
-            .rev()
-            .take(target_height)
-            .step_by(scan_interval)
-            .tuple_windows::<(_, _, _)>();
-        let mut min_splitpoint: Option<(usize, u8)> = None;
-        // This is to figure out how the loop exits. If a clean splitpoint (splitpoint which is under threshold) is found,
-        // we won't need to push the min_splitpoint into the splitpoints vector.
-        let mut clean_splitpoint_found = false;
-        for (a, b, c) in row_max_pixel_diffs {
-            // If all three rows' pixel diffs are below the threshold, mark it as a cut point.
-            if a.1 <= limit && b.1 <= limit && c.1 <= limit {
-                let curr_max = a.1.max(b.1.max(c.1));
-                let to_mark = (image.width() as f32 * (curr_max as f32 / u8::MAX as f32)) as u32;
-                for pixel in 0..to_mark {
-                    image.put_pixel(pixel, a.0 as u32, Rgb([53, 81, 92]));
+                });
+                if let Some((a, ..)) = background_row {
+                    splitpoints.push(a.0);
+                    cursor = a.0 + target_height;
+                } else {
+                    let mut min_splitpoint: Option<(usize, u8)> = None;
+                    // This is to figure out how the loop exits. If a clean splitpoint (splitpoint which is under threshold) is found,
+                    // we won't need to push the min_splitpoint into the splitpoints vector.
+                    let mut clean_splitpoint_found = false;
+                    for (a, b, c) in row_max_pixel_diffs {
+                        let curr_max = a.1.max(b.1.max(c.1));
+                        // If all three rows' pixel diffs are below the threshold, mark it as a cut point.
+                        let clean = a.1 <= limit && b.1 <= limit && c.1 <= limit;
+                        if clean && splits_text(a.0) {
+                            // passed over entirely, so the fallback doesn't cut here either
+                            if let Some(marks) = marks.as_mut() {
+                                marks.push((a.0 as u32, mark_width(curr_max), Rgb([255, 0, 0])));
+                            }
+                            continue;
+                        }
+                        if clean {
+                            if let Some(marks) = marks.as_mut() {
+                                marks.push((a.0 as u32, mark_width(curr_max), Rgb([53, 81, 92])));
+                            }
+                            splitpoints.push(a.0);
+                            cursor = a.0 + target_height;
+                            clean_splitpoint_found = true;
+                            break;
+                        }
+                        // Otherwise, keep track of the minimum maximum of the three rows' max pixel diff.
+                        if let Some(marks) = marks.as_mut() {
+                            marks.push((a.0 as u32, mark_width(curr_max), Rgb([255, 0, 0])));
+                        }
+                        match min_splitpoint {
+                            Some(prev) => {
+                                if prev.1 > curr_max {
+                                    min_splitpoint = Some(a)
+                                }
+                            }
+                            None => min_splitpoint = Some(a),
+                        }
+                    }
+                    match (clean_splitpoint_found, min_splitpoint) {
+                        (true, _) => {}
+                        (false, Some((row, _))) => {
+                            splitpoints.push(row);
+                            cursor = row + target_height;
+                            forced = true;
+                            if let Some(worst_cases) = worst_cases.as_mut() {
+                                worst_cases.push(row);
+                            }
+                        }
+                        // Too few rows were scanned to compare any (e.g. the scan interval is longer than a
+                        // page), so cut as far down as the page reaches.
+                        (false, None) => {
+                            let row = (cursor - 1).min(height.saturating_sub(1));
+                            if row > previous {
+                                splitpoints.push(row);
+                                cursor = row + target_height;
+                                forced = true;
+                                if let Some(worst_cases) = worst_cases.as_mut() {
+                                    worst_cases.push(row);
+                                }
+                            }
+                        }
+                    }
                 }
-                splitpoints.push(a.0);
-                cursor = a.0 + target_height;
-                clean_splitpoint_found = true;
-                break;
-            }
-            // Otherwise, keep track of the minimum maximum of the three rows' max pixel diff.
-            let curr_max = a.1.max(b.1.max(c.1));
-            let to_mark = (image.width() as f32 * (curr_max as f32 / u8::MAX as f32)) as u32;
-            for pixel in 0..to_mark {
-                image.put_pixel(pixel, a.0 as u32, Rgb([255, 0, 0]));
             }
+        }
+        let splitpoint = splitpoints[splitpoints.len() - 1];
+        if let Some(cuts) = cuts.filter(|_| splitpoint > previous) {
+            cuts(splitpoint, forced);
+        }
+        if cursor > height && next_boundary(splitpoint).is_none() {
+            break;
+        }
+        if let Some(progress) = progress {
+            progress.scanned(splitpoint, height);
+        }
+    }
+    splitpoints.push(height);
+    if let Some(cuts) = cuts {
+        cuts(height, false);
+    }
+    if let Some(progress) = progress {
+        progress.scanned(height, height);
+    }
+    splitpoints
+}
+
+/// Cuts the image into about `pages` pages of roughly equal height, for [`SplitStrategy::PageCount`].
+fn spread_splitpoints(
+    image: &impl StripRows,
+    pages: usize,
+    scan_interval: usize,
+    criteria: Criteria,
+    scores: &RowScores,
+    boundaries: &[usize],
+    report: ScanReport,
+) -> Vec<usize> {
+    let ScanReport {
+        progress,
+        mut worst_cases,
+        cuts,
+        ..
+    } = report;
+    let height = image.height() as usize;
+    let limit = u8::MAX - criteria.sensitivity;
+    let mut chapters: Vec<usize> = boundaries
+        .iter()
+        .copied()
+        .filter(|&boundary| boundary > 0 && boundary < height)
+        .collect();
+    chapters.sort_unstable();
+    chapters.dedup();
+    chapters.insert(0, 0);
+    chapters.push(height);
 
-            match min_splitpoint {
-                Some(prev) => {
-                    if prev.1 > curr_max {
-                        min_splitpoint = Some(a)
+    // a page is at least a row tall, so there can't be more pages than rows
+    let pages = pages.clamp(1, height.max(1)) as u128;
+
+    let mut splitpoints = vec![0];
+    for chapter in chapters.windows(2) {
+        let (start, end) = (chapter[0], chapter[1]);
+        // as many pages as the chapter's share, but enough to keep within the maximum height (the share is
+        // at most one more than the chapter's rows, so it fits back into a usize)
+        let share =
+            ((pages * (end - start) as u128 + height as u128 / 2) / height.max(1) as u128) as usize;
+        let share = share
+            .max((end - start).div_ceil(criteria.max_height))
+            .max(1);
+        for page in 1..share {
+            let previous = splitpoints[splitpoints.len() - 1];
+            // the rest of the chapter is shared equally between the pages left, so that earlier cuts
+            // which missed their ideal are made up for
+            let page_height = (end - previous) / (share - page + 1);
+            let ideal = previous + page_height;
+            // rows within half a page of the ideal cut, which leave the pages left room for the rest of
+            // the chapter
+            let rows: Vec<usize> = (ideal
+                .saturating_sub(page_height / 2)
+                .max(previous.saturating_add(criteria.min_height.max(1)))
+                .max(end.saturating_sub((share - page).saturating_mul(criteria.max_height)))
+                ..(ideal + page_height / 2)
+                    .min(end)
+                    .min(previous.saturating_add(criteria.max_height)))
+                .step_by(scan_interval.max(1))
+                .collect();
+            let scores = scores.score(image, &rows);
+            // Clean rows are preferred (those of the background color first), closest to the ideal cut
+            // first. If there aren't any, the least busy row is cut instead.
+            let best = scores
+                .iter()
+                .copied()
+                .tuple_windows::<(_, _, _)>()
+                .map(|(a, b, c)| (a.0, a.1.max(b.1.max(c.1))))
+                .filter(|&(row, diff)| !(diff <= limit && criteria.splits_text(image, row)))
+                .min_by_key(|&(row, diff)| {
+                    let distance = row.abs_diff(ideal);
+                    if diff <= limit {
+                        (false, !criteria.is_background(image, row), distance, 0)
+                    } else {
+                        (true, true, diff as usize, distance)
                     }
+                });
+            // If there's nowhere to cut within the limits, cut as close to the ideal cut as they allow.
+            let row = best
+                .map(|(row, _)| row)
+                .unwrap_or(ideal.min(previous.saturating_add(criteria.max_height)));
+            if row > previous && row < end {
+                splitpoints.push(row);
+                let forced = best.is_none_or(|(_, diff)| diff > limit);
+                if forced {
+                    if let Some(worst_cases) = worst_cases.as_mut() {
+                        worst_cases.push(row);
+                    }
+                }
+                if let Some(cuts) = cuts {
+                    cuts(row, forced);
+                }
+                if let Some(progress) = progress {
+                    progress.scanned(row, height);
                 }
-                None => min_splitpoint = Some(a),
             }
         }
-        if !clean_splitpoint_found && min_splitpoint.is_some() {
-            splitpoints.push(min_splitpoint.unwrap().0);
-            cursor = min_splitpoint.unwrap().0 + target_height;
-        }
-        if cursor > image.height() as usize {
-            break;
+        splitpoints.push(end);
+        if let Some(cuts) = cuts {
+            cuts(end, false);
         }
     }
-    splitpoints.push(ref_image.height() as usize);
+    splitpoints.dedup();
+    if let Some(progress) = progress {
+        progress.scanned(height, height);
+    }
     splitpoints
 }
 
@@ -177,124 +1136,2519 @@ fn get_num_digits(num: usize) -> usize {
     num.checked_ilog10().unwrap_or(0) as usize + 1
 }
 
+/// Device names which Windows reserves in every directory, regardless of the file extension.
+const WINDOWS_RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Makes a generated file name safe to create on Windows.
+///
+/// This is applied on every platform, as pages are often written to Windows shares from other OSes.
+///  - Trailing dots and spaces are removed, since Windows silently drops them.
+///  - Names whose stem is a reserved device name (e.g. `CON` or `lpt1.png`) are prefixed with an underscore.
+fn windows_safe_file_name(name: &str) -> String {
+    let name = name.trim_end_matches(['.', ' ']);
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    if WINDOWS_RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+    {
+        format!("_{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+/// Characters which are invalid in file names on at least one common filesystem.
+const INVALID_FILE_NAME_CHARS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// How characters which can't appear in a file name are handled when generating output names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SanitizePolicy {
+    /// Replace each invalid character with the given character.
+    ///
+    /// If the replacement is itself invalid, `_` is used instead.
+    Replace(char),
+    /// Drop invalid characters entirely.
+    Remove,
+}
+
+impl Default for SanitizePolicy {
+    fn default() -> Self {
+        SanitizePolicy::Replace('_')
+    }
+}
+
+/// Whether a character would break a file name on some filesystem.
+///
+/// Besides the characters Windows forbids, this covers control characters as well as invisible
+/// formatting characters (zero-width spaces, bidirectional overrides, byte order marks), which
+/// are valid on most filesystems but make names impossible to type or display.
+fn is_invalid_file_name_char(c: char) -> bool {
+    INVALID_FILE_NAME_CHARS.contains(&c)
+        || c.is_control()
+        || matches!(
+            c,
+            '\u{200B}'..='\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}' | '\u{FEFF}'
+        )
+}
+
+/// Sanitizes the name of a page according to the given policy, then makes it safe to create on Windows.
+///
+/// Names which end up empty are replaced with `_`.
+fn sanitize_file_name(name: &str, policy: SanitizePolicy) -> String {
+    let sanitized: String = match policy {
+        SanitizePolicy::Replace(replacement) => {
+            let replacement = if is_invalid_file_name_char(replacement) {
+                '_'
+            } else {
+                replacement
+            };
+            name.chars()
+                .map(|c| {
+                    if is_invalid_file_name_char(c) {
+                        replacement
+                    } else {
+                        c
+                    }
+                })
+                .collect()
+        }
+        SanitizePolicy::Remove => name
+            .chars()
+            .filter(|&c| !is_invalid_file_name_char(c))
+            .collect(),
+    };
+    let sanitized = windows_safe_file_name(&sanitized);
+    if sanitized.is_empty() {
+        "_".to_string()
+    } else {
+        sanitized
+    }
+}
+
+/// Converts a directory path into a form which can hold paths longer than `MAX_PATH` (260 characters).
+///
+/// On Windows, the path is made absolute and given the extended-length `\\?\` prefix (or `\\?\UNC\` for
+/// network shares). On other platforms, the path is returned as is.
+#[cfg(windows)]
+fn long_path(path: &Path) -> io::Result<PathBuf> {
+    use std::path::{Component, Prefix};
+
+    let absolute = std::path::absolute(path)?;
+    let mut components = absolute.components();
+    let mut extended = match components.next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::Disk(letter) => PathBuf::from(format!(r"\\?\{}:\", letter as char)),
+            Prefix::UNC(server, share) => {
+                let mut extended = PathBuf::from(r"\\?\UNC\");
+                extended.push(server);
+                extended.push(share);
+                extended
+            }
+            // already a verbatim or device path
+            _ => return Ok(absolute),
+        },
+        _ => return Ok(absolute),
+    };
+    // `absolute` has already resolved any `.` and `..` components, which verbatim paths do not allow.
+    for component in components {
+        if let Component::Normal(name) = component {
+            extended.push(name);
+        }
+    }
+    Ok(extended)
+}
+
+#[cfg(not(windows))]
+fn long_path(path: &Path) -> io::Result<PathBuf> {
+    Ok(path.to_path_buf())
+}
+
 #[derive(Error, Debug)]
 pub enum ImageSplitterError {
     #[error("Could not find the provided directory")]
     DirectoryNotFound,
     #[error("Insufficient permissions within the provided directory")]
     PermissionDenied,
+    #[error("Expected a page width of {expected} pixels, but a page was {actual} pixels wide")]
+    WidthMismatch { expected: u32, actual: u32 },
+    #[error("No encoder has been registered for the custom output format {0:?}")]
+    UnknownEncoder(String),
+    #[error("The exported page at {0:?} could not be read back correctly")]
+    CorruptOutput(PathBuf),
+    #[error("Not enough free space in the output directory (~{required} bytes required, {available} bytes available)")]
+    InsufficientSpace { required: u64, available: u64 },
+    #[error("Another export is already writing to {0:?}")]
+    DirectoryLocked(PathBuf),
+    #[error("Row {0} is not within the image, so it can't be a splitpoint")]
+    SplitpointOutOfBounds(usize),
+    #[error("There is no splitpoint at row {0}")]
+    SplitpointNotFound(usize),
+    #[error("There is already a splitpoint at row {0}")]
+    SplitpointExists(usize),
+    #[error("The splitpoint at row {0} starts a chapter, so it can't be removed or moved")]
+    ChapterSplitpoint(usize),
+    #[error("A page would be {height} pixels tall, which is less than the minimum of {min}")]
+    PageTooShort { height: usize, min: usize },
+    #[error("A page would be {height} pixels tall, which is more than the maximum of {max}")]
+    PageTooTall { height: usize, max: usize },
+    #[error("Rows {start} to {end} are not a region of the image")]
+    RegionOutOfBounds { start: usize, end: usize },
+    #[error("No clean row was found to cut at, so row {0} was cut regardless, which strict mode forbids")]
+    WorstCaseCut(usize),
+    #[cfg(feature = "title-card")]
+    #[error("The font could not be loaded: {0}")]
+    InvalidFont(String),
+    #[cfg(feature = "serde")]
+    #[error("The saved splitpoints can't be used: {0}")]
+    InvalidSplitpoints(String),
 
     // upstream errors
     #[error("{0:?}")]
     ImageError(ImageError),
     #[error("{0}")]
     IoError(io::Error),
+    #[cfg(feature = "zip")]
+    #[error("{0}")]
+    ZipError(zip::result::ZipError),
+    #[cfg(feature = "serde")]
+    #[error("{0}")]
+    JsonError(serde_json::Error),
 }
 
-pub enum ImageOutputFormat {
-    Png,
-    Webp,
-    Jpeg(u8),
-    Jpg(u8),
+/// How exported pages are checked after being written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Verify {
+    /// Pages are not checked.
+    #[default]
+    None,
+    /// Each page's header is re-read to check its dimensions, and its contents are compared byte for byte
+    /// with what was written. This is cheap, and catches truncated or partially written files.
+    Contents,
+    /// Each page is fully decoded again. This is slower, but also catches files which were corrupted in
+    /// other ways. Pages in formats which can't be decoded (such as AVIF) are checked as with
+    /// [`Verify::Contents`] instead.
+    Decode,
 }
 
-impl From<ImageError> for ImageSplitterError {
-    fn from(value: ImageError) -> Self {
-        Self::ImageError(value)
-    }
+/// The modification times given to pages exported to a directory, for readers which sort pages by
+/// modification time rather than by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PageTimes {
+    /// Pages keep the time they were written at, which isn't necessarily in reading order, as pages are
+    /// written in parallel.
+    #[default]
+    Written,
+    /// Page `n` is given the given time plus `n` seconds. Using a fixed time makes the output the same
+    /// across runs.
+    Sequential(SystemTime),
+    /// Page `n` is given the modification time of the newest source image plus `n` seconds, so that pages
+    /// are only newer than their sources if the sources changed. If no source image was loaded from a file,
+    /// pages keep the time they were written at.
+    FromSources,
 }
 
-impl From<io::Error> for ImageSplitterError {
-    fn from(value: io::Error) -> Self {
-        use io::ErrorKind as Kind;
-        match value.kind() {
-            Kind::PermissionDenied => ImageSplitterError::PermissionDenied,
-            _ => ImageSplitterError::IoError(value),
+impl PageTimes {
+    /// The time to give the first page, if any.
+    fn start(self, stats: &Stats) -> Option<SystemTime> {
+        match self {
+            PageTimes::Written => None,
+            PageTimes::Sequential(start) => Some(start),
+            PageTimes::FromSources => stats
+                .files
+                .iter()
+                .filter_map(|file| fs::metadata(file.path.as_ref()?).ok()?.modified().ok())
+                .max(),
         }
     }
 }
 
-/// Uses the provided splitpoints, image, and output image filetype to split the image into smaller images
-/// and exports those images into the provided output directory.
+/// What to do with pages which are larger than their format allows, such as pages more than 16383 pixels
+/// tall in WebP, or 65535 pixels in JPEG.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DimensionPolicy {
+    /// Pages which are too tall are cut into pieces of equal height which fit, regardless of what the rows
+    /// look like. Pages are marked in the [`ExportReport`] if one of their cuts was forced. Pages which
+    /// are too wide are written as PNG instead, as with [`DimensionPolicy::Fallback`].
+    #[default]
+    Cut,
+    /// Pages which are too large are written in the first fallback format which fits, or as PNG if none
+    /// does, and are marked as substituted in the [`ExportReport`].
+    Fallback,
+    /// The encoder is left to fail, throwing an error.
+    Error,
+}
+
+/// Which ICC colour profile is embedded into each page, so that pages whose images were in a wide colour
+/// space (as coloured manhua often are) aren't shown washed out.
 ///
-/// Input parameters:
-///  - image: A reference to the combined image.
-///  - splitpoints: A vector containing the pixel height at which the combined image should be split.
-///  - output_directory: The output directory where the split images are to be exported.
-///  - output_filetype: The output image filetype along with the quality setting (if applicable).
+/// Colours are never converted between profiles, so the profile of the source images is only right for the
+/// pages if the images all share it. Profiles are only embedded into JPEG, PNG, WebP and TIFF pages.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum ColorProfile {
+    /// The profile embedded in the source images (the first one, if they embed several), or none if they
+    /// have none. The profile is only known when exporting with [`crate::Stitcher`] or a pipeline, so
+    /// nothing is embedded when calling the export functions directly.
+    #[default]
+    Source,
+    /// No profile, so pages are shown as sRGB.
+    None,
+    /// The given profile.
+    Embed(Arc<[u8]>),
+}
+
+/// What to do with a page whose width differs from the width every page is required to have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WidthPolicy {
+    /// Throw an [`ImageSplitterError::WidthMismatch`] error.
+    #[default]
+    Error,
+    /// Centre narrower pages on a background of the given colour. Wider pages are scaled down.
+    ///
+    /// The edges of padded pages can be faded into the background with [`ExportConfig::seam_blend`].
+    Pad(Rgb<u8>),
+    /// Scale the page to the required width, keeping its aspect ratio.
+    Resize,
+}
+
+/// Makes sure a page has the given width, fixing it according to the policy if it doesn't.
 ///
-/// Throws an error if:
-///  - Any of the split images fails to be exported.
-///  - The output directory provided is not a valid directory.
-///  - This program does not have adequate permissions to create the images inside the provided directory.
-///  - The split images are too large in dimension for the output filetype.
-pub fn split_image(
-    image: &RgbImage,
-    splitpoints: &Vec<usize>,
+/// Returns the page along with whether it had to be adjusted.
+///
+/// Pages which are padded have their left and right edges faded into the padding over `feather` columns.
+fn enforce_width(
+    page: RgbImage,
+    width: u32,
+    policy: WidthPolicy,
+    feather: u32,
+) -> Result<(RgbImage, bool), ImageSplitterError> {
+    if page.width() == width {
+        return Ok((page, false));
+    }
+    let resize = |page: &RgbImage| {
+        let height = (page.height() as u64 * width as u64 / page.width().max(1) as u64) as u32;
+        imageops::resize(page, width, height.max(1), FilterType::Lanczos3)
+    };
+    let adjusted = match policy {
+        WidthPolicy::Error => {
+            return Err(ImageSplitterError::WidthMismatch {
+                expected: width,
+                actual: page.width(),
+            })
+        }
+        WidthPolicy::Pad(colour) if page.width() < width => {
+            let mut padded = RgbImage::from_pixel(width, page.height(), colour);
+            let left = (width - page.width()) / 2;
+            imageops::replace(&mut padded, &page, left as i64, 0);
+            blend_seams(&mut padded, left..left + page.width(), colour, feather);
+            padded
+        }
+        WidthPolicy::Pad(_) | WidthPolicy::Resize => resize(&page),
+    };
+    Ok((adjusted, true))
+}
+
+/// Fades the columns of a padded page just inside of its seams with the padding into the padding colour, so
+/// that the edges of the page look intentional rather than pasted.
+///
+/// The column on each seam is weighted most towards the padding, and the `feather`th column in from it is
+/// left almost as it is.
+fn blend_seams(padded: &mut RgbImage, page: Range<u32>, colour: Rgb<u8>, feather: u32) {
+    let feather = feather.min(page.len() as u32 / 2);
+    for offset in 0..feather {
+        // the weight of the page itself, out of `feather + 1`
+        let weight = offset + 1;
+        for x in [page.start + offset, page.end - 1 - offset] {
+            for y in 0..padded.height() {
+                let pixel = padded.get_pixel_mut(x, y);
+                for (channel, background) in pixel.0.iter_mut().zip(colour.0) {
+                    *channel = ((*channel as u32 * weight
+                        + background as u32 * (feather + 1 - weight))
+                        / (feather + 1)) as u8;
+                }
+            }
+        }
+    }
+}
+
+/// Options which control how pages are written by [`split_image`].
+#[derive(Debug, Clone, Default)]
+pub struct ExportConfig {
+    sanitize: SanitizePolicy,
+    first_page: Option<usize>,
+    page_digits: Option<usize>,
+    name_template: Option<String>,
+    preflight: bool,
+    verify: Verify,
+    encoders: EncoderRegistry,
+    fallbacks: Vec<ImageOutputFormat>,
+    dimension_policy: DimensionPolicy,
+    page_width: Option<u32>,
+    width_policy: WidthPolicy,
+    seam_feather: u32,
+    page_margin: Option<(u32, Rgb<u8>)>,
+    overlay: Option<Overlay>,
+    page_numbers: Option<PageNumbers>,
+    text_band: Option<u32>,
+    throttle: Option<(Throttle, Priority)>,
+    io_throttle: Option<(Throttle, Priority)>,
+    threads: Option<usize>,
+    page_cache: Option<PageCache>,
+    quantize: Option<(u16, bool)>,
+    progressive_jpeg: bool,
+    chroma_subsampling: ChromaSubsampling,
+    adaptive_quality: Option<u8>,
+    metadata: Option<PageMetadata>,
+    color_profile: ColorProfile,
+    page_formats: HashMap<usize, ImageOutputFormat>,
+    manifest: bool,
+    resume: bool,
+    wait_for_lock: bool,
+    page_times: PageTimes,
+    progress: Option<ProgressHook>,
+    strict: bool,
+    direction: Direction,
+    #[cfg(feature = "zip")]
+    comic_info: Option<ComicInfo>,
+    #[cfg(feature = "pdf")]
+    pdf_layout: Option<(PdfPageSize, f32)>,
+}
+
+impl ExportConfig {
+    /// Creates a config with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets how invalid characters in the names of pages are handled (defaults to replacing them with `_`).
+    ///
+    /// Page numbers are always valid, so this only matters for the text of the
+    /// [`ExportConfig::name_template`].
+    pub fn sanitize(mut self, policy: SanitizePolicy) -> Self {
+        self.sanitize = policy;
+        self
+    }
+    /// Sets the number the first page is named after (defaults to 1), e.g. to continue the numbering after
+    /// a credits page.
+    pub fn first_page(mut self, first_page: usize) -> Self {
+        self.first_page = Some(first_page);
+        self
+    }
+    /// Sets how many digits page numbers are zero-padded to (defaults to `None`, i.e. chosen from the number
+    /// of pages), e.g. to match the names of pages exported earlier.
+    ///
+    /// Page numbers with more digits than this aren't cut short.
+    pub fn page_digits(mut self, page_digits: Option<usize>) -> Self {
+        self.page_digits = page_digits;
+        self
+    }
+    /// Sets the template pages are named after, without the extension (defaults to just the page number).
+    /// `{page}` is replaced with the zero-padded page number, e.g. `"Solo Leveling - Ch. 12 - {page}"`. If
+    /// the template has no `{page}`, the number is appended to it, so that pages never share a name.
+    ///
+    /// Characters of the template which can't appear in file names (such as the `:` and `?` of a series
+    /// name, or a `/`) are handled according to [`ExportConfig::sanitize`].
+    pub fn name_template(mut self, template: impl Into<String>) -> Self {
+        self.name_template = Some(template.into());
+        self
+    }
+    /// Sets whether to check that the output volume has enough free space for the estimated
+    /// output size before writing anything (defaults to `false`).
+    ///
+    /// See [`estimate_output_size`] for how the output size is estimated.
+    pub fn preflight(mut self, preflight: bool) -> Self {
+        self.preflight = preflight;
+        self
+    }
+    /// Sets how pages are checked after being written (defaults to [`Verify::None`]).
+    ///
+    /// Pages which fail the check are reported as [`ImageSplitterError::CorruptOutput`]. This protects
+    /// against flaky network or removable drives which silently truncate files, although the read back
+    /// may be served from the operating system's cache.
+    pub fn verify(mut self, verify: Verify) -> Self {
+        self.verify = verify;
+        self
+    }
+    /// Sets the encoders available to `ImageOutputFormat::Custom` (defaults to an empty registry).
+    pub fn encoders(mut self, encoders: EncoderRegistry) -> Self {
+        self.encoders = encoders;
+        self
+    }
+    /// Sets the formats to try, in order, for pages which can't be encoded in the preferred format
+    /// (defaults to none).
+    ///
+    /// For instance, pages which are too tall for WebP can fall back to JPEG. Substitutions are
+    /// recorded in the [`ExportReport`] rather than failing the page.
+    pub fn fallbacks(mut self, fallbacks: Vec<ImageOutputFormat>) -> Self {
+        self.fallbacks = fallbacks;
+        self
+    }
+    /// Sets what to do with pages which are too large for their format (defaults to
+    /// [`DimensionPolicy::Cut`]).
+    pub fn dimension_limits(mut self, policy: DimensionPolicy) -> Self {
+        self.dimension_policy = policy;
+        self
+    }
+    /// Sets whether exporting fails rather than silently working around a problem (defaults to `false`).
+    ///
+    /// In strict mode, the fallback formats are never used, and pages which are too large for their format
+    /// are errors, as with [`DimensionPolicy::Error`]. See also [`crate::LoadConfig::strict`] and
+    /// [`StitchConfig::strict`].
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+    /// What to do with pages which are too large for their format, taking strict mode into account.
+    fn dimension_policy(&self) -> DimensionPolicy {
+        if self.strict {
+            DimensionPolicy::Error
+        } else {
+            self.dimension_policy
+        }
+    }
+    /// Sets the width every exported page must have (defaults to the width of the combined image), and
+    /// what to do with pages which don't have it (defaults to [`WidthPolicy::Error`]).
+    ///
+    /// This is checked right before each page is encoded. Pages which had to be adjusted are marked in
+    /// the [`ExportReport`].
+    pub fn uniform_width(mut self, page_width: Option<u32>, policy: WidthPolicy) -> Self {
+        self.page_width = page_width;
+        self.width_policy = policy;
+        self
+    }
+    /// Sets how many columns either side of a padded page are faded into the padding colour (defaults to 0,
+    /// i.e. hard edges). See [`WidthPolicy::Pad`].
+    ///
+    /// At most half of the page is faded.
+    pub fn seam_blend(mut self, feather: u32) -> Self {
+        self.seam_feather = feather;
+        self
+    }
+    /// Sets how many rows of the given colour are added above and below each exported page (defaults to 0),
+    /// for upload platforms which expect some breathing room between the panels of consecutive pages.
+    ///
+    /// Pages of horizontal strips get the margin to their left and right instead, between the cuts. The
+    /// margin is added after the page is given its required width, so pages are taller than the target
+    /// height they were stitched to by twice the margin, and counts towards the size limits of the output
+    /// format.
+    pub fn page_margin(mut self, margin: u32, color: Rgb<u8>) -> Self {
+        self.page_margin = (margin > 0).then_some((margin, color));
+        self
+    }
+    /// Sets an image (such as the credits or logo of a group) stamped onto each exported page (defaults to
+    /// `None`).
+    ///
+    /// The overlay is placed relative to the finished page, margin included, and is part of every page
+    /// before it is encoded.
+    pub fn overlay(mut self, overlay: Option<Overlay>) -> Self {
+        self.overlay = overlay;
+        self
+    }
+    /// Sets the page numbers drawn onto each exported page (defaults to `None`), e.g. for QC passes and
+    /// print proofs.
+    ///
+    /// Pages get the numbers they are named after (see [`ExportConfig::first_page`]), drawn over the
+    /// overlay. Regions exported on their own with `export_region` aren't numbered.
+    pub fn page_numbers(mut self, page_numbers: Option<PageNumbers>) -> Self {
+        self.page_numbers = page_numbers;
+        self
+    }
+    /// Sets the height (in pixels) of the band above and below each cut which is checked for text
+    /// (defaults to `None`, which skips the check).
+    ///
+    /// Pages with a cut which looks like it landed near text are marked in the [`ExportReport`], so that
+    /// editors can review exactly those pages.
+    pub fn flag_text_near_cuts(mut self, band: Option<u32>) -> Self {
+        self.text_band = band;
+        self
+    }
+    /// Sets a throttle limiting how many pages are encoded at once, shared with any other jobs using it,
+    /// and the priority of this job within it (defaults to no throttle).
+    pub fn throttle(mut self, throttle: Throttle, priority: Priority) -> Self {
+        self.throttle = Some((throttle, priority));
+        self
+    }
+    /// Sets a throttle limiting how many pages are written at once, shared with any other jobs using it,
+    /// and the priority of this job within it (defaults to no throttle).
+    ///
+    /// Pages are encoded in full before they are written, so on spinning disks and network shares, a low
+    /// limit (even 1) avoids thrashing the disk while still encoding on every thread.
+    pub fn io_throttle(mut self, throttle: Throttle, priority: Priority) -> Self {
+        self.io_throttle = Some((throttle, priority));
+        self
+    }
+    /// Sets how many threads pages are encoded on (defaults to `None`, i.e. rayon's global thread pool).
+    pub fn threads(mut self, threads: Option<usize>) -> Self {
+        self.threads = threads;
+        self
+    }
+    /// Sets a cache of pages which were already written, shared with any other exports using it, so that
+    /// identical pages are only encoded once (defaults to `None`).
+    ///
+    /// Only exports to a directory use the cache. Pages reused from the cache aren't verified again.
+    pub fn page_cache(mut self, page_cache: Option<PageCache>) -> Self {
+        self.page_cache = page_cache;
+        self
+    }
+    /// Sets the maximum number of colors (between 2 and 256) PNG pages are reduced to, which writes them as
+    /// 8-bit palette PNGs, and whether to dither them (defaults to `None`, i.e. full color PNGs).
+    ///
+    /// Palette PNGs are dramatically smaller for flat-colored art. Dithering hides banding in gradients, at
+    /// the cost of a larger file.
+    pub fn quantize(mut self, max_colors: Option<u16>, dither: bool) -> Self {
+        self.quantize = max_colors.map(|max_colors| (max_colors, dither));
+        self
+    }
+    /// Sets whether JPEG pages are written as progressive JPEGs (defaults to `false`, i.e. baseline JPEGs).
+    ///
+    /// Progressive JPEGs show the whole page at a low quality while they load, which many aggregator sites
+    /// prefer, and are usually slightly smaller.
+    pub fn progressive_jpeg(mut self, progressive: bool) -> Self {
+        self.progressive_jpeg = progressive;
+        self
+    }
+    /// Sets how finely the colour of JPEG pages is kept (defaults to [`ChromaSubsampling::Yuv444`], i.e. at
+    /// full resolution).
+    pub fn chroma_subsampling(mut self, subsampling: ChromaSubsampling) -> Self {
+        self.chroma_subsampling = subsampling;
+        self
+    }
+    /// Sets how many points the quality of each page may be moved away from the quality of the output format,
+    /// according to how detailed the page is (defaults to `None`, i.e. every page has the same quality).
+    ///
+    /// The detail of each page is measured by its edge density. Pages more detailed than average are given a
+    /// higher quality, and flatter pages a lower one, the most and least detailed pages being `spread` points
+    /// away. The average quality stays that of the output format, so the total size stays in the same range,
+    /// but more of it is spent where artifacts would show the most.
+    ///
+    /// Only formats with a quality (JPEG and AVIF) are adapted, and pages with an overridden format (see
+    /// [`ExportConfig::page_format`]) are left as they are. Exports which write pages as soon as they are
+    /// found, such as [`crate::Stitcher::stitch_to_stream`], don't know the average in time, so they don't
+    /// adapt the quality.
+    pub fn adaptive_quality(mut self, spread: Option<u8>) -> Self {
+        self.adaptive_quality = spread;
+        self
+    }
+    /// Sets the metadata embedded into each page as EXIF, so archive tools can tell where it came from
+    /// (defaults to `None`, i.e. no metadata).
+    ///
+    /// Only JPEG, PNG, WebP and AVIF pages can hold EXIF; pages in other formats are written without it.
+    pub fn metadata(mut self, metadata: Option<PageMetadata>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+    /// Sets which ICC colour profile is embedded into each page (defaults to [`ColorProfile::Source`]).
+    pub fn color_profile(mut self, color_profile: ColorProfile) -> Self {
+        self.color_profile = color_profile;
+        self
+    }
+    /// Overrides the format (and quality) of the page at `index` (counting from 0), e.g. to keep the cover at
+    /// a higher quality than the rest of the pages.
+    ///
+    /// The fallback formats still apply to overridden pages.
+    pub fn page_format(mut self, index: usize, format: ImageOutputFormat) -> Self {
+        self.page_formats.insert(index, format);
+        self
+    }
+    /// Sets whether a manifest of the written pages is kept in the output directory (defaults to `false`).
+    ///
+    /// The manifest is what allows an interrupted export to be resumed. It is always kept when resuming.
+    pub fn manifest(mut self, manifest: bool) -> Self {
+        self.manifest = manifest;
+        self
+    }
+    /// Sets the metadata written to `ComicInfo.xml` when exporting to a CBZ archive (defaults to `None`,
+    /// which leaves the file out).
+    #[cfg(feature = "zip")]
+    pub fn comic_info(mut self, comic_info: Option<ComicInfo>) -> Self {
+        self.comic_info = comic_info;
+        self
+    }
+    /// Sets the size of each page and the resolution (in dots per inch) images are placed at when exporting
+    /// to a PDF (defaults to [`PdfPageSize::Fit`] at 96 DPI).
+    ///
+    /// Images which don't fit on a fixed page size are scaled down to fit, and centred.
+    #[cfg(feature = "pdf")]
+    pub fn pdf_layout(mut self, page_size: PdfPageSize, dpi: f32) -> Self {
+        self.pdf_layout = Some((page_size, dpi));
+        self
+    }
+    /// Sets whether to wait for other exports to the same directory to finish, rather than failing with
+    /// [`ImageSplitterError::DirectoryLocked`] (defaults to `false`).
+    ///
+    /// Exports to a directory always lock it (with a `.quickstitch-lock` file), so that several processes
+    /// writing to the same directory can't interleave their pages or corrupt each other's manifest.
+    pub fn wait_for_lock(mut self, wait: bool) -> Self {
+        self.wait_for_lock = wait;
+        self
+    }
+    /// Sets the modification times given to pages exported to a directory (defaults to
+    /// [`PageTimes::Written`]).
+    ///
+    /// Pages reused from a [`PageCache`] share their file with the page they were reused from, and so its
+    /// modification time as well.
+    pub fn page_times(mut self, page_times: PageTimes) -> Self {
+        self.page_times = page_times;
+        self
+    }
+    /// Sets where to report each page as it is written (defaults to nowhere). See [`Progress`].
+    pub fn progress(mut self, progress: Option<Arc<dyn Progress>>) -> Self {
+        self.progress = progress.map(ProgressHook);
+        self
+    }
+    /// Sets whether pages already written by an earlier, interrupted export are left as is.
+    pub(crate) fn resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+    /// Hashes the options which affect how pages encode, and so where paginators cut.
+    pub(crate) fn hash_encoding(&self, hasher: &mut impl Hasher) {
+        format!(
+            "{:?}",
+            (
+                &self.encoders,
+                &self.fallbacks,
+                self.strict,
+                self.dimension_policy,
+                self.page_width,
+                self.width_policy,
+                (
+                    self.seam_feather,
+                    self.page_margin,
+                    &self.overlay,
+                    self.page_numbers,
+                ),
+                (
+                    self.quantize,
+                    self.progressive_jpeg,
+                    self.chroma_subsampling,
+                ),
+                self.adaptive_quality,
+                (&self.metadata, &self.color_profile),
+                self.page_formats
+                    .iter()
+                    .sorted_by_key(|(index, _)| **index)
+                    .collect_vec(),
+                self.direction,
+            )
+        )
+        .hash(hasher);
+    }
+    /// The config to export a strip loaded in the given direction with, which turns pages back the way
+    /// their images were, and embeds the ICC profile of the images if the config asks for it.
+    pub(crate) fn for_strip(
+        &self,
+        direction: Direction,
+        icc_profile: &IccProfile,
+    ) -> Cow<'_, Self> {
+        let source_profile = match (&self.color_profile, icc_profile) {
+            (ColorProfile::Source, Some(profile)) => Some(profile),
+            _ => None,
+        };
+        if self.direction == direction && source_profile.is_none() {
+            return Cow::Borrowed(self);
+        }
+        let mut config = self.clone();
+        config.direction = direction;
+        if let Some(profile) = source_profile {
+            config.color_profile = ColorProfile::Embed(profile.clone());
+        }
+        Cow::Owned(config)
+    }
+    /// The ICC profile to embed into each page, if any.
+    fn icc_profile(&self) -> Option<&[u8]> {
+        match &self.color_profile {
+            ColorProfile::Embed(profile) => Some(profile),
+            ColorProfile::Source | ColorProfile::None => None,
+        }
+    }
+}
+
+/// The format pages are exported in.
+///
+/// Some formats only exist with a feature enabled (`webp-lossy`, `avif`). The enum is non-exhaustive so that
+/// enabling one doesn't break matches on it in other crates, which need a wildcard arm.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ImageOutputFormat {
+    /// PNG, at 8 bits per channel. There is no 16-bit PNG output, as strips are 8 bits per channel: 16-bit
+    /// sources are dithered down as they are loaded (see [`crate::LoadConfig::dither`]).
+    Png,
+    /// Lossless WebP.
+    Webp,
+    /// Lossy WebP at the given quality, from 1 (worst) to 100 (best), which is far smaller than lossless WebP
+    /// for colour pages. Encoded with libwebp, through the `webp-lossy` feature.
+    ///
+    /// EXIF metadata and ICC profiles aren't embedded.
+    #[cfg(feature = "webp-lossy")]
+    WebpLossy(u8),
+    /// JPEG at the given quality, from 1 (worst) to 100 (best).
+    ///
+    /// Pages are baseline JPEGs with their colour at full resolution (4:4:4) unless the export config asks
+    /// for progressive JPEGs or subsampled colour. See [`ExportConfig::progressive_jpeg`] and
+    /// [`ExportConfig::chroma_subsampling`].
+    Jpeg(u8),
+    /// The same as [`ImageOutputFormat::Jpeg`], but written with the `.jpg` extension.
+    Jpg(u8),
+    /// Uncompressed TIFF, for typesetting tools which only accept TIFF.
+    Tiff,
+    /// Uncompressed BMP.
+    Bmp,
+    /// QOI, a lossless format which encodes and decodes much faster than PNG, for intermediate files.
+    Qoi,
+    /// AVIF, which compresses screentone-heavy pages much better than JPEG, at the cost of much slower
+    /// encoding.
+    ///
+    /// `quality` ranges from 1 (worst) to 100 (best), and `speed` from 1 (slowest, and smallest) to 10
+    /// (fastest).
+    ///
+    /// AVIF is only an output format: AVIF images can't be loaded unless a decoder for them is registered
+    /// (see [`crate::DecoderRegistry`]).
+    #[cfg(feature = "avif")]
+    Avif { quality: u8, speed: u8 },
+    /// A format provided by an [`Encoder`] registered under the given name in the
+    /// [`EncoderRegistry`] of the export config.
+    Custom(String),
+}
+
+/// The format pages which are too large for every other format are written in.
+static PNG: ImageOutputFormat = ImageOutputFormat::Png;
+
+impl ImageOutputFormat {
+    /// The largest width or height (in pixels) pages can have in this format, if it is limited.
+    fn max_dimension(&self) -> Option<u32> {
+        match self {
+            ImageOutputFormat::Webp => Some(16383),
+            #[cfg(feature = "webp-lossy")]
+            ImageOutputFormat::WebpLossy(_) => Some(16383),
+            ImageOutputFormat::Jpeg(_) | ImageOutputFormat::Jpg(_) => Some(65535),
+            #[cfg(feature = "avif")]
+            ImageOutputFormat::Avif { .. } => Some(65536),
+            ImageOutputFormat::Png
+            | ImageOutputFormat::Tiff
+            | ImageOutputFormat::Bmp
+            | ImageOutputFormat::Qoi
+            | ImageOutputFormat::Custom(_) => None,
+        }
+    }
+    /// Whether a page of the given dimensions is too large for this format.
+    fn too_large(&self, (width, height): (u32, u32)) -> bool {
+        self.max_dimension()
+            .is_some_and(|max| width > max || height > max)
+    }
+    /// A rough, deliberately pessimistic ratio between the encoded size of a page and its raw RGB size.
+    fn size_factor(&self, encoders: &EncoderRegistry) -> Result<f64, ImageSplitterError> {
+        Ok(match self {
+            ImageOutputFormat::Png => 0.6,
+            ImageOutputFormat::Webp => 0.5,
+            #[cfg(feature = "webp-lossy")]
+            ImageOutputFormat::WebpLossy(quality) => {
+                0.05 + 0.25 * (*quality).min(100) as f64 / 100.0
+            }
+            ImageOutputFormat::Jpeg(quality) | ImageOutputFormat::Jpg(quality) => {
+                0.1 + 0.3 * (*quality).min(100) as f64 / 100.0
+            }
+            // TIFF and BMP are written uncompressed, with BMP padding each row to 4 bytes
+            ImageOutputFormat::Tiff | ImageOutputFormat::Bmp => 1.0,
+            ImageOutputFormat::Qoi => 0.8,
+            #[cfg(feature = "avif")]
+            ImageOutputFormat::Avif { quality, .. } => {
+                0.05 + 0.25 * (*quality).min(100) as f64 / 100.0
+            }
+            ImageOutputFormat::Custom(name) => custom_encoder(name, encoders)?.size_factor(),
+        })
+    }
+    /// The file extension given to pages written in this format.
+    fn extension<'a>(
+        &'a self,
+        encoders: &'a EncoderRegistry,
+    ) -> Result<&'a str, ImageSplitterError> {
+        Ok(match self {
+            ImageOutputFormat::Png => "png",
+            ImageOutputFormat::Jpeg(_) => "jpeg",
+            ImageOutputFormat::Webp => "webp",
+            #[cfg(feature = "webp-lossy")]
+            ImageOutputFormat::WebpLossy(_) => "webp",
+            ImageOutputFormat::Jpg(_) => "jpg",
+            ImageOutputFormat::Tiff => "tiff",
+            ImageOutputFormat::Bmp => "bmp",
+            ImageOutputFormat::Qoi => "qoi",
+            #[cfg(feature = "avif")]
+            ImageOutputFormat::Avif { .. } => "avif",
+            ImageOutputFormat::Custom(name) => custom_encoder(name, encoders)?.extension(),
+        })
+    }
+    /// The quality of this format, if it has one.
+    fn quality(&self) -> Option<u8> {
+        match self {
+            ImageOutputFormat::Jpeg(quality) | ImageOutputFormat::Jpg(quality) => Some(*quality),
+            #[cfg(feature = "webp-lossy")]
+            ImageOutputFormat::WebpLossy(quality) => Some(*quality),
+            #[cfg(feature = "avif")]
+            ImageOutputFormat::Avif { quality, .. } => Some(*quality),
+            _ => None,
+        }
+    }
+    /// The same format with a different quality. Formats without a quality are returned as they are.
+    fn with_quality(&self, quality: u8) -> Self {
+        match self {
+            ImageOutputFormat::Jpeg(_) => ImageOutputFormat::Jpeg(quality),
+            ImageOutputFormat::Jpg(_) => ImageOutputFormat::Jpg(quality),
+            #[cfg(feature = "webp-lossy")]
+            ImageOutputFormat::WebpLossy(_) => ImageOutputFormat::WebpLossy(quality),
+            #[cfg(feature = "avif")]
+            ImageOutputFormat::Avif { speed, .. } => ImageOutputFormat::Avif {
+                quality,
+                speed: *speed,
+            },
+            format => format.clone(),
+        }
+    }
+}
+
+/// Looks up a custom encoder, throwing an error if it hasn't been registered.
+fn custom_encoder<'a>(
+    name: &str,
+    encoders: &'a EncoderRegistry,
+) -> Result<&'a dyn Encoder, ImageSplitterError> {
+    encoders
+        .get(name)
+        .ok_or_else(|| ImageSplitterError::UnknownEncoder(name.to_string()))
+}
+
+/// Estimates the total number of bytes the pages of the given image would take up once exported.
+///
+/// The estimate is the raw size of the image multiplied by a factor depending on the output format,
+/// so it is only meant to catch exports which clearly won't fit.
+///
+/// Throws an error if the output format is a custom format which hasn't been registered.
+pub fn estimate_output_size(
+    image: &impl StripRows,
+    output_filetype: &ImageOutputFormat,
+    encoders: &EncoderRegistry,
+) -> Result<u64, ImageSplitterError> {
+    let raw_size = image.width() as u64 * image.height() as u64 * Rgb::<u8>::CHANNEL_COUNT as u64;
+    Ok((raw_size as f64 * output_filetype.size_factor(encoders)?).ceil() as u64)
+}
+
+/// Checks that the volume containing `output_directory` has room for the estimated output size.
+fn check_free_space(
+    image: &impl StripRows,
+    output_directory: &Path,
+    output_filetype: &ImageOutputFormat,
+    encoders: &EncoderRegistry,
+) -> Result<(), ImageSplitterError> {
+    let required = estimate_output_size(image, output_filetype, encoders)?;
+    let available = fs4::available_space(output_directory)?;
+    if required > available {
+        return Err(ImageSplitterError::InsufficientSpace {
+            required,
+            available,
+        });
+    }
+    Ok(())
+}
+
+impl From<ImageError> for ImageSplitterError {
+    fn from(value: ImageError) -> Self {
+        Self::ImageError(value)
+    }
+}
+
+#[cfg(feature = "zip")]
+impl From<zip::result::ZipError> for ImageSplitterError {
+    fn from(value: zip::result::ZipError) -> Self {
+        match value {
+            zip::result::ZipError::Io(e) => e.into(),
+            e => Self::ZipError(e),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for ImageSplitterError {
+    fn from(value: serde_json::Error) -> Self {
+        if value.is_io() {
+            io::Error::from(value).into()
+        } else {
+            Self::JsonError(value)
+        }
+    }
+}
+
+impl From<io::Error> for ImageSplitterError {
+    fn from(value: io::Error) -> Self {
+        use io::ErrorKind as Kind;
+        match value.kind() {
+            Kind::PermissionDenied => ImageSplitterError::PermissionDenied,
+            _ => ImageSplitterError::IoError(value),
+        }
+    }
+}
+
+/// Encodes a single page in the given format.
+fn encode_page(
+    page: &RgbImage,
+    output_filetype: &ImageOutputFormat,
+    config: &ExportConfig,
+    position: Option<PagePosition>,
+    mut writer: impl Write,
+) -> Result<(), ImageSplitterError> {
+    let exif = config
+        .metadata
+        .as_ref()
+        .zip(position)
+        .map(|(metadata, position)| metadata.exif(position.index, position.total));
+    let exif = exif.as_deref();
+    let icc_profile = config.icc_profile();
+    Ok(match output_filetype {
+        ImageOutputFormat::Png => match config.quantize {
+            Some((max_colors, dither)) => {
+                write_palette_png(page, max_colors, dither, exif, icc_profile, writer)
+            }
+            None => {
+                page.write_with_encoder(with_metadata(PngEncoder::new(writer), exif, icc_profile)?)
+            }
+        },
+        ImageOutputFormat::Webp => page.write_with_encoder(with_metadata(
+            WebPEncoder::new_lossless(writer),
+            exif,
+            icc_profile,
+        )?),
+        #[cfg(feature = "webp-lossy")]
+        ImageOutputFormat::WebpLossy(quality) => {
+            let encoded = webp::Encoder::from_rgb(page.as_raw(), page.width(), page.height())
+                .encode_simple(false, (*quality).clamp(1, 100) as f32)
+                .map_err(|error| {
+                    ImageError::Encoding(EncodingError::new(
+                        ImageFormat::WebP.into(),
+                        format!("{error:?}"),
+                    ))
+                })?;
+            writer.write_all(&encoded)?;
+            Ok(())
+        }
+        ImageOutputFormat::Jpeg(quality) | ImageOutputFormat::Jpg(quality)
+            if config.progressive_jpeg
+                || config.chroma_subsampling != ChromaSubsampling::Yuv444 =>
+        {
+            write_jpeg(
+                page,
+                *quality,
+                config.progressive_jpeg,
+                config.chroma_subsampling,
+                exif,
+                icc_profile,
+                writer,
+            )
+        }
+        ImageOutputFormat::Jpeg(quality) | ImageOutputFormat::Jpg(quality) => page
+            .write_with_encoder(with_metadata(
+                JpegEncoder::new_with_quality(writer, *quality),
+                exif,
+                icc_profile,
+            )?),
+        ImageOutputFormat::Tiff => {
+            // TIFF offsets are written after the data they point to, so the encoder needs to seek
+            let mut encoded = Cursor::new(Vec::new());
+            page.write_with_encoder(with_metadata(
+                TiffEncoder::new(&mut encoded),
+                None,
+                icc_profile,
+            )?)?;
+            writer.write_all(encoded.get_ref())?;
+            Ok(())
+        }
+        ImageOutputFormat::Bmp => page.write_with_encoder(BmpEncoder::new(&mut writer)),
+        ImageOutputFormat::Qoi => page.write_with_encoder(QoiEncoder::new(writer)),
+        #[cfg(feature = "avif")]
+        ImageOutputFormat::Avif { quality, speed } => page.write_with_encoder(with_metadata(
+            AvifEncoder::new_with_speed_quality(writer, *speed, *quality),
+            exif,
+            // the AVIF encoder can't embed profiles
+            None,
+        )?),
+        ImageOutputFormat::Custom(name) => {
+            custom_encoder(name, &config.encoders)?.encode(page, &mut writer)
+        }
+    }?)
+}
+
+/// Where a page sits within an export, as recorded in its metadata.
+#[derive(Debug, Clone, Copy)]
+struct PagePosition {
+    /// The index of the page, counting from 0.
+    index: usize,
+    /// The number of pages, or 0 if it isn't known yet.
+    total: usize,
+}
+
+/// Gives the encoder the EXIF metadata and ICC profile to embed, if there are any.
+fn with_metadata<E: ImageEncoder>(
+    mut encoder: E,
+    exif: Option<&[u8]>,
+    icc_profile: Option<&[u8]>,
+) -> Result<E, ImageError> {
+    if let Some(exif) = exif {
+        encoder
+            .set_exif_metadata(exif.to_vec())
+            .map_err(ImageError::Unsupported)?;
+    }
+    if let Some(icc_profile) = icc_profile {
+        encoder
+            .set_icc_profile(icc_profile.to_vec())
+            .map_err(ImageError::Unsupported)?;
+    }
+    Ok(encoder)
+}
+
+/// Checks that a page written to `path` can be read back as expected.
+fn verify_page(
+    path: &Path,
+    encoded: &[u8],
+    dimensions: (u32, u32),
+    verify: Verify,
+) -> Result<(), ImageSplitterError> {
+    let corrupt = || ImageSplitterError::CorruptOutput(path.to_path_buf());
+    // Formats which can be encoded but not decoded (such as AVIF) can only have their contents compared.
+    let decodable = |result: Result<(u32, u32), ImageError>| match result {
+        Ok(read) => Ok(Some(read)),
+        Err(ImageError::Unsupported(_)) => Ok(None),
+        Err(_) => Err(corrupt()),
+    };
+    let read = match verify {
+        Verify::None => return Ok(()),
+        Verify::Contents => decodable(image::image_dimensions(path))?,
+        Verify::Decode => decodable(
+            ImageReader::open(path)?
+                .with_guessed_format()?
+                .decode()
+                .map(|decoded| decoded.dimensions()),
+        )?,
+    };
+    let contents_checked = verify == Verify::Contents || read.is_none();
+    if read.is_some_and(|read| read != dimensions)
+        || (contents_checked && fs::read(path)? != encoded)
+    {
+        return Err(corrupt());
+    }
+    Ok(())
+}
+
+/// The formats to try for each page, along with their extensions.
+struct PageFormats<'a> {
+    /// The preferred output format followed by its fallbacks.
+    default: Vec<(&'a ImageOutputFormat, &'a str)>,
+    /// The same, for pages with an overridden format.
+    overrides: HashMap<usize, Vec<(&'a ImageOutputFormat, &'a str)>>,
+}
+
+impl<'a> PageFormats<'a> {
+    /// The formats to try, in order, for the page at `index`.
+    fn for_page(&self, index: usize) -> &[(&'a ImageOutputFormat, &'a str)] {
+        self.overrides.get(&index).unwrap_or(&self.default)
+    }
+}
+
+/// Lists the formats to try for each page, i.e. the preferred format (or the override for the page) followed
+/// by the fallbacks.
+fn output_formats<'a>(
+    output_filetype: &'a ImageOutputFormat,
+    config: &'a ExportConfig,
+) -> Result<PageFormats<'a>, ImageSplitterError> {
+    let with_fallbacks = |preferred: &'a ImageOutputFormat| {
+        std::iter::once(preferred)
+            .chain(config.fallbacks.iter().filter(|_| !config.strict))
+            .map(|format| Ok((format, format.extension(&config.encoders)?)))
+            .collect::<Result<Vec<_>, ImageSplitterError>>()
+    };
+    Ok(PageFormats {
+        default: with_fallbacks(output_filetype)?,
+        overrides: config
+            .page_formats
+            .iter()
+            .map(|(&index, format)| Ok((index, with_fallbacks(format)?)))
+            .collect::<Result<_, ImageSplitterError>>()?,
+    })
+}
+
+/// Converts splitpoints into the `(start, height)` of each page.
+fn page_ranges(splitpoints: &[usize]) -> Vec<(usize, usize)> {
+    splitpoints
+        .windows(2)
+        .map(|slice| (slice[0], slice[1] - slice[0]))
+        .collect()
+}
+
+/// The splitpoints with extra cuts forced wherever a page would be too tall for the given format, unless
+/// the dimension policy says otherwise.
+///
+/// Pages are cut into pieces of equal height, taking into account how they will be scaled to the page
+/// width.
+fn format_cuts(
+    image: &impl StripRows,
+    splitpoints: &[usize],
+    output_filetype: &ImageOutputFormat,
+    config: &ExportConfig,
+) -> Vec<usize> {
+    let max = match (config.dimension_policy(), output_filetype.max_dimension()) {
+        (DimensionPolicy::Cut, Some(max)) => max as u64,
+        _ => return splitpoints.to_vec(),
+    };
+    // the margins take up part of the height of each page
+    let max = match config.page_margin {
+        Some((margin, _)) => max.saturating_sub(margin as u64 * 2),
+        None => max,
+    };
+    // the most rows a page can take from the strip once it has been scaled to the page width
+    let max = match (config.page_width, config.width_policy) {
+        (Some(page_width), WidthPolicy::Resize | WidthPolicy::Pad(_))
+            if page_width > image.width() =>
+        {
+            max * image.width() as u64 / page_width as u64
+        }
+        _ => max,
+    }
+    .max(1) as usize;
+    let mut cut = vec![splitpoints.first().copied().unwrap_or(0)];
+    for (start, length) in page_ranges(splitpoints) {
+        let pieces = length.div_ceil(max);
+        cut.extend((1..pieces).map(|piece| start + piece * length / pieces));
+        cut.push(start + length);
+    }
+    cut
+}
+
+/// How detailed the given rows of the image are, from 0 (flat) to 1, measured as the share of pixels
+/// whose brightness differs sharply from the pixel to their right or below them.
+///
+/// Only every other row and column is looked at, which is plenty to tell pages apart.
+fn complexity(image: &impl StripRows, start: usize, length: usize) -> f64 {
+    const EDGE: i32 = 32;
+    let luma = |row: &[u8], x: usize| {
+        let pixel = &row[x * 3..x * 3 + 3];
+        (pixel[0] as i32 * 299 + pixel[1] as i32 * 587 + pixel[2] as i32 * 114) / 1000
+    };
+    let width = image.width() as usize;
+    let (mut edges, mut samples) = (0u64, 0u64);
+    for y in (start..(start + length).saturating_sub(1)).step_by(2) {
+        let (row, below) = (image.row(y as u32), image.row(y as u32 + 1));
+        for x in (0..width.saturating_sub(1)).step_by(2) {
+            let here = luma(row, x);
+            let difference = (luma(row, x + 1) - here).abs() + (luma(below, x) - here).abs();
+            edges += (difference > EDGE) as u64;
+            samples += 1;
+        }
+    }
+    if samples == 0 {
+        return 0.0;
+    }
+    edges as f64 / samples as f64
+}
+
+/// The config with the quality of each page adapted to how detailed it is, if the config asks for it. See
+/// [`ExportConfig::adaptive_quality`].
+fn adapt_quality<'c>(
+    image: &impl StripRows,
+    splitpoints: &[usize],
+    output_filetype: &ImageOutputFormat,
+    config: &'c ExportConfig,
+) -> Cow<'c, ExportConfig> {
+    let (Some(spread), Some(quality)) = (config.adaptive_quality, output_filetype.quality()) else {
+        return Cow::Borrowed(config);
+    };
+    let complexities: Vec<f64> = with_threads(config.threads, || {
+        page_ranges(splitpoints)
+            .par_iter()
+            .map(|&(start, length)| complexity(image, start, length))
+            .collect()
+    });
+    let mean = complexities.iter().sum::<f64>() / complexities.len().max(1) as f64;
+    let range = complexities
+        .iter()
+        .map(|complexity| (complexity - mean).abs())
+        .fold(0.0, f64::max);
+    if range == 0.0 {
+        return Cow::Borrowed(config);
+    }
+    let mut config = config.clone();
+    for (index, complexity) in complexities.into_iter().enumerate() {
+        let offset = (spread as f64 * (complexity - mean) / range).round();
+        let adapted = (quality as f64 + offset).clamp(1.0, 100.0) as u8;
+        config
+            .page_formats
+            .entry(index)
+            .or_insert_with(|| output_filetype.with_quality(adapted));
+    }
+    Cow::Owned(config)
+}
+
+/// The index of the chapter a page starting at the given row belongs to, i.e. the last chapter starting
+/// at or before it.
+fn chapter_of(chapters: &[usize], start: usize) -> usize {
+    chapters
+        .iter()
+        .filter(|&&chapter| chapter <= start)
+        .count()
+        .saturating_sub(1)
+}
+
+/// A page which has been cut out of the combined image and encoded, but not written anywhere yet.
+struct RenderedPage<'a> {
+    encoded: Vec<u8>,
+    dimensions: (u32, u32),
+    format: &'a ImageOutputFormat,
+    extension: &'a str,
+    substituted: bool,
+    encode: Duration,
+}
+
+/// Cuts a single page out of the combined image and encodes it, trying each of the given formats in turn.
+fn render_page<'a>(
+    image: &impl StripRows,
+    start: usize,
+    length: usize,
+    formats: &[(&'a ImageOutputFormat, &'a str)],
+    config: &ExportConfig,
+    position: Option<PagePosition>,
+) -> Result<RenderedPage<'a>, ImageSplitterError> {
+    let index = position.map(|position| position.index);
+    let (page, _) = cut_page(image, start, length, index, config)?;
+    encode_formats(&page, formats, config, position)
+}
+
+/// Cuts the page at `index` (counting from 0, or `None` for a page which isn't part of an export) out of
+/// the combined image, making sure it has the required width, and adds the page margin, overlay and page
+/// number of the config.
+///
+/// Also returns whether the page had to be adjusted to the required width.
+fn cut_page(
+    image: &impl StripRows,
+    start: usize,
+    length: usize,
+    index: Option<usize>,
+    config: &ExportConfig,
+) -> Result<(RgbImage, bool), ImageSplitterError> {
+    let page = image.copy_rows(start as u32, length as u32);
+    let (page, adjusted) = enforce_width(
+        page,
+        config.page_width.unwrap_or(image.width()),
+        config.width_policy,
+        config.seam_feather,
+    )?;
+    let page = match config.page_margin {
+        Some((margin, color)) => {
+            let mut framed = RgbImage::from_pixel(
+                page.width(),
+                page.height().saturating_add(margin.saturating_mul(2)),
+                color,
+            );
+            imageops::replace(&mut framed, &page, 0, margin.into());
+            framed
+        }
+        None => page,
+    };
+    let mut page = config.direction.unturn(page);
+    if let Some(overlay) = &config.overlay {
+        overlay.apply(&mut page);
+    }
+    if let Some((page_numbers, index)) = config.page_numbers.zip(index) {
+        page_numbers.apply(&mut page, config.first_page.unwrap_or(1) + index);
+    }
+    Ok((page, adjusted))
+}
+
+/// Encodes a page, trying each of the given formats in turn.
+fn encode_formats<'a>(
+    page: &RgbImage,
+    formats: &[(&'a ImageOutputFormat, &'a str)],
+    config: &ExportConfig,
+    position: Option<PagePosition>,
+) -> Result<RenderedPage<'a>, ImageSplitterError> {
+    let encode_start = Instant::now();
+    // Keep the error of the preferred format in case they all fail.
+    let mut first_error = None;
+    let skip_oversized = config.dimension_policy() != DimensionPolicy::Error;
+    let fitting = formats
+        .iter()
+        .filter(|(format, _)| !(skip_oversized && format.too_large(page.dimensions())));
+    // Pages too large for every format are written as PNG, which fits anything.
+    let fitting: Vec<_> = match fitting.clone().next() {
+        Some(_) => fitting.copied().collect(),
+        None => vec![(&PNG, "png")],
+    };
+    for &(format, extension) in &fitting {
+        let mut encoded = Vec::new();
+        match throttled(&config.throttle, || {
+            encode_page(page, format, config, position, &mut encoded)
+        }) {
+            Ok(()) => {
+                return Ok(RenderedPage {
+                    encoded,
+                    dimensions: page.dimensions(),
+                    format,
+                    extension,
+                    substituted: format != formats[0].0,
+                    encode: encode_start.elapsed(),
+                })
+            }
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    // fitting is never empty, so an error must have been recorded.
+    Err(first_error.unwrap())
+}
+
+/// Writes the given rows of the combined image to `path` as a standalone image, regardless of the
+/// splitpoints, e.g. to pull out a single panel for review or typesetting.
+///
+/// The region is encoded the same way as a page would be, including the fallback formats and the page
+/// width of the config. The path is used as it is, whatever the extension of the format.
+///
+/// Throws an error if:
+///  - The range of rows is empty or extends past the end of the image.
+///  - The region can't be encoded in any of the formats.
+///  - The file can't be written.
+pub fn export_region(
+    image: &impl StripRows,
+    rows: Range<usize>,
+    path: &Path,
+    output_filetype: &ImageOutputFormat,
+    config: &ExportConfig,
+) -> Result<(), ImageSplitterError> {
+    if rows.is_empty() || rows.end > image.height() as usize {
+        return Err(ImageSplitterError::RegionOutOfBounds {
+            start: rows.start,
+            end: rows.end,
+        });
+    }
+    let formats = output_formats(output_filetype, config)?;
+    let page = render_page(
+        image,
+        rows.start,
+        rows.len(),
+        &formats.default,
+        config,
+        None,
+    )?;
+    fs::write(path, &page.encoded)?;
+    verify_page(path, &page.encoded, page.dimensions, config.verify)
+}
+
+/// Merges consecutive pages into as few pages as possible, such that each page stays within both
+/// `max_height` pixels and `max_bytes` bytes once encoded in the given format, and returns the splitpoints
+/// of the merged pages.
+///
+/// Pages are only ever merged, never cut anew, so stitching with a small target height beforehand gives
+/// more places to cut at. Pages which exceed either limit on their own are left as they are, and pages are
+/// never merged across the start of a chapter.
+///
+/// Throws an error if a page can't be encoded.
+pub fn merge_pages(
+    image: &impl StripRows,
+    splitpoints: &[usize],
+    chapters: &[usize],
+    max_height: usize,
+    max_bytes: u64,
+    output_filetype: &ImageOutputFormat,
+    config: &ExportConfig,
+) -> Result<Vec<usize>, ImageSplitterError> {
+    if splitpoints.len() < 2 {
+        return Ok(splitpoints.to_vec());
+    }
+    let formats = output_formats(output_filetype, config)?;
+    let encoded_size = |start: usize, end: usize| -> Result<u64, ImageSplitterError> {
+        let (page, _) = cut_page(image, start, end - start, None, config)?;
+        Ok(encode_formats(&page, &formats.default, config, None)?
+            .encoded
+            .len() as u64)
+    };
+    let sizes: Vec<u64> = with_threads(config.threads, || {
+        page_ranges(splitpoints)
+            .par_iter()
+            .map(|&(start, length)| encoded_size(start, start + length))
+            .collect::<Result<_, _>>()
+    })?;
+
+    let mut merged = vec![splitpoints[0]];
+    // the index of the splitpoint the current page starts at
+    let mut first = 0;
+    while first + 1 < splitpoints.len() {
+        // merge as many pages as fit on this one, estimating its size as the sum of theirs
+        let mut last = first + 1;
+        let mut estimate = sizes[first];
+        while last + 1 < splitpoints.len()
+            && !chapters.contains(&splitpoints[last])
+            && splitpoints[last + 1] - splitpoints[first] <= max_height
+            && estimate + sizes[last] <= max_bytes
+        {
+            estimate += sizes[last];
+            last += 1;
+        }
+        // The merged page doesn't necessarily encode to the sum of its parts, so check its actual size,
+        // giving back pages until it fits.
+        while last > first + 1 && encoded_size(splitpoints[first], splitpoints[last])? > max_bytes {
+            last -= 1;
+        }
+        merged.push(splitpoints[last]);
+        first = last;
+    }
+    Ok(merged)
+}
+
+/// How far below the byte limit the first attempt at cutting an oversized page aims, as the size of a page
+/// isn't quite proportional to its height.
+const SIZE_MARGIN: f64 = 0.9;
+
+/// The rows `start..start + height` of a combined image, seen as an image of their own.
+struct RowWindow<'a, S> {
+    image: &'a S,
+    start: u32,
+    height: u32,
+}
+
+impl<S: StripRows> StripRows for RowWindow<'_, S> {
+    fn width(&self) -> u32 {
+        self.image.width()
+    }
+    fn height(&self) -> u32 {
+        self.height
+    }
+    fn row(&self, y: u32) -> &[u8] {
+        assert!(y < self.height, "row {y} is out of bounds");
+        self.image.row(self.start + y)
+    }
+}
+
+/// Cuts pages which would be more than `max_bytes` bytes once encoded in the given format into smaller
+/// pages, and returns the new splitpoints, e.g. for upload sites which limit the size of each image.
+///
+/// Each oversized page is stitched again on its own with a target height scaled down by how far over the
+/// limit it is, using the other options of `stitch`, and the new pages are checked in turn until they all
+/// fit. Pages which are a single row tall are left as they are, as they can't be cut any further.
+///
+/// Throws an error if a page can't be encoded.
+pub fn split_to_size(
+    image: &impl StripRows,
+    splitpoints: &[usize],
+    max_bytes: u64,
+    stitch: &StitchConfig,
+    output_filetype: &ImageOutputFormat,
+    config: &ExportConfig,
+) -> Result<Vec<usize>, ImageSplitterError> {
+    let formats = output_formats(output_filetype, config)?;
+    let encoded_size = |start: usize, length: usize| -> Result<u64, ImageSplitterError> {
+        let (page, _) = cut_page(image, start, length, None, config)?;
+        Ok(encode_formats(&page, &formats.default, config, None)?
+            .encoded
+            .len() as u64)
+    };
+    // the options to stitch oversized pages with, which mustn't touch the cache or progress of the strip
+    let stitch = stitch.clone().score_cache(None).progress(None);
+
+    let mut fitted = vec![splitpoints.first().copied().unwrap_or(0)];
+    let mut pages = page_ranges(splitpoints);
+    while !pages.is_empty() {
+        let sizes: Vec<u64> = with_threads(config.threads, || {
+            pages
+                .par_iter()
+                .map(|&(start, length)| encoded_size(start, length))
+                .collect::<Result<_, _>>()
+        })?;
+        let mut oversized = Vec::new();
+        for (&(start, length), size) in pages.iter().zip(sizes) {
+            if size <= max_bytes || length < 2 {
+                fitted.push(start + length);
+                continue;
+            }
+            let target_height = (length as f64 * max_bytes as f64 / size as f64 * SIZE_MARGIN)
+                .clamp(1.0, (length - 1) as f64) as usize;
+            let window = RowWindow {
+                image,
+                start: start as u32,
+                height: length as u32,
+            };
+            let cuts = find_splitpoints(&window, &stitch.clone().target_height(target_height), &[]);
+            oversized.extend(
+                page_ranges(&cuts)
+                    .into_iter()
+                    .map(|(cut, length)| (start + cut, length)),
+            );
+        }
+        pages = oversized;
+    }
+    fitted.sort_unstable();
+    fitted.dedup();
+    Ok(fitted)
+}
+
+/// Pixels with a luma below this are counted as dark when looking for text.
+const TEXT_LUMA: u8 = 96;
+/// The range of dark pixel densities typical of text. Bands with fewer dark pixels are background, and bands
+/// with more are artwork.
+const TEXT_DENSITY: std::ops::RangeInclusive<f64> = 0.02..=0.35;
+
+/// The columns holding dark pixels within the given rows, along with the density of dark pixels within
+/// those columns, or `None` if there are no dark pixels.
+fn ink(image: &impl StripRows, rows: Range<usize>) -> Option<(Range<usize>, f64)> {
+    let mut columns: Option<Range<usize>> = None;
+    let mut dark = 0;
+    for row in rows.clone() {
+        let pixels = image
+            .row(row as u32)
+            .chunks_exact(Rgb::<u8>::CHANNEL_COUNT as usize);
+        for (column, pixel) in pixels.enumerate() {
+            if Rgb::from_slice(pixel).to_luma()[0] < TEXT_LUMA {
+                dark += 1;
+                columns = Some(match columns {
+                    Some(span) => span.start.min(column)..span.end.max(column + 1),
+                    None => column..column + 1,
+                });
+            }
+        }
+    }
+    let columns = columns?;
+    let density = dark as f64 / (columns.len() * rows.len()) as f64;
+    Some((columns, density))
+}
+
+/// Checks whether the given row lies between two lines of text, i.e. both the `band` rows above it and the
+/// `band` rows below it hold text-like ink, in mostly the same columns.
+fn between_text_lines(image: &impl StripRows, row: usize, band: u32) -> bool {
+    let height = image.height() as usize;
+    if row == 0 || row + 1 >= height {
+        return false;
+    }
+    let band = band as usize;
+    let above = ink(image, row.saturating_sub(band)..row);
+    let below = ink(image, row + 1..(row + 1 + band).min(height));
+    match (above, below) {
+        (Some((above, above_density)), Some((below, below_density))) => {
+            let overlap = above
+                .end
+                .min(below.end)
+                .saturating_sub(above.start.max(below.start));
+            TEXT_DENSITY.contains(&above_density)
+                && TEXT_DENSITY.contains(&below_density)
+                && overlap * 2 >= above.len().min(below.len())
+        }
+        _ => false,
+    }
+}
+
+/// Checks whether the given rows have the density of dark pixels typical of text.
+fn looks_like_text(image: &impl StripRows, rows: std::ops::Range<usize>) -> bool {
+    let width = image.width() as usize;
+    let total = rows.len() * width;
+    if total == 0 {
+        return false;
+    }
+    let dark: usize = rows
+        .map(|row| {
+            image
+                .row(row as u32)
+                .chunks_exact(Rgb::<u8>::CHANNEL_COUNT as usize)
+                .filter(|&pixel| Rgb::from_slice(pixel).to_luma()[0] < TEXT_LUMA)
+                .count()
+        })
+        .sum();
+    TEXT_DENSITY.contains(&(dark as f64 / total as f64))
+}
+
+/// Checks whether a cut at the given row looks like it landed near text, by checking the band of rows
+/// on either side of it.
+fn cut_near_text(image: &impl StripRows, cut: usize, band: u32) -> bool {
+    let height = image.height() as usize;
+    if cut == 0 || cut >= height {
+        // the top and bottom of the strip aren't cuts
+        return false;
+    }
+    let band = band as usize;
+    looks_like_text(image, cut.saturating_sub(band)..cut)
+        || looks_like_text(image, cut..(cut + band).min(height))
+}
+
+/// A summary of an export, listing each page which was written.
+#[derive(Debug, Clone, Default)]
+pub struct ExportReport {
+    /// The pages which were written, in reading order.
+    pub pages: Vec<ExportedPage>,
+}
+
+impl ExportReport {
+    /// The indices of the pages which were written in a fallback format.
+    pub fn substitutions(&self) -> Vec<usize> {
+        self.pages
+            .iter()
+            .enumerate()
+            .filter(|(_, page)| page.substituted)
+            .map(|(index, _)| index)
+            .collect()
+    }
+    /// The indices of the pages with a cut which was forced, as they were too tall for their format.
+    pub fn forced(&self) -> Vec<usize> {
+        self.pages
+            .iter()
+            .enumerate()
+            .filter(|(_, page)| page.forced)
+            .map(|(index, _)| index)
+            .collect()
+    }
+    /// The indices of the pages which had to be padded or resized to match the required page width.
+    pub fn adjusted(&self) -> Vec<usize> {
+        self.pages
+            .iter()
+            .enumerate()
+            .filter(|(_, page)| page.adjusted)
+            .map(|(index, _)| index)
+            .collect()
+    }
+    /// The indices of the pages with a cut which looks like it landed near text, if the check was enabled.
+    pub fn near_text(&self) -> Vec<usize> {
+        self.pages
+            .iter()
+            .enumerate()
+            .filter(|(_, page)| page.near_text)
+            .map(|(index, _)| index)
+            .collect()
+    }
+    /// The indices of the pages which were linked or copied from the [`PageCache`] instead of being encoded.
+    pub fn deduplicated(&self) -> Vec<usize> {
+        self.pages
+            .iter()
+            .enumerate()
+            .filter(|(_, page)| page.deduplicated)
+            .map(|(index, _)| index)
+            .collect()
+    }
+    /// The indices of the pages which were left as is when resuming an export.
+    pub fn resumed(&self) -> Vec<usize> {
+        self.pages
+            .iter()
+            .enumerate()
+            .filter(|(_, page)| page.resumed)
+            .map(|(index, _)| index)
+            .collect()
+    }
+    /// The indices of the pages which begin a new chapter, for building bookmarks or an outline.
+    pub fn chapter_starts(&self) -> Vec<usize> {
+        self.pages
+            .iter()
+            .enumerate()
+            .filter(|(_, page)| page.starts_chapter)
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+/// Details about a single exported page.
+#[derive(Debug, Clone)]
+pub struct ExportedPage {
+    /// Where the page was written.
+    pub path: PathBuf,
+    /// The row of the combined image at which the page starts.
+    pub start: usize,
+    /// The height of the page in pixels.
+    pub height: usize,
+    /// The index of the chapter the page belongs to.
+    pub chapter: usize,
+    /// Whether this is the first page of its chapter.
+    pub starts_chapter: bool,
+    /// The format the page was written in.
+    pub format: ImageOutputFormat,
+    /// The size of the page file in bytes.
+    pub size: u64,
+    /// Whether the page was cut above or below regardless of what the rows looked like, as it was too
+    /// tall for its format. See [`DimensionPolicy::Cut`].
+    pub forced: bool,
+    /// Whether the page couldn't be encoded in the preferred format, and a fallback format was used instead.
+    pub substituted: bool,
+    /// Whether the page had to be padded or resized to match the required page width.
+    pub adjusted: bool,
+    /// Whether the cut above or below the page looks like it landed near text.
+    pub near_text: bool,
+    /// Whether the page was identical to one already in the [`PageCache`], and was linked or copied from it
+    /// instead of being encoded.
+    pub deduplicated: bool,
+    /// Whether the page was already written by an earlier, interrupted export, and was left as is.
+    pub resumed: bool,
+}
+
+/// What an export would write, as worked out by [`crate::Stitcher::plan_export`].
+#[derive(Debug, Clone, Default)]
+pub struct ExportPlan {
+    /// The pages which would be written, in reading order.
+    pub pages: Vec<PlannedPage>,
+}
+
+impl ExportPlan {
+    /// The estimated total size of the pages, in bytes.
+    pub fn estimated_size(&self) -> u64 {
+        self.pages.iter().map(|page| page.estimated_size).sum()
+    }
+}
+
+/// Details about a single page an export would write.
+#[derive(Debug, Clone)]
+pub struct PlannedPage {
+    /// Where the page would be written.
+    pub path: PathBuf,
+    /// The row of the combined image at which the page starts.
+    pub start: usize,
+    /// How many rows of the combined image the page takes up.
+    pub height: usize,
+    /// The width and height of the page once written, in pixels.
+    pub dimensions: (u32, u32),
+    /// The index of the chapter the page belongs to.
+    pub chapter: usize,
+    /// Whether this is the first page of its chapter.
+    pub starts_chapter: bool,
+    /// The format the page would be written in, unless it fails to encode in it.
+    pub format: ImageOutputFormat,
+    /// A rough estimate of the size of the page file in bytes.
+    pub estimated_size: u64,
+    /// Whether the page would be cut above or below regardless of what the rows look like, as it is too
+    /// tall for its format. See [`DimensionPolicy::Cut`].
+    pub forced: bool,
+}
+
+/// A page which is in place in the output directory, however it got there.
+struct WrittenPage {
+    path: PathBuf,
+    format: ImageOutputFormat,
+    substituted: bool,
+    deduplicated: bool,
+    resumed: bool,
+    size: u64,
+    hash: u64,
+    encode: Duration,
+    write: Duration,
+}
+
+/// The name given to the page at `index` out of `pages` pages, numbered and zero-padded and filled into
+/// the name template according to the config.
+fn page_file_name(index: usize, pages: usize, extension: &str, config: &ExportConfig) -> String {
+    let first_page = config.first_page.unwrap_or(1);
+    let digits = config
+        .page_digits
+        .unwrap_or_else(|| get_num_digits(first_page + pages));
+    let number = format!("{:0digits$}", first_page + index);
+    let stem = match &config.name_template {
+        Some(template) if template.contains("{page}") => template.replace("{page}", &number),
+        Some(template) => format!("{template}{number}"),
+        None => number,
+    };
+    sanitize_file_name(&format!("{stem}.{extension}"), config.sanitize)
+}
+
+/// Uses the provided splitpoints, image, and output image filetype to split the image into smaller images
+/// and exports those images into the provided output directory.
+///
+/// Input parameters:
+///  - image: A reference to the combined image.
+///  - splitpoints: A vector containing the pixel height at which the combined image should be split.
+///  - chapters: The row at which each chapter starts, used to mark which pages begin a new chapter.
+///  - output_directory: The output directory where the split images are to be exported.
+///  - output_filetype: The output image filetype along with the quality setting (if applicable).
+///  - config: Additional export options, such as how generated file names are sanitized.
+///  - stats: Where the time taken to encode and write each page is recorded.
+///
+/// Returns a report listing each page which was written.
+///
+/// Throws an error if:
+///  - Any of the split images fails to be exported (in any of the fallback formats, if set).
+///  - The output directory provided is not a valid directory.
+///  - This program does not have adequate permissions to create the images inside the provided directory.
+///  - The split images are too large in dimension for the output filetype, and the dimension policy is to
+///    throw an error.
+///  - The preflight check is enabled and the output volume doesn't have enough free space.
+///  - The output format is a custom format which hasn't been registered.
+///  - Verification is enabled and a page could not be read back correctly.
+///  - A page doesn't have the required width, and the width policy is to throw an error.
+pub fn split_image(
+    image: &impl StripRows,
+    splitpoints: &[usize],
+    chapters: &[usize],
     output_directory: impl AsRef<Path>,
     output_filetype: ImageOutputFormat,
-) -> Result<(), Vec<ImageSplitterError>> {
-    let output_directory = output_directory.as_ref().to_path_buf();
+    config: &ExportConfig,
+    stats: &mut Stats,
+) -> Result<ExportReport, Vec<ImageSplitterError>> {
+    let export_start = Instant::now();
+    let output_directory = output_directory.as_ref();
     if !output_directory.is_dir() {
         return Err(vec![ImageSplitterError::DirectoryNotFound]);
     }
-    let max_digits = get_num_digits(splitpoints.len());
-    let output: Vec<Result<(), ImageSplitterError>> = splitpoints
-        .windows(2)
-        .map(|slice| (slice[0], slice[1] - slice[0]))
-        .collect::<Vec<(_, _)>>()
-        .par_iter()
+    let output_directory =
+        long_path(output_directory).map_err(|e| vec![ImageSplitterError::from(e)])?;
+    // held until the export is done
+    let _lock = DirectoryLock::acquire(&output_directory, config.wait_for_lock)
+        .map_err(|e| vec![e.into()])?
+        .ok_or_else(|| {
+            vec![ImageSplitterError::DirectoryLocked(
+                output_directory.clone(),
+            )]
+        })?;
+    if config.preflight {
+        check_free_space(image, &output_directory, &output_filetype, &config.encoders)
+            .map_err(|e| vec![e])?;
+    }
+    let requested = splitpoints;
+    let splitpoints = &format_cuts(image, requested, &output_filetype, config);
+    let config = &adapt_quality(image, splitpoints, &output_filetype, config);
+    let formats = output_formats(&output_filetype, config).map_err(|e| vec![e])?;
+    let manifest = (config.manifest || config.resume)
+        .then(|| Manifest::create(&output_directory, config.resume))
+        .transpose()
+        .map_err(|e| vec![e.into()])?;
+    let tally = Tally::new(&config.progress);
+    let pages_total = splitpoints.len().saturating_sub(1);
+    let first_page_time = config.page_times.start(stats);
+    let output: Vec<Result<(ExportedPage, PageStats), ImageSplitterError>> =
+        with_threads(config.threads, || {
+            page_ranges(splitpoints)
+                .par_iter()
+                .enumerate()
+                .map(|(index, &(start, length))| {
+                    let (page, adjusted) = cut_page(image, start, length, Some(index), config)?;
+                    let page_formats = formats.for_page(index);
+                    // only needed to recognise pages which were already written
+                    let key = (config.page_cache.is_some() || manifest.is_some())
+                        .then(|| PageCache::key(&page, page_formats[0].0));
+                    let resumed = manifest
+                        .as_ref()
+                        .zip(key)
+                        .and_then(|(manifest, key)| {
+                            manifest.unchanged(index, key, &output_directory)
+                        })
+                        .and_then(|entry| {
+                            // the page is only left as is if it's in one of the formats it may be written in
+                            let extension = Path::new(&entry.name).extension()?.to_str()?;
+                            let attempt = page_formats.iter().position(|&(_, e)| e == extension)?;
+                            Some((entry, page_formats[attempt].0, attempt > 0))
+                        });
+                    let cached = config
+                        .page_cache
+                        .as_ref()
+                        .zip(key)
+                        .and_then(|(cache, key)| Some((cache, cache.get(key)?)));
+                    let written = if let Some((entry, format, substituted)) = resumed {
+                        // written by an earlier export which was interrupted, so leave it as is
+                        WrittenPage {
+                            path: output_directory.join(&entry.name),
+                            format: format.clone(),
+                            substituted,
+                            deduplicated: false,
+                            resumed: true,
+                            size: entry.size,
+                            hash: entry.hash,
+                            encode: Duration::ZERO,
+                            write: Duration::ZERO,
+                        }
+                    } else if let Some((cache, cached)) = cached {
+                        // identical to a page which was already written, so reuse it instead of encoding it
+                        let extension = cached.path.extension().unwrap_or_default();
+                        let mut output_filepath = output_directory.clone();
+                        output_filepath.push(page_file_name(
+                            index,
+                            pages_total,
+                            &extension.to_string_lossy(),
+                            config,
+                        ));
+                        let write_start = Instant::now();
+                        throttled(&config.io_throttle, || {
+                            cache.reuse(&cached, &output_filepath)
+                        })?;
+                        WrittenPage {
+                            path: output_filepath,
+                            format: cached.format,
+                            substituted: cached.substituted,
+                            deduplicated: true,
+                            resumed: false,
+                            size: cached.size,
+                            hash: cached.hash,
+                            encode: Duration::ZERO,
+                            write: write_start.elapsed(),
+                        }
+                    } else {
+                        let RenderedPage {
+                            encoded,
+                            dimensions,
+                            format,
+                            extension,
+                            substituted,
+                            encode,
+                        } = encode_formats(
+                            &page,
+                            page_formats,
+                            config,
+                            Some(PagePosition {
+                                index,
+                                total: pages_total,
+                            }),
+                        )?;
+                        let mut output_filepath = output_directory.clone();
+                        output_filepath.push(page_file_name(index, pages_total, extension, config));
+                        let write_start = Instant::now();
+                        throttled(&config.io_throttle, || {
+                            let mut file = File::create(&output_filepath)?;
+                            file.write_all(&encoded)?;
+                            if config.verify != Verify::None {
+                                // make sure the page actually reached the disk before reading it back
+                                file.sync_all()?;
+                                drop(file);
+                                verify_page(&output_filepath, &encoded, dimensions, config.verify)?;
+                            }
+                            Ok::<_, ImageSplitterError>(())
+                        })?;
+                        let hash = key.map_or(0, |_| content_hash(&encoded));
+                        if let Some((cache, key)) = config.page_cache.as_ref().zip(key) {
+                            cache.insert(
+                                key,
+                                CachedPage {
+                                    path: output_filepath.clone(),
+                                    format: format.clone(),
+                                    substituted,
+                                    size: encoded.len() as u64,
+                                    hash,
+                                },
+                            );
+                        }
+                        WrittenPage {
+                            path: output_filepath,
+                            format: format.clone(),
+                            substituted,
+                            deduplicated: false,
+                            resumed: false,
+                            size: encoded.len() as u64,
+                            hash,
+                            encode,
+                            write: write_start.elapsed(),
+                        }
+                    };
+                    if let Some(first_page_time) = first_page_time {
+                        File::options()
+                            .write(true)
+                            .open(&written.path)?
+                            .set_modified(first_page_time + Duration::from_secs(index as u64))?;
+                    }
+                    if let Some((manifest, key)) = manifest.as_ref().zip(key) {
+                        manifest.record(&ManifestEntry {
+                            index,
+                            name: written
+                                .path
+                                .file_name()
+                                .unwrap_or_default()
+                                .to_string_lossy()
+                                .into_owned(),
+                            key,
+                            size: written.size,
+                            hash: written.hash,
+                        })?;
+                    }
+                    tally.tick(|progress, written| progress.page_written(written, pages_total));
+                    let page_stats = PageStats {
+                        path: written.path.clone(),
+                        encode: written.encode,
+                        write: written.write,
+                    };
+                    let exported = ExportedPage {
+                        path: written.path,
+                        start,
+                        height: length,
+                        chapter: chapter_of(chapters, start),
+                        starts_chapter: index == 0 || chapters.contains(&start),
+                        format: written.format,
+                        size: written.size,
+                        forced: requested.binary_search(&start).is_err()
+                            || requested.binary_search(&(start + length)).is_err(),
+                        substituted: written.substituted,
+                        adjusted,
+                        deduplicated: written.deduplicated,
+                        resumed: written.resumed,
+                        near_text: config.text_band.is_some_and(|band| {
+                            cut_near_text(image, start, band)
+                                || cut_near_text(image, start + length, band)
+                        }),
+                    };
+                    Ok((exported, page_stats))
+                })
+                .collect()
+        });
+    let (pages, errors): (Vec<_>, Vec<_>) = output.into_iter().partition_result();
+    stats.export += export_start.elapsed();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let (pages, page_stats) = pages.into_iter().unzip();
+    stats.pages = page_stats;
+    Ok(ExportReport { pages })
+}
+
+/// Works out what `split_image` would write with the same arguments, without encoding or writing anything,
+/// e.g. for a dry run.
+///
+/// Sizes are estimated the same way as by [`estimate_output_size`], page by page, and assume every page is
+/// written in its preferred format.
+///
+/// Throws an error if:
+///  - The output format (or the format of a page) is a custom format which hasn't been registered.
+///  - A page doesn't have the required width, and the width policy is to throw an error.
+pub fn plan_export(
+    image: &impl StripRows,
+    splitpoints: &[usize],
+    chapters: &[usize],
+    output_directory: impl AsRef<Path>,
+    output_filetype: &ImageOutputFormat,
+    config: &ExportConfig,
+) -> Result<ExportPlan, ImageSplitterError> {
+    let requested = splitpoints;
+    let splitpoints = &format_cuts(image, requested, output_filetype, config);
+    let config = &adapt_quality(image, splitpoints, output_filetype, config);
+    let formats = output_formats(output_filetype, config)?;
+    let pages_total = splitpoints.len().saturating_sub(1);
+    let page_width = config.page_width.unwrap_or(image.width());
+    let pages = page_ranges(splitpoints)
+        .into_iter()
         .enumerate()
         .map(|(index, (start, length))| {
-            let page = image
-                .view(
-                    0,
-                    start.to_owned() as u32,
-                    image.width(),
-                    length.to_owned() as u32,
-                )
-                .to_image();
-            let mut output_filepath = output_directory.clone();
-            output_filepath.push(format!(
-                "{}{}.{}",
-                "0".repeat(max_digits - get_num_digits(index + 1)),
-                index + 1,
-                match output_filetype {
-                    ImageOutputFormat::Png => "png",
-                    ImageOutputFormat::Jpeg(_) => "jpeg",
-                    ImageOutputFormat::Webp => "webp",
-                    ImageOutputFormat::Jpg(_) => "jpg",
+            // the dimensions the page is given by `enforce_width`
+            let height = match config.width_policy {
+                _ if page_width == image.width() => length as u64,
+                WidthPolicy::Error => {
+                    return Err(ImageSplitterError::WidthMismatch {
+                        expected: page_width,
+                        actual: image.width(),
+                    })
                 }
-            ));
-            let file = match File::create(output_filepath) {
-                Ok(file) => file,
-                Err(e) => {
-                    return Err(ImageSplitterError::from(e));
+                WidthPolicy::Pad(_) if image.width() < page_width => length as u64,
+                WidthPolicy::Pad(_) | WidthPolicy::Resize => {
+                    (length as u64 * page_width as u64 / image.width().max(1) as u64).max(1)
                 }
             };
-            // May be the cause of unknown errors.
-            let res = match output_filetype {
-                ImageOutputFormat::Png => {
-                    page.write_with_encoder(PngEncoder::new(BufWriter::new(file)))
+            let dimensions = config
+                .direction
+                .turn_dimensions((page_width, height as u32));
+            let (format, extension) = formats.for_page(index)[0];
+            let raw_size =
+                dimensions.0 as u64 * dimensions.1 as u64 * Rgb::<u8>::CHANNEL_COUNT as u64;
+            Ok(PlannedPage {
+                path: output_directory.as_ref().join(page_file_name(
+                    index,
+                    pages_total,
+                    extension,
+                    config,
+                )),
+                start,
+                height: length,
+                dimensions,
+                chapter: chapter_of(chapters, start),
+                starts_chapter: index == 0 || chapters.contains(&start),
+                format: format.clone(),
+                estimated_size: (raw_size as f64 * format.size_factor(&config.encoders)?).ceil()
+                    as u64,
+                forced: requested.binary_search(&start).is_err()
+                    || requested.binary_search(&(start + length)).is_err(),
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(ExportPlan { pages })
+}
+
+/// Cuts every page out of the combined image and encodes it, for exports which write all the pages at
+/// once at the end.
+fn render_pages<'a>(
+    image: &impl StripRows,
+    splitpoints: &[usize],
+    output_filetype: &'a ImageOutputFormat,
+    config: &'a ExportConfig,
+) -> Result<Vec<RenderedPage<'a>>, Vec<ImageSplitterError>> {
+    let formats = output_formats(output_filetype, config).map_err(|e| vec![e])?;
+    let output: Vec<Result<RenderedPage, ImageSplitterError>> =
+        with_threads(config.threads, || {
+            page_ranges(splitpoints)
+                .par_iter()
+                .enumerate()
+                .map(|(index, &(start, length))| {
+                    let position = PagePosition {
+                        index,
+                        total: splitpoints.len() - 1,
+                    };
+                    render_page(
+                        image,
+                        start,
+                        length,
+                        formats.for_page(index),
+                        config,
+                        Some(position),
+                    )
+                })
+                .collect()
+        });
+    let (pages, errors): (Vec<_>, Vec<_>) = output.into_iter().partition_result();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(pages)
+}
+
+/// Does exactly the same thing as the `split_image` function, but instead of writing each page to a file,
+/// the pages are written to a single stream, in reading order, using the framing described in the
+/// `stream` module.
+///
+/// Pages are only written once all of them have been encoded, so nothing is written if any page fails.
+pub fn split_image_to_stream(
+    image: &impl StripRows,
+    splitpoints: &[usize],
+    writer: impl Write,
+    output_filetype: ImageOutputFormat,
+    config: &ExportConfig,
+    stats: &mut Stats,
+) -> Result<(), Vec<ImageSplitterError>> {
+    let export_start = Instant::now();
+    let splitpoints = &format_cuts(image, splitpoints, &output_filetype, config);
+    let config = &adapt_quality(image, splitpoints, &output_filetype, config);
+    let pages = render_pages(image, splitpoints, &output_filetype, config).inspect_err(|_| {
+        stats.export += export_start.elapsed();
+    })?;
+    let mut writer = BufWriter::new(writer);
+    let mut page_stats = Vec::new();
+    let tally = Tally::new(&config.progress);
+    let pages_total = pages.len();
+    for page in pages {
+        let write_start = Instant::now();
+        write_frame(&mut writer, &page.encoded).map_err(|e| vec![e.into()])?;
+        page_stats.push(PageStats {
+            path: PathBuf::new(),
+            encode: page.encode,
+            write: write_start.elapsed(),
+        });
+        tally.tick(|progress, written| progress.page_written(written, pages_total));
+    }
+    writer.flush().map_err(|e| vec![e.into()])?;
+    stats.export += export_start.elapsed();
+    stats.pages = page_stats;
+    Ok(())
+}
+
+/// Does exactly the same thing as the `split_image` function, but instead of writing each page to a file,
+/// each page is handed to the given sink, in reading order, along with the name it would have on disk.
+///
+/// Pages are only handed over once all of them have been encoded, so nothing is written if any page
+/// fails. Writing stops at the first page the sink fails to take.
+pub fn split_image_to_writers(
+    image: &impl StripRows,
+    splitpoints: &[usize],
+    mut sink: impl OutputSink,
+    output_filetype: ImageOutputFormat,
+    config: &ExportConfig,
+    stats: &mut Stats,
+) -> Result<(), Vec<ImageSplitterError>> {
+    let export_start = Instant::now();
+    let splitpoints = &format_cuts(image, splitpoints, &output_filetype, config);
+    let config = &adapt_quality(image, splitpoints, &output_filetype, config);
+    let pages = render_pages(image, splitpoints, &output_filetype, config).inspect_err(|_| {
+        stats.export += export_start.elapsed();
+    })?;
+    let mut page_stats = Vec::new();
+    let tally = Tally::new(&config.progress);
+    let pages_total = pages.len();
+    for (index, page) in pages.into_iter().enumerate() {
+        let write_start = Instant::now();
+        let name = page_file_name(index, pages_total, page.extension, config);
+        sink.write_page(&name, &page.encoded)
+            .map_err(|e| vec![e.into()])?;
+        page_stats.push(PageStats {
+            path: PathBuf::from(name),
+            encode: page.encode,
+            write: write_start.elapsed(),
+        });
+        tally.tick(|progress, written| progress.page_written(written, pages_total));
+    }
+    stats.export += export_start.elapsed();
+    stats.pages = page_stats;
+    Ok(())
+}
+
+/// Does exactly the same thing as the `split_image_to_stream` function, but the splitpoints are found at
+/// the same time, and each page is written as soon as the scan has settled on the cut at its bottom.
+///
+/// Returns the splitpoints along with the rows cut for lack of a clean row, which are only recorded if the
+/// stitch config is strict. In that case, nothing more is written once such a row is reached, and
+/// [`ImageSplitterError::WorstCaseCut`] is thrown instead.
+pub(crate) fn scan_to_stream(
+    image: &impl StripRows,
+    scan: &Scan,
+    writer: impl Write,
+    output_filetype: &ImageOutputFormat,
+    config: &ExportConfig,
+    stats: &mut Stats,
+) -> Result<(Vec<usize>, Vec<usize>), Vec<ImageSplitterError>> {
+    let formats = output_formats(output_filetype, config).map_err(|e| vec![e])?;
+    let strict = scan.config.strict;
+    let mut worst_cases = Vec::new();
+    let (sender, receiver) = mpsc::channel();
+    let (scanned, written) = thread::scope(|scope| {
+        let worst_cases = &mut worst_cases;
+        let scanner = scope.spawn(move || {
+            let score_start = Instant::now();
+            let report = move |row: usize, forced: bool| {
+                // the receiver is only gone once writing has failed, in which case the rest is ignored
+                let _ = sender.send((row, forced));
+            };
+            let splitpoints = scan.run(image, strict.then_some(worst_cases), Some(&report));
+            (splitpoints, score_start.elapsed())
+        });
+
+        let export_start = Instant::now();
+        let mut writer = BufWriter::new(writer);
+        let mut page_stats = Vec::new();
+        let mut written = || {
+            let mut previous = 0;
+            for (row, forced) in &receiver {
+                if row <= previous {
+                    continue;
                 }
-                ImageOutputFormat::Webp => {
-                    page.write_with_encoder(WebPEncoder::new_lossless(BufWriter::new(file)))
+                if forced && strict {
+                    return Err(vec![ImageSplitterError::WorstCaseCut(row)]);
                 }
-                ImageOutputFormat::Jpeg(quality) | ImageOutputFormat::Jpg(quality) => page
-                    .write_with_encoder(JpegEncoder::new_with_quality(
-                        BufWriter::new(file),
-                        quality,
-                    )),
-            };
-            match res {
-                Ok(_) => Ok(()),
-                Err(e) => Err(ImageSplitterError::from(e)),
+                let splitpoints = format_cuts(image, &[previous, row], output_filetype, config);
+                for (start, length) in page_ranges(&splitpoints) {
+                    let position = PagePosition {
+                        index: page_stats.len(),
+                        total: 0,
+                    };
+                    let formats = formats.for_page(position.index);
+                    let page = render_page(image, start, length, formats, config, Some(position))
+                        .map_err(|e| vec![e])?;
+                    let write_start = Instant::now();
+                    write_frame(&mut writer, &page.encoded).map_err(|e| vec![e.into()])?;
+                    page_stats.push(PageStats {
+                        path: PathBuf::new(),
+                        encode: page.encode,
+                        write: write_start.elapsed(),
+                    });
+                }
+                previous = row;
             }
-        })
-        .collect();
-    let errors: Vec<_> = output.into_iter().filter_map(|out| out.err()).collect();
+            writer.flush().map_err(|e| vec![e.into()])
+        };
+        let written = written();
+        // stop reporting to the scanner, which then finishes without waiting on anything
+        drop(receiver);
+        stats.export += export_start.elapsed();
+        stats.pages = page_stats;
+        let scanned = scanner
+            .join()
+            .unwrap_or_else(|payload| panic::resume_unwind(payload));
+        (scanned, written)
+    });
+    let (splitpoints, score) = scanned;
+    stats.score += score;
+    written?;
+    Ok((splitpoints, worst_cases))
+}
+
+/// Does exactly the same thing as the `split_image` function, but instead of writing each page to a file,
+/// the pages are written to a single tar archive on the given stream, named the same way as they would
+/// be on disk.
+///
+/// Unlike `split_image_to_stream`, the output can be unpacked by standard tools, which makes it suitable
+/// for shell pipelines. Pages are only written once all of them have been encoded.
+#[cfg(feature = "tar")]
+pub fn split_image_to_tar(
+    image: &impl StripRows,
+    splitpoints: &[usize],
+    writer: impl Write,
+    output_filetype: ImageOutputFormat,
+    config: &ExportConfig,
+    stats: &mut Stats,
+) -> Result<(), Vec<ImageSplitterError>> {
+    let export_start = Instant::now();
+    let splitpoints = &format_cuts(image, splitpoints, &output_filetype, config);
+    let config = &adapt_quality(image, splitpoints, &output_filetype, config);
+    let pages = render_pages(image, splitpoints, &output_filetype, config).inspect_err(|_| {
+        stats.export += export_start.elapsed();
+    })?;
+    let mut archive = tar::Builder::new(BufWriter::new(writer));
+    let mut page_stats = Vec::new();
+    let tally = Tally::new(&config.progress);
+    let pages_total = pages.len();
+    for (index, page) in pages.into_iter().enumerate() {
+        let write_start = Instant::now();
+        let name = page_file_name(index, pages_total, page.extension, config);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(page.encoded.len() as u64);
+        header.set_mode(0o644);
+        archive
+            .append_data(&mut header, &name, page.encoded.as_slice())
+            .map_err(|e| vec![e.into()])?;
+        page_stats.push(PageStats {
+            path: PathBuf::from(name),
+            encode: page.encode,
+            write: write_start.elapsed(),
+        });
+        tally.tick(|progress, written| progress.page_written(written, pages_total));
+    }
+    archive
+        .into_inner()
+        .and_then(|mut writer| writer.flush())
+        .map_err(|e| vec![e.into()])?;
+    stats.export += export_start.elapsed();
+    stats.pages = page_stats;
+    Ok(())
+}
+
+/// Does exactly the same thing as the `split_image` function, but instead of writing each page to a
+/// separate file, the pages are written into a single CBZ (zip) archive at `path`, named the same way as they
+/// would be on disk.
+///
+/// If the config has [`ComicInfo`] set, a `ComicInfo.xml` file is included, listing every page and
+/// bookmarking the start of each chapter. Pages are only written once all of them have been encoded, so the
+/// archive isn't created if any page fails.
+#[cfg(feature = "zip")]
+pub fn split_image_to_cbz(
+    image: &impl StripRows,
+    splitpoints: &[usize],
+    chapters: &[usize],
+    path: impl AsRef<Path>,
+    output_filetype: ImageOutputFormat,
+    config: &ExportConfig,
+    stats: &mut Stats,
+) -> Result<(), Vec<ImageSplitterError>> {
+    use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+    let export_start = Instant::now();
+    let splitpoints = &format_cuts(image, splitpoints, &output_filetype, config);
+    let config = &adapt_quality(image, splitpoints, &output_filetype, config);
+    let pages = render_pages(image, splitpoints, &output_filetype, config).inspect_err(|_| {
+        stats.export += export_start.elapsed();
+    })?;
+    let write_pages = || -> Result<Vec<PageStats>, ImageSplitterError> {
+        let mut archive = ZipWriter::new(BufWriter::new(File::create(long_path(path.as_ref())?)?));
+        // pages are already compressed
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        if let Some(comic_info) = &config.comic_info {
+            let comic_pages: Vec<_> = page_ranges(splitpoints)
+                .iter()
+                .zip(&pages)
+                .map(|(&(start, _), page)| ComicPage {
+                    width: page.dimensions.0,
+                    height: page.dimensions.1,
+                    size: page.encoded.len(),
+                    bookmark: (chapters.len() > 1 && chapters.contains(&start))
+                        .then(|| chapter_of(chapters, start)),
+                })
+                .collect();
+            archive.start_file("ComicInfo.xml", options)?;
+            archive.write_all(comic_info.to_xml(&comic_pages).as_bytes())?;
+        }
+        let mut page_stats = Vec::new();
+        let tally = Tally::new(&config.progress);
+        let pages_total = pages.len();
+        for (index, page) in pages.iter().enumerate() {
+            let write_start = Instant::now();
+            let name = page_file_name(index, pages_total, page.extension, config);
+            archive.start_file(name.as_str(), options)?;
+            archive.write_all(&page.encoded)?;
+            page_stats.push(PageStats {
+                path: PathBuf::from(name),
+                encode: page.encode,
+                write: write_start.elapsed(),
+            });
+            tally.tick(|progress, written| progress.page_written(written, pages_total));
+        }
+        archive.finish()?.flush()?;
+        Ok(page_stats)
+    };
+    let page_stats = write_pages();
+    stats.export += export_start.elapsed();
+    stats.pages = page_stats.map_err(|e| vec![e])?;
+    Ok(())
+}
+
+/// Does exactly the same thing as the `split_image` function, but instead of writing each page to a
+/// separate file, the pages are encoded as JPEGs of the given quality and written into a single PDF
+/// document at `path`, one page each.
+///
+/// The size of each PDF page and the resolution images are placed at are set with
+/// [`ExportConfig::pdf_layout`]. If there are several chapters, the start of each one is added to the outline
+/// of the document. Pages are only written once all of them have been encoded.
+#[cfg(feature = "pdf")]
+pub fn split_image_to_pdf(
+    image: &impl StripRows,
+    splitpoints: &[usize],
+    chapters: &[usize],
+    path: impl AsRef<Path>,
+    quality: u8,
+    config: &ExportConfig,
+    stats: &mut Stats,
+) -> Result<(), Vec<ImageSplitterError>> {
+    let export_start = Instant::now();
+    let splitpoints = &format_cuts(
+        image,
+        splitpoints,
+        &ImageOutputFormat::Jpeg(quality),
+        config,
+    );
+    let output: Vec<Result<(PdfPage, Duration), ImageSplitterError>> =
+        with_threads(config.threads, || {
+            page_ranges(splitpoints)
+                .par_iter()
+                .enumerate()
+                .map(|(index, &(start, length))| {
+                    let (page, _) = cut_page(image, start, length, Some(index), config)?;
+                    let encode_start = Instant::now();
+                    let mut jpeg = Vec::new();
+                    throttled(&config.throttle, || {
+                        encode_page(
+                            &page,
+                            &ImageOutputFormat::Jpeg(quality),
+                            config,
+                            None,
+                            &mut jpeg,
+                        )
+                    })?;
+                    let pdf_page = PdfPage {
+                        jpeg,
+                        width: page.width(),
+                        height: page.height(),
+                        bookmark: (chapters.len() > 1 && chapters.contains(&start))
+                            .then(|| chapter_of(chapters, start)),
+                    };
+                    Ok((pdf_page, encode_start.elapsed()))
+                })
+                .collect()
+        });
+    let (pages, errors): (Vec<_>, Vec<_>) = output.into_iter().partition_result();
     if !errors.is_empty() {
+        stats.export += export_start.elapsed();
         return Err(errors);
     }
+    let (pages, encode_times): (Vec<_>, Vec<_>) = pages.into_iter().unzip();
+    let write_start = Instant::now();
+    let (page_size, dpi) = config.pdf_layout.unwrap_or((PdfPageSize::Fit, DEFAULT_DPI));
+    let written = long_path(path.as_ref())
+        .and_then(|path| fs::write(path, write_pdf(&pages, page_size, dpi)));
+    let write = write_start.elapsed();
+    stats.export += export_start.elapsed();
+    written.map_err(|e| vec![e.into()])?;
+    // the document is written in one go, so its time is split evenly between the pages
+    let write = write / pages.len().max(1) as u32;
+    let tally = Tally::new(&config.progress);
+    for _ in &pages {
+        tally.tick(|progress, written| progress.page_written(written, pages.len()));
+    }
+    stats.pages = encode_times
+        .into_iter()
+        .map(|encode| PageStats {
+            path: PathBuf::new(),
+            encode,
+            write,
+        })
+        .collect();
     Ok(())
 }
@@ -0,0 +1,66 @@
+//! This module writes pages as progressive or chroma-subsampled JPEGs, which the JPEG encoder of the `image`
+//! crate can't produce.
+
+use std::io::Write;
+
+use image::{
+    error::{EncodingError, ImageFormatHint, ParameterError, ParameterErrorKind},
+    ImageError, ImageFormat, RgbImage,
+};
+use jpeg_encoder::{ColorType, Encoder, SamplingFactor};
+
+/// How finely the colour of JPEG pages is kept, relative to their brightness (see
+/// [`crate::ExportConfig::chroma_subsampling`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ChromaSubsampling {
+    /// 4:4:4: colour is kept at full resolution, which keeps coloured text and line art sharp.
+    #[default]
+    Yuv444,
+    /// 4:2:0: colour is kept at half the resolution in each direction, which makes pages noticeably smaller
+    /// at the cost of blurring the edges of coloured text.
+    Yuv420,
+}
+
+/// Writes a page as a JPEG at the given quality (from 1 to 100), progressive if `progressive` is set, with
+/// its colour subsampled as given. If `exif` or `icc_profile` are given, they are embedded into the JPEG.
+pub fn write_jpeg(
+    page: &RgbImage,
+    quality: u8,
+    progressive: bool,
+    subsampling: ChromaSubsampling,
+    exif: Option<&[u8]>,
+    icc_profile: Option<&[u8]>,
+    writer: impl Write,
+) -> Result<(), ImageError> {
+    let (Ok(width), Ok(height)) = (u16::try_from(page.width()), u16::try_from(page.height()))
+    else {
+        return Err(ImageError::Parameter(ParameterError::from_kind(
+            ParameterErrorKind::DimensionMismatch,
+        )));
+    };
+    let encoding_error = |e: jpeg_encoder::EncodingError| match e {
+        jpeg_encoder::EncodingError::IoError(e) => ImageError::IoError(e),
+        e => ImageError::Encoding(EncodingError::new(
+            ImageFormatHint::Exact(ImageFormat::Jpeg),
+            e,
+        )),
+    };
+    let mut encoder = Encoder::new(writer, quality.clamp(1, 100));
+    encoder.set_progressive(progressive);
+    encoder.set_sampling_factor(match subsampling {
+        ChromaSubsampling::Yuv444 => SamplingFactor::R_4_4_4,
+        ChromaSubsampling::Yuv420 => SamplingFactor::R_4_2_0,
+    });
+    if let Some(exif) = exif {
+        encoder.add_exif_metadata(exif).map_err(encoding_error)?;
+    }
+    if let Some(icc_profile) = icc_profile {
+        encoder
+            .add_icc_profile(icc_profile)
+            .map_err(encoding_error)?;
+    }
+    encoder
+        .encode(page.as_raw(), width, height, ColorType::Rgb)
+        .map_err(encoding_error)
+}
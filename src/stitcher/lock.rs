@@ -0,0 +1,42 @@
+//! This module contains the lock which stops several exports from writing to the same directory at once.
+
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    path::Path,
+};
+
+use fs4::fs_std::FileExt;
+
+/// The name of the lock file within the output directory.
+///
+/// The file is left in place once the export is done, as removing it could let two waiting exports lock
+/// different files. Only the lock held on it matters, which the operating system releases as soon as the
+/// export ends, even if the process is killed.
+pub const LOCK_FILE_NAME: &str = ".quickstitch-lock";
+
+/// An exclusive lock on an output directory, held until dropped.
+pub(crate) struct DirectoryLock {
+    _file: File,
+}
+
+impl DirectoryLock {
+    /// Locks the given directory, shared with every other process using quickstitch.
+    ///
+    /// If another export already holds the lock, this either waits for it to be released, or returns `None`
+    /// straight away.
+    pub(crate) fn acquire(directory: &Path, wait: bool) -> io::Result<Option<Self>> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(directory.join(LOCK_FILE_NAME))?;
+        if wait {
+            file.lock_exclusive()?;
+        } else if !file.try_lock_exclusive()? {
+            return Ok(None);
+        }
+        Ok(Some(Self { _file: file }))
+    }
+}
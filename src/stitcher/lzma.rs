@@ -0,0 +1,372 @@
+//! This module decodes LZMA and LZMA2 streams, the compression 7z archives use (see the LZMA specification
+//! of the LZMA SDK, which the decoder follows).
+//!
+//! Streams are always decoded whole into memory, so the output is the dictionary, and the dictionary size
+//! of a stream doesn't matter.
+
+/// The number of states of the decoder, which tell what kinds of packets came before.
+const STATES: usize = 12;
+/// The most bits of the position which can select probabilities.
+const POS_BITS_MAX: usize = 4;
+/// Distances with a slot below this have all their bits coded with probabilities.
+const END_POS_MODEL_INDEX: u32 = 14;
+/// Distances below this have all their bits coded with probabilities.
+const FULL_DISTANCES: usize = 1 << (END_POS_MODEL_INDEX >> 1);
+/// How many of the lowest bits of long distances are coded with probabilities.
+const ALIGN_BITS: u32 = 4;
+/// The shortest match.
+const MATCH_MIN_LEN: usize = 2;
+/// The initial probability, which is half of the range of probabilities.
+const PROBABILITY_INIT: u16 = 1 << 10;
+
+/// Decodes an LZMA stream as it is stored in 7z archives: with its 5 bytes of properties kept apart, and
+/// decoding to `size` bytes. Returns `None` if the stream is damaged.
+pub(crate) fn decode_lzma(properties: &[u8], data: &[u8], size: usize) -> Option<Vec<u8>> {
+    let mut lzma = Lzma::new(*properties.first()?)?;
+    let mut decoder = RangeDecoder::new(data)?;
+    let mut out = Vec::with_capacity(size);
+    lzma.decode(&mut decoder, &mut out, size)?;
+    Some(out)
+}
+
+/// Decodes an LZMA2 stream, which splits LZMA data into chunks (some of which are stored as they are),
+/// decoding to `size` bytes. Returns `None` if the stream is damaged.
+pub(crate) fn decode_lzma2(data: &[u8], size: usize) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(size);
+    let mut lzma: Option<Lzma> = None;
+    let mut pos = 0;
+    let u16_at =
+        |pos: usize| Some(u16::from_be_bytes([*data.get(pos)?, *data.get(pos + 1)?]) as usize);
+    loop {
+        let control = *data.get(pos)?;
+        pos += 1;
+        match control {
+            // the end of the stream
+            0x00 => break,
+            // a chunk stored as it is
+            0x01 | 0x02 => {
+                let len = u16_at(pos)? + 1;
+                out.extend_from_slice(data.get(pos + 2..pos + 2 + len)?);
+                pos += 2 + len;
+            }
+            // a chunk of LZMA data, which may reset the state of the decoder first
+            0x80.. => {
+                let unpacked = ((control as usize & 0x1f) << 16) + u16_at(pos)? + 1;
+                let packed = u16_at(pos + 2)? + 1;
+                pos += 4;
+                match (control >> 5) & 0x3 {
+                    0 => {}
+                    1 => lzma.as_mut()?.reset(),
+                    _ => {
+                        let lzma = lzma.insert(Lzma::new(*data.get(pos)?)?);
+                        // LZMA2 limits how many probabilities literals can have
+                        if lzma.lc + lzma.lp > 4 {
+                            return None;
+                        }
+                        pos += 1;
+                    }
+                }
+                if out.len() + unpacked > size {
+                    return None;
+                }
+                let mut decoder = RangeDecoder::new(data.get(pos..pos + packed)?)?;
+                lzma.as_mut()?.decode(&mut decoder, &mut out, unpacked)?;
+                pos += packed;
+            }
+            _ => return None,
+        }
+        if out.len() > size {
+            return None;
+        }
+    }
+    (out.len() == size).then_some(out)
+}
+
+/// The arithmetic decoder every bit of an LZMA stream is read with.
+struct RangeDecoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+    range: u32,
+    code: u32,
+}
+
+impl<'a> RangeDecoder<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let [0, a, b, c, d, ..] = *data else {
+            return None;
+        };
+        let code = u32::from_be_bytes([a, b, c, d]);
+        (code != u32::MAX).then_some(Self {
+            data,
+            pos: 5,
+            range: u32::MAX,
+            code,
+        })
+    }
+    /// Whether the decoder has read past the end of its data, which means the stream was cut short.
+    fn overran(&self) -> bool {
+        self.pos > self.data.len()
+    }
+    fn normalize(&mut self) {
+        if self.range < 1 << 24 {
+            // reading past the end is caught by `overran`, once the packet being read is done
+            let byte = self.data.get(self.pos).copied().unwrap_or(0);
+            self.pos += 1;
+            self.range <<= 8;
+            self.code = (self.code << 8) | byte as u32;
+        }
+    }
+    /// Decodes a bit with the given probability of it being 0, adapting the probability to it.
+    fn bit(&mut self, probability: &mut u16) -> usize {
+        let bound = (self.range >> 11) * *probability as u32;
+        let bit = if self.code < bound {
+            *probability += ((1 << 11) - *probability) >> 5;
+            self.range = bound;
+            0
+        } else {
+            *probability -= *probability >> 5;
+            self.code -= bound;
+            self.range -= bound;
+            1
+        };
+        self.normalize();
+        bit
+    }
+    /// Decodes bits which are equally likely to be 0 or 1.
+    fn direct_bits(&mut self, count: u32) -> u32 {
+        let mut value = 0;
+        for _ in 0..count {
+            self.range >>= 1;
+            let bit = self.code >= self.range;
+            if bit {
+                self.code -= self.range;
+            }
+            value = (value << 1) | bit as u32;
+            self.normalize();
+        }
+        value
+    }
+    /// Decodes a number of `bits` bits, most significant first.
+    fn tree(&mut self, probabilities: &mut [u16], bits: u32) -> usize {
+        let mut node = 1;
+        for _ in 0..bits {
+            node = (node << 1) | self.bit(&mut probabilities[node]);
+        }
+        node - (1 << bits)
+    }
+    /// Decodes a number of `bits` bits, least significant first.
+    fn reverse_tree(&mut self, probabilities: &mut [u16], bits: u32) -> u32 {
+        let mut node = 1;
+        let mut value = 0;
+        for index in 0..bits {
+            let bit = self.bit(&mut probabilities[node]);
+            node = (node << 1) | bit;
+            value |= (bit as u32) << index;
+        }
+        value
+    }
+}
+
+/// The probabilities the length of a match is decoded with.
+struct LenDecoder {
+    choice: u16,
+    choice_2: u16,
+    low: [[u16; 8]; 1 << POS_BITS_MAX],
+    mid: [[u16; 8]; 1 << POS_BITS_MAX],
+    high: [u16; 256],
+}
+
+impl LenDecoder {
+    fn new() -> Self {
+        Self {
+            choice: PROBABILITY_INIT,
+            choice_2: PROBABILITY_INIT,
+            low: [[PROBABILITY_INIT; 8]; 1 << POS_BITS_MAX],
+            mid: [[PROBABILITY_INIT; 8]; 1 << POS_BITS_MAX],
+            high: [PROBABILITY_INIT; 256],
+        }
+    }
+    /// Decodes a length, less the shortest match.
+    fn decode(&mut self, decoder: &mut RangeDecoder, pos_state: usize) -> usize {
+        if decoder.bit(&mut self.choice) == 0 {
+            decoder.tree(&mut self.low[pos_state], 3)
+        } else if decoder.bit(&mut self.choice_2) == 0 {
+            8 + decoder.tree(&mut self.mid[pos_state], 3)
+        } else {
+            16 + decoder.tree(&mut self.high, 8)
+        }
+    }
+}
+
+/// The state of an LZMA decoder, which carries over between the chunks of an LZMA2 stream.
+struct Lzma {
+    /// How many of the high bits of the previous byte select the probabilities of a literal.
+    lc: u32,
+    /// How many of the low bits of the position select the probabilities of a literal.
+    lp: u32,
+    /// How many of the low bits of the position select the probabilities of a packet.
+    pb: u32,
+    literals: Vec<u16>,
+    is_match: [u16; STATES << POS_BITS_MAX],
+    is_rep: [u16; STATES],
+    is_rep_g0: [u16; STATES],
+    is_rep_g1: [u16; STATES],
+    is_rep_g2: [u16; STATES],
+    is_rep0_long: [u16; STATES << POS_BITS_MAX],
+    slots: [[u16; 64]; 4],
+    special: [u16; 1 + FULL_DISTANCES - END_POS_MODEL_INDEX as usize],
+    align: [u16; 1 << ALIGN_BITS],
+    len: LenDecoder,
+    rep_len: LenDecoder,
+    state: usize,
+    /// The distances of the last 4 matches, which can be repeated.
+    reps: [usize; 4],
+}
+
+impl Lzma {
+    /// Creates a decoder from the byte packing the `lc`, `lp` and `pb` properties.
+    fn new(properties: u8) -> Option<Self> {
+        if properties >= 9 * 5 * 5 {
+            return None;
+        }
+        let properties = properties as u32;
+        let (lc, lp, pb) = (properties % 9, properties / 9 % 5, properties / 45);
+        Some(Self {
+            lc,
+            lp,
+            pb,
+            literals: vec![PROBABILITY_INIT; 0x300 << (lc + lp)],
+            is_match: [PROBABILITY_INIT; STATES << POS_BITS_MAX],
+            is_rep: [PROBABILITY_INIT; STATES],
+            is_rep_g0: [PROBABILITY_INIT; STATES],
+            is_rep_g1: [PROBABILITY_INIT; STATES],
+            is_rep_g2: [PROBABILITY_INIT; STATES],
+            is_rep0_long: [PROBABILITY_INIT; STATES << POS_BITS_MAX],
+            slots: [[PROBABILITY_INIT; 64]; 4],
+            special: [PROBABILITY_INIT; 1 + FULL_DISTANCES - END_POS_MODEL_INDEX as usize],
+            align: [PROBABILITY_INIT; 1 << ALIGN_BITS],
+            len: LenDecoder::new(),
+            rep_len: LenDecoder::new(),
+            state: 0,
+            reps: [0; 4],
+        })
+    }
+    /// Resets the state and the probabilities, keeping the properties.
+    fn reset(&mut self) {
+        let properties = (self.pb * 5 + self.lp) * 9 + self.lc;
+        *self = Self::new(properties as u8).expect("the properties were valid");
+    }
+    /// Decodes `size` more bytes onto `out`, which holds the bytes decoded before them.
+    fn decode(&mut self, decoder: &mut RangeDecoder, out: &mut Vec<u8>, size: usize) -> Option<()> {
+        let end = out.len() + size;
+        while out.len() < end {
+            let pos_state = out.len() & ((1 << self.pb) - 1);
+            let state = self.state;
+            if decoder.bit(&mut self.is_match[(state << POS_BITS_MAX) + pos_state]) == 0 {
+                self.literal(decoder, out)?;
+                self.state = match state {
+                    0..4 => 0,
+                    4..10 => state - 3,
+                    _ => state - 6,
+                };
+                continue;
+            }
+            let len = if decoder.bit(&mut self.is_rep[state]) == 1 {
+                if out.is_empty() {
+                    return None;
+                }
+                if decoder.bit(&mut self.is_rep_g0[state]) == 0 {
+                    // a single byte repeated from the last distance
+                    if decoder.bit(&mut self.is_rep0_long[(state << POS_BITS_MAX) + pos_state]) == 0
+                    {
+                        self.state = if state < 7 { 9 } else { 11 };
+                        let byte = out[out.len().checked_sub(self.reps[0] + 1)?];
+                        out.push(byte);
+                        continue;
+                    }
+                } else {
+                    let distance = if decoder.bit(&mut self.is_rep_g1[state]) == 0 {
+                        self.reps[1]
+                    } else {
+                        let distance = if decoder.bit(&mut self.is_rep_g2[state]) == 0 {
+                            self.reps[2]
+                        } else {
+                            let distance = self.reps[3];
+                            self.reps[3] = self.reps[2];
+                            distance
+                        };
+                        self.reps[2] = self.reps[1];
+                        distance
+                    };
+                    self.reps[1] = self.reps[0];
+                    self.reps[0] = distance;
+                }
+                self.state = if state < 7 { 8 } else { 11 };
+                self.rep_len.decode(decoder, pos_state)
+            } else {
+                self.reps.rotate_right(1);
+                let len = self.len.decode(decoder, pos_state);
+                self.state = if state < 7 { 7 } else { 10 };
+                let distance = self.distance(decoder, len);
+                // the end marker, which can only come once every byte has been decoded
+                if distance == u32::MAX {
+                    return None;
+                }
+                self.reps[0] = distance as usize;
+                len
+            } + MATCH_MIN_LEN;
+            let distance = self.reps[0] + 1;
+            if distance > out.len() || len > end - out.len() {
+                return None;
+            }
+            for _ in 0..len {
+                let byte = out[out.len() - distance];
+                out.push(byte);
+            }
+        }
+        (!decoder.overran()).then_some(())
+    }
+    /// Decodes a literal byte onto `out`.
+    fn literal(&mut self, decoder: &mut RangeDecoder, out: &mut Vec<u8>) -> Option<()> {
+        let previous = out.last().copied().unwrap_or(0) as usize;
+        let literal_state =
+            ((out.len() & ((1 << self.lp) - 1)) << self.lc) + (previous >> (8 - self.lc));
+        let probabilities = &mut self.literals[0x300 * literal_state..][..0x300];
+        let mut symbol = 1;
+        // after a match, the byte after the match is used to predict the literal
+        if self.state >= 7 {
+            let mut matched = *out.get(out.len().checked_sub(self.reps[0] + 1)?)? as usize;
+            loop {
+                let matched_bit = (matched >> 7) & 1;
+                matched <<= 1;
+                let bit = decoder.bit(&mut probabilities[((1 + matched_bit) << 8) + symbol]);
+                symbol = (symbol << 1) | bit;
+                if matched_bit != bit || symbol >= 0x100 {
+                    break;
+                }
+            }
+        }
+        while symbol < 0x100 {
+            symbol = (symbol << 1) | decoder.bit(&mut probabilities[symbol]);
+        }
+        out.push((symbol - 0x100) as u8);
+        Some(())
+    }
+    /// Decodes the distance of a match, less 1, given its length less the shortest match.
+    fn distance(&mut self, decoder: &mut RangeDecoder, len: usize) -> u32 {
+        let slot = decoder.tree(&mut self.slots[len.min(3)], 6) as u32;
+        if slot < 4 {
+            return slot;
+        }
+        let direct_bits = (slot >> 1) - 1;
+        let distance = (2 | (slot & 1)) << direct_bits;
+        if slot < END_POS_MODEL_INDEX {
+            let probabilities = &mut self.special[(distance - slot) as usize..];
+            distance + decoder.reverse_tree(probabilities, direct_bits)
+        } else {
+            distance
+                + (decoder.direct_bits(direct_bits - ALIGN_BITS) << ALIGN_BITS)
+                + decoder.reverse_tree(&mut self.align, ALIGN_BITS)
+        }
+    }
+}
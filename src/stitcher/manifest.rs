@@ -0,0 +1,113 @@
+//! This module contains the manifest written alongside exported pages, used to resume interrupted exports.
+//!
+//! The manifest is a text file with one line per page which has been written, in the order they were
+//! written: the index of the page, its file name, a hash of the page (see `PageCache::key`), and the size
+//! and hash of the file, separated by tabs. Lines are appended as soon as each page is written, so the
+//! manifest of an interrupted export lists exactly the pages which were completed.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    hash::{DefaultHasher, Hasher},
+    io::{self, ErrorKind, Write},
+    path::Path,
+    sync::{Mutex, PoisonError},
+};
+
+/// The name of the manifest file within the output directory.
+pub const MANIFEST_FILE_NAME: &str = ".quickstitch-manifest";
+
+/// A page recorded in the manifest.
+#[derive(Debug, Clone)]
+pub(crate) struct ManifestEntry {
+    pub(crate) index: usize,
+    pub(crate) name: String,
+    pub(crate) key: u64,
+    pub(crate) size: u64,
+    pub(crate) hash: u64,
+}
+
+impl ManifestEntry {
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        let entry = Self {
+            index: fields.next()?.parse().ok()?,
+            name: fields.next()?.to_string(),
+            key: u64::from_str_radix(fields.next()?, 16).ok()?,
+            size: fields.next()?.parse().ok()?,
+            hash: u64::from_str_radix(fields.next()?, 16).ok()?,
+        };
+        fields.next().is_none().then_some(entry)
+    }
+}
+
+/// The manifest of an export in progress.
+pub(crate) struct Manifest {
+    /// The pages recorded by the previous export to the same directory, if resuming.
+    previous: HashMap<usize, ManifestEntry>,
+    file: Mutex<File>,
+}
+
+impl Manifest {
+    /// Starts a new manifest in the given directory, first reading the previous one if `resume` is set.
+    pub(crate) fn create(directory: &Path, resume: bool) -> io::Result<Self> {
+        let path = directory.join(MANIFEST_FILE_NAME);
+        let previous = if resume {
+            match fs::read_to_string(&path) {
+                // unreadable lines (e.g. a line cut short by a crash) are ignored
+                Ok(manifest) => manifest
+                    .lines()
+                    .filter_map(ManifestEntry::parse)
+                    .map(|entry| (entry.index, entry))
+                    .collect(),
+                Err(e) if e.kind() == ErrorKind::NotFound => HashMap::new(),
+                Err(e) => return Err(e),
+            }
+        } else {
+            HashMap::new()
+        };
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        Ok(Self {
+            previous,
+            file: Mutex::new(file),
+        })
+    }
+    /// Looks up the page at `index` in the previous manifest, returning it if it has the same contents and
+    /// is still intact on disk.
+    pub(crate) fn unchanged(
+        &self,
+        index: usize,
+        key: u64,
+        directory: &Path,
+    ) -> Option<&ManifestEntry> {
+        let entry = self.previous.get(&index).filter(|entry| entry.key == key)?;
+        let contents = fs::read(directory.join(&entry.name)).ok()?;
+        (contents.len() as u64 == entry.size && content_hash(&contents) == entry.hash)
+            .then_some(entry)
+    }
+    /// Records a page which has been written.
+    pub(crate) fn record(&self, entry: &ManifestEntry) -> io::Result<()> {
+        let line = format!(
+            "{}\t{}\t{:016x}\t{}\t{:016x}\n",
+            entry.index, entry.name, entry.key, entry.size, entry.hash
+        );
+        self.file
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .write_all(line.as_bytes())
+    }
+}
+
+/// Hashes the contents of a file.
+///
+/// The hash is only stable between runs of the same build of quickstitch, which at worst causes pages to be
+/// encoded again unnecessarily.
+pub(crate) fn content_hash(contents: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(contents);
+    hasher.finish()
+}
@@ -0,0 +1,445 @@
+//! This module contains the message catalog used to show errors in languages other than English.
+
+use std::{collections::HashMap, io};
+
+use itertools::Itertools;
+
+use super::{
+    image_loader::{describe_source, ImageLoaderError},
+    image_splitter::ImageSplitterError,
+    pipeline::PipelineError,
+};
+
+/// The English messages, which are also what errors display as.
+const ENGLISH: &str = r#"
+# Errors raised while loading images
+loader-not-found = Could not find the provided file or directory
+loader-permission-denied = Insufficient permissions to access the provided file or directory
+loader-no-images = No images were found in the selected directory
+loader-no-glob-matches = No images match the pattern { $pattern }
+loader-invalid-glob-pattern = The pattern { $pattern } is invalid, as { $reason }
+loader-invalid-order = The order listed in { $path } can't be used, as { $reason }
+loader-expected-directory = Expected a directory
+loader-no-chapters = No chapters were provided
+loader-source-changed = { $path } was modified or removed while images were being loaded
+loader-empty-image = The images have no pixels to stitch
+loader-strip-too-tall = The combined image would be { $height } pixels tall, which is more than can be stitched
+loader-unsafe-archive-entry = { $path } would be extracted outside of the archive
+loader-too-many-archive-entries = The archive has more than { $limit } entries
+loader-archive-entry-too-large = { $path } is more than { $limit } bytes once decompressed
+loader-archive-too-large = The images in the archive are more than { $limit } bytes once decompressed
+loader-archive-decode-work-too-large = Reading the archive would take decompressing more than { $limit } bytes
+loader-invalid-archive = The archive can't be read, as { $reason }
+loader-unsupported-archive-entry = { $path } is stored in the archive in a way which isn't supported ({ $reason })
+loader-animated = { $image } is animated
+loader-invalid-pdf = The file is not a valid PDF
+loader-no-images-in-pdf = The PDF does not contain any images
+loader-unsupported-pdf-image = An image on page { $page } of the PDF is stored in a way which isn't supported ({ $reason })
+
+# Errors raised while exporting pages
+splitter-directory-not-found = Could not find the provided directory
+splitter-permission-denied = Insufficient permissions within the provided directory
+splitter-width-mismatch = Expected a page width of { $expected } pixels, but a page was { $actual } pixels wide
+splitter-unknown-encoder = No encoder has been registered for the custom output format { $format }
+splitter-corrupt-output = The exported page at { $path } could not be read back correctly
+splitter-insufficient-space = Not enough free space in the output directory (~{ $required } bytes required, { $available } bytes available)
+splitter-directory-locked = Another export is already writing to { $path }
+splitter-splitpoint-out-of-bounds = Row { $row } is not within the image, so it can't be a splitpoint
+splitter-splitpoint-not-found = There is no splitpoint at row { $row }
+splitter-splitpoint-exists = There is already a splitpoint at row { $row }
+splitter-chapter-splitpoint = The splitpoint at row { $row } starts a chapter, so it can't be removed or moved
+splitter-page-too-short = A page would be { $height } pixels tall, which is less than the minimum of { $min }
+splitter-page-too-tall = A page would be { $height } pixels tall, which is more than the maximum of { $max }
+splitter-region-out-of-bounds = Rows { $start } to { $end } are not a region of the image
+splitter-worst-case-cut = No clean row was found to cut at, so row { $row } was cut regardless, which strict mode forbids
+splitter-invalid-font = The font could not be loaded: { $reason }
+splitter-invalid-splitpoints = The saved splitpoints can't be used: { $reason }
+
+# Errors raised while running a pipeline
+pipeline-missing-stage = The pipeline has no { $stage } stage
+
+# Suggestions for recovering from errors
+loader-not-found-suggestion = Check that the path is spelled correctly, and that the drive it is on is connected
+loader-permission-denied-suggestion = Run with access to the images, or copy them somewhere you can read them
+loader-no-images-suggestion = Make sure the images are .jpg, .jpeg, .png or .webp files directly inside the directory, not in a folder within it
+loader-no-glob-matches-suggestion = Check that the pattern is relative to the directory being run from, and use ** to match images in folders within a directory
+loader-invalid-glob-pattern-suggestion = Close each [ with a ], and write ** only as a whole folder name, such as raws/**/*.png
+loader-invalid-order-suggestion = List each image once by its file name alone, or remove the order file to order the images by name
+loader-expected-directory-suggestion = Choose the directory holding the images, rather than a single image
+loader-no-chapters-suggestion = Load at least one chapter before combining them
+loader-source-changed-suggestion = Wait for whatever is writing to the directory (such as a sync client) to finish, or turn on rescanning when it changes
+loader-empty-image-suggestion = Check that the images aren't empty or damaged
+loader-strip-too-tall-suggestion = Stitch fewer images at once, or set a smaller width to downscale them
+loader-unloadable-suggestion = Skip images which can't be loaded with the ignore unloadable option, or replace the damaged image
+loader-out-of-memory-suggestion = Set a spill threshold so the combined image is kept in a temp file instead of memory, or set a smaller width to downscale the images
+loader-unsafe-archive-entry-suggestion = Don't trust the archive, as it may have been made to overwrite files elsewhere; repack it with only its images if it came from a trusted source
+loader-archive-limits-suggestion = Raise the archive limits if the archive comes from a trusted source
+loader-archive-suggestion = Extract the archive with another tool and stitch the directory instead, or repack it as a .cbz
+loader-animated-suggestion = Replace the animated image with a still one, or load its first frame or skip it with the animated policy
+loader-pdf-suggestion = Extract the pages of the PDF as images with another tool, and stitch those instead
+splitter-directory-not-found-suggestion = Create the output directory first, or check that its path is spelled correctly
+splitter-permission-denied-suggestion = Run with write access to the output directory, or choose a directory you can write to
+splitter-width-mismatch-suggestion = Pad or resize pages to the required width instead of failing
+splitter-unknown-encoder-suggestion = Register an encoder for { $format } before exporting
+splitter-corrupt-output-suggestion = Check the output drive for errors and export again, or export to a local drive
+splitter-insufficient-space-suggestion = Free up at least { $missing } bytes, or export to a different drive
+splitter-directory-locked-suggestion = Wait for the other export to finish, or export to a different directory
+splitter-page-limits-suggestion = Choose a different row, or loosen the page limits
+splitter-region-out-of-bounds-suggestion = Choose rows within the image, starting before they end
+splitter-worst-case-cut-suggestion = Move or remove the cut by hand, or stitch again with a larger target height or a lower sensitivity
+splitter-invalid-font-suggestion = Use a TrueType (.ttf) or OpenType (.otf) font file
+splitter-invalid-splitpoints-suggestion = Stitch the strip again rather than loading the saved splitpoints
+pipeline-missing-stage-suggestion = Set the { $stage } of the pipeline before building it
+"#;
+
+/// A set of translated messages, for showing errors in the user's language.
+///
+/// Catalogs are written in a subset of the [Fluent](https://projectfluent.org) syntax: one `id = message`
+/// per line, with arguments written as `{ $name }`, and comments starting with `#`. See the source of
+/// [`Catalog::english`] for every id and the arguments it takes. Messages missing from a catalog fall
+/// back to English.
+///
+/// Errors raised by other libraries (such as I/O errors) are always shown as those libraries display them.
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    messages: HashMap<String, String>,
+}
+
+impl Default for Catalog {
+    fn default() -> Self {
+        Self::english()
+    }
+}
+
+impl Catalog {
+    /// The English messages.
+    pub fn english() -> Self {
+        Self {
+            messages: parse(ENGLISH),
+        }
+    }
+    /// Reads a catalog, such as a translation embedded with `include_str!`.
+    ///
+    /// Lines which aren't comments or messages are ignored.
+    pub fn parse(source: &str) -> Self {
+        let mut catalog = Self::english();
+        catalog.messages.extend(parse(source));
+        catalog
+    }
+    /// Formats the message with the given id, filling in its arguments.
+    ///
+    /// Unknown ids are returned as they are, and unknown arguments are left in place.
+    pub fn format(&self, id: &str, args: &[(&str, String)]) -> String {
+        let Some(message) = self.messages.get(id) else {
+            return id.to_string();
+        };
+        let mut formatted = String::with_capacity(message.len());
+        let mut rest = message.as_str();
+        while let Some(start) = rest.find('{') {
+            formatted.push_str(&rest[..start]);
+            let placeable = rest[start..]
+                .find('}')
+                .map(|end| &rest[start..start + end + 1]);
+            let value = placeable.and_then(|placeable| {
+                let name = placeable[1..placeable.len() - 1].trim().strip_prefix('$')?;
+                args.iter()
+                    .find(|(arg, _)| *arg == name)
+                    .map(|(_, value)| value)
+            });
+            match (placeable, value) {
+                (Some(placeable), Some(value)) => {
+                    formatted.push_str(value);
+                    rest = &rest[start + placeable.len()..];
+                }
+                _ => {
+                    formatted.push('{');
+                    rest = &rest[start + 1..];
+                }
+            }
+        }
+        formatted.push_str(rest);
+        formatted
+    }
+}
+
+fn parse(source: &str) -> HashMap<String, String> {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(id, message)| (id.trim().to_string(), message.trim().to_string()))
+        .filter(|(id, _)| !id.is_empty())
+        .collect()
+}
+
+impl ImageLoaderError {
+    /// The message of the error in the given catalog.
+    pub fn localize(&self, catalog: &Catalog) -> String {
+        let (id, args) = match self {
+            ImageLoaderError::NotFound => ("loader-not-found", vec![]),
+            ImageLoaderError::PermissionDenied => ("loader-permission-denied", vec![]),
+            ImageLoaderError::NoImagesInDirectory => ("loader-no-images", vec![]),
+            ImageLoaderError::NoGlobMatches(pattern) => {
+                ("loader-no-glob-matches", vec![("pattern", pattern.clone())])
+            }
+            ImageLoaderError::InvalidGlobPattern { pattern, reason } => (
+                "loader-invalid-glob-pattern",
+                vec![("pattern", pattern.clone()), ("reason", reason.clone())],
+            ),
+            ImageLoaderError::InvalidOrder { path, reason } => (
+                "loader-invalid-order",
+                vec![("path", format!("{path:?}")), ("reason", reason.clone())],
+            ),
+            ImageLoaderError::ExpectedDirectory => ("loader-expected-directory", vec![]),
+            ImageLoaderError::NoChapters => ("loader-no-chapters", vec![]),
+            ImageLoaderError::SourceChangedDuringRun(path) => {
+                ("loader-source-changed", vec![("path", format!("{path:?}"))])
+            }
+            ImageLoaderError::EmptyImage => ("loader-empty-image", vec![]),
+            ImageLoaderError::StripTooTall(height) => (
+                "loader-strip-too-tall",
+                vec![("height", height.to_string())],
+            ),
+            ImageLoaderError::UnsafeArchiveEntry(path) => (
+                "loader-unsafe-archive-entry",
+                vec![("path", format!("{path:?}"))],
+            ),
+            ImageLoaderError::TooManyArchiveEntries(limit) => (
+                "loader-too-many-archive-entries",
+                vec![("limit", limit.to_string())],
+            ),
+            ImageLoaderError::ArchiveEntryTooLarge { path, limit } => (
+                "loader-archive-entry-too-large",
+                vec![("path", format!("{path:?}")), ("limit", limit.to_string())],
+            ),
+            ImageLoaderError::ArchiveTooLarge(limit) => (
+                "loader-archive-too-large",
+                vec![("limit", limit.to_string())],
+            ),
+            ImageLoaderError::ArchiveDecodeWorkTooLarge(limit) => (
+                "loader-archive-decode-work-too-large",
+                vec![("limit", limit.to_string())],
+            ),
+            ImageLoaderError::InvalidArchive(reason) => {
+                ("loader-invalid-archive", vec![("reason", reason.clone())])
+            }
+            ImageLoaderError::UnsupportedArchiveEntry { path, reason } => (
+                "loader-unsupported-archive-entry",
+                vec![("path", format!("{path:?}")), ("reason", reason.clone())],
+            ),
+            ImageLoaderError::AnimatedImage { index, path } => (
+                "loader-animated",
+                vec![("image", describe_source(*index, path.as_deref()))],
+            ),
+            ImageLoaderError::InvalidPdf => ("loader-invalid-pdf", vec![]),
+            ImageLoaderError::NoImagesInPdf => ("loader-no-images-in-pdf", vec![]),
+            ImageLoaderError::UnsupportedPdfImage { page, reason } => (
+                "loader-unsupported-pdf-image",
+                vec![("page", page.to_string()), ("reason", reason.clone())],
+            ),
+            _ => return self.to_string(),
+        };
+        catalog.format(id, &args)
+    }
+    /// A suggestion of how to recover from the error, in English, if there is one.
+    pub fn suggestion(&self) -> Option<String> {
+        self.localize_suggestion(&Catalog::english())
+    }
+    /// A suggestion of how to recover from the error in the given catalog, if there is one.
+    pub fn localize_suggestion(&self, catalog: &Catalog) -> Option<String> {
+        let id = match self {
+            ImageLoaderError::NotFound => "loader-not-found-suggestion",
+            ImageLoaderError::PermissionDenied => "loader-permission-denied-suggestion",
+            ImageLoaderError::NoImagesInDirectory => "loader-no-images-suggestion",
+            ImageLoaderError::NoGlobMatches(_) => "loader-no-glob-matches-suggestion",
+            ImageLoaderError::InvalidGlobPattern { .. } => "loader-invalid-glob-pattern-suggestion",
+            ImageLoaderError::InvalidOrder { .. } => "loader-invalid-order-suggestion",
+            ImageLoaderError::ExpectedDirectory => "loader-expected-directory-suggestion",
+            ImageLoaderError::NoChapters => "loader-no-chapters-suggestion",
+            ImageLoaderError::SourceChangedDuringRun(_) => "loader-source-changed-suggestion",
+            ImageLoaderError::EmptyImage => "loader-empty-image-suggestion",
+            ImageLoaderError::StripTooTall(_) => "loader-strip-too-tall-suggestion",
+            ImageLoaderError::UnsafeArchiveEntry(_) => "loader-unsafe-archive-entry-suggestion",
+            ImageLoaderError::TooManyArchiveEntries(_)
+            | ImageLoaderError::ArchiveEntryTooLarge { .. }
+            | ImageLoaderError::ArchiveTooLarge(_)
+            | ImageLoaderError::ArchiveDecodeWorkTooLarge(_) => "loader-archive-limits-suggestion",
+            ImageLoaderError::InvalidArchive(_)
+            | ImageLoaderError::UnsupportedArchiveEntry { .. } => "loader-archive-suggestion",
+            ImageLoaderError::AnimatedImage { .. } => "loader-animated-suggestion",
+            ImageLoaderError::InvalidPdf
+            | ImageLoaderError::NoImagesInPdf
+            | ImageLoaderError::UnsupportedPdfImage { .. } => "loader-pdf-suggestion",
+            ImageLoaderError::ImageError(_) => "loader-unloadable-suggestion",
+            ImageLoaderError::IoError(error) if error.kind() == io::ErrorKind::OutOfMemory => {
+                "loader-out-of-memory-suggestion"
+            }
+            _ => return None,
+        };
+        Some(catalog.format(id, &[]))
+    }
+}
+
+impl ImageSplitterError {
+    /// The message of the error in the given catalog.
+    pub fn localize(&self, catalog: &Catalog) -> String {
+        let (id, args) = match self {
+            ImageSplitterError::DirectoryNotFound => ("splitter-directory-not-found", vec![]),
+            ImageSplitterError::PermissionDenied => ("splitter-permission-denied", vec![]),
+            ImageSplitterError::WidthMismatch { expected, actual } => (
+                "splitter-width-mismatch",
+                vec![
+                    ("expected", expected.to_string()),
+                    ("actual", actual.to_string()),
+                ],
+            ),
+            ImageSplitterError::UnknownEncoder(format) => (
+                "splitter-unknown-encoder",
+                vec![("format", format!("{format:?}"))],
+            ),
+            ImageSplitterError::CorruptOutput(path) => (
+                "splitter-corrupt-output",
+                vec![("path", format!("{path:?}"))],
+            ),
+            ImageSplitterError::InsufficientSpace {
+                required,
+                available,
+            } => (
+                "splitter-insufficient-space",
+                vec![
+                    ("required", required.to_string()),
+                    ("available", available.to_string()),
+                ],
+            ),
+            ImageSplitterError::DirectoryLocked(path) => (
+                "splitter-directory-locked",
+                vec![("path", format!("{path:?}"))],
+            ),
+            ImageSplitterError::SplitpointOutOfBounds(row) => (
+                "splitter-splitpoint-out-of-bounds",
+                vec![("row", row.to_string())],
+            ),
+            ImageSplitterError::SplitpointNotFound(row) => (
+                "splitter-splitpoint-not-found",
+                vec![("row", row.to_string())],
+            ),
+            ImageSplitterError::SplitpointExists(row) => {
+                ("splitter-splitpoint-exists", vec![("row", row.to_string())])
+            }
+            ImageSplitterError::ChapterSplitpoint(row) => (
+                "splitter-chapter-splitpoint",
+                vec![("row", row.to_string())],
+            ),
+            ImageSplitterError::PageTooShort { height, min } => (
+                "splitter-page-too-short",
+                vec![("height", height.to_string()), ("min", min.to_string())],
+            ),
+            ImageSplitterError::PageTooTall { height, max } => (
+                "splitter-page-too-tall",
+                vec![("height", height.to_string()), ("max", max.to_string())],
+            ),
+            ImageSplitterError::RegionOutOfBounds { start, end } => (
+                "splitter-region-out-of-bounds",
+                vec![("start", start.to_string()), ("end", end.to_string())],
+            ),
+            ImageSplitterError::WorstCaseCut(row) => {
+                ("splitter-worst-case-cut", vec![("row", row.to_string())])
+            }
+            #[cfg(feature = "title-card")]
+            ImageSplitterError::InvalidFont(reason) => {
+                ("splitter-invalid-font", vec![("reason", reason.clone())])
+            }
+            #[cfg(feature = "serde")]
+            ImageSplitterError::InvalidSplitpoints(reason) => (
+                "splitter-invalid-splitpoints",
+                vec![("reason", reason.clone())],
+            ),
+            _ => return self.to_string(),
+        };
+        catalog.format(id, &args)
+    }
+    /// A suggestion of how to recover from the error, in English, if there is one.
+    pub fn suggestion(&self) -> Option<String> {
+        self.localize_suggestion(&Catalog::english())
+    }
+    /// A suggestion of how to recover from the error in the given catalog, if there is one.
+    pub fn localize_suggestion(&self, catalog: &Catalog) -> Option<String> {
+        let (id, args) = match self {
+            ImageSplitterError::DirectoryNotFound => {
+                ("splitter-directory-not-found-suggestion", vec![])
+            }
+            ImageSplitterError::PermissionDenied => {
+                ("splitter-permission-denied-suggestion", vec![])
+            }
+            ImageSplitterError::WidthMismatch { .. } => {
+                ("splitter-width-mismatch-suggestion", vec![])
+            }
+            ImageSplitterError::UnknownEncoder(format) => (
+                "splitter-unknown-encoder-suggestion",
+                vec![("format", format!("{format:?}"))],
+            ),
+            ImageSplitterError::CorruptOutput(_) => ("splitter-corrupt-output-suggestion", vec![]),
+            ImageSplitterError::InsufficientSpace {
+                required,
+                available,
+            } => (
+                "splitter-insufficient-space-suggestion",
+                vec![("missing", required.saturating_sub(*available).to_string())],
+            ),
+            ImageSplitterError::DirectoryLocked(_) => {
+                ("splitter-directory-locked-suggestion", vec![])
+            }
+            ImageSplitterError::PageTooShort { .. } | ImageSplitterError::PageTooTall { .. } => {
+                ("splitter-page-limits-suggestion", vec![])
+            }
+            ImageSplitterError::RegionOutOfBounds { .. } => {
+                ("splitter-region-out-of-bounds-suggestion", vec![])
+            }
+            ImageSplitterError::WorstCaseCut(_) => ("splitter-worst-case-cut-suggestion", vec![]),
+            #[cfg(feature = "title-card")]
+            ImageSplitterError::InvalidFont(_) => ("splitter-invalid-font-suggestion", vec![]),
+            #[cfg(feature = "serde")]
+            ImageSplitterError::InvalidSplitpoints(_) => {
+                ("splitter-invalid-splitpoints-suggestion", vec![])
+            }
+            _ => return None,
+        };
+        Some(catalog.format(id, &args))
+    }
+}
+
+impl PipelineError {
+    /// The message of the error in the given catalog.
+    pub fn localize(&self, catalog: &Catalog) -> String {
+        match self {
+            PipelineError::MissingStage(stage) => {
+                catalog.format("pipeline-missing-stage", &[("stage", stage.to_string())])
+            }
+            PipelineError::Load(error) => error.localize(catalog),
+            PipelineError::Export(errors) => errors
+                .iter()
+                .map(|error| error.localize(catalog))
+                .join("; "),
+        }
+    }
+    /// A suggestion of how to recover from the error, in English, if there is one.
+    pub fn suggestion(&self) -> Option<String> {
+        self.localize_suggestion(&Catalog::english())
+    }
+    /// A suggestion of how to recover from the error in the given catalog, if there is one.
+    ///
+    /// When several pages failed to export, the suggestion for the first failure which has one is given.
+    pub fn localize_suggestion(&self, catalog: &Catalog) -> Option<String> {
+        match self {
+            PipelineError::MissingStage(stage) => Some(catalog.format(
+                "pipeline-missing-stage-suggestion",
+                &[("stage", stage.to_string())],
+            )),
+            PipelineError::Load(error) => error.localize_suggestion(catalog),
+            PipelineError::Export(errors) => errors
+                .iter()
+                .find_map(|error| error.localize_suggestion(catalog)),
+        }
+    }
+}
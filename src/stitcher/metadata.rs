@@ -0,0 +1,96 @@
+//! This module builds the EXIF metadata embedded into exported pages, so that archive tools can tell where a
+//! page came from.
+
+/// The metadata embedded into each exported page, as EXIF (see [`crate::ExportConfig::metadata`]).
+///
+/// Besides the series and chapter, each page records its position within the export and the version of
+/// quickstitch which wrote it. The fields are written as the standard TIFF tags `DocumentName` (the series),
+/// `PageName` (the chapter), `PageNumber` and `Software`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PageMetadata {
+    series: Option<String>,
+    chapter: Option<String>,
+}
+
+/// The TIFF field types used.
+const ASCII: u16 = 2;
+const SHORT: u16 = 3;
+
+impl PageMetadata {
+    /// Creates metadata with no series or chapter.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets the name of the series.
+    pub fn series(mut self, series: impl Into<String>) -> Self {
+        self.series = Some(series.into());
+        self
+    }
+    /// Sets the name (or number) of the chapter.
+    pub fn chapter(mut self, chapter: impl Into<String>) -> Self {
+        self.chapter = Some(chapter.into());
+        self
+    }
+    /// Builds the EXIF block (a little-endian TIFF structure) of the page at `index` (counting from 0, as
+    /// TIFF does) out of `total` pages. A total of 0 means it isn't known yet.
+    pub(crate) fn exif(&self, index: usize, total: usize) -> Vec<u8> {
+        let software = format!("quickstitch {}", env!("CARGO_PKG_VERSION"));
+        let clamp = |n: usize| n.min(u16::MAX as usize) as u16;
+        let mut page_number = clamp(index).to_le_bytes().to_vec();
+        page_number.extend(clamp(total).to_le_bytes());
+        // entries must be sorted by tag
+        let entries: Vec<(u16, u16, u32, Vec<u8>)> = [
+            self.series.as_deref().map(|series| (0x010d, series)),
+            self.chapter.as_deref().map(|chapter| (0x011d, chapter)),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|(tag, text)| (tag, ASCII, text_value(text)))
+        .chain([
+            (0x0129, SHORT, page_number),
+            (0x0131, ASCII, text_value(&software)),
+        ])
+        .map(|(tag, field_type, value)| {
+            // SHORT values are 2 bytes each, and ASCII values 1
+            let count = value.len() as u32 / if field_type == SHORT { 2 } else { 1 };
+            (tag, field_type, count, value)
+        })
+        .collect();
+
+        let mut exif = b"II*\0".to_vec();
+        exif.extend(8u32.to_le_bytes());
+        exif.extend((entries.len() as u16).to_le_bytes());
+        // values which don't fit in an entry are stored after the directory
+        let mut data_offset = 8 + 2 + entries.len() as u32 * 12 + 4;
+        let mut data = Vec::new();
+        for (tag, field_type, count, value) in &entries {
+            exif.extend(tag.to_le_bytes());
+            exif.extend(field_type.to_le_bytes());
+            exif.extend(count.to_le_bytes());
+            if value.len() <= 4 {
+                let mut inline = value.clone();
+                inline.resize(4, 0);
+                exif.extend(inline);
+            } else {
+                exif.extend(data_offset.to_le_bytes());
+                data.extend(value);
+                // values start on a word boundary
+                if value.len() % 2 == 1 {
+                    data.push(0);
+                }
+                data_offset += value.len().next_multiple_of(2) as u32;
+            }
+        }
+        // there is no next directory
+        exif.extend(0u32.to_le_bytes());
+        exif.extend(data);
+        exif
+    }
+}
+
+/// Encodes text as a NUL-terminated TIFF ASCII value.
+///
+/// Text outside of ASCII (such as the title of a manhwa) is kept as UTF-8, which most readers accept.
+fn text_value(text: &str) -> Vec<u8> {
+    text.bytes().filter(|&byte| byte != 0).chain([0]).collect()
+}
@@ -0,0 +1,122 @@
+//! This module contains the summary of a run, for applications embedding quickstitch which want to show
+//! their own dashboards. Nothing is ever sent anywhere by quickstitch itself.
+
+use std::time::Duration;
+
+use super::{image_splitter::ExportReport, stats::Stats};
+
+/// Counts, byte totals, durations and warnings of a run, built from its [`Stats`] and [`ExportReport`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunMetrics {
+    /// How many source images were loaded.
+    pub images_loaded: usize,
+    /// How many source images were detected as sideways and rotated.
+    pub images_rotated: usize,
+    /// How many pages were exported, including pages left as is when resuming.
+    pub pages_exported: usize,
+    /// How many chapters the exported pages belong to.
+    pub chapters: usize,
+    /// The total size of the exported pages, in bytes.
+    pub bytes_exported: u64,
+    /// The total height of the exported pages, in pixels.
+    pub rows_exported: u64,
+    /// Time spent decoding and resizing the source images.
+    pub load: Duration,
+    /// Time spent copying the source images into the combined image.
+    pub copy: Duration,
+    /// Time spent scoring rows and finding splitpoints.
+    pub score: Duration,
+    /// Time spent encoding and writing pages, summed over every export.
+    pub export: Duration,
+    /// Everything which didn't go as asked for, but didn't stop the run, in reading order.
+    pub warnings: Vec<RunWarning>,
+}
+
+/// Something which didn't go as asked for during a run, but didn't stop it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunWarning {
+    /// The source images looked like pages which were already stitched, so they were cut again along their
+    /// seams. See [`crate::LoadConfig::detect_stitched`].
+    AlreadyStitched,
+    /// The source image at the given position was animated, so only its first frame was loaded or it was
+    /// skipped. See [`crate::AnimatedPolicy`].
+    AnimatedImage(usize),
+    /// The source image at the given position was an exact duplicate of an earlier one, so it was skipped.
+    /// See [`crate::LoadConfig::skip_duplicates`].
+    DuplicateImage(usize),
+    /// The source image at the given position couldn't be loaded, so it was skipped. See
+    /// [`crate::LoadConfig::ignore_unloadable`].
+    UnloadableImage(usize),
+    /// The page with the given index couldn't be encoded in the preferred format, and was written in a
+    /// fallback format instead.
+    FallbackFormat(usize),
+    /// The page with the given index was cut regardless of what the rows looked like, as it was too tall for
+    /// its format.
+    ForcedCut(usize),
+    /// The page with the given index had to be padded or resized to the required page width.
+    WidthAdjusted(usize),
+    /// A cut above or below the page with the given index looks like it landed near text.
+    CutNearText(usize),
+}
+
+impl RunMetrics {
+    /// Summarises a run from the stats of the stitcher and the report of its export.
+    pub fn new(stats: &Stats, report: &ExportReport) -> Self {
+        let pages = &report.pages;
+        let mut warnings: Vec<RunWarning> = pages
+            .iter()
+            .enumerate()
+            .flat_map(|(index, page)| {
+                [
+                    page.substituted
+                        .then_some(RunWarning::FallbackFormat(index)),
+                    page.forced.then_some(RunWarning::ForcedCut(index)),
+                    page.adjusted.then_some(RunWarning::WidthAdjusted(index)),
+                    page.near_text.then_some(RunWarning::CutNearText(index)),
+                ]
+            })
+            .flatten()
+            .collect();
+        // the inputs come before any of the pages in reading order
+        let inputs = stats
+            .already_stitched
+            .then_some(RunWarning::AlreadyStitched)
+            .into_iter()
+            .chain(
+                stats
+                    .animated
+                    .iter()
+                    .map(|&(index, _)| RunWarning::AnimatedImage(index)),
+            )
+            .chain(
+                stats
+                    .duplicates
+                    .iter()
+                    .map(|duplicate| RunWarning::DuplicateImage(duplicate.index)),
+            )
+            .chain(
+                stats
+                    .skipped
+                    .iter()
+                    .map(|skipped| RunWarning::UnloadableImage(skipped.index)),
+            );
+        warnings.splice(0..0, inputs);
+        Self {
+            images_loaded: stats.files.len(),
+            images_rotated: stats.files.iter().filter(|file| file.rotated).count(),
+            pages_exported: pages.len(),
+            chapters: pages.iter().filter(|page| page.starts_chapter).count(),
+            bytes_exported: pages.iter().map(|page| page.size).sum(),
+            rows_exported: pages.iter().map(|page| page.height as u64).sum(),
+            load: stats.load,
+            copy: stats.copy,
+            score: stats.score,
+            export: stats.export,
+            warnings,
+        }
+    }
+    /// The total time spent on every phase.
+    pub fn total(&self) -> Duration {
+        self.load + self.copy + self.score + self.export
+    }
+}
@@ -1,2 +1,51 @@
+#[cfg(any(feature = "zip", feature = "7z"))]
+pub mod archive;
+pub mod chapter_breaks;
+#[cfg(feature = "zip")]
+pub mod comic_info;
+pub mod decoder;
+pub mod edit;
+pub mod encoder;
+pub mod exif;
+#[cfg(feature = "glob")]
+pub mod glob;
+pub mod golden;
+pub mod host_profile;
 pub mod image_loader;
 pub mod image_splitter;
+pub mod jpeg;
+pub mod lock;
+#[cfg(feature = "7z")]
+pub mod lzma;
+pub mod manifest;
+pub mod messages;
+pub mod metadata;
+pub mod metrics;
+pub mod output_sink;
+pub mod overlay;
+pub mod page_cache;
+pub mod page_numbers;
+pub mod palette;
+#[cfg(feature = "pdf")]
+pub mod pdf;
+#[cfg(feature = "pdf")]
+pub mod pdf_reader;
+pub mod pipeline;
+pub mod progress;
+pub mod raw;
+#[cfg(feature = "serde")]
+pub mod saved_splitpoints;
+pub mod score_cache;
+#[cfg(feature = "7z")]
+pub mod seven_zip;
+pub mod sharpen;
+pub mod stage_cache;
+pub mod stats;
+pub mod stream;
+pub mod strip;
+pub mod sweep;
+pub mod temp;
+pub mod throttle;
+#[cfg(feature = "title-card")]
+pub mod title_card;
+pub mod virtual_strip;
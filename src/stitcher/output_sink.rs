@@ -0,0 +1,50 @@
+//! This module contains the destinations encoded pages can be handed to when they shouldn't be written to
+//! files, e.g. to upload them straight to object storage without going through a temp directory.
+
+use std::io::{self, Write};
+
+/// Somewhere each encoded page can be handed to once it has been encoded.
+///
+/// Pages are given in reading order, along with the file name they would have been written to by
+/// [`crate::Stitcher::export`]. Implemented for `Vec<(String, Vec<u8>)>`, which keeps every page in memory,
+/// and for [`Writers`], which writes each page to a writer of the caller's choosing.
+pub trait OutputSink {
+    /// Takes a single encoded page.
+    fn write_page(&mut self, name: &str, page: &[u8]) -> io::Result<()>;
+}
+
+impl OutputSink for Vec<(String, Vec<u8>)> {
+    fn write_page(&mut self, name: &str, page: &[u8]) -> io::Result<()> {
+        self.push((name.to_string(), page.to_vec()));
+        Ok(())
+    }
+}
+
+impl<S: OutputSink + ?Sized> OutputSink for &mut S {
+    fn write_page(&mut self, name: &str, page: &[u8]) -> io::Result<()> {
+        (**self).write_page(name, page)
+    }
+}
+
+/// Opens a writer for each page with the given function, which is passed the name of the page, then
+/// writes the page to it and flushes it.
+///
+/// ```no_run
+/// # use quickstitch::Writers;
+/// # use std::fs::File;
+/// let mut sink = Writers(|name: &str| File::create(format!("/mnt/bucket/{name}")));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Writers<F>(pub F);
+
+impl<F, W> OutputSink for Writers<F>
+where
+    F: FnMut(&str) -> io::Result<W>,
+    W: Write,
+{
+    fn write_page(&mut self, name: &str, page: &[u8]) -> io::Result<()> {
+        let mut writer = (self.0)(name)?;
+        writer.write_all(page)?;
+        writer.flush()
+    }
+}
@@ -0,0 +1,153 @@
+//! This module stamps an image (such as a group's credits or logo) onto each exported page.
+
+use std::{fmt, path::Path, sync::Arc};
+
+use image::{imageops, Rgb, RgbImage, RgbaImage};
+
+use super::{image_splitter::ImageSplitterError, manifest::content_hash};
+
+/// Where on a page an [`Overlay`] is placed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum OverlayPosition {
+    /// The top left corner.
+    TopLeft,
+    /// Centred along the top edge.
+    Top,
+    /// The top right corner.
+    TopRight,
+    /// The centre of the page.
+    Center,
+    /// The bottom left corner.
+    BottomLeft,
+    /// Centred along the bottom edge.
+    Bottom,
+    /// The bottom right corner.
+    #[default]
+    BottomRight,
+}
+
+/// An image stamped onto each exported page (see [`crate::ExportConfig::overlay`]), blended according to
+/// its alpha channel and opacity (defaults to the bottom right corner, fully opaque, with no offset).
+///
+/// Overlays which don't fit within a page are scaled down to fit it, keeping their aspect ratio.
+#[derive(Clone)]
+pub struct Overlay {
+    image: Arc<RgbaImage>,
+    /// A hash of the pixels of the image, which stands in for them when the config is hashed.
+    hash: u64,
+    position: OverlayPosition,
+    opacity: f32,
+    offset: u32,
+}
+
+impl fmt::Debug for Overlay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Overlay")
+            .field("dimensions", &self.image.dimensions())
+            .field("hash", &self.hash)
+            .field("position", &self.position)
+            .field("opacity", &self.opacity)
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+impl Overlay {
+    /// Creates an overlay with the default options out of the given image.
+    pub fn new(image: RgbaImage) -> Self {
+        let mut contents = image.as_raw().clone();
+        contents.extend(image.width().to_le_bytes());
+        Self {
+            hash: content_hash(&contents),
+            image: Arc::new(image),
+            position: OverlayPosition::default(),
+            opacity: 1.0,
+            offset: 0,
+        }
+    }
+    /// Creates an overlay with the default options out of the image at the given path.
+    ///
+    /// Throws an error if the image can't be read or decoded.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ImageSplitterError> {
+        Ok(Self::new(image::open(path)?.into_rgba8()))
+    }
+    /// Sets where on each page the overlay is placed.
+    pub fn position(mut self, position: OverlayPosition) -> Self {
+        self.position = position;
+        self
+    }
+    /// Sets how opaque the overlay is, from 0 (invisible) to 1 (as opaque as its alpha channel allows).
+    pub fn opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity.clamp(0.0, 1.0);
+        self
+    }
+    /// Sets how many pixels the overlay is kept away from the edges of the page it is placed against.
+    pub fn offset(mut self, offset: u32) -> Self {
+        self.offset = offset;
+        self
+    }
+    /// Stamps the overlay onto a page.
+    pub(crate) fn apply(&self, page: &mut RgbImage) {
+        stamp(page, &self.image, self.position, self.offset, self.opacity);
+    }
+}
+
+/// Blends an image onto a page at the given position, `offset` pixels away from the edges it is placed
+/// against, scaling it down if it doesn't fit within the page.
+pub(crate) fn stamp(
+    page: &mut RgbImage,
+    image: &RgbaImage,
+    position: OverlayPosition,
+    offset: u32,
+    opacity: f32,
+) {
+    let room = (
+        page.width().saturating_sub(offset.saturating_mul(2)),
+        page.height().saturating_sub(offset.saturating_mul(2)),
+    );
+    if opacity == 0.0 || room.0 == 0 || room.1 == 0 {
+        return;
+    }
+    let resized;
+    let image = match image.dimensions() {
+        (width, height) if width > room.0 || height > room.1 => {
+            let scale = f64::min(room.0 as f64 / width as f64, room.1 as f64 / height as f64);
+            let width = ((width as f64 * scale) as u32).clamp(1, room.0);
+            let height = ((height as f64 * scale) as u32).clamp(1, room.1);
+            resized = imageops::resize(image, width, height, imageops::Triangle);
+            &resized
+        }
+        _ => image,
+    };
+
+    let place = |available: u32, length: u32, alignment: u8| {
+        offset
+            + match alignment {
+                0 => 0,
+                1 => (available - length) / 2,
+                _ => available - length,
+            }
+    };
+    let (horizontal, vertical) = match position {
+        OverlayPosition::TopLeft => (0, 0),
+        OverlayPosition::Top => (1, 0),
+        OverlayPosition::TopRight => (2, 0),
+        OverlayPosition::Center => (1, 1),
+        OverlayPosition::BottomLeft => (0, 2),
+        OverlayPosition::Bottom => (1, 2),
+        OverlayPosition::BottomRight => (2, 2),
+    };
+    let x = place(room.0, image.width(), horizontal);
+    let y = place(room.1, image.height(), vertical);
+    for (dx, dy, pixel) in image.enumerate_pixels() {
+        let alpha = pixel.0[3] as f32 / 255.0 * opacity;
+        if alpha == 0.0 {
+            continue;
+        }
+        let Rgb(below) = page.get_pixel_mut(x + dx, y + dy);
+        for (channel, &above) in below.iter_mut().zip(&pixel.0[..3]) {
+            *channel = (*channel as f32 * (1.0 - alpha) + above as f32 * alpha).round() as u8;
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! This module contains the cache used to avoid encoding the same page more than once across exports.
+
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+};
+
+use image::RgbImage;
+
+use super::image_splitter::ImageOutputFormat;
+
+/// A page which has already been written, and can be reused for identical pages.
+#[derive(Debug, Clone)]
+pub(crate) struct CachedPage {
+    pub(crate) path: PathBuf,
+    pub(crate) format: ImageOutputFormat,
+    pub(crate) substituted: bool,
+    pub(crate) size: u64,
+    pub(crate) hash: u64,
+}
+
+#[derive(Default)]
+struct Shared {
+    pages: Mutex<HashMap<u64, CachedPage>>,
+    reused_pages: AtomicU64,
+    reused_bytes: AtomicU64,
+}
+
+/// A cache of exported pages, keyed by their contents, for sharing between exports (e.g. every chapter
+/// of a series).
+///
+/// When a page is identical to one which was already written (such as a credits page repeated in every
+/// chapter), the existing file is hard-linked (or copied, if that fails) instead of encoding the page again.
+/// Pages are compared by a 64-bit hash of their pixels, so the chance of two different pages being
+/// mistaken for each other is negligible, but not zero.
+///
+/// Cloning a cache gives another handle to the same cache.
+#[derive(Clone, Default)]
+pub struct PageCache {
+    shared: Arc<Shared>,
+}
+
+impl PageCache {
+    /// Creates an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// The number of pages which were reused instead of being encoded.
+    pub fn reused_pages(&self) -> u64 {
+        self.shared.reused_pages.load(Ordering::Relaxed)
+    }
+    /// The total size (in bytes) of the pages which were reused instead of being encoded.
+    pub fn reused_bytes(&self) -> u64 {
+        self.shared.reused_bytes.load(Ordering::Relaxed)
+    }
+    /// Identifies a page by its pixels and the format it was requested in.
+    pub(crate) fn key(page: &RgbImage, format: &ImageOutputFormat) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        page.dimensions().hash(&mut hasher);
+        page.as_raw().hash(&mut hasher);
+        format.hash(&mut hasher);
+        hasher.finish()
+    }
+    /// Looks up a page which is still on disk.
+    pub(crate) fn get(&self, key: u64) -> Option<CachedPage> {
+        let pages = self
+            .shared
+            .pages
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        pages.get(&key).filter(|page| page.path.is_file()).cloned()
+    }
+    pub(crate) fn insert(&self, key: u64, page: CachedPage) {
+        self.shared
+            .pages
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key, page);
+    }
+    /// Links (or copies) a cached page to a new path, and records the savings.
+    pub(crate) fn reuse(&self, page: &CachedPage, path: &Path) -> io::Result<()> {
+        // when exporting to the same directory again, the page is already in place
+        if page.path != path {
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+            if fs::hard_link(&page.path, path).is_err() {
+                fs::copy(&page.path, path)?;
+            }
+        }
+        self.shared.reused_pages.fetch_add(1, Ordering::Relaxed);
+        self.shared
+            .reused_bytes
+            .fetch_add(page.size, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl fmt::Debug for PageCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pages = self
+            .shared
+            .pages
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        f.debug_struct("PageCache")
+            .field("pages", &pages.len())
+            .field("reused_pages", &self.reused_pages())
+            .field("reused_bytes", &self.reused_bytes())
+            .finish()
+    }
+}
@@ -0,0 +1,116 @@
+//! This module draws page numbers onto exported pages, for QC passes and print proofs.
+//!
+//! Only digits are ever drawn, so they come from a small built-in bitmap font rather than a font file.
+
+use image::{Rgb, RgbImage, Rgba, RgbaImage};
+
+use super::overlay::{stamp, OverlayPosition};
+
+/// The 5x7 bitmaps of the digits 0 to 9, one byte per row, with the leftmost pixel in the fifth bit.
+const DIGITS: [[u8; 7]; 10] = [
+    [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+    [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+    [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+    [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+    [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+    [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+    [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+    [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+    [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+    [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+];
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+
+/// The page numbers drawn onto each exported page (see [`crate::ExportConfig::page_numbers`]), which are
+/// the numbers the pages are named after.
+///
+/// Defaults to digits 24 pixels tall, in black on a white box, in the bottom right corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PageNumbers {
+    size: u32,
+    position: OverlayPosition,
+    color: Rgb<u8>,
+    background: Option<Rgb<u8>>,
+    offset: u32,
+}
+
+impl Default for PageNumbers {
+    fn default() -> Self {
+        Self {
+            size: 24,
+            position: OverlayPosition::BottomRight,
+            color: Rgb([0, 0, 0]),
+            background: Some(Rgb([255, 255, 255])),
+            offset: 0,
+        }
+    }
+}
+
+impl PageNumbers {
+    /// Creates page numbers with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets how tall the digits are, in pixels (at least 7, the height of the font).
+    pub fn size(mut self, size: u32) -> Self {
+        self.size = size.max(GLYPH_HEIGHT);
+        self
+    }
+    /// Sets where on each page the number is drawn.
+    pub fn position(mut self, position: OverlayPosition) -> Self {
+        self.position = position;
+        self
+    }
+    /// Sets the colors of the digits and of the box behind them (`None` draws the digits straight onto the
+    /// page).
+    pub fn colors(mut self, text: Rgb<u8>, background: Option<Rgb<u8>>) -> Self {
+        self.color = text;
+        self.background = background;
+        self
+    }
+    /// Sets how many pixels the number is kept away from the edges of the page it is placed against.
+    pub fn offset(mut self, offset: u32) -> Self {
+        self.offset = offset;
+        self
+    }
+    /// Draws the given number onto a page.
+    pub(crate) fn apply(&self, page: &mut RgbImage, number: usize) {
+        stamp(page, &self.render(number), self.position, self.offset, 1.0);
+    }
+    /// Renders a number, with its box if it has one.
+    fn render(&self, number: usize) -> RgbaImage {
+        let digits: Vec<usize> = number
+            .to_string()
+            .bytes()
+            .map(|digit| (digit - b'0') as usize)
+            .collect();
+        let glyph_width = (self.size * GLYPH_WIDTH).div_ceil(GLYPH_HEIGHT);
+        let gap = self.size / GLYPH_HEIGHT;
+        let padding = self.size / 4;
+        let width = digits.len() as u32 * (glyph_width + gap) - gap + 2 * padding;
+        let height = self.size + 2 * padding;
+
+        let background = match self.background {
+            Some(Rgb([r, g, b])) => Rgba([r, g, b, 255]),
+            None => Rgba([0, 0, 0, 0]),
+        };
+        let Rgb([r, g, b]) = self.color;
+        RgbaImage::from_fn(width, height, |x, y| {
+            let (Some(x), Some(y)) = (x.checked_sub(padding), y.checked_sub(padding)) else {
+                return background;
+            };
+            let (index, x) = (x / (glyph_width + gap), x % (glyph_width + gap));
+            let lit = index < digits.len() as u32
+                && x < glyph_width
+                && y < self.size
+                && DIGITS[digits[index as usize]][(y * GLYPH_HEIGHT / self.size) as usize]
+                    & (0x10 >> (x * GLYPH_WIDTH / glyph_width))
+                    != 0;
+            match lit {
+                true => Rgba([r, g, b, 255]),
+                false => background,
+            }
+        })
+    }
+}
@@ -0,0 +1,85 @@
+//! This module contains the color quantization used to write pages as 8-bit palette PNGs.
+
+use std::{borrow::Cow, io::Write};
+
+use color_quant::NeuQuant;
+use image::{
+    error::{EncodingError, ImageFormatHint},
+    imageops::{self, colorops::ColorMap},
+    ImageError, ImageFormat, Rgb, RgbImage,
+};
+
+/// How finely NeuQuant samples the page when building the palette (1 is the slowest and most accurate, 30
+/// the fastest).
+const SAMPLE_FACTOR: i32 = 10;
+
+/// A palette built from the colors of a page.
+struct Palette(NeuQuant);
+
+impl ColorMap for Palette {
+    type Color = Rgb<u8>;
+
+    fn index_of(&self, color: &Rgb<u8>) -> usize {
+        let [r, g, b] = color.0;
+        self.0.index_of(&[r, g, b, 255])
+    }
+    fn lookup(&self, index: usize) -> Option<Rgb<u8>> {
+        self.0.lookup(index).map(|[r, g, b, _]| Rgb([r, g, b]))
+    }
+    fn has_lookup(&self) -> bool {
+        true
+    }
+    fn map_color(&self, color: &mut Rgb<u8>) {
+        if let Some(mapped) = self.lookup(self.index_of(color)) {
+            *color = mapped;
+        }
+    }
+}
+
+/// Reduces a page to at most `max_colors` colors (between 2 and 256), and writes it as an 8-bit palette PNG.
+///
+/// If `dither` is set, Floyd-Steinberg dithering is used to hide banding in gradients, at the cost of a
+/// larger file. If `exif` or `icc_profile` are given, they are embedded into the PNG.
+pub fn write_palette_png(
+    page: &RgbImage,
+    max_colors: u16,
+    dither: bool,
+    exif: Option<&[u8]>,
+    icc_profile: Option<&[u8]>,
+    writer: impl Write,
+) -> Result<(), ImageError> {
+    let rgba: Vec<u8> = page
+        .pixels()
+        .flat_map(|&Rgb([r, g, b])| [r, g, b, 255])
+        .collect();
+    let palette = Palette(NeuQuant::new(
+        SAMPLE_FACTOR,
+        max_colors.clamp(2, 256) as usize,
+        &rgba,
+    ));
+    let indices = if dither {
+        let mut dithered = page.clone();
+        imageops::dither(&mut dithered, &palette);
+        imageops::index_colors(&dithered, &palette)
+    } else {
+        imageops::index_colors(page, &palette)
+    };
+
+    let encoding_error = |e: png::EncodingError| {
+        ImageError::Encoding(EncodingError::new(
+            ImageFormatHint::Exact(ImageFormat::Png),
+            e,
+        ))
+    };
+    let mut info = png::Info::with_size(page.width(), page.height());
+    info.exif_metadata = exif.map(Cow::Borrowed);
+    info.icc_profile = icc_profile.map(Cow::Borrowed);
+    let mut encoder = png::Encoder::with_info(writer, info).map_err(encoding_error)?;
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(palette.0.color_map_rgb());
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(indices.as_raw()))
+        .map_err(encoding_error)
+}
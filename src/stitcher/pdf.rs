@@ -0,0 +1,135 @@
+//! This module lays out exported pages in a PDF document.
+
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, TextStr};
+
+/// The resolution images are placed at by default, in dots per inch.
+pub(crate) const DEFAULT_DPI: f32 = 96.0;
+
+/// The size of each page of an exported PDF.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PdfPageSize {
+    /// Each PDF page is exactly the size of the image on it, at the configured DPI.
+    #[default]
+    Fit,
+    /// ISO A4 (210 × 297 mm).
+    A4,
+    /// US Letter (8.5 × 11 in).
+    Letter,
+    /// A custom size, in points (1/72 in).
+    Custom { width: f32, height: f32 },
+}
+
+impl PdfPageSize {
+    /// The size of a page holding an image of the given size (in points), in points.
+    fn dimensions(self, image: (f32, f32)) -> (f32, f32) {
+        match self {
+            PdfPageSize::Fit => image,
+            PdfPageSize::A4 => (595.28, 841.89),
+            PdfPageSize::Letter => (612.0, 792.0),
+            PdfPageSize::Custom { width, height } => (width, height),
+        }
+    }
+}
+
+/// A page to be placed in the PDF.
+pub(crate) struct PdfPage {
+    /// The page, encoded as a JPEG.
+    pub(crate) jpeg: Vec<u8>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    /// The chapter the page starts, if any, which is added to the outline.
+    pub(crate) bookmark: Option<usize>,
+}
+
+/// Writes the given pages into a PDF document, one image per page.
+///
+/// Images are sized at the given DPI, and scaled down to fit pages of a fixed size if needed, centred.
+pub(crate) fn write_pdf(pages: &[PdfPage], page_size: PdfPageSize, dpi: f32) -> Vec<u8> {
+    let dpi = if dpi > 0.0 { dpi } else { DEFAULT_DPI };
+    let mut pdf = Pdf::new();
+    let mut next_id = Ref::new(1);
+    let mut alloc = || next_id.bump();
+    let catalog_id = alloc();
+    let page_tree_id = alloc();
+    let outline_id = alloc();
+    let image_name = Name(b"Im1");
+
+    let page_ids: Vec<Ref> = pages.iter().map(|_| alloc()).collect();
+    pdf.pages(page_tree_id)
+        .kids(page_ids.iter().copied())
+        .count(pages.len() as i32);
+
+    for (page, &page_id) in pages.iter().zip(&page_ids) {
+        let image_id = alloc();
+        let content_id = alloc();
+        let image = (
+            page.width as f32 * 72.0 / dpi,
+            page.height as f32 * 72.0 / dpi,
+        );
+        let (page_width, page_height) = page_size.dimensions(image);
+        // scale the image down (never up) to fit the page
+        let scale = (page_width / image.0).min(page_height / image.1).min(1.0);
+        let (width, height) = (image.0 * scale, image.1 * scale);
+
+        let mut pdf_page = pdf.page(page_id);
+        pdf_page.media_box(Rect::new(0.0, 0.0, page_width, page_height));
+        pdf_page.parent(page_tree_id);
+        pdf_page.contents(content_id);
+        pdf_page.resources().x_objects().pair(image_name, image_id);
+        pdf_page.finish();
+
+        let mut xobject = pdf.image_xobject(image_id, &page.jpeg);
+        xobject.filter(Filter::DctDecode);
+        xobject.width(page.width as i32);
+        xobject.height(page.height as i32);
+        xobject.color_space().device_rgb();
+        xobject.bits_per_component(8);
+        xobject.finish();
+
+        let mut content = Content::new();
+        content.save_state();
+        content.transform([
+            width,
+            0.0,
+            0.0,
+            height,
+            (page_width - width) / 2.0,
+            (page_height - height) / 2.0,
+        ]);
+        content.x_object(image_name);
+        content.restore_state();
+        pdf.stream(content_id, &content.finish());
+    }
+
+    // one outline item per chapter, linked to the page it starts on
+    let bookmarks: Vec<(Ref, usize, Ref)> = pages
+        .iter()
+        .zip(&page_ids)
+        .filter_map(|(page, &page_id)| Some((alloc(), page.bookmark?, page_id)))
+        .collect();
+    let mut catalog = pdf.catalog(catalog_id);
+    catalog.pages(page_tree_id);
+    if !bookmarks.is_empty() {
+        catalog.outlines(outline_id);
+    }
+    catalog.finish();
+    if let (Some(first), Some(last)) = (bookmarks.first(), bookmarks.last()) {
+        pdf.outline(outline_id)
+            .first(first.0)
+            .last(last.0)
+            .count(bookmarks.len() as i32);
+        for (index, &(item_id, chapter, page_id)) in bookmarks.iter().enumerate() {
+            let title = format!("Chapter {}", chapter + 1);
+            let mut item = pdf.outline_item(item_id);
+            item.title(TextStr(&title)).parent(outline_id);
+            if let Some(prev) = index.checked_sub(1) {
+                item.prev(bookmarks[prev].0);
+            }
+            if let Some(next) = bookmarks.get(index + 1) {
+                item.next(next.0);
+            }
+            item.dest().page(page_id).fit();
+        }
+    }
+    pdf.finish()
+}
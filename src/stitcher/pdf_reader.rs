@@ -0,0 +1,1000 @@
+//! This module is for loading the images embedded in a PDF, for raws which are distributed as PDFs of page
+//! images.
+//!
+//! Pages aren't rendered: only the images drawn on them are extracted, which is all a PDF of page images
+//! holds. Text and vector art are left out, so pages which only hold text or vector art are skipped
+//! altogether, as are images drawn inline in a page's contents rather than stored as objects.
+//!
+//! Only as much of PDF is understood as is needed to find the images, and damaged files are read the way
+//! most viewers repair them, by scanning for objects rather than trusting the cross-reference table (or
+//! cross-reference streams, which are never read). Objects in object streams are found as well. Truncated
+//! files are reported as invalid rather than loaded without the pages which were cut off.
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fs,
+    io::{Cursor, Read},
+    path::Path,
+};
+
+use flate2::read::ZlibDecoder;
+use image::{DynamicImage, ImageFormat, RgbImage};
+
+use super::{
+    image_loader::{load_streamed, ImageLoaderError, LoadConfig},
+    stats::Stats,
+    strip::Strip,
+};
+
+/// How many bytes a single image or object stream may take up at most once decompressed, so that a
+/// malicious PDF can't exhaust memory.
+const MAX_DECODED_SIZE: u64 = 256 << 20;
+
+/// How deeply objects may be nested, so that a malicious PDF can't overflow the stack.
+const MAX_DEPTH: usize = 64;
+
+/// Loads the images embedded in a PDF into a single image strip.
+///
+/// Images are taken in page order, and in the order they are listed within each page. Pages without images
+/// (such as a text-only credits page, or a page drawn with vector art) are left out.
+///
+/// Throws an error if:
+///  - The file cannot be read, isn't a PDF, or is truncated.
+///  - The PDF does not contain any images.
+///  - An image is stored in a way which isn't supported, or cannot be decoded.
+pub fn load_pdf(
+    path: impl AsRef<Path>,
+    config: &LoadConfig,
+    stats: &mut Stats,
+) -> Result<Strip, ImageLoaderError> {
+    let bytes = fs::read(path)?;
+    let document = Document::parse(&bytes)?;
+    let mut error = None;
+    let entries = document
+        .page_images()?
+        .into_iter()
+        .map_while(|(page, number)| match document.image(number, page) {
+            Ok(image) => Some(Ok(image)),
+            Err(e) => {
+                error = Some(e);
+                None
+            }
+        });
+    let strip = load_streamed(entries, config, stats);
+    match error {
+        Some(e) => Err(e),
+        None => strip,
+    }
+}
+
+/// Extracts the images embedded in a PDF, in the same order as [`load_pdf`] loads them, as encoded images
+/// which can be loaded from memory (e.g. with [`crate::pipeline::Memory`]).
+///
+/// Throws an error if the PDF is invalid or truncated, doesn't contain any images, or an image is stored in
+/// a way which isn't supported.
+pub fn pdf_images(pdf: &[u8]) -> Result<Vec<Vec<u8>>, ImageLoaderError> {
+    let document = Document::parse(pdf)?;
+    document
+        .page_images()?
+        .into_iter()
+        .map(|(page, number)| document.image(number, page))
+        .collect()
+}
+
+/// A PDF object, as far as it is needed to find the images of each page. Streams are kept apart from
+/// their dictionary, in [`Indirect`].
+#[derive(Debug, Clone, PartialEq)]
+enum Object {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Name(Vec<u8>),
+    String(Vec<u8>),
+    Array(Vec<Object>),
+    Dict(Dict),
+    /// A reference to the indirect object with the given number.
+    Ref(u32),
+}
+
+type Dict = Vec<(Vec<u8>, Object)>;
+
+impl Object {
+    fn as_dict(&self) -> Option<&Dict> {
+        match self {
+            Object::Dict(dict) => Some(dict),
+            _ => None,
+        }
+    }
+    fn as_name(&self) -> Option<&[u8]> {
+        match self {
+            Object::Name(name) => Some(name),
+            _ => None,
+        }
+    }
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Object::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+}
+
+/// Looks up a key of a dictionary.
+fn get<'d>(dict: &'d Dict, key: &[u8]) -> Option<&'d Object> {
+    dict.iter()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value)
+}
+
+/// Whether a dictionary has the given `/Type` (or `/Subtype`, if `key` says so).
+fn has_type(dict: &Dict, key: &[u8], name: &[u8]) -> bool {
+    get(dict, key).and_then(Object::as_name) == Some(name)
+}
+
+fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, 0 | b'\t' | b'\n' | 0x0c | b'\r' | b' ')
+}
+
+fn is_delimiter(byte: u8) -> bool {
+    matches!(
+        byte,
+        b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+    )
+}
+
+/// Finds the first occurrence of `needle` in `haystack`.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Reads objects out of the bytes of a PDF.
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(bytes: &'a [u8], pos: usize) -> Self {
+        Self { bytes, pos }
+    }
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+    fn starts_with(&self, prefix: &[u8]) -> bool {
+        self.bytes
+            .get(self.pos..)
+            .is_some_and(|rest| rest.starts_with(prefix))
+    }
+    fn skip_whitespace(&mut self) {
+        while let Some(byte) = self.peek() {
+            if is_whitespace(byte) {
+                self.pos += 1;
+            } else if byte == b'%' {
+                // comments run to the end of the line
+                while self
+                    .peek()
+                    .is_some_and(|byte| byte != b'\n' && byte != b'\r')
+                {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+    /// Reads a run of regular characters, such as a number or a keyword.
+    fn token(&mut self) -> &'a [u8] {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|byte| !is_whitespace(byte) && !is_delimiter(byte))
+        {
+            self.pos += 1;
+        }
+        &self.bytes[start..self.pos]
+    }
+    /// Reads an unsigned integer token, moving past it only if there is one.
+    fn integer(&mut self) -> Option<u32> {
+        let start = self.pos;
+        let token = self.token();
+        let integer = std::str::from_utf8(token).ok()?.parse().ok();
+        if integer.is_none() {
+            self.pos = start;
+        }
+        integer
+    }
+    fn object(&mut self, depth: usize) -> Option<Object> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        self.skip_whitespace();
+        match self.peek()? {
+            b'<' if self.starts_with(b"<<") => {
+                self.pos += 2;
+                let mut dict = Dict::new();
+                loop {
+                    self.skip_whitespace();
+                    if self.starts_with(b">>") {
+                        self.pos += 2;
+                        return Some(Object::Dict(dict));
+                    }
+                    let Object::Name(key) = self.object(depth + 1)? else {
+                        return None;
+                    };
+                    let value = self.object(depth + 1)?;
+                    dict.push((key, value));
+                }
+            }
+            b'<' => {
+                self.pos += 1;
+                let end = self.pos + find(&self.bytes[self.pos..], b">")?;
+                let digits: Vec<u8> = self.bytes[self.pos..end]
+                    .iter()
+                    .filter(|byte| !is_whitespace(**byte))
+                    .copied()
+                    .collect();
+                self.pos = end + 1;
+                // an odd final digit is followed by an implied 0
+                let string = digits
+                    .chunks(2)
+                    .map(|pair| {
+                        let high = (pair[0] as char).to_digit(16)?;
+                        let low = pair
+                            .get(1)
+                            .map_or(Some(0), |&low| (low as char).to_digit(16))?;
+                        Some((high * 16 + low) as u8)
+                    })
+                    .collect::<Option<_>>()?;
+                Some(Object::String(string))
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut array = Vec::new();
+                loop {
+                    self.skip_whitespace();
+                    if self.peek()? == b']' {
+                        self.pos += 1;
+                        return Some(Object::Array(array));
+                    }
+                    array.push(self.object(depth + 1)?);
+                }
+            }
+            b'(' => self.literal_string().map(Object::String),
+            b'/' => {
+                self.pos += 1;
+                let token = self.token();
+                let mut name = Vec::with_capacity(token.len());
+                let mut bytes = token.iter();
+                while let Some(&byte) = bytes.next() {
+                    // #xx escapes a byte by its hex code
+                    let escaped = (byte == b'#')
+                        .then(|| {
+                            let code = bytes.as_slice().get(..2)?;
+                            u8::from_str_radix(std::str::from_utf8(code).ok()?, 16).ok()
+                        })
+                        .flatten();
+                    match escaped {
+                        Some(escaped) => {
+                            name.push(escaped);
+                            bytes.nth(1);
+                        }
+                        None => name.push(byte),
+                    }
+                }
+                Some(Object::Name(name))
+            }
+            _ => {
+                let start = self.pos;
+                if let Some(number) = self.integer() {
+                    // an integer followed by another and `R` is a reference
+                    let after = self.pos;
+                    self.skip_whitespace();
+                    if self.integer().is_some() {
+                        self.skip_whitespace();
+                        if self.token() == b"R" {
+                            return Some(Object::Ref(number));
+                        }
+                    }
+                    self.pos = after;
+                    return Some(Object::Number(number as f64));
+                }
+                self.pos = start;
+                match self.token() {
+                    b"true" => Some(Object::Bool(true)),
+                    b"false" => Some(Object::Bool(false)),
+                    b"null" => Some(Object::Null),
+                    token => std::str::from_utf8(token)
+                        .ok()?
+                        .parse()
+                        .ok()
+                        .map(Object::Number),
+                }
+            }
+        }
+    }
+    /// Reads a string in parentheses, which may hold balanced parentheses and escapes.
+    fn literal_string(&mut self) -> Option<Vec<u8>> {
+        self.pos += 1;
+        let mut string = Vec::new();
+        let mut depth = 1;
+        loop {
+            let byte = self.peek()?;
+            self.pos += 1;
+            match byte {
+                b'(' => depth += 1,
+                b')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(string);
+                    }
+                }
+                b'\\' => {
+                    let escaped = self.peek()?;
+                    self.pos += 1;
+                    match escaped {
+                        b'n' => string.push(b'\n'),
+                        b'r' => string.push(b'\r'),
+                        b't' => string.push(b'\t'),
+                        b'b' => string.push(0x08),
+                        b'f' => string.push(0x0c),
+                        b'0'..=b'7' => {
+                            // up to three octal digits
+                            let mut code = (escaped - b'0') as u32;
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(digit @ b'0'..=b'7') => {
+                                        code = code * 8 + (digit - b'0') as u32;
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            string.push(code as u8);
+                        }
+                        // a backslash at the end of a line continues the string on the next one
+                        b'\r' => {
+                            if self.peek() == Some(b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => {}
+                        other => string.push(other),
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            string.push(byte);
+        }
+    }
+}
+
+/// An indirect object, along with its raw (still encoded) stream if it is one.
+struct Indirect<'a> {
+    object: Object,
+    stream: Option<&'a [u8]>,
+}
+
+/// The objects of a PDF, by number.
+struct Document<'a> {
+    objects: HashMap<u32, Indirect<'a>>,
+    root: u32,
+}
+
+impl<'a> Document<'a> {
+    /// Finds every object of the PDF, and its catalog.
+    fn parse(bytes: &'a [u8]) -> Result<Self, ImageLoaderError> {
+        // the header may be preceded by junk, which viewers tolerate within the first kilobyte
+        if find(&bytes[..bytes.len().min(1024)], b"%PDF-").is_none() {
+            return Err(ImageLoaderError::InvalidPdf);
+        }
+        let mut document = Self {
+            objects: scan_objects(bytes),
+            root: 0,
+        };
+        document.expand_object_streams()?;
+        document.root = document
+            .trailer_root(bytes)
+            .or_else(|| {
+                document
+                    .objects
+                    .iter()
+                    .filter(|(_, indirect)| {
+                        indirect
+                            .object
+                            .as_dict()
+                            .is_some_and(|dict| has_type(dict, b"Type", b"Catalog"))
+                    })
+                    .map(|(&number, _)| number)
+                    .max()
+            })
+            .ok_or(ImageLoaderError::InvalidPdf)?;
+        Ok(document)
+    }
+    /// The catalog named by the last trailer of the PDF, if it has one which names an existing catalog.
+    fn trailer_root(&self, bytes: &[u8]) -> Option<u32> {
+        // incremental updates append a new trailer after the old ones
+        let at = bytes
+            .windows(b"trailer".len())
+            .rposition(|window| window == b"trailer")?;
+        let trailer = Parser::new(bytes, at + b"trailer".len()).object(0)?;
+        let Some(Object::Ref(root)) = get(trailer.as_dict()?, b"Root") else {
+            return None;
+        };
+        self.objects.contains_key(root).then_some(*root)
+    }
+    /// Adds the objects held in object streams, unless an object with the same number was found outside of
+    /// them.
+    ///
+    /// Throws an error if the object streams together decompress to more than [`MAX_DECODED_SIZE`] bytes, so
+    /// that a PDF packed with them can't exhaust memory or keep the loader busy.
+    fn expand_object_streams(&mut self) -> Result<(), ImageLoaderError> {
+        let mut budget = MAX_DECODED_SIZE;
+        let mut compressed = Vec::new();
+        for indirect in self.objects.values() {
+            let Some(dict) = indirect.object.as_dict() else {
+                continue;
+            };
+            let Some(data) = indirect
+                .stream
+                .filter(|_| has_type(dict, b"Type", b"ObjStm"))
+            else {
+                continue;
+            };
+            let Ok(stream) = self.decode(dict, data, &self.filters(dict)) else {
+                continue;
+            };
+            budget = budget
+                .checked_sub(stream.len() as u64)
+                .ok_or(ImageLoaderError::InvalidPdf)?;
+            let count = self.number(dict, b"N") as usize;
+            let first = self.number(dict, b"First") as usize;
+            // the stream starts with pairs of object numbers and offsets from `first`
+            let mut header = Parser::new(&stream, 0);
+            for _ in 0..count {
+                header.skip_whitespace();
+                let Some(number) = header.integer() else {
+                    break;
+                };
+                header.skip_whitespace();
+                let Some(offset) = header.integer() else {
+                    break;
+                };
+                let at = first.saturating_add(offset as usize);
+                if let Some(object) = Parser::new(&stream, at).object(0) {
+                    compressed.push((number, object));
+                }
+            }
+        }
+        for (number, object) in compressed {
+            self.objects.entry(number).or_insert(Indirect {
+                object,
+                stream: None,
+            });
+        }
+        Ok(())
+    }
+    /// Follows references until an object which isn't one.
+    fn resolve<'o>(&'o self, mut object: &'o Object) -> &'o Object {
+        for _ in 0..MAX_DEPTH {
+            let Object::Ref(number) = object else {
+                return object;
+            };
+            match self.objects.get(number) {
+                Some(indirect) => object = &indirect.object,
+                None => return &Object::Null,
+            }
+        }
+        &Object::Null
+    }
+    fn dict<'o>(&'o self, object: &'o Object) -> Option<&'o Dict> {
+        self.resolve(object).as_dict()
+    }
+    /// Follows references like [`Self::resolve`], except that a reference to an object which isn't in the
+    /// file is an error rather than null. Such an object was most likely cut off the end of the file, and
+    /// leaving out what it held would silently drop pages.
+    fn resolve_present<'o>(
+        &'o self,
+        mut object: &'o Object,
+    ) -> Result<&'o Object, ImageLoaderError> {
+        for _ in 0..MAX_DEPTH {
+            let Object::Ref(number) = object else {
+                return Ok(object);
+            };
+            object = &self
+                .objects
+                .get(number)
+                .ok_or(ImageLoaderError::InvalidPdf)?
+                .object;
+        }
+        Ok(&Object::Null)
+    }
+    /// The dictionary and raw data of the stream with the given number.
+    fn stream(&self, number: u32) -> Option<(&Dict, &'a [u8])> {
+        let indirect = self.objects.get(&number)?;
+        Some((indirect.object.as_dict()?, indirect.stream?))
+    }
+    /// The page (counting from 1) and object number of every image drawn on a page, in page order.
+    ///
+    /// Throws an error if part of the page tree, or an image drawn on a page, is missing from the file, or
+    /// if no page has an image.
+    fn page_images(&self) -> Result<Vec<(usize, u32)>, ImageLoaderError> {
+        let mut pages = Vec::new();
+        if let Some(catalog) = self
+            .objects
+            .get(&self.root)
+            .and_then(|root| root.object.as_dict())
+        {
+            if let Some(tree) = get(catalog, b"Pages") {
+                self.walk_pages(tree, None, &mut HashSet::new(), &mut pages, 0)?;
+            }
+        }
+        let images: Vec<_> = pages
+            .into_iter()
+            .enumerate()
+            .flat_map(|(index, images)| images.into_iter().map(move |image| (index + 1, image)))
+            .collect();
+        if images.is_empty() {
+            return Err(ImageLoaderError::NoImagesInPdf);
+        }
+        Ok(images)
+    }
+    /// Lists the images of each page under a node of the page tree. Resources are inherited from the nodes
+    /// above a page.
+    fn walk_pages(
+        &self,
+        node: &Object,
+        inherited: Option<&Object>,
+        visited: &mut HashSet<u32>,
+        pages: &mut Vec<Vec<u32>>,
+        depth: usize,
+    ) -> Result<(), ImageLoaderError> {
+        if let Object::Ref(number) = node {
+            // a malformed tree may loop back on itself
+            if !visited.insert(*number) {
+                return Ok(());
+            }
+        }
+        let Some(dict) = self.resolve_present(node)?.as_dict() else {
+            return Ok(());
+        };
+        if depth > MAX_DEPTH {
+            return Ok(());
+        }
+        let resources = get(dict, b"Resources").or(inherited);
+        match get(dict, b"Kids")
+            .map(|kids| self.resolve_present(kids))
+            .transpose()?
+        {
+            Some(Object::Array(kids)) => {
+                for kid in kids {
+                    self.walk_pages(kid, resources, visited, pages, depth + 1)?;
+                }
+            }
+            _ => {
+                let mut images = Vec::new();
+                self.xobject_images(resources, &mut HashSet::new(), &mut images, 0)?;
+                pages.push(images);
+            }
+        }
+        Ok(())
+    }
+    /// Lists the images among the external objects of some resources, including those drawn by forms. Each
+    /// object is only listed once per page, however many forms draw it.
+    fn xobject_images(
+        &self,
+        resources: Option<&Object>,
+        visited: &mut HashSet<u32>,
+        images: &mut Vec<u32>,
+        depth: usize,
+    ) -> Result<(), ImageLoaderError> {
+        if depth > MAX_DEPTH {
+            return Ok(());
+        }
+        let Some(resources) = resources else {
+            return Ok(());
+        };
+        let Some(xobjects) = self
+            .resolve_present(resources)?
+            .as_dict()
+            .and_then(|resources| get(resources, b"XObject"))
+        else {
+            return Ok(());
+        };
+        let Some(xobjects) = self.resolve_present(xobjects)?.as_dict() else {
+            return Ok(());
+        };
+        for (_, xobject) in xobjects {
+            let Object::Ref(number) = xobject else {
+                continue;
+            };
+            // a form listing itself (or another form twice, over and over) would otherwise be walked an
+            // exponential number of times
+            if !visited.insert(*number) {
+                continue;
+            }
+            let Some(dict) = self.resolve_present(xobject)?.as_dict() else {
+                continue;
+            };
+            // an image whose data was cut off is kept, so that extracting it reports the file as invalid
+            if has_type(dict, b"Subtype", b"Image") {
+                images.push(*number);
+            } else if has_type(dict, b"Subtype", b"Form") {
+                self.xobject_images(get(dict, b"Resources"), visited, images, depth + 1)?;
+            }
+        }
+        Ok(())
+    }
+    /// Extracts an image as an encoded image: JPEG (and JPEG 2000) images are passed on as they are, and
+    /// images stored as raw pixels are encoded as BMP, which is quick to encode and decode.
+    fn image(&self, number: u32, page: usize) -> Result<Vec<u8>, ImageLoaderError> {
+        let (dict, data) = self.stream(number).ok_or(ImageLoaderError::InvalidPdf)?;
+        let unsupported = |reason: String| ImageLoaderError::UnsupportedPdfImage { page, reason };
+        let width = self.number(dict, b"Width") as u32;
+        let height = self.number(dict, b"Height") as u32;
+        let bits = get(dict, b"BitsPerComponent")
+            .map_or(8.0, |bits| self.resolve(bits).as_number().unwrap_or(8.0))
+            as u32;
+        let filters = self.filters(dict);
+        let unsupported_filter = |filter| unsupported(format!("the {filter} filter"));
+
+        // the encoded data of JPEG images is a JPEG file
+        if let Some((last, rest)) = filters.split_last() {
+            if matches!(&last[..], b"DCTDecode" | b"DCT" | b"JPXDecode") {
+                let decoded = self.decode(dict, data, rest).map_err(unsupported_filter)?;
+                return Ok(decoded.into_owned());
+            }
+        }
+
+        let (components, palette) = self
+            .color_space(get(dict, b"ColorSpace"), 0)
+            .map_err(unsupported)?;
+        if bits != 8 {
+            return Err(unsupported(format!("{bits} bits per component")));
+        }
+        let size = width as u64 * height as u64 * components as u64;
+        if width == 0 || height == 0 || size > MAX_DECODED_SIZE {
+            return Err(unsupported(format!("dimensions of {width}x{height}")));
+        }
+        let pixels = self
+            .decode(dict, data, &filters)
+            .map_err(unsupported_filter)?;
+        if (pixels.len() as u64) < size {
+            return Err(ImageLoaderError::InvalidPdf);
+        }
+        let image = RgbImage::from_fn(width, height, |x, y| {
+            let at = (y as usize * width as usize + x as usize) * components;
+            let pixel = &pixels[at..at + components];
+            match &palette {
+                Some((base, lookup)) => {
+                    let at = pixel[0] as usize * base;
+                    to_rgb(lookup.get(at..at + base).unwrap_or(&[0, 0, 0, 0][..*base]))
+                }
+                None => to_rgb(pixel),
+            }
+        });
+        let mut encoded = Vec::new();
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut Cursor::new(&mut encoded), ImageFormat::Bmp)?;
+        Ok(encoded)
+    }
+    /// The names of the filters a stream is encoded with, in the order they must be undone.
+    fn filters(&self, dict: &Dict) -> Vec<Vec<u8>> {
+        match get(dict, b"Filter").map(|filter| self.resolve(filter)) {
+            Some(Object::Name(name)) => vec![name.clone()],
+            Some(Object::Array(names)) => names
+                .iter()
+                .filter_map(|name| self.resolve(name).as_name().map(<[u8]>::to_vec))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+    /// Undoes the given filters of a stream. Only Flate compression (with or without PNG predictors) is
+    /// supported; the name of the first other filter is returned as the error.
+    fn decode<'d>(
+        &self,
+        dict: &Dict,
+        data: &'d [u8],
+        names: &[Vec<u8>],
+    ) -> Result<Cow<'d, [u8]>, String> {
+        let mut data = Cow::Borrowed(data);
+        for (index, name) in names.iter().enumerate() {
+            let failed = || String::from_utf8_lossy(name).into_owned();
+            if !matches!(&name[..], b"FlateDecode" | b"Fl") {
+                return Err(failed());
+            }
+            let mut inflated = Vec::new();
+            // one byte past the limit is read, to tell whether the data goes over it
+            ZlibDecoder::new(&data[..])
+                .take(MAX_DECODED_SIZE + 1)
+                .read_to_end(&mut inflated)
+                .map_err(|_| failed())?;
+            if inflated.len() as u64 > MAX_DECODED_SIZE {
+                return Err(failed());
+            }
+            // the parameters of each filter are either a single dictionary, or an array with one per filter
+            let params = get(dict, b"DecodeParms")
+                .or_else(|| get(dict, b"DP"))
+                .map(|params| self.resolve(params));
+            let params = match params {
+                Some(Object::Dict(params)) => Some(params),
+                Some(Object::Array(params)) => {
+                    params.get(index).and_then(|params| self.dict(params))
+                }
+                _ => None,
+            };
+            data = Cow::Owned(match params {
+                Some(params) => unpredict(inflated, params)?,
+                None => inflated,
+            });
+        }
+        Ok(data)
+    }
+    /// A number within a dictionary, following references (defaults to 0).
+    fn number(&self, dict: &Dict, key: &[u8]) -> f64 {
+        get(dict, key)
+            .and_then(|value| self.resolve(value).as_number())
+            .unwrap_or(0.0)
+    }
+    /// How many components each pixel has in a colour space, and the palette of an indexed colour space
+    /// (along with how many components each of its colours has). Colour spaces which can't be read are
+    /// described in the error.
+    fn color_space(
+        &self,
+        space: Option<&Object>,
+        depth: usize,
+    ) -> Result<(usize, Option<Palette>), String> {
+        let unsupported = || {
+            let name = match space.map(|space| self.resolve(space)) {
+                Some(Object::Name(name)) => Some(&name[..]),
+                Some(Object::Array(array)) => array.first().and_then(Object::as_name),
+                _ => None,
+            };
+            match name {
+                Some(name) => format!("the {} colour space", String::from_utf8_lossy(name)),
+                None => "its colour space".to_owned(),
+            }
+        };
+        let Some(space) = space.map(|space| self.resolve(space)) else {
+            // only image masks have no colour space, and they aren't page images
+            return Ok((1, None));
+        };
+        if depth > 4 {
+            return Err(unsupported());
+        }
+        let components = match space {
+            Object::Name(name) => match &name[..] {
+                b"DeviceGray" | b"G" | b"CalGray" => 1,
+                b"DeviceRGB" | b"RGB" | b"CalRGB" => 3,
+                b"DeviceCMYK" | b"CMYK" => 4,
+                _ => return Err(unsupported()),
+            },
+            Object::Array(array) => match array.first().and_then(Object::as_name) {
+                Some(b"ICCBased") => {
+                    let Some(Object::Ref(profile)) = array.get(1) else {
+                        return Err(unsupported());
+                    };
+                    let (dict, _) = self.stream(*profile).ok_or_else(unsupported)?;
+                    self.number(dict, b"N") as usize
+                }
+                Some(b"CalGray") => 1,
+                Some(b"CalRGB") | Some(b"Lab") => 3,
+                Some(b"Indexed" | b"I") => {
+                    let (base, _) = self.color_space(array.get(1), depth + 1)?;
+                    let lookup = match array.get(3) {
+                        Some(Object::Ref(number)) => match self.stream(*number) {
+                            Some((dict, data)) => self
+                                .decode(dict, data, &self.filters(dict))
+                                .map_err(|_| unsupported())?
+                                .into_owned(),
+                            None => match self.resolve(&Object::Ref(*number)) {
+                                Object::String(lookup) => lookup.clone(),
+                                _ => return Err(unsupported()),
+                            },
+                        },
+                        Some(Object::String(lookup)) => lookup.clone(),
+                        _ => return Err(unsupported()),
+                    };
+                    return Ok((1, Some((base, lookup))));
+                }
+                _ => return Err(unsupported()),
+            },
+            _ => return Err(unsupported()),
+        };
+        match components {
+            1 | 3 | 4 => Ok((components, None)),
+            _ => Err(unsupported()),
+        }
+    }
+}
+
+/// The palette of an indexed colour space: how many components each of its colours has, and the colours.
+type Palette = (usize, Vec<u8>);
+
+/// Converts a gray, RGB or CMYK pixel to RGB.
+fn to_rgb(pixel: &[u8]) -> image::Rgb<u8> {
+    match *pixel {
+        [gray] => image::Rgb([gray; 3]),
+        [r, g, b] => image::Rgb([r, g, b]),
+        [c, m, y, k] => {
+            let ink = |channel: u8| ((255 - channel as u32) * (255 - k as u32) / 255) as u8;
+            image::Rgb([ink(c), ink(m), ink(y)])
+        }
+        _ => image::Rgb([0; 3]),
+    }
+}
+
+/// Finds every indirect object of a PDF by scanning it, rather than trusting its cross-reference table,
+/// which damaged files often get wrong. When an object appears several times, such as after an
+/// incremental update, the last one wins.
+fn scan_objects(bytes: &[u8]) -> HashMap<u32, Indirect<'_>> {
+    let mut objects = HashMap::new();
+    let mut pos = 0;
+    while let Some(found) = find(&bytes[pos..], b"obj") {
+        let at = pos + found;
+        pos = at + 3;
+        let Some(number) = object_number(bytes, at) else {
+            continue;
+        };
+        let mut parser = Parser::new(bytes, pos);
+        let Some(object) = parser.object(0) else {
+            continue;
+        };
+        parser.skip_whitespace();
+        let stream = if parser.starts_with(b"stream") {
+            let (data, end) = stream_data(bytes, parser.pos + b"stream".len(), &object);
+            parser.pos = end;
+            Some(data)
+        } else {
+            None
+        };
+        pos = parser.pos;
+        objects.insert(number, Indirect { object, stream });
+    }
+    objects
+}
+
+/// The number of the object whose `obj` keyword is at the given position, if it is preceded by an object
+/// number and a generation number.
+fn object_number(bytes: &[u8], at: usize) -> Option<u32> {
+    if bytes
+        .get(at + 3)
+        .is_some_and(|&byte| !is_whitespace(byte) && !is_delimiter(byte))
+    {
+        return None;
+    }
+    let mut end = at;
+    let mut numbers = [0; 2];
+    for number in numbers.iter_mut().rev() {
+        let digits_end = bytes[..end]
+            .iter()
+            .rposition(|&byte| !is_whitespace(byte))?
+            + 1;
+        if digits_end == end {
+            return None;
+        }
+        let digits_start = bytes[..digits_end]
+            .iter()
+            .rposition(|byte| !byte.is_ascii_digit())
+            .map_or(0, |position| position + 1);
+        *number = std::str::from_utf8(&bytes[digits_start..digits_end])
+            .ok()?
+            .parse()
+            .ok()?;
+        end = digits_start;
+    }
+    // the object number must start a token
+    if end > 0 && !is_whitespace(bytes[end - 1]) && !is_delimiter(bytes[end - 1]) {
+        return None;
+    }
+    Some(numbers[0])
+}
+
+/// The data of a stream starting right after its `stream` keyword, and the position after its
+/// `endstream` keyword.
+fn stream_data<'a>(bytes: &'a [u8], mut start: usize, object: &Object) -> (&'a [u8], usize) {
+    // the keyword is followed by a line break
+    if bytes[start..].starts_with(b"\r\n") {
+        start += 2;
+    } else if bytes[start..].starts_with(b"\n") || bytes[start..].starts_with(b"\r") {
+        start += 1;
+    }
+    // the length may be wrong, or a reference, so it is only trusted if `endstream` follows it
+    let length = object
+        .as_dict()
+        .and_then(|dict| get(dict, b"Length"))
+        .and_then(Object::as_number)
+        .map(|length| length as usize);
+    if let Some(end) = length.and_then(|length| start.checked_add(length)) {
+        if end <= bytes.len() {
+            let mut parser = Parser::new(bytes, end);
+            parser.skip_whitespace();
+            if parser.starts_with(b"endstream") {
+                return (&bytes[start..end], parser.pos + b"endstream".len());
+            }
+        }
+    }
+    match find(&bytes[start..], b"endstream") {
+        Some(found) => {
+            let mut end = start + found;
+            // the line break before the keyword isn't part of the data
+            if bytes[..end].ends_with(b"\r\n") {
+                end -= 2;
+            } else if bytes[..end].ends_with(b"\n") || bytes[..end].ends_with(b"\r") {
+                end -= 1;
+            }
+            (
+                &bytes[start..end.max(start)],
+                start + found + b"endstream".len(),
+            )
+        }
+        None => (&bytes[start..], bytes.len()),
+    }
+}
+
+/// Undoes the PNG predictors data was encoded with before being compressed.
+fn unpredict(data: Vec<u8>, params: &Dict) -> Result<Vec<u8>, String> {
+    let param = |key: &[u8], default: f64| {
+        get(params, key)
+            .and_then(Object::as_number)
+            .unwrap_or(default) as usize
+    };
+    let predictor = param(b"Predictor", 1.0);
+    if predictor == 1 {
+        return Ok(data);
+    }
+    if predictor < 10 {
+        return Err(format!("predictor {predictor}"));
+    }
+    let bits = param(b"Colors", 1.0).saturating_mul(param(b"BitsPerComponent", 8.0));
+    let stride = bits.saturating_mul(param(b"Columns", 1.0)).div_ceil(8);
+    // each row is held in full, so a row can't be longer than the data it comes from
+    if stride >= data.len() {
+        return Err(format!("rows of {stride} bytes"));
+    }
+    let offset = bits.div_ceil(8).max(1);
+    let mut output = Vec::with_capacity(data.len());
+    let mut previous = vec![0; stride];
+    for row in data.chunks(stride + 1) {
+        let (&filter, row) = row.split_first().ok_or("an empty row")?;
+        let mut current = row.to_vec();
+        current.resize(stride, 0);
+        for x in 0..stride {
+            let left = if x >= offset { current[x - offset] } else { 0 };
+            let up = previous[x];
+            let up_left = if x >= offset { previous[x - offset] } else { 0 };
+            let prediction = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return Err(format!("PNG filter {filter}")),
+            };
+            current[x] = current[x].wrapping_add(prediction);
+        }
+        output.extend(&current);
+        previous = current;
+    }
+    Ok(output)
+}
+
+/// The Paeth predictor of PNG.
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let (to_left, to_up, to_up_left) = (
+        (estimate - left as i16).abs(),
+        (estimate - up as i16).abs(),
+        (estimate - up_left as i16).abs(),
+    );
+    if to_left <= to_up && to_left <= to_up_left {
+        left
+    } else if to_up <= to_up_left {
+        up
+    } else {
+        up_left
+    }
+}
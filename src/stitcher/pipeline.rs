@@ -0,0 +1,762 @@
+//! This module contains the pipeline API, which runs a whole job as a chain of stages:
+//!
+//! Source → Filters → Composer → Detector → Paginators → Encoder → Sink
+//!
+//! Each stage is a trait with built-in implementations matching what [`crate::Stitcher`] does, so that
+//! advanced users can swap in their own stage (such as a filter which cleans up scans, or a sink which
+//! uploads pages) while reusing everything else. The encoder stage is the output format along with the
+//! [`ExportConfig`], which is extended with custom encoders through its [`crate::EncoderRegistry`].
+//!
+//! ```no_run
+//! use quickstitch::{pipeline, ImageOutputFormat, StitchConfig};
+//!
+//! let metrics = pipeline::builder()
+//!     .source(pipeline::Directory("chapter".into()))
+//!     .filter(|mut page: image::RgbImage| {
+//!         image::imageops::invert(&mut page);
+//!         page
+//!     })
+//!     .detector(StitchConfig::new().target_height(5000))
+//!     .format(ImageOutputFormat::Jpeg(90))
+//!     .sink(pipeline::Folder("pages".into()))
+//!     .build()?
+//!     .run()?;
+//! # Ok::<(), pipeline::PipelineError>(())
+//! ```
+
+use std::{
+    fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::Write,
+    path::PathBuf,
+    time::Instant,
+};
+
+use image::{DynamicImage, RgbImage};
+use itertools::Itertools;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use thiserror::Error;
+
+use super::{
+    image_loader::{
+        combine, decode_images, find_loadable_images, fit, ImageLoaderError, LoadConfig,
+        Source as ImageSource,
+    },
+    image_splitter::{
+        merge_pages, split_image, split_image_to_stream, split_image_to_writers, split_to_size,
+        try_find_splitpoints, ExportConfig, ExportReport, ImageOutputFormat, ImageSplitterError,
+        StitchConfig,
+    },
+    metrics::RunMetrics,
+    output_sink::OutputSink,
+    stage_cache::StageCache,
+    stats::Stats,
+    strip::Strip,
+    throttle::with_threads,
+};
+
+#[cfg(feature = "glob")]
+use super::glob::glob_images;
+
+/// Errors thrown while running a pipeline.
+#[derive(Error, Debug)]
+pub enum PipelineError {
+    #[error("The pipeline has no {0} stage")]
+    MissingStage(&'static str),
+    #[error("{0}")]
+    Load(ImageLoaderError),
+    #[error("{}", .0.iter().join("; "))]
+    Export(Vec<ImageSplitterError>),
+}
+
+impl From<ImageLoaderError> for PipelineError {
+    fn from(value: ImageLoaderError) -> Self {
+        Self::Load(value)
+    }
+}
+
+impl From<ImageSplitterError> for PipelineError {
+    fn from(value: ImageSplitterError) -> Self {
+        Self::Export(vec![value])
+    }
+}
+
+impl From<Vec<ImageSplitterError>> for PipelineError {
+    fn from(value: Vec<ImageSplitterError>) -> Self {
+        Self::Export(value)
+    }
+}
+
+/// Hashes a value into a fingerprint.
+fn fingerprint(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Where the images of a pipeline come from.
+pub trait Source: Send + Sync {
+    /// Decodes the images, in reading order, rotated and resized to a common width according to the
+    /// config.
+    fn load(
+        &self,
+        config: &LoadConfig,
+        stats: &mut Stats,
+    ) -> Result<Vec<RgbImage>, ImageLoaderError>;
+    /// A value which changes whenever the images would, used to key the results kept in a [`StageCache`]
+    /// (defaults to none, so that nothing depending on this stage is cached).
+    ///
+    /// Every stage has a fingerprint. Custom stages which can be cached should return one covering all of
+    /// their options.
+    fn fingerprint(&self, _config: &LoadConfig) -> Option<u64> {
+        None
+    }
+}
+
+/// The given image files, in order.
+#[derive(Debug, Clone)]
+pub struct Files(pub Vec<PathBuf>);
+
+impl Source for Files {
+    fn load(
+        &self,
+        config: &LoadConfig,
+        stats: &mut Stats,
+    ) -> Result<Vec<RgbImage>, ImageLoaderError> {
+        let sources: Vec<_> = self.0.iter().map(|path| ImageSource::Path(path)).collect();
+        decode_images(&sources, config, stats)
+    }
+    /// Covers the path, size and modification time of each file.
+    fn fingerprint(&self, _config: &LoadConfig) -> Option<u64> {
+        let files = self
+            .0
+            .iter()
+            .map(|path| {
+                let metadata = fs::metadata(path).ok()?;
+                Some((path, metadata.len(), metadata.modified().ok()?))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(fingerprint(files))
+    }
+}
+
+/// The images found in a directory, ordered according to the `sort` option of the config.
+#[derive(Debug, Clone)]
+pub struct Directory(pub PathBuf);
+
+impl Source for Directory {
+    fn load(
+        &self,
+        config: &LoadConfig,
+        stats: &mut Stats,
+    ) -> Result<Vec<RgbImage>, ImageLoaderError> {
+        Files(find_loadable_images(&self.0, config)?).load(config, stats)
+    }
+    /// Covers the path, size and modification time of each image within the directory.
+    fn fingerprint(&self, config: &LoadConfig) -> Option<u64> {
+        Files(find_loadable_images(&self.0, config).ok()?).fingerprint(config)
+    }
+}
+
+/// The images matching a glob pattern, ordered according to the `sort` option of the config.
+#[derive(Debug, Clone)]
+#[cfg(feature = "glob")]
+pub struct Glob(pub String);
+
+#[cfg(feature = "glob")]
+impl Source for Glob {
+    fn load(
+        &self,
+        config: &LoadConfig,
+        stats: &mut Stats,
+    ) -> Result<Vec<RgbImage>, ImageLoaderError> {
+        Files(glob_images(&self.0, config)?).load(config, stats)
+    }
+    /// Covers the path, size and modification time of each image matching the pattern.
+    fn fingerprint(&self, config: &LoadConfig) -> Option<u64> {
+        Files(glob_images(&self.0, config).ok()?).fingerprint(config)
+    }
+}
+
+/// Encoded images held in memory, in order. The format of each image is guessed from its contents.
+#[derive(Debug, Clone)]
+pub struct Memory(pub Vec<Vec<u8>>);
+
+impl Source for Memory {
+    fn load(
+        &self,
+        config: &LoadConfig,
+        stats: &mut Stats,
+    ) -> Result<Vec<RgbImage>, ImageLoaderError> {
+        let sources: Vec<_> = self
+            .0
+            .iter()
+            .map(|bytes| ImageSource::Bytes(bytes))
+            .collect();
+        decode_images(&sources, config, stats)
+    }
+    fn fingerprint(&self, _config: &LoadConfig) -> Option<u64> {
+        Some(fingerprint(&self.0))
+    }
+}
+
+/// A change made to each image before the images are composed into a strip, such as cleaning up a scan.
+///
+/// Filters are applied in parallel, in the order they were added. Images whose width a filter changes are
+/// resized back to a common width afterwards, chosen by [`LoadConfig::width_strategy`].
+pub trait Filter: Send + Sync {
+    fn apply(&self, image: RgbImage) -> RgbImage;
+    /// See [`Source::fingerprint`]. Closures have no fingerprint.
+    fn fingerprint(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl<F: Fn(RgbImage) -> RgbImage + Send + Sync> Filter for F {
+    fn apply(&self, image: RgbImage) -> RgbImage {
+        self(image)
+    }
+}
+
+/// How the images are put together into a single strip.
+pub trait Composer: Send + Sync {
+    /// Composes images of the same width into a strip.
+    fn compose(
+        &self,
+        images: Vec<RgbImage>,
+        config: &LoadConfig,
+        stats: &mut Stats,
+    ) -> Result<Strip, ImageLoaderError>;
+    /// See [`Source::fingerprint`].
+    fn fingerprint(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Stacks the images on top of each other, separated by the gap of [`LoadConfig::inter_image_gap`], as
+/// loading with [`crate::Stitcher`] does.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stack;
+
+impl Composer for Stack {
+    fn compose(
+        &self,
+        images: Vec<RgbImage>,
+        config: &LoadConfig,
+        stats: &mut Stats,
+    ) -> Result<Strip, ImageLoaderError> {
+        let width = images
+            .first()
+            .ok_or(ImageLoaderError::NoImagesInDirectory)?
+            .width();
+        combine(images, width, config, stats)
+    }
+    fn fingerprint(&self) -> Option<u64> {
+        Some(0)
+    }
+}
+
+/// Where the strip is cut.
+pub trait Detector: Send + Sync {
+    /// Finds the splitpoints of the strip, which must include `0`, the height of the strip, and the start of
+    /// every chapter.
+    fn detect(&self, strip: &Strip, chapters: &[usize]) -> Result<Vec<usize>, ImageSplitterError>;
+    /// See [`Source::fingerprint`].
+    fn fingerprint(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Finds the splitpoints as stitching with [`crate::Stitcher`] does, except that strict configs fail
+/// straight away. See [`StitchConfig::strict`].
+impl Detector for StitchConfig {
+    fn detect(&self, strip: &Strip, chapters: &[usize]) -> Result<Vec<usize>, ImageSplitterError> {
+        try_find_splitpoints(strip, self, chapters)
+    }
+    fn fingerprint(&self) -> Option<u64> {
+        Some(fingerprint(format!("{self:?}")))
+    }
+}
+
+/// A change made to the pages once the strip has been cut, such as merging them or cutting them further.
+pub trait Paginator: Send + Sync {
+    /// Returns the new splitpoints of the strip, given the current ones and how the pages will be exported.
+    fn paginate(
+        &self,
+        strip: &Strip,
+        splitpoints: Vec<usize>,
+        chapters: &[usize],
+        output_filetype: &ImageOutputFormat,
+        config: &ExportConfig,
+    ) -> Result<Vec<usize>, ImageSplitterError>;
+    /// See [`Source::fingerprint`]. The output format and the options of the export config which affect
+    /// how pages encode are covered by the pipeline.
+    fn fingerprint(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Merges consecutive pages with [`merge_pages`]. See [`crate::Stitcher::merge_pages`].
+#[derive(Debug, Clone, Copy)]
+pub struct MergePages {
+    pub max_height: usize,
+    pub max_bytes: u64,
+}
+
+impl Paginator for MergePages {
+    fn paginate(
+        &self,
+        strip: &Strip,
+        splitpoints: Vec<usize>,
+        chapters: &[usize],
+        output_filetype: &ImageOutputFormat,
+        config: &ExportConfig,
+    ) -> Result<Vec<usize>, ImageSplitterError> {
+        merge_pages(
+            strip,
+            &splitpoints,
+            chapters,
+            self.max_height,
+            self.max_bytes,
+            output_filetype,
+            config,
+        )
+    }
+    fn fingerprint(&self) -> Option<u64> {
+        Some(fingerprint((self.max_height, self.max_bytes)))
+    }
+}
+
+/// Cuts pages which are too large into smaller pages with [`split_to_size`]. See
+/// [`crate::Stitcher::split_to_size`].
+#[derive(Debug, Clone)]
+pub struct SplitToSize {
+    pub max_bytes: u64,
+    pub stitch: StitchConfig,
+}
+
+impl Paginator for SplitToSize {
+    fn paginate(
+        &self,
+        strip: &Strip,
+        splitpoints: Vec<usize>,
+        _chapters: &[usize],
+        output_filetype: &ImageOutputFormat,
+        config: &ExportConfig,
+    ) -> Result<Vec<usize>, ImageSplitterError> {
+        split_to_size(
+            strip,
+            &splitpoints,
+            self.max_bytes,
+            &self.stitch,
+            output_filetype,
+            config,
+        )
+    }
+    fn fingerprint(&self) -> Option<u64> {
+        Some(fingerprint(format!("{self:?}")))
+    }
+}
+
+/// Where the pages end up.
+pub trait Sink: Send {
+    /// Encodes and writes every page, and reports the pages which were written (if any are known).
+    fn write(
+        &mut self,
+        strip: &Strip,
+        splitpoints: &[usize],
+        chapters: &[usize],
+        output_filetype: ImageOutputFormat,
+        config: &ExportConfig,
+        stats: &mut Stats,
+    ) -> Result<ExportReport, Vec<ImageSplitterError>>;
+}
+
+/// Writes the pages to files in a directory, as [`crate::Stitcher::export_with`] does.
+#[derive(Debug, Clone)]
+pub struct Folder(pub PathBuf);
+
+impl Sink for Folder {
+    fn write(
+        &mut self,
+        strip: &Strip,
+        splitpoints: &[usize],
+        chapters: &[usize],
+        output_filetype: ImageOutputFormat,
+        config: &ExportConfig,
+        stats: &mut Stats,
+    ) -> Result<ExportReport, Vec<ImageSplitterError>> {
+        split_image(
+            strip,
+            splitpoints,
+            chapters,
+            &self.0,
+            output_filetype,
+            config,
+            stats,
+        )
+    }
+}
+
+/// Writes the pages to a stream, framed as with [`crate::Stitcher::export_stream`]. As pages aren't
+/// written to files, the report doesn't list any.
+#[derive(Debug)]
+pub struct Stream<W>(pub W);
+
+impl<W: Write + Send> Sink for Stream<W> {
+    fn write(
+        &mut self,
+        strip: &Strip,
+        splitpoints: &[usize],
+        _chapters: &[usize],
+        output_filetype: ImageOutputFormat,
+        config: &ExportConfig,
+        stats: &mut Stats,
+    ) -> Result<ExportReport, Vec<ImageSplitterError>> {
+        split_image_to_stream(
+            strip,
+            splitpoints,
+            &mut self.0,
+            output_filetype,
+            config,
+            stats,
+        )?;
+        Ok(ExportReport::default())
+    }
+}
+
+/// Hands each page to an [`OutputSink`], as [`crate::Stitcher::export_to_writers`] does. The report doesn't
+/// list any pages, as none are written to files.
+#[derive(Debug)]
+pub struct Pages<O>(pub O);
+
+impl<O: OutputSink + Send> Sink for Pages<O> {
+    fn write(
+        &mut self,
+        strip: &Strip,
+        splitpoints: &[usize],
+        _chapters: &[usize],
+        output_filetype: ImageOutputFormat,
+        config: &ExportConfig,
+        stats: &mut Stats,
+    ) -> Result<ExportReport, Vec<ImageSplitterError>> {
+        split_image_to_writers(
+            strip,
+            splitpoints,
+            &mut self.0,
+            output_filetype,
+            config,
+            stats,
+        )?;
+        Ok(ExportReport::default())
+    }
+}
+
+/// A stage after which a run can stop. See [`PipelineBuilder::stop_after`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    /// Once the images have been loaded, filtered and composed into a strip.
+    Compose,
+    /// Once the strip has been cut by the detector.
+    Detect,
+    /// Once the pages have been changed by every paginator.
+    Paginate,
+    /// Once the pages have been written by the sink, i.e. after every stage.
+    #[default]
+    Write,
+}
+
+/// Creates a builder for a pipeline. A source and a sink must be set, and every other stage defaults to
+/// what [`crate::Stitcher`] does.
+pub fn builder<'a>() -> PipelineBuilder<'a> {
+    PipelineBuilder::default()
+}
+
+/// Sets up the stages of a [`Pipeline`]. See [`builder`].
+#[derive(Default)]
+pub struct PipelineBuilder<'a> {
+    source: Option<Box<dyn Source + 'a>>,
+    load_config: LoadConfig,
+    filters: Vec<Box<dyn Filter + 'a>>,
+    composer: Option<Box<dyn Composer + 'a>>,
+    detector: Option<Box<dyn Detector + 'a>>,
+    paginators: Vec<Box<dyn Paginator + 'a>>,
+    output_filetype: Option<ImageOutputFormat>,
+    export_config: ExportConfig,
+    sink: Option<Box<dyn Sink + 'a>>,
+    cache: Option<StageCache>,
+    stop_after: Stage,
+}
+
+impl<'a> PipelineBuilder<'a> {
+    /// Sets where the images come from.
+    pub fn source(mut self, source: impl Source + 'a) -> Self {
+        self.source = Some(Box::new(source));
+        self
+    }
+    /// Sets how the images are loaded (defaults to the default [`LoadConfig`]).
+    pub fn load_config(mut self, config: LoadConfig) -> Self {
+        self.load_config = config;
+        self
+    }
+    /// Adds a filter, applied to each image after the filters added before it.
+    pub fn filter(mut self, filter: impl Filter + 'a) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+    /// Sets how the images are put together (defaults to [`Stack`]).
+    pub fn composer(mut self, composer: impl Composer + 'a) -> Self {
+        self.composer = Some(Box::new(composer));
+        self
+    }
+    /// Sets where the strip is cut (defaults to the default [`StitchConfig`]).
+    pub fn detector(mut self, detector: impl Detector + 'a) -> Self {
+        self.detector = Some(Box::new(detector));
+        self
+    }
+    /// Adds a paginator, applied to the pages after the paginators added before it.
+    pub fn paginator(mut self, paginator: impl Paginator + 'a) -> Self {
+        self.paginators.push(Box::new(paginator));
+        self
+    }
+    /// Sets the format pages are encoded in (defaults to [`ImageOutputFormat::Png`]).
+    pub fn format(mut self, output_filetype: ImageOutputFormat) -> Self {
+        self.output_filetype = Some(output_filetype);
+        self
+    }
+    /// Sets how pages are encoded and written (defaults to the default [`ExportConfig`]).
+    pub fn export_config(mut self, config: ExportConfig) -> Self {
+        self.export_config = config;
+        self
+    }
+    /// Sets where the pages end up.
+    pub fn sink(mut self, sink: impl Sink + 'a) -> Self {
+        self.sink = Some(Box::new(sink));
+        self
+    }
+    /// Sets where the results of each stage are kept between runs, so that stages whose inputs haven't
+    /// changed are skipped (defaults to none). See [`StageCache`].
+    ///
+    /// The default detector also keeps its row scores in the cache.
+    pub fn cache(mut self, cache: Option<StageCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+    /// Sets the stage after which runs stop (defaults to [`Stage::Write`], which runs every stage).
+    ///
+    /// Stopping early is a dry run of the stages before it: nothing is written, but their results are
+    /// still kept in the cache, ready for the next run.
+    pub fn stop_after(mut self, stage: Stage) -> Self {
+        self.stop_after = stage;
+        self
+    }
+    /// Checks that every required stage is set.
+    ///
+    /// Throws [`PipelineError::MissingStage`] if the source or sink hasn't been set.
+    pub fn build(self) -> Result<Pipeline<'a>, PipelineError> {
+        Ok(Pipeline {
+            source: self.source.ok_or(PipelineError::MissingStage("source"))?,
+            sink: self.sink.ok_or(PipelineError::MissingStage("sink"))?,
+            load_config: self.load_config,
+            filters: self.filters,
+            composer: self.composer.unwrap_or_else(|| Box::new(Stack)),
+            detector: self.detector.unwrap_or_else(|| {
+                let score_cache = self.cache.as_ref().map(StageCache::score_cache);
+                Box::new(StitchConfig::default().score_cache(score_cache))
+            }),
+            paginators: self.paginators,
+            output_filetype: self.output_filetype.unwrap_or(ImageOutputFormat::Png),
+            export_config: self.export_config,
+            cache: self.cache,
+            stop_after: self.stop_after,
+        })
+    }
+}
+
+/// A job made up of stages, ready to run. See [`builder`].
+pub struct Pipeline<'a> {
+    source: Box<dyn Source + 'a>,
+    load_config: LoadConfig,
+    filters: Vec<Box<dyn Filter + 'a>>,
+    composer: Box<dyn Composer + 'a>,
+    detector: Box<dyn Detector + 'a>,
+    paginators: Vec<Box<dyn Paginator + 'a>>,
+    output_filetype: ImageOutputFormat,
+    export_config: ExportConfig,
+    sink: Box<dyn Sink + 'a>,
+    cache: Option<StageCache>,
+    stop_after: Stage,
+}
+
+impl Pipeline<'_> {
+    /// Runs every stage in turn (up to the stage set by [`PipelineBuilder::stop_after`]), and summarises
+    /// the run.
+    ///
+    /// Throws an error if any stage fails, as loading and exporting with [`crate::Stitcher`] would.
+    pub fn run(mut self) -> Result<RunMetrics, PipelineError> {
+        let mut stats = Stats::default();
+        let chapters = vec![0];
+        let strip = self.compose(&mut stats)?;
+        if self.stop_after == Stage::Compose {
+            return Ok(RunMetrics::new(&stats, &ExportReport::default()));
+        }
+
+        let score_start = Instant::now();
+        let icc_profile = stats.files.iter().find_map(|file| file.icc_profile.clone());
+        let export_config = self
+            .export_config
+            .for_strip(self.load_config.direction, &icc_profile);
+        let detected_key = self
+            .strip_key()
+            .zip(self.detector.fingerprint())
+            .map(fingerprint);
+        let mut splitpoints = match self.cached(detected_key, |cache, key| cache.splitpoints(key)) {
+            Some(splitpoints) => splitpoints,
+            None => {
+                let splitpoints = self.detector.detect(&strip, &chapters)?;
+                self.keep(detected_key, |cache, key| {
+                    cache.save_splitpoints(key, &splitpoints)
+                });
+                splitpoints
+            }
+        };
+        if self.stop_after > Stage::Detect && !self.paginators.is_empty() {
+            let paginated_key = self
+                .paginators
+                .iter()
+                .map(|paginator| paginator.fingerprint())
+                .collect::<Option<Vec<_>>>()
+                .zip(detected_key)
+                .map(|(paginators, detected_key)| {
+                    let mut hasher = DefaultHasher::new();
+                    (detected_key, paginators, &self.output_filetype).hash(&mut hasher);
+                    export_config.hash_encoding(&mut hasher);
+                    hasher.finish()
+                });
+            match self.cached(paginated_key, |cache, key| cache.splitpoints(key)) {
+                Some(paginated) => splitpoints = paginated,
+                None => {
+                    for paginator in &self.paginators {
+                        splitpoints = paginator.paginate(
+                            &strip,
+                            splitpoints,
+                            &chapters,
+                            &self.output_filetype,
+                            &export_config,
+                        )?;
+                    }
+                    self.keep(paginated_key, |cache, key| {
+                        cache.save_splitpoints(key, &splitpoints)
+                    });
+                }
+            }
+        }
+        stats.score += score_start.elapsed();
+        if self.stop_after < Stage::Write {
+            return Ok(RunMetrics::new(&stats, &ExportReport::default()));
+        }
+
+        let report = self.sink.write(
+            &strip,
+            &splitpoints,
+            &chapters,
+            self.output_filetype.clone(),
+            &export_config,
+            &mut stats,
+        )?;
+        Ok(RunMetrics::new(&stats, &report))
+    }
+    /// Loads, filters and composes the images into a strip, or reads the strip from the cache.
+    fn compose(&self, stats: &mut Stats) -> Result<Strip, PipelineError> {
+        let config = &self.load_config;
+        let key = self.strip_key();
+        if let Some(strip) = self.cached(key, |cache, key| cache.strip(key, config)) {
+            return Ok(strip);
+        }
+        let images = self.source.load(config, stats)?;
+
+        let load_start = Instant::now();
+        let filters = &self.filters;
+        let images = with_threads(config.threads, || apply_filters(filters, images, config));
+        stats.load += load_start.elapsed();
+        let strip = self.composer.compose(images, config, stats)?;
+        self.keep(key, |cache, key| cache.save_strip(key, &strip));
+        Ok(strip)
+    }
+    /// The key the strip is kept under, if every stage which produces it has a fingerprint.
+    fn strip_key(&self) -> Option<u64> {
+        let config = &self.load_config;
+        let filters = self
+            .filters
+            .iter()
+            .map(|filter| filter.fingerprint())
+            .collect::<Option<Vec<_>>>()?;
+        Some(fingerprint((
+            self.source.fingerprint(config)?,
+            filters,
+            self.composer.fingerprint()?,
+            // the options which change the images, rather than how they are loaded
+            (
+                config.width,
+                config.resize_filter,
+                config.upscale,
+                config.trim_margins,
+                config.skips_unloadable(),
+                config.auto_rotate,
+                config.exif_orientation,
+                config.dither,
+                config.skip_duplicates,
+            ),
+            format!(
+                "{:?}",
+                (
+                    config.direction,
+                    config.sort,
+                    config.alpha,
+                    config.animated,
+                    config.sharpen
+                )
+            ),
+            (config.inter_image_gap, config.gap_color.0),
+        )))
+    }
+    /// Reads a result from the cache, if there is one and it has a key.
+    fn cached<T>(
+        &self,
+        key: Option<u64>,
+        read: impl FnOnce(&StageCache, u64) -> Option<T>,
+    ) -> Option<T> {
+        read(self.cache.as_ref()?, key?)
+    }
+    /// Saves a result to the cache, if there is one and the result has a key.
+    fn keep(&self, key: Option<u64>, save: impl FnOnce(&StageCache, u64)) {
+        if let (Some(cache), Some(key)) = (&self.cache, key) {
+            save(cache, key);
+        }
+    }
+}
+
+/// Applies every filter to each image, and resizes them back to a common width (chosen by the width
+/// strategy of the config) if needed.
+fn apply_filters(
+    filters: &[Box<dyn Filter + '_>],
+    images: Vec<RgbImage>,
+    config: &LoadConfig,
+) -> Vec<RgbImage> {
+    if filters.is_empty() {
+        return images;
+    }
+    let images: Vec<RgbImage> = images
+        .into_par_iter()
+        .map(|image| {
+            filters
+                .iter()
+                .fold(image, |image, filter| filter.apply(image))
+        })
+        .collect();
+    let width = config.choose_width(images.iter().map(RgbImage::width));
+    images
+        .into_par_iter()
+        .map(|image| fit(DynamicImage::from(image), width, config))
+        .collect()
+}
@@ -0,0 +1,62 @@
+//! This module contains the hooks used to report progress while loading, scanning and exporting.
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// Receives progress updates, for showing progress bars in GUIs and CLIs.
+///
+/// Every method does nothing by default, so only the updates of interest need to be implemented. Updates
+/// may arrive from several threads at once, and not necessarily in order.
+pub trait Progress: Send + Sync {
+    /// Called after each image has been decoded, with the number of images loaded so far and the total
+    /// number of images, if known (it isn't when loading from a stream).
+    fn image_loaded(&self, loaded: usize, total: Option<usize>) {
+        let _ = (loaded, total);
+    }
+    /// Called each time the scan for splitpoints advances, with the row it has reached so far and the height
+    /// of the combined image.
+    fn scanned(&self, row: usize, height: usize) {
+        let _ = (row, height);
+    }
+    /// Called after each page has been written, with the number of pages written so far and the total number
+    /// of pages.
+    fn page_written(&self, written: usize, total: usize) {
+        let _ = (written, total);
+    }
+}
+
+/// A [`Progress`] held by a config.
+#[derive(Clone)]
+pub(crate) struct ProgressHook(pub(crate) Arc<dyn Progress>);
+
+impl fmt::Debug for ProgressHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressHook")
+    }
+}
+
+/// Counts the steps of a phase as they complete, reporting each one.
+pub(crate) struct Tally<'a> {
+    progress: Option<&'a dyn Progress>,
+    done: AtomicUsize,
+}
+
+impl<'a> Tally<'a> {
+    pub(crate) fn new(progress: &'a Option<ProgressHook>) -> Self {
+        Self {
+            progress: progress.as_ref().map(|hook| &*hook.0),
+            done: AtomicUsize::new(0),
+        }
+    }
+    /// Records a completed step, and reports the number of steps completed so far.
+    pub(crate) fn tick(&self, report: impl FnOnce(&dyn Progress, usize)) {
+        if let Some(progress) = self.progress {
+            report(progress, self.done.fetch_add(1, Ordering::Relaxed) + 1);
+        }
+    }
+}
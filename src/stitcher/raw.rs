@@ -0,0 +1,62 @@
+//! This module gathers the low-level functions behind [`crate::Stitcher`] in one place, for advanced users
+//! who want to drive each step themselves (e.g. to find the splitpoints of a strip they composed on their
+//! own, or to encode pages without going through the typestate API).
+//!
+//! Every step works on a [`Strip`] (or anything implementing [`StripRows`]) and takes the same config
+//! structs as the `Stitcher` does, so the two can be mixed freely:
+//!
+//! ```no_run
+//! use quickstitch::raw::{self, ExportConfig, ImageOutputFormat, LoadConfig, StitchConfig};
+//! use quickstitch::Stats;
+//!
+//! let mut stats = Stats::default();
+//! let paths = raw::find_images("chapter", quickstitch::Sort::Logical).unwrap();
+//! let strip = raw::load_images(&paths, &LoadConfig::new(), &mut stats).unwrap();
+//! let splitpoints =
+//!     raw::find_splitpoints(&strip, &StitchConfig::new(), &[]);
+//! raw::split_image(
+//!     &strip,
+//!     &splitpoints,
+//!     &[],
+//!     "pages",
+//!     ImageOutputFormat::Png,
+//!     &ExportConfig::new(),
+//!     &mut stats,
+//! )
+//! .unwrap();
+//! ```
+//!
+//! Unlike the `Stitcher`, these functions don't apply the reading direction of the [`LoadConfig`] to the
+//! export config, nor check that a review has been completed.
+
+pub use super::{
+    chapter_breaks::detect_chapter_breaks,
+    image_loader::{
+        concat_strips, find_images, find_loadable_images, is_image_path, load_images,
+        load_images_from_bytes, load_images_virtual, load_sources, load_streamed, sort_paths,
+        ImageLoaderError, LoadConfig, Source, ORDER_FILES,
+    },
+    image_splitter::{
+        calibrate_sensitivity, estimate_output_size, estimate_scan_interval, estimate_sensitivity,
+        export_region, find_splitpoints, find_splitpoints_debug, merge_pages, plan_export,
+        split_image, split_image_to_stream, split_image_to_writers, split_to_size, ExportConfig,
+        ImageOutputFormat, ImageSplitterError, StitchConfig,
+    },
+    palette::write_palette_png,
+    strip::{new_strip, preview, Strip},
+    sweep::{compare_runs, run_sweep},
+    virtual_strip::StripRows,
+};
+
+#[cfg(any(feature = "zip", feature = "7z"))]
+pub use super::archive::load_archive;
+#[cfg(feature = "glob")]
+pub use super::glob::glob_images;
+#[cfg(feature = "zip")]
+pub use super::image_splitter::split_image_to_cbz;
+#[cfg(feature = "pdf")]
+pub use super::image_splitter::split_image_to_pdf;
+#[cfg(feature = "tar")]
+pub use super::image_splitter::split_image_to_tar;
+#[cfg(feature = "pdf")]
+pub use super::pdf_reader::{load_pdf, pdf_images};
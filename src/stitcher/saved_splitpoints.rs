@@ -0,0 +1,84 @@
+//! This module saves splitpoints to JSON files and loads them back, so that a strip can be analysed once,
+//! reviewed or edited by other tools, and exported later without scanning it again.
+//!
+//! The file is a JSON object holding the dimensions of the strip the splitpoints were found in, and the
+//! splitpoints themselves:
+//!
+//! ```json
+//! { "version": 1, "width": 800, "height": 12000, "splitpoints": [0, 4870, 9655, 12000] }
+//! ```
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::image_splitter::ImageSplitterError;
+
+/// The version of the file format written by this version of quickstitch.
+const VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedSplitpoints {
+    version: u32,
+    width: u32,
+    height: u32,
+    splitpoints: Vec<usize>,
+}
+
+/// Writes the splitpoints of a strip of the given dimensions to a JSON file.
+pub(crate) fn save_splitpoints(
+    path: &Path,
+    (width, height): (u32, u32),
+    splitpoints: &[usize],
+) -> Result<(), ImageSplitterError> {
+    let saved = SavedSplitpoints {
+        version: VERSION,
+        width,
+        height,
+        splitpoints: splitpoints.to_vec(),
+    };
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, &saved)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads splitpoints from a JSON file, checking that they are valid splitpoints for a strip of the given
+/// dimensions which starts a chapter at each of `chapters`.
+pub(crate) fn load_splitpoints(
+    path: &Path,
+    (width, height): (u32, u32),
+    chapters: &[usize],
+) -> Result<Vec<usize>, ImageSplitterError> {
+    let saved: SavedSplitpoints = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+    let invalid = |reason: String| Err(ImageSplitterError::InvalidSplitpoints(reason));
+    if saved.version > VERSION {
+        return invalid(format!("version {} is not supported", saved.version));
+    }
+    if (saved.width, saved.height) != (width, height) {
+        return invalid(format!(
+            "they were found in a {}x{} strip, but this strip is {width}x{height}",
+            saved.width, saved.height
+        ));
+    }
+    let splitpoints = saved.splitpoints;
+    if splitpoints.first() != Some(&0) || splitpoints.last() != Some(&(height as usize)) {
+        return invalid(format!("they must start at 0 and end at {height}"));
+    }
+    if !splitpoints.windows(2).all(|pair| pair[0] < pair[1]) {
+        return invalid("they must be in increasing order".to_string());
+    }
+    if let Some(chapter) = chapters
+        .iter()
+        .find(|chapter| splitpoints.binary_search(chapter).is_err())
+    {
+        return invalid(format!(
+            "the chapter starting at row {chapter} isn't cut at"
+        ));
+    }
+    Ok(splitpoints)
+}
@@ -0,0 +1,68 @@
+//! This module contains the on-disk cache of row scores, used to skip scanning when stitching the same
+//! combined image again.
+
+use std::{
+    fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use tempfile::NamedTempFile;
+
+use super::{image_splitter::score_every_row, virtual_strip::StripRows};
+
+/// A directory in which the score of every row of a combined image is kept between runs.
+///
+/// Scoring rows is the expensive part of stitching. When tuning the target height or sensitivity for the
+/// same chapter over several runs, the scores don't change, so once they have been saved, stitching
+/// only looks them up. Scores are keyed by a hash of the combined image's pixels, so different chapters
+/// can share the same directory.
+///
+/// The first stitch with a cache scores every row, rather than every `scan_interval`th row around each
+/// cut, so it is slower than stitching without one.
+#[derive(Debug, Clone)]
+pub struct ScoreCache {
+    directory: PathBuf,
+}
+
+impl ScoreCache {
+    /// Uses the given directory, which is created if it doesn't exist yet.
+    pub fn new(directory: impl AsRef<Path>) -> Self {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+        }
+    }
+    /// The file the scores of the given image are kept in.
+    fn path(&self, image: &impl StripRows) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        (image.width(), image.height()).hash(&mut hasher);
+        for row in 0..image.height() {
+            hasher.write(image.row(row));
+        }
+        self.directory
+            .join(format!("{:016x}.scores", hasher.finish()))
+    }
+    /// Returns the score of every row of the image, reading them from the cache if they have been saved
+    /// before, and saving them otherwise.
+    ///
+    /// Scores which can't be saved are still returned, as the cache is only an optimisation.
+    pub(crate) fn scores(&self, image: &impl StripRows) -> Vec<u8> {
+        let path = self.path(image);
+        // a file of the wrong length was cut short, so the image is scored again
+        if let Ok(scores) = fs::read(&path) {
+            if scores.len() == image.height() as usize {
+                return scores;
+            }
+        }
+        let scores = score_every_row(image);
+        // written to a temp file first, so that an interrupted write never leaves partial scores behind
+        let _ = fs::create_dir_all(&self.directory)
+            .and_then(|_| NamedTempFile::new_in(&self.directory))
+            .and_then(|mut file| {
+                file.write_all(&scores)?;
+                file.persist(&path).map_err(|e| e.error)
+            });
+        scores
+    }
+}
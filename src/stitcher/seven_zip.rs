@@ -0,0 +1,644 @@
+//! This module reads `.cb7`/`.7z` archives.
+//!
+//! Entries compressed with LZMA or LZMA2 (which 7-Zip uses by default), or stored without compression, can
+//! be read. Entries which are encrypted, or compressed with other methods or filters (such as BCJ, which is
+//! only used for executables), are reported as unsupported when they are loaded.
+
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use super::{
+    archive::{check_entry_count, enclosed_path, Archive, ArchiveLimits},
+    image_loader::ImageLoaderError,
+    lzma::{decode_lzma, decode_lzma2},
+};
+
+/// The largest header read, before or after it is decompressed. Headers hold little more than the names of
+/// the entries, so this is far more than any real archive needs.
+const MAX_HEADER_SIZE: u64 = 64 << 20;
+
+/// The ids of the properties making up the header.
+const END: u64 = 0x00;
+const HEADER: u64 = 0x01;
+const ARCHIVE_PROPERTIES: u64 = 0x02;
+const ADDITIONAL_STREAMS_INFO: u64 = 0x03;
+const MAIN_STREAMS_INFO: u64 = 0x04;
+const FILES_INFO: u64 = 0x05;
+const PACK_INFO: u64 = 0x06;
+const UNPACK_INFO: u64 = 0x07;
+const SUBSTREAMS_INFO: u64 = 0x08;
+const SIZE: u64 = 0x09;
+const CRC: u64 = 0x0a;
+const FOLDER: u64 = 0x0b;
+const CODERS_UNPACK_SIZE: u64 = 0x0c;
+const NUM_UNPACK_STREAM: u64 = 0x0d;
+const EMPTY_STREAM: u64 = 0x0e;
+const NAME: u64 = 0x11;
+const ENCODED_HEADER: u64 = 0x17;
+
+/// A 7z archive whose entries have been listed.
+pub(crate) struct SevenZipArchive {
+    file: File,
+    folders: Vec<Folder>,
+    entries: Vec<Entry>,
+    /// The folder decoded last, as solid archives pack many entries into each folder.
+    decoded: Option<(usize, Vec<u8>)>,
+    /// The largest folder which may be decoded, which is the most the limits allow the images to take up.
+    max_folder_size: u64,
+    /// How many more bytes may be decoded, out of the decode work the limits allow.
+    budget: Budget,
+}
+
+/// How many bytes may still be decoded while an archive is read.
+struct Budget {
+    remaining: u64,
+    limit: u64,
+}
+
+impl Budget {
+    /// Takes `size` bytes out of the budget, failing if there aren't that many left.
+    fn spend(&mut self, size: u64) -> Result<(), ImageLoaderError> {
+        self.remaining = self
+            .remaining
+            .checked_sub(size)
+            .ok_or(ImageLoaderError::ArchiveDecodeWorkTooLarge(self.limit))?;
+        Ok(())
+    }
+}
+
+/// A file within a 7z archive.
+struct Entry {
+    path: PathBuf,
+    /// The folder holding the entry.
+    folder: usize,
+    /// Where the entry starts within the decoded folder.
+    offset: u64,
+    size: u64,
+}
+
+/// A block of packed data, which decodes to the data of one or more entries, one after the other.
+struct Folder {
+    /// Where the packed data starts within the archive.
+    offset: u64,
+    packed_size: u64,
+    unpacked_size: u64,
+    method: Method,
+}
+
+/// How the data of a folder is packed.
+enum Method {
+    Copy,
+    /// LZMA, with the properties of the stream.
+    Lzma(Vec<u8>),
+    Lzma2,
+    Encrypted,
+    Unsupported,
+}
+
+/// A folder as it is described in the header, before its packed data is located.
+struct FolderInfo {
+    method: Method,
+    /// How many of the packed streams of the archive the folder takes.
+    packed_streams: usize,
+    /// How many streams the coders of the folder output, and which of them is the output of the folder.
+    out_streams: usize,
+    main_stream: usize,
+    unpacked_size: u64,
+    has_crc: bool,
+}
+
+/// The layout of the packed data of an archive, as it is given in its header.
+#[derive(Default)]
+struct Streams {
+    folders: Vec<Folder>,
+    /// The size of each entry stored in each folder.
+    substreams: Vec<Vec<u64>>,
+}
+
+impl SevenZipArchive {
+    /// Lists the entries of a 7z archive, checking their paths and how many there are.
+    pub(crate) fn open(mut file: File, limits: &ArchiveLimits) -> Result<Self, ImageLoaderError> {
+        let len = file.metadata()?.len();
+        // the signature (6 bytes), version (2) and CRC (4), followed by where the header is
+        let mut start = [0; 32];
+        file.read_exact(&mut start).map_err(|_| damaged())?;
+        let mut fields = Fields(&start[12..]);
+        let header_offset = fields.u64()?;
+        let header_size = fields.u64()?;
+        let header_start = header_offset.checked_add(32).ok_or_else(damaged)?;
+        if header_size > MAX_HEADER_SIZE || header_start.saturating_add(header_size) > len {
+            return Err(damaged());
+        }
+        let mut header = vec![0; header_size as usize];
+        file.seek(SeekFrom::Start(header_start))?;
+        file.read_exact(&mut header)?;
+
+        let mut archive = Self {
+            file,
+            folders: Vec::new(),
+            entries: Vec::new(),
+            decoded: None,
+            max_folder_size: limits.max_total_size,
+            budget: Budget {
+                remaining: limits.max_decode_work,
+                limit: limits.max_decode_work,
+            },
+        };
+        // an empty archive has no header at all
+        if header.is_empty() {
+            return Ok(archive);
+        }
+        // headers are usually compressed themselves, as the data of a folder described by a smaller header.
+        // 7-Zip never compresses them twice, so the decoded header must be a plain one: otherwise a header
+        // could decode to itself, over and over
+        let mut fields = Fields(&header);
+        match fields.number()? {
+            HEADER => {}
+            ENCODED_HEADER => {
+                let streams = fields.streams(len)?;
+                let folder = streams.folders.first().ok_or_else(damaged)?;
+                if folder.unpacked_size > MAX_HEADER_SIZE {
+                    return Err(damaged());
+                }
+                header =
+                    decode(&mut archive.file, folder, &mut archive.budget).map_err(|error| {
+                        match folder.method {
+                            Method::Encrypted => ImageLoaderError::InvalidArchive(
+                                "its file names are encrypted".to_owned(),
+                            ),
+                            _ => error,
+                        }
+                    })?;
+                if Fields(&header).number()? != HEADER {
+                    return Err(damaged());
+                }
+            }
+            _ => return Err(damaged()),
+        }
+
+        let mut fields = Fields(&header[1..]);
+        let mut streams = None;
+        loop {
+            match fields.number()? {
+                END => break,
+                ARCHIVE_PROPERTIES => {
+                    while fields.number()? != END {
+                        let size = fields.count()?;
+                        fields.bytes(size)?;
+                    }
+                }
+                ADDITIONAL_STREAMS_INFO => {
+                    fields.streams(len)?;
+                }
+                // the entries refer to the folders by index, so they must all come from the same streams
+                MAIN_STREAMS_INFO if streams.is_none() => streams = Some(fields.streams(len)?),
+                FILES_INFO => {
+                    let streams = streams.get_or_insert_with(Streams::default);
+                    archive.entries = fields.files(streams, limits)?;
+                }
+                _ => return Err(damaged()),
+            }
+        }
+        archive.folders = streams.unwrap_or_default().folders;
+        Ok(archive)
+    }
+}
+
+impl Archive for SevenZipArchive {
+    fn files(&self) -> Vec<PathBuf> {
+        self.entries
+            .iter()
+            .map(|entry| entry.path.clone())
+            .collect()
+    }
+    fn read(&mut self, path: &Path, limit: u64) -> Result<Vec<u8>, ImageLoaderError> {
+        // when a path appears several times, the last one wins
+        let entry = self
+            .entries
+            .iter()
+            .rev()
+            .find(|entry| entry.path == path)
+            .ok_or(ImageLoaderError::NotFound)?;
+        let (index, offset, size) = (entry.folder, entry.offset, entry.size);
+        let folder = self.folders.get(index).ok_or_else(damaged)?;
+        let reason = match folder.method {
+            Method::Encrypted => Some("it is encrypted"),
+            Method::Unsupported => Some("it is compressed with a method which isn't supported"),
+            _ => None,
+        };
+        if let Some(reason) = reason {
+            return Err(ImageLoaderError::UnsupportedArchiveEntry {
+                path: path.to_owned(),
+                reason: reason.to_owned(),
+            });
+        }
+        if folder.unpacked_size > self.max_folder_size {
+            return Err(ImageLoaderError::ArchiveTooLarge(self.max_folder_size));
+        }
+        if self
+            .decoded
+            .as_ref()
+            .is_none_or(|(decoded, _)| *decoded != index)
+        {
+            let decoded = decode(&mut self.file, folder, &mut self.budget)?;
+            self.decoded = Some((index, decoded));
+        }
+        let (_, decoded) = self.decoded.as_ref().expect("the folder was just decoded");
+        let start = usize::try_from(offset).map_err(|_| damaged())?;
+        let len = usize::try_from(size.min(limit.saturating_add(1))).map_err(|_| damaged())?;
+        let end = start.checked_add(len).ok_or_else(damaged)?;
+        Ok(decoded.get(start..end).ok_or_else(damaged)?.to_vec())
+    }
+}
+
+/// The fields of a header, read from front to back.
+struct Fields<'a>(&'a [u8]);
+
+impl<'a> Fields<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], ImageLoaderError> {
+        if len > self.0.len() {
+            return Err(damaged());
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+    fn byte(&mut self) -> Result<u8, ImageLoaderError> {
+        Ok(self.bytes(1)?[0])
+    }
+    fn u64(&mut self) -> Result<u64, ImageLoaderError> {
+        let bytes = self.bytes(8)?;
+        Ok(u64::from_le_bytes(
+            bytes.try_into().expect("8 bytes were read"),
+        ))
+    }
+    /// A variable-length number: the number of leading 1 bits of the first byte tells how many bytes
+    /// follow it (least significant first), and the rest of its bits are the most significant ones.
+    fn number(&mut self) -> Result<u64, ImageLoaderError> {
+        let first = self.byte()?;
+        let mut value = 0;
+        for index in 0..8 {
+            let mask = 0x80 >> index;
+            if first & mask == 0 {
+                let high = (first & (mask - 1)) as u64;
+                return Ok(value | high << (8 * index));
+            }
+            value |= (self.byte()? as u64) << (8 * index);
+        }
+        Ok(value)
+    }
+    /// A number counting items which each take up at least a bit of the rest of the header, so that a
+    /// damaged count is caught before anything is allocated for it.
+    fn count(&mut self) -> Result<usize, ImageLoaderError> {
+        let count = self.number()?;
+        if count > self.0.len() as u64 * 8 {
+            return Err(damaged());
+        }
+        Ok(count as usize)
+    }
+    /// A list of `len` booleans, 8 to a byte with the first one in the highest bit.
+    fn bits(&mut self, len: usize) -> Result<Vec<bool>, ImageLoaderError> {
+        let bytes = self.bytes(len.div_ceil(8))?;
+        Ok((0..len)
+            .map(|index| bytes[index / 8] & (0x80 >> (index % 8)) != 0)
+            .collect())
+    }
+    /// Skips the CRCs of `len` items, returning which items have one.
+    fn crcs(&mut self, len: usize) -> Result<Vec<bool>, ImageLoaderError> {
+        let defined = match self.byte()? {
+            0 => self.bits(len)?,
+            _ => vec![true; len],
+        };
+        let count = defined.iter().filter(|&&defined| defined).count();
+        self.bytes(count * 4)?;
+        Ok(defined)
+    }
+    /// Reads where the packed data of an archive is and how it decodes, checking that it is within the
+    /// `len` bytes of the archive.
+    fn streams(&mut self, len: u64) -> Result<Streams, ImageLoaderError> {
+        let mut pack_pos = 0;
+        let mut pack_sizes = Vec::new();
+        let mut folders = Vec::new();
+        let mut substreams = None;
+        loop {
+            match self.number()? {
+                END => break,
+                PACK_INFO => {
+                    pack_pos = self.number()?;
+                    let count = self.count()?;
+                    loop {
+                        match self.number()? {
+                            END => break,
+                            SIZE => {
+                                pack_sizes = (0..count)
+                                    .map(|_| self.number())
+                                    .collect::<Result<_, _>>()?;
+                            }
+                            CRC => {
+                                self.crcs(count)?;
+                            }
+                            _ => return Err(damaged()),
+                        }
+                    }
+                }
+                UNPACK_INFO => folders = self.folders()?,
+                SUBSTREAMS_INFO => substreams = Some(self.substreams(&folders)?),
+                _ => return Err(damaged()),
+            }
+        }
+
+        // folders take their packed streams in order
+        let mut packed = pack_sizes.iter();
+        let mut offset = pack_pos.checked_add(32).ok_or_else(damaged)?;
+        let folders = folders
+            .into_iter()
+            .map(|folder| {
+                let mut packed_size = 0u64;
+                let start = offset;
+                for _ in 0..folder.packed_streams {
+                    let size = *packed.next().ok_or_else(damaged)?;
+                    packed_size = packed_size.checked_add(size).ok_or_else(damaged)?;
+                    offset = offset.checked_add(size).ok_or_else(damaged)?;
+                }
+                if offset > len {
+                    return Err(damaged());
+                }
+                Ok(Folder {
+                    offset: start,
+                    packed_size,
+                    unpacked_size: folder.unpacked_size,
+                    method: folder.method,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let substreams = match substreams {
+            Some(substreams) => substreams,
+            None => folders
+                .iter()
+                .map(|folder| vec![folder.unpacked_size])
+                .collect(),
+        };
+        Ok(Streams {
+            folders,
+            substreams,
+        })
+    }
+    /// Reads the folders of an archive.
+    fn folders(&mut self) -> Result<Vec<FolderInfo>, ImageLoaderError> {
+        if self.number()? != FOLDER {
+            return Err(damaged());
+        }
+        let count = self.count()?;
+        // folders stored elsewhere in the archive aren't written by any known tool
+        if self.byte()? != 0 {
+            return Err(damaged());
+        }
+        let mut folders = Vec::with_capacity(count);
+        for _ in 0..count {
+            folders.push(self.folder()?);
+        }
+        if self.number()? != CODERS_UNPACK_SIZE {
+            return Err(damaged());
+        }
+        for folder in &mut folders {
+            for stream in 0..folder.out_streams {
+                let size = self.number()?;
+                if stream == folder.main_stream {
+                    folder.unpacked_size = size;
+                }
+            }
+        }
+        loop {
+            match self.number()? {
+                END => break,
+                CRC => {
+                    for (folder, has_crc) in folders.iter_mut().zip(self.crcs(count)?) {
+                        folder.has_crc = has_crc;
+                    }
+                }
+                _ => return Err(damaged()),
+            }
+        }
+        Ok(folders)
+    }
+    /// Reads how a folder is packed: the coders its data goes through, and how their streams are bound
+    /// together. Only folders with a single coder can be decoded.
+    fn folder(&mut self) -> Result<FolderInfo, ImageLoaderError> {
+        let count = self.count()?;
+        if count == 0 || count > 64 {
+            return Err(damaged());
+        }
+        let mut coders = Vec::with_capacity(count);
+        let (mut in_streams, mut out_streams) = (0usize, 0usize);
+        for _ in 0..count {
+            let flags = self.byte()?;
+            // alternative methods were planned by the format, but are never written
+            if flags & 0x80 != 0 {
+                return Err(damaged());
+            }
+            let id = self.bytes((flags & 0x0f) as usize)?;
+            let (ins, outs) = match flags & 0x10 {
+                0 => (1, 1),
+                _ => (self.count()?, self.count()?),
+            };
+            let properties = match flags & 0x20 {
+                0 => &[][..],
+                _ => {
+                    let size = self.count()?;
+                    self.bytes(size)?
+                }
+            };
+            in_streams = in_streams.checked_add(ins).ok_or_else(damaged)?;
+            out_streams = out_streams.checked_add(outs).ok_or_else(damaged)?;
+            coders.push((id, properties));
+        }
+        // every output but the last feeds into the input of another coder
+        let mut bound = vec![false; out_streams];
+        for _ in 1..out_streams {
+            let _in_stream = self.number()?;
+            let out_stream = usize::try_from(self.number()?).map_err(|_| damaged())?;
+            *bound.get_mut(out_stream).ok_or_else(damaged)? = true;
+        }
+        let packed_streams = (in_streams + 1)
+            .checked_sub(out_streams)
+            .ok_or_else(damaged)?;
+        if packed_streams > 1 {
+            for _ in 0..packed_streams {
+                self.number()?;
+            }
+        }
+        let main_stream = bound.iter().position(|&bound| !bound).ok_or_else(damaged)?;
+        let method = match coders[..] {
+            [([0x00], _)] => Method::Copy,
+            [([0x03, 0x01, 0x01], properties)] => Method::Lzma(properties.to_vec()),
+            [([0x21], _)] => Method::Lzma2,
+            _ if coders.iter().any(|(id, _)| *id == [0x06, 0xf1, 0x07, 0x01]) => Method::Encrypted,
+            _ => Method::Unsupported,
+        };
+        Ok(FolderInfo {
+            method,
+            packed_streams,
+            out_streams,
+            main_stream,
+            unpacked_size: 0,
+            has_crc: false,
+        })
+    }
+    /// Reads the sizes of the entries stored in each folder.
+    fn substreams(&mut self, folders: &[FolderInfo]) -> Result<Vec<Vec<u64>>, ImageLoaderError> {
+        let mut counts = vec![1; folders.len()];
+        let mut sizes = None;
+        loop {
+            match self.number()? {
+                END => break,
+                NUM_UNPACK_STREAM => {
+                    for count in &mut counts {
+                        *count = self.count()?;
+                    }
+                }
+                SIZE => {
+                    let mut all_sizes = Vec::with_capacity(folders.len());
+                    for (folder, &count) in folders.iter().zip(&counts) {
+                        let mut sizes = Vec::with_capacity(count);
+                        let mut total = 0u64;
+                        // the size of the last entry is whatever the others leave of the folder
+                        for _ in 1..count {
+                            let size = self.number()?;
+                            total = total.checked_add(size).ok_or_else(damaged)?;
+                            sizes.push(size);
+                        }
+                        if count > 0 {
+                            let last = folder.unpacked_size.checked_sub(total);
+                            sizes.push(last.ok_or_else(damaged)?);
+                        }
+                        all_sizes.push(sizes);
+                    }
+                    sizes = Some(all_sizes);
+                }
+                CRC => {
+                    // folders holding a single entry give its CRC as their own
+                    let count = folders
+                        .iter()
+                        .zip(&counts)
+                        .map(|(folder, &count)| match count {
+                            1 if folder.has_crc => 0,
+                            count => count,
+                        })
+                        .sum();
+                    self.crcs(count)?;
+                }
+                _ => return Err(damaged()),
+            }
+        }
+        match sizes {
+            Some(sizes) => Ok(sizes),
+            None => folders
+                .iter()
+                .zip(counts)
+                .map(|(folder, count)| match count {
+                    0 => Ok(Vec::new()),
+                    1 => Ok(vec![folder.unpacked_size]),
+                    _ => Err(damaged()),
+                })
+                .collect(),
+        }
+    }
+    /// Reads the names of the entries, pairing the ones with data up with the entries stored in the folders.
+    fn files(
+        &mut self,
+        streams: &Streams,
+        limits: &ArchiveLimits,
+    ) -> Result<Vec<Entry>, ImageLoaderError> {
+        let count = usize::try_from(self.number()?).unwrap_or(usize::MAX);
+        check_entry_count(count, limits)?;
+        let mut empty_streams = vec![false; count];
+        let mut names = Vec::new();
+        loop {
+            let property = self.number()?;
+            if property == END {
+                break;
+            }
+            let size = self.count()?;
+            let mut data = Fields(self.bytes(size)?);
+            match property {
+                // directories and empty files, which have no data
+                EMPTY_STREAM => empty_streams = data.bits(count)?,
+                NAME => {
+                    if data.byte()? != 0 {
+                        return Err(damaged());
+                    }
+                    let units: Vec<u16> = data
+                        .0
+                        .chunks_exact(2)
+                        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                        .collect();
+                    names = units
+                        .split(|&unit| unit == 0)
+                        .take(count)
+                        .map(String::from_utf16_lossy)
+                        .collect();
+                }
+                // times, attributes and the like
+                _ => {}
+            }
+        }
+        if names.len() < count {
+            return Err(damaged());
+        }
+
+        let mut substreams = streams
+            .substreams
+            .iter()
+            .enumerate()
+            .flat_map(|(folder, sizes)| {
+                sizes.iter().scan(0, move |offset, &size| {
+                    let entry = (folder, *offset, size);
+                    *offset += size;
+                    Some(entry)
+                })
+            });
+        let mut entries = Vec::new();
+        for (name, is_empty) in names.iter().zip(empty_streams) {
+            let path = enclosed_path(name)?;
+            if is_empty {
+                continue;
+            }
+            let (folder, offset, size) = substreams.next().ok_or_else(damaged)?;
+            entries.push(Entry {
+                path,
+                folder,
+                offset,
+                size,
+            });
+        }
+        Ok(entries)
+    }
+}
+
+/// Decodes the whole of a folder, taking its size out of the budget.
+fn decode(
+    file: &mut File,
+    folder: &Folder,
+    budget: &mut Budget,
+) -> Result<Vec<u8>, ImageLoaderError> {
+    let size = usize::try_from(folder.unpacked_size).map_err(|_| damaged())?;
+    budget.spend(folder.unpacked_size)?;
+    let mut packed = vec![0; folder.packed_size as usize];
+    file.seek(SeekFrom::Start(folder.offset))?;
+    file.read_exact(&mut packed)?;
+    let unpacked = match &folder.method {
+        Method::Copy => (packed.len() == size).then_some(packed),
+        Method::Lzma(properties) => decode_lzma(properties, &packed, size),
+        Method::Lzma2 => decode_lzma2(&packed, size),
+        Method::Encrypted | Method::Unsupported => None,
+    };
+    unpacked.ok_or_else(damaged)
+}
+
+/// The error for an archive which isn't laid out as a 7z archive should be.
+fn damaged() -> ImageLoaderError {
+    ImageLoaderError::InvalidArchive("it is damaged".to_owned())
+}
@@ -0,0 +1,69 @@
+//! This module contains the unsharp mask applied to images after they are resized, to restore the detail
+//! (such as screentones and lettering) which resizing softens.
+
+use image::{imageops, RgbImage};
+
+/// An unsharp mask: each pixel is pushed away from a blurred copy of the image, which steepens edges
+/// (defaults to an amount of 0.5, a radius of 1 and a threshold of 2).
+///
+/// Only images which are resized are sharpened, as images which already have the width of the strip
+/// haven't been softened.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sharpen {
+    amount: f32,
+    radius: f32,
+    threshold: u8,
+}
+
+impl Default for Sharpen {
+    fn default() -> Self {
+        Self {
+            amount: 0.5,
+            radius: 1.0,
+            threshold: 2,
+        }
+    }
+}
+
+impl Sharpen {
+    /// Creates a mask with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets how strongly edges are sharpened, as the fraction of the difference from the blurred copy
+    /// which is added to each pixel (defaults to 0.5).
+    pub fn amount(mut self, amount: f32) -> Self {
+        self.amount = amount.max(0.0);
+        self
+    }
+    /// Sets the radius of the blur (the standard deviation of the gaussian, in pixels), which is how wide
+    /// the details sharpened are (defaults to 1).
+    pub fn radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+    /// Sets by how much a pixel must differ from the blurred copy to be sharpened, so that flat areas and
+    /// noise are left alone (defaults to 2).
+    pub fn threshold(mut self, threshold: u8) -> Self {
+        self.threshold = threshold;
+        self
+    }
+    /// Sharpens an image.
+    pub(crate) fn apply(&self, mut image: RgbImage) -> RgbImage {
+        if self.amount == 0.0 || self.radius <= 0.0 {
+            return image;
+        }
+        let blurred = imageops::blur(&image, self.radius);
+        for (pixel, blurred) in image.pixels_mut().zip(blurred.pixels()) {
+            for (channel, &blurred) in pixel.0.iter_mut().zip(&blurred.0) {
+                let difference = *channel as f32 - blurred as f32;
+                if difference.abs() >= self.threshold as f32 {
+                    *channel = (*channel as f32 + difference * self.amount)
+                        .round()
+                        .clamp(0.0, 255.0) as u8;
+                }
+            }
+        }
+        image
+    }
+}
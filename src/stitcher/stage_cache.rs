@@ -0,0 +1,114 @@
+//! This module contains the on-disk cache of intermediate results of a pipeline, used to skip the stages
+//! which haven't changed since the last run.
+
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use tempfile::NamedTempFile;
+
+use super::{
+    image_loader::LoadConfig,
+    score_cache::ScoreCache,
+    strip::{new_strip, Strip},
+};
+
+/// A directory in which a [`crate::pipeline::Pipeline`] keeps the results of its stages between runs.
+///
+/// The combined strip is kept once it has been composed, and the splitpoints once the strip has been cut
+/// and paginated, so that when only the output format or the sink changes, the next run skips straight to
+/// encoding. Results are keyed by the fingerprints of the stages which produced them (see
+/// [`crate::pipeline::Source::fingerprint`]), and a result is only kept if every stage before it has a
+/// fingerprint.
+///
+/// Row scores are kept by the [`ScoreCache`] given by [`StageCache::score_cache`], which the pipeline uses
+/// with its default detector. Custom detectors can be given the same cache through
+/// [`crate::StitchConfig::score_cache`].
+///
+/// Strips are kept uncompressed, so the directory can grow large. Nothing is ever removed from it.
+#[derive(Debug, Clone)]
+pub struct StageCache {
+    directory: PathBuf,
+}
+
+impl StageCache {
+    /// Uses the given directory, which is created if it doesn't exist yet.
+    pub fn new(directory: impl AsRef<Path>) -> Self {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+        }
+    }
+    /// The cache of row scores kept within this directory.
+    pub fn score_cache(&self) -> ScoreCache {
+        ScoreCache::new(self.directory.join("scores"))
+    }
+    /// Reads the strip saved under the given key, if there is one.
+    ///
+    /// The strip is backed by heap memory or a temp file according to the config, as a loaded strip is.
+    pub(crate) fn strip(&self, key: u64, config: &LoadConfig) -> Option<Strip> {
+        let mut file = BufReader::new(File::open(self.path(key, "strip")).ok()?);
+        let mut header = [0; 8];
+        file.read_exact(&mut header).ok()?;
+        let width = u32::from_le_bytes(header[..4].try_into().ok()?);
+        let height = u32::from_le_bytes(header[4..].try_into().ok()?);
+        let mut strip = new_strip(width, height, config.spill_threshold, &config.temp).ok()?;
+        file.read_exact(&mut strip).ok()?;
+        // a file with trailing bytes was written for a different strip, so it is ignored
+        file.read(&mut [0]).ok().filter(|&read| read == 0)?;
+        Some(strip)
+    }
+    /// Saves the strip under the given key.
+    ///
+    /// Strips which can't be saved are skipped, as the cache is only an optimisation.
+    pub(crate) fn save_strip(&self, key: u64, strip: &Strip) {
+        self.save(key, "strip", |writer| {
+            writer.write_all(&strip.width().to_le_bytes())?;
+            writer.write_all(&strip.height().to_le_bytes())?;
+            writer.write_all(strip)
+        });
+    }
+    /// Reads the splitpoints saved under the given key, if there are any.
+    pub(crate) fn splitpoints(&self, key: u64) -> Option<Vec<usize>> {
+        let bytes = fs::read(self.path(key, "splitpoints")).ok()?;
+        // a file of the wrong length was cut short, so the splitpoints are found again
+        if bytes.is_empty() || bytes.len() % 8 != 0 {
+            return None;
+        }
+        bytes
+            .chunks_exact(8)
+            .map(|chunk| usize::try_from(u64::from_le_bytes(chunk.try_into().ok()?)).ok())
+            .collect()
+    }
+    /// Saves the splitpoints under the given key.
+    pub(crate) fn save_splitpoints(&self, key: u64, splitpoints: &[usize]) {
+        self.save(key, "splitpoints", |writer| {
+            splitpoints
+                .iter()
+                .try_for_each(|&splitpoint| writer.write_all(&(splitpoint as u64).to_le_bytes()))
+        });
+    }
+    /// The file the result with the given key is kept in.
+    fn path(&self, key: u64, extension: &str) -> PathBuf {
+        self.directory.join(format!("{key:016x}.{extension}"))
+    }
+    /// Writes a result to a temp file first, so that an interrupted write never leaves a partial result
+    /// behind.
+    fn save(
+        &self,
+        key: u64,
+        extension: &str,
+        write: impl FnOnce(&mut BufWriter<&mut NamedTempFile>) -> std::io::Result<()>,
+    ) {
+        let _ = fs::create_dir_all(&self.directory)
+            .and_then(|_| NamedTempFile::new_in(&self.directory))
+            .and_then(|mut file| {
+                let mut writer = BufWriter::new(&mut file);
+                write(&mut writer)?;
+                writer.flush()?;
+                drop(writer);
+                file.persist(self.path(key, extension)).map_err(|e| e.error)
+            });
+    }
+}
@@ -0,0 +1,111 @@
+//! This module contains the timing breakdown recorded while stitching.
+
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use super::image_loader::ImageLoaderError;
+
+/// Wall-clock timings for each phase of a run, along with a breakdown per source file and per page.
+///
+/// Decoding, resizing and encoding happen in parallel, so the per-file and per-page durations may add up
+/// to more than the phase they belong to.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    /// Time spent decoding and resizing the source images.
+    pub load: Duration,
+    /// Time spent copying the source images into the combined image.
+    pub copy: Duration,
+    /// Time spent scoring rows and finding splitpoints.
+    pub score: Duration,
+    /// Time spent encoding and writing pages, summed over every export.
+    pub export: Duration,
+    /// Timings for each source image which was loaded successfully, in load order.
+    pub files: Vec<FileStats>,
+    /// Timings for each page of the most recent export, in reading order.
+    pub pages: Vec<PageStats>,
+    /// Whether the source images looked like pages which were already stitched, and were cut again along
+    /// their seams rather than from scratch.
+    pub already_stitched: bool,
+    /// The position among the given images, and the path unless it was loaded from memory, of each image
+    /// which was animated, whether it was loaded from its first frame or skipped. See
+    /// [`crate::AnimatedPolicy`].
+    pub animated: Vec<(usize, Option<PathBuf>)>,
+    /// Each image which couldn't be loaded, and was skipped as `ignore_unloadable` was set. See
+    /// [`crate::LoadConfig::ignore_unloadable`].
+    pub skipped: Vec<SkippedImage>,
+    /// Each image which was left out of the strip as an exact duplicate of an earlier one. See
+    /// [`crate::LoadConfig::skip_duplicates`].
+    pub duplicates: Vec<Duplicate>,
+}
+
+/// Timings for a single source image.
+#[derive(Debug, Clone)]
+pub struct FileStats {
+    /// The position of the image among the images which were loaded.
+    pub index: usize,
+    /// The path of the image, unless it was loaded from memory.
+    pub path: Option<PathBuf>,
+    /// Whether the image was detected as sideways and rotated before stitching.
+    pub rotated: bool,
+    /// The height of the image once rotated and resized, as it appears in the combined image.
+    pub height: u32,
+    /// The ICC colour profile embedded in the image, if it had one.
+    pub icc_profile: Option<Arc<[u8]>>,
+    pub decode: Duration,
+    pub resize: Duration,
+}
+
+/// An image which couldn't be loaded, and was left out of the strip.
+#[derive(Debug, Clone)]
+pub struct SkippedImage {
+    /// The position of the image among the given images.
+    pub index: usize,
+    /// The path of the image, unless it was loaded from memory.
+    pub path: Option<PathBuf>,
+    /// Why the image couldn't be loaded.
+    pub error: Arc<ImageLoaderError>,
+}
+
+/// An image which was left out of the strip as an exact duplicate of an earlier one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Duplicate {
+    /// The position of the image among the given images.
+    pub index: usize,
+    /// The path of the image, unless it was loaded from memory.
+    pub path: Option<PathBuf>,
+    /// The position of the earlier image it duplicates, which was loaded.
+    pub original: usize,
+}
+
+/// The images which were given to be loaded but were left out of the strip, so that it can be checked that
+/// nothing important was dropped.
+#[derive(Debug, Clone, Default)]
+pub struct LoadReport {
+    /// The images which couldn't be loaded, along with why, in the order they were given.
+    pub skipped: Vec<SkippedImage>,
+    /// The images which were exact duplicates of an earlier one, in the order they were given.
+    pub duplicates: Vec<Duplicate>,
+}
+
+impl LoadReport {
+    /// Gathers the images which were left out from the stats of a run.
+    pub fn new(stats: &Stats) -> Self {
+        Self {
+            skipped: stats.skipped.clone(),
+            duplicates: stats.duplicates.clone(),
+        }
+    }
+    /// Whether every image which was given was loaded.
+    pub fn is_complete(&self) -> bool {
+        self.skipped.is_empty() && self.duplicates.is_empty()
+    }
+}
+
+/// Timings for a single exported page.
+#[derive(Debug, Clone)]
+pub struct PageStats {
+    /// Where the page was written, its name within the archive if it was written to a tar stream, or an
+    /// empty path if it was written to a framed stream.
+    pub path: PathBuf,
+    pub encode: Duration,
+    pub write: Duration,
+}
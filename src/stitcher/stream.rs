@@ -0,0 +1,46 @@
+//! This module implements the framing used to pass images through a single byte stream (e.g. stdin/stdout).
+//!
+//! A stream is a sequence of frames, each consisting of the length of an encoded image as a big-endian
+//! `u64`, followed by that many bytes of the encoded image. The stream ends at EOF, which must fall on a
+//! frame boundary.
+
+use std::io::{self, ErrorKind, Read, Write};
+
+/// Reads a single frame, returning `None` if the stream ended cleanly before it.
+pub fn read_frame(mut reader: impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut length = [0; 8];
+    // Read the first byte on its own, so a clean EOF can be told apart from a truncated frame.
+    match reader.read(&mut length[..1]) {
+        Ok(0) => return Ok(None),
+        Ok(_) => {}
+        Err(e) if e.kind() == ErrorKind::Interrupted => return read_frame(reader),
+        Err(e) => return Err(e),
+    }
+    reader.read_exact(&mut length[1..])?;
+    let length = usize::try_from(u64::from_be_bytes(length))
+        .map_err(|_| io::Error::new(ErrorKind::InvalidData, "frame is too large"))?;
+    let mut frame = Vec::new();
+    reader
+        .by_ref()
+        .take(length as u64)
+        .read_to_end(&mut frame)?;
+    if frame.len() != length {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    Ok(Some(frame))
+}
+
+/// Reads every frame until the end of the stream.
+pub fn read_frames(mut reader: impl Read) -> io::Result<Vec<Vec<u8>>> {
+    let mut frames = Vec::new();
+    while let Some(frame) = read_frame(&mut reader)? {
+        frames.push(frame);
+    }
+    Ok(frames)
+}
+
+/// Writes a single frame.
+pub fn write_frame(mut writer: impl Write, frame: &[u8]) -> io::Result<()> {
+    writer.write_all(&(frame.len() as u64).to_be_bytes())?;
+    writer.write_all(frame)
+}
@@ -0,0 +1,164 @@
+//! This module contains the storage backing the combined image strip.
+
+use std::{
+    fmt,
+    io::{self, ErrorKind},
+    ops::{Deref, DerefMut},
+};
+
+use image::{
+    imageops::{self, FilterType},
+    ImageBuffer, Pixel, Rgb, RgbImage,
+};
+use memmap2::MmapMut;
+
+use super::temp::TempPolicy;
+
+/// The combined image strip.
+///
+/// This behaves like an [`image::RgbImage`], except that its pixels may live in a memory-mapped temp file
+/// rather than on the heap. See [`StripStorage`].
+pub type Strip = ImageBuffer<Rgb<u8>, StripStorage>;
+
+/// The buffer holding the pixels of a [`Strip`].
+pub enum StripStorage {
+    /// The pixels are held in heap memory.
+    Heap(Vec<u8>),
+    /// The pixels are held in a memory-mapped temp file, which is deleted once the strip is dropped.
+    ///
+    /// Accessing the strip is slower, but the operating system is free to page it out, so strips
+    /// larger than the available memory can still be processed.
+    Mapped(MmapMut),
+}
+
+impl Deref for StripStorage {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            StripStorage::Heap(buffer) => buffer,
+            StripStorage::Mapped(map) => map,
+        }
+    }
+}
+
+impl DerefMut for StripStorage {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match self {
+            StripStorage::Heap(buffer) => buffer,
+            StripStorage::Mapped(map) => map,
+        }
+    }
+}
+
+impl fmt::Debug for StripStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StripStorage::Heap(buffer) => write!(f, "Heap({} bytes)", buffer.len()),
+            StripStorage::Mapped(map) => write!(f, "Mapped({} bytes)", map.len()),
+        }
+    }
+}
+
+/// Allocates a blank strip of the given dimensions.
+///
+/// If `spill_threshold` is set and the strip would take up more bytes than it, the strip is backed by a
+/// memory-mapped scratch file created according to the temp policy instead of heap memory.
+///
+/// Throws an error (rather than aborting) if there isn't enough memory for the strip.
+pub fn new_strip(
+    width: u32,
+    height: u32,
+    spill_threshold: Option<u64>,
+    temp: &TempPolicy,
+) -> io::Result<Strip> {
+    let len = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|len| len.checked_mul(3))
+        .ok_or_else(|| io::Error::new(ErrorKind::OutOfMemory, "the strip is too large"))?;
+    let storage = match spill_threshold {
+        Some(threshold) if len as u64 > threshold => {
+            let file = temp.file(len as u64)?;
+            // Safety: the file was just created by us and is never exposed, so nothing else can resize
+            // or modify it while it is mapped.
+            let map = unsafe { MmapMut::map_mut(&file)? };
+            StripStorage::Mapped(map)
+        }
+        _ => {
+            let mut buffer = Vec::new();
+            buffer
+                .try_reserve_exact(len)
+                .map_err(|e| io::Error::new(ErrorKind::OutOfMemory, e))?;
+            buffer.resize(len, 0);
+            StripStorage::Heap(buffer)
+        }
+    };
+    // The storage was sized for the given dimensions, so this can't fail.
+    Ok(ImageBuffer::from_raw(width, height, storage).expect("all according to keikaku"))
+}
+
+/// Crops a strip to the `width` columns starting at `left` and to its first `height` rows, or extends it
+/// with black rows up to `height`.
+///
+/// Heap strips which don't grow are cropped in place, so that no second copy of the strip is ever held in
+/// memory. Other strips are copied into a new strip, allocated like [`new_strip`] does.
+pub(crate) fn reshape_strip(
+    strip: Strip,
+    left: u32,
+    width: u32,
+    height: u32,
+    spill_threshold: Option<u64>,
+    temp: &TempPolicy,
+) -> io::Result<Strip> {
+    let (old_width, old_height) = strip.dimensions();
+    assert!(
+        left + width <= old_width,
+        "the columns are not within the strip"
+    );
+    if (left, width, height) == (0, old_width, old_height) {
+        return Ok(strip);
+    }
+    let channels = Rgb::<u8>::CHANNEL_COUNT as usize;
+    let (row, old_row, offset) = (
+        width as usize * channels,
+        old_width as usize * channels,
+        left as usize * channels,
+    );
+    let rows = height.min(old_height) as usize;
+    let mut storage = strip.into_raw();
+    if let StripStorage::Heap(buffer) = &mut storage {
+        if row * height as usize <= buffer.len() {
+            // rows only ever move towards the start of the buffer, so moving them in order never overwrites
+            // one which is still to be moved
+            for y in 0..rows {
+                let start = y * old_row + offset;
+                buffer.copy_within(start..start + row, y * row);
+            }
+            buffer.truncate(row * height as usize);
+            buffer.shrink_to_fit();
+            // The buffer was sized for the given dimensions, so this can't fail.
+            return Ok(
+                ImageBuffer::from_raw(width, height, storage).expect("all according to keikaku")
+            );
+        }
+    }
+    let mut reshaped = new_strip(width, height, spill_threshold, temp)?;
+    let pixels: &mut [u8] = &mut reshaped;
+    for y in 0..rows {
+        let start = y * old_row + offset;
+        pixels[y * row..(y + 1) * row].copy_from_slice(&storage[start..start + row]);
+    }
+    Ok(reshaped)
+}
+
+/// Produces a downscaled copy of a strip which is at most `max_height` pixels tall, keeping its aspect ratio.
+///
+/// Nearest-neighbour sampling is used, so this is fast even for huge strips, at the cost of quality.
+pub fn preview(strip: &Strip, max_height: u32) -> RgbImage {
+    let (width, height) = strip.dimensions();
+    if height <= max_height {
+        return imageops::crop_imm(strip, 0, 0, width, height).to_image();
+    }
+    let preview_width = (width as u64 * max_height as u64 / height as u64).max(1) as u32;
+    imageops::resize(strip, preview_width, max_height.max(1), FilterType::Nearest)
+}
@@ -0,0 +1,113 @@
+//! This module runs detection with a grid of options, so that their results can be compared side by side
+//! when picking the options for a new series.
+
+use image::{imageops, Rgb, RgbImage};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use super::{
+    image_splitter::{find_splitpoints, StitchConfig},
+    virtual_strip::StripRows,
+};
+
+/// The color cuts are drawn in on comparison previews.
+const CUT_COLOR: Rgb<u8> = Rgb([255, 0, 0]);
+
+/// The color of the gap between neighbouring previews.
+const GAP_COLOR: Rgb<u8> = Rgb([128, 128, 128]);
+
+/// How many pixels wide the gap between neighbouring previews is.
+const GAP_WIDTH: u32 = 8;
+
+/// A grid of sensitivities and scan intervals to try. Every combination of the two is run.
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    base: StitchConfig,
+    sensitivities: Vec<u8>,
+    scan_intervals: Vec<usize>,
+}
+
+impl Sweep {
+    /// Creates a sweep which runs the given config as it is. Every other option of the config is shared
+    /// by all runs.
+    pub fn new(base: StitchConfig) -> Self {
+        Self {
+            sensitivities: vec![base.sensitivity],
+            scan_intervals: vec![base.scan_interval],
+            base,
+        }
+    }
+    /// Sets the sensitivities to try (defaults to the sensitivity of the base config).
+    pub fn sensitivities(mut self, sensitivities: Vec<u8>) -> Self {
+        self.sensitivities = sensitivities;
+        self
+    }
+    /// Sets the scan intervals to try (defaults to the scan interval of the base config).
+    pub fn scan_intervals(mut self, scan_intervals: Vec<usize>) -> Self {
+        self.scan_intervals = scan_intervals;
+        self
+    }
+    /// Every combination of options, ordered by sensitivity and then by scan interval.
+    fn configs(&self) -> Vec<StitchConfig> {
+        self.sensitivities
+            .iter()
+            .flat_map(|&sensitivity| {
+                self.scan_intervals.iter().map(move |&scan_interval| {
+                    self.base
+                        .clone()
+                        .sensitivity(sensitivity)
+                        .scan_interval(scan_interval)
+                })
+            })
+            .collect()
+    }
+}
+
+/// The result of one combination of options in a [`Sweep`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SweepRun {
+    pub sensitivity: u8,
+    pub scan_interval: usize,
+    pub splitpoints: Vec<usize>,
+}
+
+/// Finds the splitpoints of the image with every combination of options in the sweep, in parallel.
+pub fn run_sweep(image: &impl StripRows, sweep: &Sweep, boundaries: &[usize]) -> Vec<SweepRun> {
+    sweep
+        .configs()
+        .par_iter()
+        .map(|config| SweepRun {
+            sensitivity: config.sensitivity,
+            scan_interval: config.scan_interval,
+            splitpoints: find_splitpoints(image, config, boundaries),
+        })
+        .collect()
+}
+
+/// Places a preview of the strip for each run side by side, left to right in the order of the runs, with
+/// the cuts of each run drawn across its preview.
+///
+/// `preview` must be a downscaled copy of a strip `height` pixels tall.
+pub fn compare_runs(preview: &RgbImage, height: usize, runs: &[SweepRun]) -> RgbImage {
+    let (width, preview_height) = preview.dimensions();
+    let count = runs.len() as u32;
+    let mut comparison = RgbImage::from_pixel(
+        count * width + count.saturating_sub(1) * GAP_WIDTH,
+        preview_height,
+        GAP_COLOR,
+    );
+    for (index, run) in runs.iter().enumerate() {
+        let left = index as u32 * (width + GAP_WIDTH);
+        imageops::replace(&mut comparison, preview, left as i64, 0);
+        let last = run.splitpoints.len().saturating_sub(1);
+        for &splitpoint in run.splitpoints.get(1..last).unwrap_or_default() {
+            let y = (splitpoint as u64 * preview_height as u64 / height.max(1) as u64) as u32;
+            // two rows thick, so cuts stay visible once downscaled further
+            for row in y.saturating_sub(1)..(y + 1).min(preview_height) {
+                for column in left..left + width {
+                    comparison.put_pixel(column, row, CUT_COLOR);
+                }
+            }
+        }
+    }
+    comparison
+}
@@ -0,0 +1,73 @@
+//! This module contains the policy for the scratch space used while stitching.
+
+use std::{
+    fs::{self, File},
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+/// Where scratch files are kept, and how much space they may take up (defaults to the system temp
+/// directory, with no limit).
+///
+/// Scratch files back strips which are too large to keep in memory (see
+/// [`crate::LoadConfig::spill_threshold`]). The same policy is used for every strip made from a loaded
+/// one, such as when a title card is added, so servers can point all scratch space at a fast local disk.
+///
+/// Scratch files are deleted as soon as they are created, and only live on as long as the strip holding
+/// them open, so they are always cleaned up, whether the run succeeds, fails, or the process is killed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TempPolicy {
+    directory: PathBuf,
+    max_size: Option<u64>,
+}
+
+impl Default for TempPolicy {
+    fn default() -> Self {
+        Self {
+            directory: std::env::temp_dir(),
+            max_size: None,
+        }
+    }
+}
+
+impl TempPolicy {
+    /// Creates the default policy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets the directory scratch files are created in, which is created if it doesn't exist yet (defaults
+    /// to the system temp directory).
+    pub fn directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.directory = directory.into();
+        self
+    }
+    /// Sets how many bytes a single scratch file may take up at most (defaults to `None`, i.e. no limit).
+    ///
+    /// Strips which would need a larger file fail to load with a [`ErrorKind::StorageFull`] error rather
+    /// than filling up the disk.
+    pub fn max_size(mut self, max_size: Option<u64>) -> Self {
+        self.max_size = max_size;
+        self
+    }
+    /// The directory scratch files are created in.
+    pub fn path(&self) -> &Path {
+        &self.directory
+    }
+    /// Creates a scratch file of the given size.
+    pub(crate) fn file(&self, size: u64) -> io::Result<File> {
+        if let Some(max_size) = self.max_size.filter(|&max_size| size > max_size) {
+            return Err(io::Error::new(
+                ErrorKind::StorageFull,
+                format!(
+                    "{size} bytes of scratch space are needed, but at most {max_size} are allowed"
+                ),
+            ));
+        }
+        fs::create_dir_all(&self.directory)?;
+        // The file is unlinked straight away (or on close, on Windows), so it is cleaned up even if the
+        // process is killed.
+        let file = tempfile::tempfile_in(&self.directory)?;
+        file.set_len(size)?;
+        Ok(file)
+    }
+}
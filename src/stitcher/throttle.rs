@@ -0,0 +1,112 @@
+//! This module contains the limiter used to share decoding and encoding capacity between concurrent jobs,
+//! and the thread pools used to cap how many threads a single job uses.
+
+use std::{
+    fmt,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+};
+
+/// How urgently a job needs its share of a [`Throttle`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    /// Bulk work, such as re-stitching a whole library, which only runs when nothing else is waiting.
+    Background,
+    #[default]
+    Normal,
+    /// Work a user is waiting on, such as a GUI preview.
+    Interactive,
+}
+
+#[derive(Default)]
+struct State {
+    running: usize,
+    // the number of waiting jobs of each priority, indexed by `Priority as usize`
+    waiting: [usize; 3],
+}
+
+/// A limit on how many decodes (or encodes) may run at once, shared between every job it is given to.
+///
+/// When the limit is reached, further work waits, and waiting work with a higher [`Priority`] is always
+/// let through first. Cloning a throttle gives another handle to the same limit.
+#[derive(Clone)]
+pub struct Throttle {
+    max_concurrent: usize,
+    shared: Arc<(Mutex<State>, Condvar)>,
+}
+
+impl Throttle {
+    /// Creates a throttle which lets at most `max_concurrent` (at least one) operations run at once.
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            max_concurrent: max_concurrent.max(1),
+            shared: Arc::default(),
+        }
+    }
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.shared.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+    /// Runs `f` once the throttle has room for it.
+    pub fn run<T>(&self, priority: Priority, f: impl FnOnce() -> T) -> T {
+        let (_, condvar) = &*self.shared;
+        let mut state = self.lock();
+        state.waiting[priority as usize] += 1;
+        while state.running >= self.max_concurrent
+            || state.waiting[priority as usize + 1..]
+                .iter()
+                .any(|&n| n > 0)
+        {
+            state = condvar.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+        state.waiting[priority as usize] -= 1;
+        state.running += 1;
+        drop(state);
+        // lower priority work may have been waiting on this work alone
+        condvar.notify_all();
+
+        // release the slot even if `f` panics
+        struct Slot<'a>(&'a Throttle);
+        impl Drop for Slot<'_> {
+            fn drop(&mut self) {
+                self.0.lock().running -= 1;
+                self.0.shared.1.notify_all();
+            }
+        }
+        let _slot = Slot(self);
+        f()
+    }
+}
+
+impl fmt::Debug for Throttle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("Throttle")
+            .field("max_concurrent", &self.max_concurrent)
+            .field("running", &state.running)
+            .field("waiting", &state.waiting)
+            .finish()
+    }
+}
+
+/// Runs `f` through the throttle, if there is one.
+pub(crate) fn throttled<T>(throttle: &Option<(Throttle, Priority)>, f: impl FnOnce() -> T) -> T {
+    match throttle {
+        Some((throttle, priority)) => throttle.run(*priority, f),
+        None => f(),
+    }
+}
+
+/// Runs `f` on a thread pool of its own with the given number of threads (at least one), if set, so that
+/// its parallel work uses at most that many threads. Otherwise (or if the pool can't be created), `f` runs
+/// on the current thread pool, which is usually rayon's global pool.
+pub(crate) fn with_threads<T: Send>(threads: Option<usize>, f: impl FnOnce() -> T + Send) -> T {
+    let pool = threads.and_then(|threads| {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads.max(1))
+            .build()
+            .ok()
+    });
+    match pool {
+        Some(pool) => pool.install(f),
+        None => f(),
+    }
+}
@@ -0,0 +1,158 @@
+//! This module renders the title card which can be inserted at the start of the output.
+
+use fontdue::{
+    layout::{CoordinateSystem, HorizontalAlign, Layout, LayoutSettings, TextStyle, VerticalAlign},
+    Font, FontSettings,
+};
+use std::io;
+
+use image::{GenericImage, Rgb, RgbImage};
+
+use super::{
+    image_splitter::ImageSplitterError,
+    strip::{new_strip, Strip, StripStorage},
+    temp::TempPolicy,
+};
+
+/// A generated title page, with the series name, chapter and group name centred on a solid background.
+///
+/// Every line is optional, and is rendered as given, so the card can be in any language the font supports.
+#[derive(Debug, Clone)]
+pub struct TitleCard {
+    font: Font,
+    series: Option<String>,
+    chapter: Option<String>,
+    group: Option<String>,
+    background: Rgb<u8>,
+    text_color: Rgb<u8>,
+    height: Option<u32>,
+}
+
+impl TitleCard {
+    /// Creates a card with no text, which renders text in the given font (the contents of a TrueType or
+    /// OpenType font file) in black on white.
+    ///
+    /// Throws an error if the font can't be parsed.
+    pub fn new(font: &[u8]) -> Result<Self, ImageSplitterError> {
+        let font = Font::from_bytes(font, FontSettings::default())
+            .map_err(|e| ImageSplitterError::InvalidFont(e.to_string()))?;
+        Ok(Self {
+            font,
+            series: None,
+            chapter: None,
+            group: None,
+            background: Rgb([255, 255, 255]),
+            text_color: Rgb([0, 0, 0]),
+            height: None,
+        })
+    }
+    /// Sets the name of the series, which is the largest line.
+    pub fn series(mut self, series: impl Into<String>) -> Self {
+        self.series = Some(series.into());
+        self
+    }
+    /// Sets the line naming the chapter (e.g. `"Chapter 12"`).
+    pub fn chapter(mut self, chapter: impl Into<String>) -> Self {
+        self.chapter = Some(chapter.into());
+        self
+    }
+    /// Sets the name of the group which worked on the chapter, which is the smallest line.
+    pub fn group(mut self, group: impl Into<String>) -> Self {
+        self.group = Some(group.into());
+        self
+    }
+    /// Sets the colors of the background and the text.
+    pub fn colors(mut self, background: Rgb<u8>, text: Rgb<u8>) -> Self {
+        self.background = background;
+        self.text_color = text;
+        self
+    }
+    /// Sets the height of the card (defaults to the width of the strip, i.e. a square card).
+    pub fn height(mut self, height: Option<u32>) -> Self {
+        self.height = height;
+        self
+    }
+    /// Renders the card at the given width.
+    ///
+    /// Text is sized relative to the width, and wrapped if it doesn't fit on one line.
+    pub(crate) fn render(&self, width: u32) -> RgbImage {
+        let height = self.height.unwrap_or(width).max(1);
+        let mut card = RgbImage::from_pixel(width, height, self.background);
+        let margin = width as f32 / 12.0;
+        let mut layout = Layout::new(CoordinateSystem::PositiveYDown);
+        layout.reset(&LayoutSettings {
+            x: margin,
+            y: margin,
+            max_width: Some(width as f32 - 2.0 * margin),
+            max_height: Some(height as f32 - 2.0 * margin),
+            horizontal_align: HorizontalAlign::Center,
+            vertical_align: VerticalAlign::Middle,
+            ..LayoutSettings::default()
+        });
+        let lines = [
+            (&self.series, width as f32 / 12.0),
+            (&self.chapter, width as f32 / 18.0),
+            (&self.group, width as f32 / 28.0),
+        ];
+        let lines: Vec<_> = lines
+            .into_iter()
+            .filter_map(|(text, px)| Some((text.as_deref()?, px)))
+            .collect();
+        for (index, (text, px)) in lines.iter().enumerate() {
+            let text = if index + 1 < lines.len() {
+                format!("{text}\n")
+            } else {
+                text.to_string()
+            };
+            layout.append(&[&self.font], &TextStyle::new(&text, *px, 0));
+        }
+
+        for glyph in layout.glyphs() {
+            if glyph.width == 0 || glyph.height == 0 {
+                continue;
+            }
+            let (metrics, coverage) = self.font.rasterize_config(glyph.key);
+            for (index, &alpha) in coverage.iter().enumerate() {
+                let x = glyph.x as i64 + (index % metrics.width) as i64;
+                let y = glyph.y as i64 + (index / metrics.width) as i64;
+                if alpha == 0 || x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+                    continue;
+                }
+                let pixel = card.get_pixel_mut(x as u32, y as u32);
+                for (channel, &text) in pixel.0.iter_mut().zip(&self.text_color.0) {
+                    *channel = ((text as u16 * alpha as u16
+                        + *channel as u16 * (u8::MAX - alpha) as u16)
+                        / u8::MAX as u16) as u8;
+                }
+            }
+        }
+        card
+    }
+}
+
+/// Renders the card at the width of the strip, and stacks it on top of the strip.
+///
+/// The new strip is memory-mapped if the old one was, according to the given temp policy.
+pub(crate) fn prepend_title_card(
+    card: &TitleCard,
+    strip: &Strip,
+    temp: &TempPolicy,
+) -> io::Result<Strip> {
+    let rendered = card.render(strip.width());
+    let spill_threshold = match strip.as_raw() {
+        StripStorage::Heap(_) => None,
+        StripStorage::Mapped(_) => Some(0),
+    };
+    let mut combined = new_strip(
+        strip.width(),
+        rendered.height() + strip.height(),
+        spill_threshold,
+        temp,
+    )?;
+    // the new strip was sized to fit both, so this can't fail
+    combined
+        .copy_from(&rendered, 0, 0)
+        .and_then(|_| combined.copy_from(strip, 0, rendered.height()))
+        .expect("all according to keikaku");
+    Ok(combined)
+}
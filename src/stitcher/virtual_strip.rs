@@ -0,0 +1,124 @@
+//! This module contains the virtual strip, which presents several images as a single combined image
+//! without copying them into one buffer.
+
+use image::{Pixel, Rgb, RgbImage};
+
+use super::strip::Strip;
+
+/// Row by row access to a combined image, which is all that finding splitpoints and exporting pages need.
+///
+/// This is implemented both for [`Strip`], which holds the combined image in a single buffer, and for
+/// [`VirtualStrip`], which doesn't.
+pub trait StripRows: Sync {
+    /// The width of the combined image, in pixels.
+    fn width(&self) -> u32;
+    /// The height of the combined image, in pixels.
+    fn height(&self) -> u32;
+    /// The raw RGB subpixels of the given row. Panics if the row is out of bounds.
+    fn row(&self, y: u32) -> &[u8];
+    /// Copies `height` rows starting at `start` into a new image. Panics if the rows are out of bounds.
+    fn copy_rows(&self, start: u32, height: u32) -> RgbImage {
+        let mut raw = Vec::with_capacity(self.width() as usize * height as usize * 3);
+        for y in start..start + height {
+            raw.extend_from_slice(self.row(y));
+        }
+        // each row holds exactly `width` pixels, so this can't fail
+        RgbImage::from_raw(self.width(), height, raw).expect("all according to keikaku")
+    }
+    /// The pixel at the given position. Panics if it is out of bounds.
+    fn pixel(&self, x: u32, y: u32) -> Rgb<u8> {
+        let channels = Rgb::<u8>::CHANNEL_COUNT as usize;
+        let offset = x as usize * channels;
+        *Rgb::from_slice(&self.row(y)[offset..offset + channels])
+    }
+}
+
+impl StripRows for Strip {
+    fn width(&self) -> u32 {
+        self.width()
+    }
+    fn height(&self) -> u32 {
+        self.height()
+    }
+    fn row(&self, y: u32) -> &[u8] {
+        let len = self.width() as usize * Rgb::<u8>::CHANNEL_COUNT as usize;
+        let start = y as usize * len;
+        &self.as_raw()[start..start + len]
+    }
+    fn copy_rows(&self, start: u32, height: u32) -> RgbImage {
+        let len = self.width() as usize * Rgb::<u8>::CHANNEL_COUNT as usize;
+        let raw = self.as_raw()[start as usize * len..(start + height) as usize * len].to_vec();
+        // the slice holds exactly `height` rows, so this can't fail
+        RgbImage::from_raw(self.width(), height, raw).expect("all according to keikaku")
+    }
+}
+
+/// A combined image made up of separate images of the same width, stacked on top of each other.
+///
+/// Rows are read straight out of each image, so the combined image is never held in memory as a whole.
+/// This halves the memory needed to stitch long chapters, at the cost of slightly slower row access.
+/// Images may be separated by a gap of a solid color, as with [`crate::LoadConfig::inter_image_gap`].
+#[derive(Debug, Clone)]
+pub struct VirtualStrip {
+    images: Vec<RgbImage>,
+    // the first row of each image
+    starts: Vec<u32>,
+    width: u32,
+    height: u32,
+    // a single row of the gap color
+    gap_row: Vec<u8>,
+}
+
+impl VirtualStrip {
+    /// Stacks the given images, separated by `gap` rows of `gap_color`.
+    ///
+    /// Returns `None` if no images were provided, if they don't all have the same width, or if the strip
+    /// would be more than `u32::MAX` pixels tall.
+    pub fn new(images: Vec<RgbImage>, gap: u32, gap_color: Rgb<u8>) -> Option<Self> {
+        let width = images.first()?.width();
+        if images.iter().any(|image| image.width() != width) {
+            return None;
+        }
+        let mut starts = Vec::with_capacity(images.len());
+        let mut height = 0u32;
+        for (index, image) in images.iter().enumerate() {
+            if index > 0 {
+                height = height.checked_add(gap)?;
+            }
+            starts.push(height);
+            height = height.checked_add(image.height())?;
+        }
+        Some(Self {
+            images,
+            starts,
+            width,
+            height,
+            gap_row: gap_color.0.repeat(width as usize),
+        })
+    }
+    /// The images making up the strip.
+    pub fn images(&self) -> &[RgbImage] {
+        &self.images
+    }
+}
+
+impl StripRows for VirtualStrip {
+    fn width(&self) -> u32 {
+        self.width
+    }
+    fn height(&self) -> u32 {
+        self.height
+    }
+    fn row(&self, y: u32) -> &[u8] {
+        assert!(y < self.height, "row {y} is out of bounds");
+        // the last image starting at or before the row
+        let index = self.starts.partition_point(|&start| start <= y) - 1;
+        let image = &self.images[index];
+        let y = y - self.starts[index];
+        if y >= image.height() {
+            return &self.gap_row;
+        }
+        let len = self.width as usize * Rgb::<u8>::CHANNEL_COUNT as usize;
+        &image.as_raw()[y as usize * len..(y as usize + 1) * len]
+    }
+}
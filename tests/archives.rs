@@ -0,0 +1,97 @@
+//! Round-trip tests of loading images out of the 7z archives in `tests/archives`, and of RAR archives being
+//! reported as unsupported.
+//!
+//! The archives are written by `tests/archives/make_fixtures.py`, which holds the same pages as
+//! `tests/archives/pages`.
+
+use std::{fs, path::PathBuf};
+
+use image::RgbImage;
+use quickstitch::{LoadConfig, Stitcher};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(format!(
+        "{}/tests/archives/{name}",
+        env!("CARGO_MANIFEST_DIR")
+    ))
+}
+
+/// The strip the pages make when they are loaded on their own.
+fn expected_strip() -> RgbImage {
+    let pages: Vec<Vec<u8>> = ["01.png", "02.png", "10.png"]
+        .iter()
+        .map(|name| fs::read(fixture(&format!("pages/{name}"))).unwrap())
+        .collect();
+    Stitcher::new()
+        .load_bytes(&pages, &LoadConfig::new())
+        .unwrap()
+        .preview(u32::MAX)
+}
+
+fn load(name: &str) -> Result<RgbImage, String> {
+    Stitcher::new()
+        .load_archive(fixture(name), &LoadConfig::new())
+        .map(|loaded| loaded.preview(u32::MAX))
+        .map_err(|error| error.to_string())
+}
+
+/// Whether loading the archive fails as it is damaged.
+fn is_damaged(name: &str) -> bool {
+    load(name).is_err_and(|error| error == "The archive can't be read, as it is damaged")
+}
+
+#[test]
+fn stored_7z() {
+    assert_eq!(load("stored.7z").unwrap(), expected_strip());
+}
+
+#[test]
+fn lzma_7z() {
+    assert_eq!(load("lzma.7z").unwrap(), expected_strip());
+}
+
+#[test]
+fn lzma2_7z() {
+    assert_eq!(load("lzma2.7z").unwrap(), expected_strip());
+}
+
+#[test]
+fn solid_7z() {
+    assert_eq!(load("solid.7z").unwrap(), expected_strip());
+    assert_eq!(load("solid_lzma2.7z").unwrap(), expected_strip());
+}
+
+#[test]
+fn truncated_7z() {
+    assert!(is_damaged("truncated.7z"));
+}
+
+#[test]
+fn repeated_streams_7z() {
+    assert!(is_damaged("two_streams.7z"));
+}
+
+#[test]
+fn oversized_entries_7z() {
+    assert!(is_damaged("oversized_entries.7z"));
+}
+
+#[test]
+fn encoded_header_loop_7z() {
+    assert!(is_damaged("encoded_loop.7z"));
+}
+
+#[test]
+fn rar_is_unsupported() {
+    for name in ["stored4.rar", "stored5.rar"] {
+        assert_eq!(
+            load(name).unwrap_err(),
+            "The archive can't be read, as it is a RAR archive, which isn't supported"
+        );
+    }
+}
+
+#[test]
+fn huge_page_7z() {
+    assert_eq!(load("huge_page.7z").unwrap_err(), "Memory limit exceeded");
+}
@@ -0,0 +1,265 @@
+#!/usr/bin/env python3
+"""Writes the archives in this directory, which `tests/archives.rs` loads.
+
+No 7z or RAR tool is needed: the archives are laid out by hand, following the 7z format documentation
+(7zFormat.txt of the LZMA SDK) and the RAR 4 and RAR 5 technotes. RAR archives can't be loaded, so they are
+only written to check that they are reported as such. The pages are small PNGs, so that the archives stay
+small enough to commit.
+
+Run from anywhere with `python3 tests/archives/make_fixtures.py`.
+"""
+
+import lzma
+import os
+import struct
+import zlib
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+
+# --- pages ---------------------------------------------------------------------------------------------
+
+
+def png(width, height, pixel):
+    """Encodes an RGB PNG whose pixels are given by `pixel(x, y)`."""
+
+    def chunk(kind, data):
+        return (
+            struct.pack(">I", len(data))
+            + kind
+            + data
+            + struct.pack(">I", zlib.crc32(kind + data))
+        )
+
+    rows = b"".join(
+        b"\x00" + b"".join(bytes(pixel(x, y)) for x in range(width)) for y in range(height)
+    )
+    return (
+        b"\x89PNG\r\n\x1a\n"
+        + chunk(b"IHDR", struct.pack(">IIBBBBB", width, height, 8, 2, 0, 0, 0))
+        + chunk(b"IDAT", zlib.compress(rows, 9))
+        + chunk(b"IEND", b"")
+    )
+
+
+PAGES = [
+    ("chapter/01.png", png(24, 40, lambda x, y: (255, 255, 255) if y % 10 else (x * 10, 0, 0))),
+    ("chapter/02.png", png(24, 32, lambda x, y: (0, y * 7, 255 - x * 10))),
+    ("chapter/10.png", png(24, 48, lambda x, y: ((x ^ y) * 9 % 256, 128, 30))),
+]
+
+# --- 7z ------------------------------------------------------------------------------------------------
+
+
+def number(value):
+    """A 7z variable-length number."""
+    for extra in range(8):
+        if value < 1 << (8 * extra + 7 - extra):
+            first = (0xFF00 >> extra) & 0xFF | value >> (8 * extra)
+            return bytes([first]) + (value & ((1 << (8 * extra)) - 1)).to_bytes(extra, "little")
+    return b"\xff" + value.to_bytes(8, "little")
+
+
+def lzma1(data):
+    """Compresses data with LZMA, returning the 5 bytes of properties 7z keeps apart and the stream."""
+    dict_size = 1 << 16
+    stream = lzma.compress(
+        data,
+        format=lzma.FORMAT_RAW,
+        filters=[{"id": lzma.FILTER_LZMA1, "dict_size": dict_size, "lc": 3, "lp": 0, "pb": 2}],
+    )
+    properties = bytes([(2 * 5 + 0) * 9 + 3]) + struct.pack("<I", dict_size)
+    return properties, stream
+
+
+def lzma2(data):
+    """Compresses data with LZMA2, returning the byte of properties (the dictionary size) and the stream."""
+    stream = lzma.compress(
+        data,
+        format=lzma.FORMAT_RAW,
+        filters=[{"id": lzma.FILTER_LZMA2, "dict_size": 1 << 16}],
+    )
+    return bytes([8]), stream
+
+
+def coder(method, properties):
+    """A folder with a single coder."""
+    ids = {"copy": b"\x00", "lzma": b"\x03\x01\x01", "lzma2": b"\x21"}[method]
+    flags = len(ids) | (0x20 if properties else 0)
+    out = bytes([1, flags]) + ids
+    if properties:
+        out += number(len(properties)) + properties
+    return out
+
+
+def pack(method, data):
+    """Packs the data of a folder, returning its coder and the packed stream."""
+    if method == "copy":
+        return coder("copy", b""), data
+    properties, stream = {"lzma": lzma1, "lzma2": lzma2}[method](data)
+    return coder(method, properties), stream
+
+
+def streams_info(pack_pos, folders):
+    """The streams of an archive: `folders` is a list of (coder, packed size, unpacked size, entry sizes)."""
+    out = b"\x06" + number(pack_pos) + number(len(folders)) + b"\x09"
+    out += b"".join(number(packed) for _, packed, _, _ in folders) + b"\x00"
+    out += b"\x07\x0b" + number(len(folders)) + b"\x00"
+    out += b"".join(coder for coder, _, _, _ in folders)
+    out += b"\x0c" + b"".join(number(unpacked) for _, _, unpacked, _ in folders) + b"\x00"
+    if any(len(sizes) != 1 for _, _, _, sizes in folders):
+        out += b"\x08\x0d" + b"".join(number(len(sizes)) for _, _, _, sizes in folders)
+        out += b"\x09" + b"".join(
+            number(size) for _, _, _, sizes in folders for size in sizes[:-1]
+        )
+        out += b"\x00"
+    return out + b"\x00"
+
+
+def files_info(names):
+    data = b"\x00" + b"".join(name.encode("utf-16-le") + b"\x00\x00" for name in names)
+    return b"\x05" + number(len(names)) + b"\x11" + number(len(data)) + data + b"\x00"
+
+
+def seven_zip(method, solid, encode_header=False, header=None, pages=PAGES):
+    """Writes the pages into a 7z archive, one folder per page or all of them in one (solid) folder."""
+    groups = [pages] if solid else [[page] for page in pages]
+    packed = b""
+    folders = []
+    for group in groups:
+        data = b"".join(page for _, page in group)
+        folder_coder, stream = pack(method, data)
+        packed += stream
+        folders.append((folder_coder, len(stream), len(data), [len(page) for _, page in group]))
+    if header is None:
+        header = b"\x01\x04" + streams_info(0, folders) + files_info([n for n, _ in pages]) + b"\x00"
+    if encode_header:
+        header_coder, stream = pack("lzma", header)
+        pack_pos = len(packed)
+        packed += stream
+        header = b"\x17" + streams_info(pack_pos, [(header_coder, len(stream), len(header), [len(header)])])
+    start = struct.pack("<QQI", len(packed), len(header), zlib.crc32(header))
+    return b"7z\xbc\xaf\x27\x1c\x00\x04" + struct.pack("<I", zlib.crc32(start)) + start + packed + header
+
+
+def two_streams():
+    """An archive whose header lists its streams a second time after its files, with no folders at all."""
+    data = b"".join(page for _, page in PAGES)
+    folder = (coder("copy", b""), len(data), len(data), [len(page) for _, page in PAGES])
+    empty = b"\x00"
+    header = (
+        b"\x01\x04"
+        + streams_info(0, [folder])
+        + files_info([n for n, _ in PAGES])
+        + b"\x04"
+        + empty
+        + b"\x00"
+    )
+    return seven_zip("copy", True, header=header)
+
+
+def oversized_entries():
+    """An archive whose entries add up to more than the folder holding them."""
+    data = b"".join(page for _, page in PAGES)
+    sizes = [len(page) for _, page in PAGES]
+    sizes[0] += len(data)
+    folder = (coder("copy", b""), len(data), len(data), sizes)
+    header = b"\x01\x04" + streams_info(0, [folder]) + files_info([n for n, _ in PAGES]) + b"\x00"
+    return seven_zip("copy", True, header=header)
+
+
+def encoded_loop():
+    """An archive whose encoded header is a stored folder holding that same encoded header."""
+    # numbers below 0x80 take up a single byte, so the size of the header doesn't depend on itself
+    size = len(b"\x17" + streams_info(0, [(coder("copy", b""), 0, 0, [0])]))
+    header = b"\x17" + streams_info(0, [(coder("copy", b""), size, size, [size])])
+    assert len(header) == size
+    start = struct.pack("<QQI", len(header), len(header), zlib.crc32(header))
+    return b"7z\xbc\xaf\x27\x1c\x00\x04" + struct.pack("<I", zlib.crc32(start)) + start + header + header
+
+
+def huge_page():
+    """An archive holding a PNG whose header claims far more pixels than it has data for."""
+    rows = zlib.compress(b"\x00" * 31, 9)
+    ihdr = struct.pack(">IIBBBBB", 100_000, 100_000, 16, 6, 0, 0, 0)
+    page = b"\x89PNG\r\n\x1a\n"
+    for kind, data in [(b"IHDR", ihdr), (b"IDAT", rows), (b"IEND", b"")]:
+        page += struct.pack(">I", len(data)) + kind + data + struct.pack(">I", zlib.crc32(kind + data))
+    return seven_zip("copy", False, pages=[("chapter/01.png", page)])
+
+
+# --- RAR -----------------------------------------------------------------------------------------------
+
+
+def rar4():
+    out = b"Rar!\x1a\x07\x00"
+
+    def block(kind, flags, fields, data=b""):
+        body = bytes([kind]) + struct.pack("<HH", flags, 7 + len(fields)) + fields
+        return struct.pack("<H", zlib.crc32(body) & 0xFFFF) + body + data
+
+    out += block(0x73, 0, b"\x00" * 6)
+    for name, page in PAGES:
+        encoded = name.encode()
+        fields = struct.pack(
+            "<IIBIIBBHI", len(page), len(page), 3, zlib.crc32(page), 0, 29, 0x30, len(encoded), 0o100644
+        )
+        out += block(0x74, 0x8000, fields + encoded, page)
+    return out + block(0x7B, 0x4000, b"")
+
+
+def vint(value):
+    out = b""
+    while True:
+        byte = value & 0x7F
+        value >>= 7
+        if value:
+            out += bytes([byte | 0x80])
+        else:
+            return out + bytes([byte])
+
+
+def rar5():
+    out = b"Rar!\x1a\x07\x01\x00"
+
+    def block(fields, data=b""):
+        header = vint(len(fields)) + fields
+        return struct.pack("<I", zlib.crc32(header)) + header + data
+
+    out += block(vint(1) + vint(0) + vint(0))
+    for name, page in PAGES:
+        encoded = name.encode()
+        fields = vint(2) + vint(0x0002) + vint(len(page))
+        fields += vint(0) + vint(len(page)) + vint(0o100644) + vint(0) + vint(1)
+        fields += vint(len(encoded)) + encoded
+        out += block(fields, page)
+    return out + block(vint(5) + vint(0) + vint(0))
+
+
+def main():
+    for name, page in PAGES:
+        path = os.path.join(HERE, "pages", os.path.basename(name))
+        os.makedirs(os.path.dirname(path), exist_ok=True)
+        with open(path, "wb") as file:
+            file.write(page)
+    solid = seven_zip("lzma", True, encode_header=True)
+    fixtures = {
+        "stored.7z": seven_zip("copy", False),
+        "lzma.7z": seven_zip("lzma", False),
+        "lzma2.7z": seven_zip("lzma2", False),
+        "solid.7z": solid,
+        "solid_lzma2.7z": seven_zip("lzma2", True, encode_header=True),
+        "truncated.7z": solid[: len(solid) // 2],
+        "two_streams.7z": two_streams(),
+        "oversized_entries.7z": oversized_entries(),
+        "encoded_loop.7z": encoded_loop(),
+        "huge_page.7z": huge_page(),
+        "stored4.rar": rar4(),
+        "stored5.rar": rar5(),
+    }
+    for name, data in fixtures.items():
+        with open(os.path.join(HERE, name), "wb") as file:
+            file.write(data)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,83 @@
+//! Tests of how exported pages are named from a user-supplied template.
+
+use std::{fs, io::Cursor};
+
+use image::{ImageFormat, Rgb, RgbImage};
+use quickstitch::{ExportConfig, ImageOutputFormat, LoadConfig, SanitizePolicy, Stitcher};
+
+/// Exports a strip of three plain pages with the given config, and returns the names of the pages.
+fn export_names(config: &ExportConfig) -> Vec<String> {
+    let image = RgbImage::from_pixel(100, 300, Rgb([255, 255, 255]));
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .unwrap();
+    let stitched = Stitcher::new()
+        .load_bytes(&[png], &LoadConfig::new())
+        .unwrap()
+        .stitch(100, 5, 242);
+    let directory = tempfile::tempdir().unwrap();
+    stitched
+        .export_with(directory.path(), ImageOutputFormat::Png, config)
+        .unwrap();
+    let mut names: Vec<String> = fs::read_dir(directory.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.ends_with(".png"))
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn invalid_characters_are_replaced() {
+    let names =
+        export_names(&ExportConfig::new().name_template("Re:Zero? \u{202E}Ch. 1/2 - {page}"));
+    assert_eq!(
+        names,
+        [
+            "Re_Zero_ _Ch. 1_2 - 1.png",
+            "Re_Zero_ _Ch. 1_2 - 2.png",
+            "Re_Zero_ _Ch. 1_2 - 3.png"
+        ]
+    );
+}
+
+#[test]
+fn invalid_characters_are_removed() {
+    let names = export_names(
+        &ExportConfig::new()
+            .name_template("Re:Zero? \u{200B}Ch. 1 - {page}")
+            .sanitize(SanitizePolicy::Remove),
+    );
+    assert_eq!(
+        names,
+        [
+            "ReZero Ch. 1 - 1.png",
+            "ReZero Ch. 1 - 2.png",
+            "ReZero Ch. 1 - 3.png"
+        ]
+    );
+}
+
+#[test]
+fn invalid_replacement_falls_back_to_underscore() {
+    let names = export_names(
+        &ExportConfig::new()
+            .name_template("a*b {page}")
+            .sanitize(SanitizePolicy::Replace('?')),
+    );
+    assert_eq!(names, ["a_b 1.png", "a_b 2.png", "a_b 3.png"]);
+}
+
+#[test]
+fn page_number_is_appended_without_placeholder() {
+    let names = export_names(&ExportConfig::new().name_template("page-").first_page(0));
+    assert_eq!(names, ["page-0.png", "page-1.png", "page-2.png"]);
+}
+
+#[test]
+fn reserved_names_are_prefixed() {
+    let names = export_names(&ExportConfig::new().name_template("CON.{page}"));
+    assert_eq!(names, ["_CON.1.png", "_CON.2.png", "_CON.3.png"]);
+}
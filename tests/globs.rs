@@ -0,0 +1,54 @@
+//! Tests of finding images by glob pattern.
+
+use std::{fs, path::Path};
+
+use image::{Rgb, RgbImage};
+use quickstitch::{raw::glob_images, LoadConfig, Sort};
+
+/// Writes a plain page to each of the paths within the directory.
+fn write_pages(directory: &Path, paths: &[&str]) {
+    for path in paths {
+        let path = directory.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        RgbImage::from_pixel(4, 4, Rgb([255, 255, 255]))
+            .save(path)
+            .unwrap();
+    }
+}
+
+#[test]
+fn each_image_is_found_once() {
+    let directory = tempfile::tempdir().unwrap();
+    write_pages(directory.path(), &["01.png", "a/02.png", "a/b/03.png"]);
+    let pattern = format!("{}/**/**/*.png", directory.path().display());
+    for sort in [Sort::Natural, Sort::ModifiedTime] {
+        let mut images = glob_images(&pattern, &LoadConfig::new().sort(sort)).unwrap();
+        assert_eq!(images.len(), 3);
+        images.sort();
+        images.dedup();
+        assert_eq!(images.len(), 3);
+    }
+}
+
+#[test]
+fn hidden_files_and_other_files_are_skipped() {
+    let directory = tempfile::tempdir().unwrap();
+    write_pages(directory.path(), &["01.png", ".02.png", "03.png"]);
+    fs::write(directory.path().join("notes.txt"), "").unwrap();
+    let pattern = format!("{}/*", directory.path().display());
+    let images = glob_images(&pattern, &LoadConfig::new()).unwrap();
+    let names: Vec<_> = images
+        .iter()
+        .map(|path| path.file_name().unwrap())
+        .collect();
+    assert_eq!(names, ["01.png", "03.png"]);
+}
+
+#[test]
+fn invalid_pattern() {
+    let error = glob_images("raws/[a-", &LoadConfig::new()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "The pattern raws/[a- is invalid, as invalid range pattern"
+    );
+}
@@ -0,0 +1,38 @@
+//! Round-trip tests of the whole pipeline against the golden files in `tests/golden`.
+//!
+//! Run with `QUICKSTITCH_BLESS=1` to record the current output after an intended change.
+
+use quickstitch::golden::{self, Synthetic};
+use quickstitch::{ExportConfig, ImageOutputFormat, StitchConfig};
+
+fn check(name: &str, synthetic: Synthetic, stitch_config: StitchConfig) {
+    let golden = golden::run(
+        &synthetic,
+        stitch_config,
+        ImageOutputFormat::Png,
+        ExportConfig::new(),
+    )
+    .unwrap();
+    let path = format!("{}/tests/golden/{name}.txt", env!("CARGO_MANIFEST_DIR"));
+    if let Err(e) = golden.check(path) {
+        panic!("{e}");
+    }
+}
+
+#[test]
+fn default_chapter() {
+    check(
+        "default",
+        Synthetic::new(),
+        StitchConfig::new().target_height(2000),
+    );
+}
+
+#[test]
+fn short_pages() {
+    check(
+        "short_pages",
+        Synthetic::new().seed(7).gutter_height(40),
+        StitchConfig::new().target_height(900),
+    );
+}
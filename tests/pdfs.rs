@@ -0,0 +1,93 @@
+//! Tests of loading the images embedded in the PDFs in `tests/pdfs`, however their objects and
+//! cross-reference data are laid out.
+//!
+//! The PDFs are written by `tests/pdfs/make_fixtures.py`. Each holds the pages of `tests/pdfs/pages` as
+//! images, followed by a text-only page and a page of vector art.
+
+use std::{fs, path::PathBuf};
+
+use image::RgbImage;
+use quickstitch::{raw::pdf_images, LoadConfig, Stitcher};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(format!("{}/tests/pdfs/{name}", env!("CARGO_MANIFEST_DIR")))
+}
+
+/// The strip the pages make when they are loaded on their own.
+fn expected_strip() -> RgbImage {
+    let pages: Vec<Vec<u8>> = ["01.png", "02.png", "03.png"]
+        .iter()
+        .map(|name| fs::read(fixture(&format!("pages/{name}"))).unwrap())
+        .collect();
+    Stitcher::new()
+        .load_bytes(&pages, &LoadConfig::new())
+        .unwrap()
+        .preview(u32::MAX)
+}
+
+fn load(name: &str) -> Result<RgbImage, String> {
+    Stitcher::new()
+        .load_pdf(fixture(name), &LoadConfig::new())
+        .map(|loaded| loaded.preview(u32::MAX))
+        .map_err(|error| error.to_string())
+}
+
+#[test]
+fn xref_table() {
+    assert_eq!(load("xref_table.pdf").unwrap(), expected_strip());
+}
+
+#[test]
+fn xref_stream() {
+    assert_eq!(load("xref_stream.pdf").unwrap(), expected_strip());
+}
+
+#[test]
+fn object_streams() {
+    assert_eq!(load("object_streams.pdf").unwrap(), expected_strip());
+}
+
+#[test]
+fn broken_xref_is_bypassed() {
+    assert_eq!(load("broken_xref.pdf").unwrap(), expected_strip());
+    assert_eq!(load("missing_xref.pdf").unwrap(), expected_strip());
+}
+
+#[test]
+fn pages_without_images_are_skipped() {
+    let pdf = fs::read(fixture("xref_table.pdf")).unwrap();
+    assert_eq!(pdf_images(&pdf).unwrap().len(), 3);
+}
+
+#[test]
+fn form_loop() {
+    assert_eq!(load("form_loop.pdf").unwrap(), expected_strip());
+}
+
+#[test]
+fn without_images() {
+    assert_eq!(
+        load("without_images.pdf").unwrap_err(),
+        "The PDF does not contain any images"
+    );
+}
+
+#[test]
+fn truncated() {
+    assert_eq!(
+        load("truncated.pdf").unwrap_err(),
+        "The file is not a valid PDF"
+    );
+}
+
+#[test]
+fn truncated_anywhere() {
+    // wherever a PDF is cut off, either all of its images are still found, or it is reported as invalid
+    let pdf = fs::read(fixture("xref_table.pdf")).unwrap();
+    let images = pdf_images(&pdf).unwrap();
+    for length in 0..pdf.len() {
+        if let Ok(found) = pdf_images(&pdf[..length]) {
+            assert!(found == images, "cut off after {length} bytes");
+        }
+    }
+}
@@ -0,0 +1,234 @@
+#!/usr/bin/env python3
+"""Writes the PDFs in this directory, which `tests/pdfs.rs` loads.
+
+The PDFs are laid out by hand, following the PDF 1.7 specification (ISO 32000-1), so that each one holds
+exactly the structure a test is about. Every PDF holds the same three pages of images as `pages`, followed
+by a text-only page and a page of vector art, which have no images.
+
+Run from anywhere with `python3 tests/pdfs/make_fixtures.py`.
+"""
+
+import os
+import struct
+import zlib
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+
+# --- pages ---------------------------------------------------------------------------------------------
+
+
+def png(width, height, pixel):
+    """Encodes an RGB PNG whose pixels are given by `pixel(x, y)`."""
+
+    def chunk(kind, data):
+        return (
+            struct.pack(">I", len(data))
+            + kind
+            + data
+            + struct.pack(">I", zlib.crc32(kind + data))
+        )
+
+    rows = b"".join(
+        b"\x00" + b"".join(bytes(pixel(x, y)) for x in range(width)) for y in range(height)
+    )
+    return (
+        b"\x89PNG\r\n\x1a\n"
+        + chunk(b"IHDR", struct.pack(">IIBBBBB", width, height, 8, 2, 0, 0, 0))
+        + chunk(b"IDAT", zlib.compress(rows, 9))
+        + chunk(b"IEND", b"")
+    )
+
+
+def gray(x, y):
+    level = (x * 10 + y * 3) % 256
+    return (level, level, level)
+
+
+# (name, width, height, pixel, colour space)
+PAGES = [
+    ("01.png", 24, 40, lambda x, y: (255, 255, 255) if y % 10 else (x * 10, 0, 0), "rgb"),
+    ("02.png", 24, 32, lambda x, y: (0, y * 7, 255 - x * 10), "rgb-predicted"),
+    ("03.png", 24, 48, gray, "gray"),
+]
+
+# --- objects -------------------------------------------------------------------------------------------
+
+
+def stream(entries, data):
+    """The body of a Flate-compressed stream object with the given dictionary entries."""
+    data = zlib.compress(data, 9)
+    entries = (entries + b" /Filter /FlateDecode").strip()
+    return b"<< %s /Length %d >>\nstream\n" % (entries, len(data)) + data + b"\nendstream"
+
+
+def image(width, height, pixel, kind):
+    """The body of an image XObject holding the given pixels."""
+    entries = b"/Type /XObject /Subtype /Image /Width %d /Height %d /BitsPerComponent 8" % (width, height)
+    if kind == "gray":
+        data = bytes(pixel(x, y)[0] for y in range(height) for x in range(width))
+        return stream(entries + b" /ColorSpace /DeviceGray", data)
+    rows = [b"".join(bytes(pixel(x, y)) for x in range(width)) for y in range(height)]
+    if kind == "rgb":
+        return stream(entries + b" /ColorSpace /DeviceRGB", b"".join(rows))
+    # every row is stored as the difference from the one above it (PNG's Up filter)
+    previous = bytes(len(rows[0]))
+    data = b""
+    for row in rows:
+        data += b"\x02" + bytes((a - b) % 256 for a, b in zip(row, previous))
+        previous = row
+    params = b" /DecodeParms << /Predictor 12 /Colors 3 /Columns %d >>" % width
+    return stream(entries + b" /ColorSpace /DeviceRGB" + params, data)
+
+
+def document():
+    """The objects of the PDF, by number: the catalog is object 1."""
+    objects = {
+        1: b"<< /Type /Catalog /Pages 2 0 R >>",
+        # the font of the text-only page
+        3: b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>",
+    }
+    kids = []
+    number = 10
+    for _, width, height, pixel, kind in PAGES:
+        page, contents, xobject = number, number + 1, number + 2
+        number += 3
+        kids.append(page)
+        objects[page] = (
+            b"<< /Type /Page /Parent 2 0 R /MediaBox [0 0 %d %d] /Contents %d 0 R "
+            b"/Resources << /XObject << /Im0 %d 0 R >> >> >>" % (width, height, contents, xobject)
+        )
+        objects[contents] = stream(b"", b"q %d 0 0 %d 0 0 cm /Im0 Do Q" % (width, height))
+        objects[xobject] = image(width, height, pixel, kind)
+    text, vector = number, number + 2
+    kids += [text, vector]
+    objects[text] = (
+        b"<< /Type /Page /Parent 2 0 R /MediaBox [0 0 24 40] /Contents %d 0 R "
+        b"/Resources << /Font << /F1 3 0 R >> >> >>" % (text + 1)
+    )
+    objects[text + 1] = stream(b"", b"BT /F1 8 Tf 2 20 Td (Credits) Tj ET")
+    objects[vector] = b"<< /Type /Page /Parent 2 0 R /MediaBox [0 0 24 40] /Contents %d 0 R >>" % (vector + 1)
+    objects[vector + 1] = stream(b"", b"0 0 1 rg 2 2 20 36 re f")
+    objects[2] = b"<< /Type /Pages /Kids [%s] /Count %d >>" % (
+        b" ".join(b"%d 0 R" % kid for kid in kids),
+        len(kids),
+    )
+    return objects
+
+
+def form_loop():
+    """The objects of the PDF, with the image of the first page drawn by a form which lists itself twice."""
+    objects = document()
+    objects[10] = objects[10].replace(b"/Im0 12 0 R", b"/Fm0 30 0 R")
+    objects[30] = stream(
+        b"/Type /XObject /Subtype /Form /BBox [0 0 24 40] "
+        b"/Resources << /XObject << /A 30 0 R /B 30 0 R /Im0 12 0 R >> >>",
+        b"/Im0 Do",
+    )
+    return objects
+
+
+def without_images():
+    """The objects of the PDF, with only the text-only page and the page of vector art left."""
+    objects = {n: o for n, o in document().items() if not 10 <= n < 19}
+    objects[2] = b"<< /Type /Pages /Kids [19 0 R 21 0 R] /Count 2 >>"
+    return objects
+
+
+HEADER = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n"
+
+# --- layouts -------------------------------------------------------------------------------------------
+
+
+def with_xref_table(objects, offsets="correct", trailer=True):
+    """Writes the objects followed by a cross-reference table, whose offsets may be wrong."""
+    out = HEADER
+    positions = {}
+    for number in sorted(objects):
+        positions[number] = len(out)
+        out += b"%d 0 obj\n" % number + objects[number] + b"\nendobj\n"
+    if not trailer:
+        return out
+    size = max(objects) + 1
+    xref = len(out)
+    out += b"xref\n0 %d\n0000000000 65535 f \n" % size
+    for number in range(1, size):
+        if number not in positions:
+            out += b"0000000000 65535 f \n"
+        elif offsets == "correct":
+            out += b"%010d 00000 n \n" % positions[number]
+        else:
+            # offsets from another revision of the file, which no longer point at the objects
+            out += b"%010d 00000 n \n" % (positions[number] * 3 + 17)
+    out += b"trailer\n<< /Size %d /Root 1 0 R >>\n" % size
+    if offsets != "correct":
+        xref += 12345
+    return out + b"startxref\n%d\n%%%%EOF\n" % xref
+
+
+def with_xref_stream(objects, compressed=()):
+    """Writes the objects followed by a cross-reference stream (PDF 1.5), with the objects in `compressed`
+    held in an object stream rather than written out on their own.
+    """
+    out = HEADER.replace(b"1.4", b"1.5")
+    size = max(objects) + 3
+    object_stream, xref_stream = size - 2, size - 1
+    entries = {}
+
+    if compressed:
+        header = b""
+        body = b""
+        for index, number in enumerate(compressed):
+            header += b"%d %d " % (number, len(body))
+            body += objects[number] + b"\n"
+            entries[number] = (2, object_stream, index)
+        objects = {n: o for n, o in objects.items() if n not in compressed}
+        objects[object_stream] = stream(
+            b"/Type /ObjStm /N %d /First %d" % (len(compressed), len(header)), header + body
+        )
+
+    for number in sorted(objects):
+        entries[number] = (1, len(out), 0)
+        out += b"%d 0 obj\n" % number + objects[number] + b"\nendobj\n"
+
+    entries[xref_stream] = (1, len(out), 0)
+    rows = b""
+    for number in range(size):
+        kind, field, index = entries.get(number, (0, 0, 65535 if number == 0 else 0))
+        rows += struct.pack(">BIH", kind, field, index)
+    out += b"%d 0 obj\n" % xref_stream + stream(
+        b"/Type /XRef /Size %d /W [1 4 2] /Root 1 0 R" % size, rows
+    ) + b"\nendobj\n"
+    return out + b"startxref\n%d\n%%%%EOF\n" % entries[xref_stream][1]
+
+
+def main():
+    os.makedirs(os.path.join(HERE, "pages"), exist_ok=True)
+    for name, width, height, pixel, _ in PAGES:
+        with open(os.path.join(HERE, "pages", name), "wb") as file:
+            file.write(png(width, height, pixel))
+
+    objects = document()
+    xref_table = with_xref_table(objects)
+    # the second image is object 15 (the 3rd of the 2nd page)
+    second_image = xref_table.index(b"\n15 0 obj")
+    fixtures = {
+        "xref_table.pdf": xref_table,
+        "xref_stream.pdf": with_xref_stream(objects),
+        # everything but the streams (which can't be) is held in an object stream
+        "object_streams.pdf": with_xref_stream(
+            objects, compressed=[n for n, o in sorted(objects.items()) if b"stream" not in o]
+        ),
+        "broken_xref.pdf": with_xref_table(objects, offsets="wrong"),
+        "missing_xref.pdf": with_xref_table(objects, trailer=False),
+        "form_loop.pdf": with_xref_table(form_loop()),
+        "without_images.pdf": with_xref_table(without_images()),
+        # cut off halfway through the pixels of the second image
+        "truncated.pdf": xref_table[: second_image + 250],
+    }
+    for name, data in fixtures.items():
+        with open(os.path.join(HERE, name), "wb") as file:
+            file.write(data)
+
+
+if __name__ == "__main__":
+    main()
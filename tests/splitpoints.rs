@@ -0,0 +1,58 @@
+//! Tests of where the combined image is cut under extreme limits.
+
+use std::io::Cursor;
+
+use image::{ImageFormat, Rgb, RgbImage};
+use quickstitch::{raw::find_splitpoints, LoadConfig, SplitStrategy, StitchConfig, Stitcher};
+
+/// Finds the splitpoints of a plain white strip of the given size.
+fn splitpoints(width: u32, height: u32, config: &StitchConfig) -> Vec<usize> {
+    let mut png = Vec::new();
+    RgbImage::from_pixel(width, height, Rgb([255, 255, 255]))
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .unwrap();
+    let loaded = Stitcher::new()
+        .load_bytes(&[png], &LoadConfig::new())
+        .unwrap()
+        .stitch(height as usize, 5, 242);
+    find_splitpoints(loaded.view_strip(), config, &[])
+}
+
+#[test]
+fn huge_min_aspect_ratio() {
+    let config = StitchConfig::new()
+        .target_height(100)
+        .aspect_ratio(Some(1e300), None);
+    let splitpoints = splitpoints(20, 500, &config);
+    assert_eq!(
+        (splitpoints[0], splitpoints[splitpoints.len() - 1]),
+        (0, 500)
+    );
+}
+
+#[test]
+fn invalid_aspect_ratios_are_ignored() {
+    let unlimited = splitpoints(20, 500, &StitchConfig::new().target_height(100));
+    for (min, max) in [
+        (Some(f64::INFINITY), None),
+        (Some(f64::NAN), None),
+        (None, Some(-1.0)),
+        (Some(4.0), Some(2.0)),
+    ] {
+        let config = StitchConfig::new()
+            .target_height(100)
+            .aspect_ratio(min, max);
+        assert_eq!(splitpoints(20, 500, &config), unlimited);
+    }
+}
+
+#[test]
+fn more_pages_than_rows() {
+    let config = StitchConfig::new().strategy(SplitStrategy::PageCount(usize::MAX));
+    let splitpoints = splitpoints(20, 50, &config);
+    assert!(splitpoints.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(
+        (splitpoints[0], splitpoints[splitpoints.len() - 1]),
+        (0, 50)
+    );
+}